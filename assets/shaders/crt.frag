@@ -0,0 +1,39 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+uniform sampler2D Texture;
+
+// Size of the canvas in pixels, so there's one scanline per row
+uniform vec2 CanvasSize;
+
+const float curvature = 0.06;
+const float scanline_darkness = 0.25;
+const float vignette_strength = 0.2;
+
+// Bulge the screen outwards like the glass on an old TV
+vec2 curve(vec2 p) {
+    p = p * 2.0 - 1.0;
+    vec2 offset = abs(p.yx) * curvature;
+    p = p + p * offset * offset;
+    return p * 0.5 + 0.5;
+}
+
+void main() {
+    vec2 curved = curve(uv);
+    if (curved.x < 0.0 || curved.x > 1.0 || curved.y < 0.0 || curved.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 col = texture2D(Texture, curved).rgb;
+
+    float scan = cos(curved.y * CanvasSize.y * 3.14159 * 2.0) * 0.5 + 0.5;
+    col *= 1.0 - scan * scanline_darkness;
+
+    float vignette = 16.0 * curved.x * curved.y * (1.0 - curved.x) * (1.0 - curved.y);
+    col *= mix(1.0 - vignette_strength, 1.0, pow(vignette, 0.3));
+
+    gl_FragColor = color * vec4(col, 1.0);
+}
@@ -1,16 +1,25 @@
 #version 100
 precision highp float;
 
-// Distance along is passed to U
+// Distance along is passed to U, and distance across to V
 varying vec2 uv;
 
 uniform vec4 _Time;
+// How many pixels thick the beam is
+uniform float Width;
 
 const float dimmest = 0.0;
 const float speed = 3.0;
+const vec3 outline = vec3(0.078, 0.094, 0.180);
 
 void main() {
     float brightness = pow(cos(3.14159 * (uv.x - mod(_Time.x * speed, 1.0))), 6.0) * (1.0 - dimmest) + dimmest;
 
-    gl_FragColor = vec4(1.0, 1.0, 1.0, brightness);
+    // Thick beams get a dark pixel along each side so they stand out against the marbles
+    float across = abs(uv.y * 2.0 - 1.0);
+    if (Width >= 3.0 && across > 1.0 - 2.0 / Width) {
+        gl_FragColor = vec4(outline, max(brightness, 0.5));
+    } else {
+        gl_FragColor = vec4(1.0, 1.0, 1.0, brightness);
+    }
 }
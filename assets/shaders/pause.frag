@@ -0,0 +1,30 @@
+#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+uniform sampler2D Texture;
+
+// Size of the canvas in pixels, so the blur is measured in pixels
+uniform vec2 CanvasSize;
+
+const float desaturation = 0.7;
+const float brightness = 0.55;
+
+void main() {
+    vec2 texel = 1.0 / CanvasSize;
+
+    // Box blur
+    vec3 sum = vec3(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            sum += texture2D(Texture, uv + vec2(float(x), float(y)) * texel).rgb;
+        }
+    }
+    vec3 blurred = sum / 25.0;
+
+    float grey = dot(blurred, vec3(0.299, 0.587, 0.114));
+    vec3 col = mix(blurred, vec3(grey), desaturation) * brightness;
+
+    gl_FragColor = color * vec4(col, 1.0);
+}
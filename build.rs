@@ -0,0 +1,47 @@
+//! Writes the asset manifest: every file under `assets/` and a hash of it,
+//! so the game can tell if something's gone missing or got corrupted.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[path = "src/assets/hash.rs"]
+mod hash;
+
+fn main() {
+    println!("cargo:rerun-if-changed=assets");
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut files = Vec::new();
+    walk(&root, &mut files);
+    files.sort();
+
+    let mut manifest = String::new();
+    for path in files {
+        let bytes = fs::read(&path).unwrap();
+        // Always use forward slashes so it reads the same on every platform
+        let relative = path
+            .strip_prefix(&root)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        manifest.push_str(&format!("{:016x} {}\n", hash::hash_bytes(&bytes), relative));
+    }
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("asset_manifest.txt");
+    fs::write(out, manifest).unwrap();
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            walk(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! This is shared with the build script, so it can't use anything from the crate.
+
+/// Hash a file's contents for the asset manifest.
+///
+/// This is 64-bit FNV-1a, which is plenty to notice a truncated or mangled file.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
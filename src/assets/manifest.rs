@@ -0,0 +1,77 @@
+use macroquad::prelude::*;
+use once_cell::sync::OnceCell;
+
+use super::{hash::hash_bytes, ASSETS_ROOT};
+use crate::utils::config::CONFIG;
+
+/// Every file the game shipped with and the hash of what was in it, one per line.
+/// `build.rs` writes this from the `assets` folder.
+const MANIFEST: &str = include_str!(concat!(env!("OUT_DIR"), "/asset_manifest.txt"));
+
+/// Everything that failed the check at startup.
+static PROBLEMS: OnceCell<Vec<AssetProblem>> = OnceCell::new();
+
+/// Something wrong with one of the files in the manifest.
+#[derive(Debug, Clone)]
+pub struct AssetProblem {
+    /// Path under the assets folder, like `textures/marbles.png`
+    pub path: String,
+    pub kind: AssetProblemKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetProblemKind {
+    /// It couldn't be loaded at all
+    Missing,
+    /// It loaded, but it's not what the game shipped with
+    Corrupted,
+}
+
+impl AssetProblemKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AssetProblemKind::Missing => "MISSING",
+            AssetProblemKind::Corrupted => "CORRUPTED",
+        }
+    }
+}
+
+/// Check every file in the manifest is there and has the right contents.
+///
+/// This has to happen before anything gets loaded, so the loaders know to skip broken files.
+/// Custom asset folders are left alone, since they're supposed to be different.
+pub async fn verify() {
+    let mut problems = Vec::new();
+    if CONFIG.assets_path.is_some() {
+        info!("Using a custom assets folder, so not checking it against the manifest");
+    } else {
+        for line in MANIFEST.lines() {
+            let (hash, path) = line.split_at(16);
+            let path = path.trim_start();
+            let expected = u64::from_str_radix(hash, 16).unwrap();
+
+            let kind = match load_file(ASSETS_ROOT.join(path).to_string_lossy().as_ref()).await {
+                Ok(bytes) if hash_bytes(&bytes) == expected => continue,
+                Ok(_) => AssetProblemKind::Corrupted,
+                Err(_) => AssetProblemKind::Missing,
+            };
+            warn!("Asset {} is {}!", path, kind.name().to_lowercase());
+            problems.push(AssetProblem {
+                path: path.to_owned(),
+                kind,
+            });
+        }
+    }
+    // This only runs once
+    let _ = PROBLEMS.set(problems);
+}
+
+/// Everything that failed the check, or nothing if it hasn't run.
+pub fn problems() -> &'static [AssetProblem] {
+    PROBLEMS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Whether this file failed the check and should get a placeholder instead.
+pub fn is_broken(path: &str) -> bool {
+    problems().iter().any(|problem| problem.path == path)
+}
@@ -1,227 +1,480 @@
-#![allow(clippy::eval_order_dependence)]
-
-use macroquad::{
-    audio::{load_sound, Sound},
-    miniquad::*,
-    prelude::*,
-};
-use once_cell::sync::Lazy;
-
-use std::path::PathBuf;
-
-pub struct Assets {
-    pub textures: Textures,
-    pub sounds: Sounds,
-    pub shaders: Shaders,
-}
-
-impl Assets {
-    pub async fn init() -> Self {
-        Self {
-            textures: Textures::init().await,
-            sounds: Sounds::init().await,
-            shaders: Shaders::init().await,
-        }
-    }
-}
-
-pub struct Textures {
-    pub fonts: Fonts,
-
-    pub title_banner: Texture2D,
-    pub billboard_patch9: Texture2D,
-
-    pub title_logo: Texture2D,
-    pub title_stencil: Texture2D,
-    pub marble_atlas: Texture2D,
-}
-
-impl Textures {
-    async fn init() -> Self {
-        Self {
-            fonts: Fonts::init().await,
-            title_banner: texture("splash/banner").await,
-            billboard_patch9: texture("ui/billboard_patch9").await,
-            title_logo: texture("splash").await,
-            title_stencil: texture("splash_stencil").await,
-            marble_atlas: texture("marbles").await,
-        }
-    }
-}
-
-pub struct Fonts {
-    pub small: Texture2D,
-    pub medium: Texture2D,
-}
-
-impl Fonts {
-    async fn init() -> Self {
-        Self {
-            small: texture("ui/font_small").await,
-            medium: texture("ui/font_medium").await,
-        }
-    }
-}
-
-pub struct Sounds {
-    pub splash_jingle: Sound,
-
-    pub title_music: Sound,
-    pub end_jingle: Sound,
-
-    pub music0: Sound,
-    pub music1: Sound,
-    pub music2: Sound,
-
-    pub select: Sound,
-    pub close_loop: Sound,
-    pub shunt: Sound,
-    pub clear1: Sound,
-    pub clear2: Sound,
-    pub clear3: Sound,
-    pub clear4: Sound,
-    pub clear5: Sound,
-    pub clear_all: Sound,
-}
-
-impl Sounds {
-    async fn init() -> Self {
-        Self {
-            splash_jingle: sound("splash/jingle").await,
-
-            title_music: sound("music/title").await,
-            end_jingle: sound("music/ending").await,
-
-            music0: sound("music/music0").await,
-            music1: sound("music/music1").await,
-            music2: sound("music/music2").await,
-
-            select: sound("sfx/select").await,
-            close_loop: sound("sfx/close_loop").await,
-            shunt: sound("sfx/shunt").await,
-            clear1: sound("sfx/clear1").await,
-            clear2: sound("sfx/clear2").await,
-            clear3: sound("sfx/clear3").await,
-            clear4: sound("sfx/clear4").await,
-            clear5: sound("sfx/clear5").await,
-            clear_all: sound("sfx/clear_all").await,
-        }
-    }
-}
-
-pub struct Shaders {
-    pub pattern_beam: Material,
-    pub noise: Material,
-}
-
-impl Shaders {
-    async fn init() -> Self {
-        Self {
-            pattern_beam: material_vert_frag(
-                "standard",
-                "pattern_beam",
-                MaterialParams {
-                    textures: Vec::new(),
-                    uniforms: Vec::new(),
-                    pipeline_params: PipelineParams {
-                        color_blend: Some(BlendState::new(
-                            Equation::Add,
-                            BlendFactor::Value(BlendValue::SourceAlpha),
-                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
-                        )),
-                        ..Default::default()
-                    },
-                },
-            )
-            .await,
-            noise: material_vert_frag(
-                "standard",
-                "noise",
-                MaterialParams {
-                    textures: Vec::new(),
-                    uniforms: Vec::new(),
-                    pipeline_params: PipelineParams {
-                        color_blend: Some(BlendState::new(
-                            Equation::Add,
-                            BlendFactor::Value(BlendValue::SourceAlpha),
-                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
-                        )),
-                        ..Default::default()
-                    },
-                },
-            )
-            .await,
-        }
-    }
-}
-
-/// Path to the assets root
-static ASSETS_ROOT: Lazy<PathBuf> = Lazy::new(|| {
-    if cfg!(target_arch = "wasm32") {
-        PathBuf::from("./assets")
-    } else if cfg!(target_os = "android") {
-        // does have to be "" and not "."
-        // i guess android doesn't like dots in its paths
-        // probably rolls its own filesystem path impl
-        PathBuf::from("")
-    } else if cfg!(debug_assertions) {
-        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"))
-    } else {
-        todo!("assets path for release hasn't been finalized yet ;-;")
-    }
-});
-
-async fn texture(path: &str) -> Texture2D {
-    let with_extension = path.to_owned() + ".png";
-    let tex = load_texture(
-        ASSETS_ROOT
-            .join("textures")
-            .join(with_extension)
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
-    tex.set_filter(FilterMode::Nearest);
-    tex
-}
-
-async fn sound(path: &str) -> Sound {
-    let with_extension = path.to_owned() + ".ogg";
-    load_sound(
-        ASSETS_ROOT
-            .join("sounds")
-            .join(with_extension)
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap()
-}
-
-async fn material_vert_frag(vert_stub: &str, frag_stub: &str, params: MaterialParams) -> Material {
-    let full_stub = ASSETS_ROOT.join("shaders");
-    let vert = load_string(
-        full_stub
-            .join(vert_stub)
-            .with_extension("vert")
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
-    let frag = load_string(
-        full_stub
-            .join(frag_stub)
-            .with_extension("frag")
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
-    load_material(&vert, &frag, params).unwrap()
-}
-
-async fn material(path_stub: &str, params: MaterialParams) -> Material {
-    material_vert_frag(path_stub, path_stub, params).await
-}
+#![allow(clippy::eval_order_dependence)]
+
+mod hash;
+pub mod manifest;
+mod sfx;
+
+pub use sfx::{SfxEvent, SfxTable};
+
+use macroquad::{miniquad::*, prelude::*};
+use once_cell::sync::{Lazy, OnceCell};
+
+use std::path::PathBuf;
+
+use crate::{
+    audio::{Audio, Sound},
+    utils::{config::CONFIG, effects::heavy_effects},
+};
+
+pub struct Assets {
+    pub audio: Audio,
+    pub textures: Textures,
+    pub sounds: Sounds,
+    pub sfx: SfxTable,
+    pub shaders: Shaders,
+}
+
+impl Assets {
+    /// Load everything needed to get to the title screen.
+    ///
+    /// The rest loads afterwards with `load_deferred`, while the title's already up.
+    pub async fn init() -> Self {
+        let audio = Audio::new();
+        let textures = Textures::init().await;
+        let sounds = Sounds::init(&audio).await;
+        let sfx = SfxTable::init(&audio).await;
+        Self {
+            audio,
+            textures,
+            sounds,
+            sfx,
+            shaders: Shaders::init().await,
+        }
+    }
+
+    /// Load the assets that aren't needed right away, like the game music.
+    pub async fn load_deferred(&self) {
+        self.sounds.load_deferred(&self.audio).await;
+        self.sfx.load_deferred(&self.audio).await;
+    }
+
+    /// Play whatever sound goes with the event.
+    pub fn play_sfx(&self, event: SfxEvent) {
+        self.sfx.play(&self.audio, event, 0.0);
+    }
+
+    /// Play whatever sound goes with the event, leaning toward where on the canvas it happened.
+    pub fn play_sfx_at(&self, event: SfxEvent, x: f32) {
+        // Only lean partway so nothing ends up in just one ear
+        let pan = (x / crate::width() * 2.0 - 1.0).clamp(-1.0, 1.0) * 0.5;
+        self.sfx.play(&self.audio, event, pan);
+    }
+}
+
+pub struct Textures {
+    pub fonts: Fonts,
+
+    pub title_banner: Texture2D,
+    pub billboard_patch9: Texture2D,
+
+    pub title_logo: Texture2D,
+    pub title_stencil: Texture2D,
+    pub marble_atlas: Texture2D,
+}
+
+impl Textures {
+    async fn init() -> Self {
+        Self {
+            fonts: Fonts::init().await,
+            title_banner: texture("splash/banner").await,
+            billboard_patch9: texture("ui/billboard_patch9").await,
+            title_logo: texture("splash").await,
+            title_stencil: texture("splash_stencil").await,
+            marble_atlas: texture("marbles").await,
+        }
+    }
+}
+
+pub struct Fonts {
+    pub small: Texture2D,
+    pub medium: Texture2D,
+}
+
+impl Fonts {
+    async fn init() -> Self {
+        Self {
+            small: texture("ui/font_small").await,
+            medium: texture("ui/font_medium").await,
+        }
+    }
+}
+
+/// An asset that loads in the background once the title's up.
+///
+/// Until it's in, `get` returns `None` and whatever wanted it has to make do without.
+pub struct Deferred<T>(OnceCell<T>);
+
+impl<T: Copy> Deferred<T> {
+    fn new() -> Self {
+        Self(OnceCell::new())
+    }
+
+    pub fn get(&self) -> Option<T> {
+        self.0.get().copied()
+    }
+
+    fn fill(&self, value: T) {
+        // It only gets loaded once, so this can't already be full
+        let _ = self.0.set(value);
+    }
+}
+
+pub struct Sounds {
+    pub splash_jingle: Sound,
+
+    pub title_music: Sound,
+
+    pub music0: Deferred<Sound>,
+    pub music1: Deferred<Sound>,
+    pub music2: Deferred<Sound>,
+
+    /// A soft tone for each color of marble, in the same order as `Marble`
+    pub tones: Deferred<[Sound; 7]>,
+}
+
+impl Sounds {
+    async fn init(audio: &Audio) -> Self {
+        Self {
+            splash_jingle: sound(audio, "splash/jingle").await,
+
+            title_music: sound(audio, "music/title").await,
+
+            music0: Deferred::new(),
+            music1: Deferred::new(),
+            music2: Deferred::new(),
+
+            tones: Deferred::new(),
+        }
+    }
+
+    async fn load_deferred(&self, audio: &Audio) {
+        // Music first, since every run wants some and the tones are optional
+        self.music0.fill(sound(audio, "music/music0").await);
+        self.music1.fill(sound(audio, "music/music1").await);
+        self.music2.fill(sound(audio, "music/music2").await);
+
+        self.tones.fill([
+            sound_with_extension(audio, "tones/red", "wav").await,
+            sound_with_extension(audio, "tones/green", "wav").await,
+            sound_with_extension(audio, "tones/blue", "wav").await,
+            sound_with_extension(audio, "tones/yellow", "wav").await,
+            sound_with_extension(audio, "tones/cyan", "wav").await,
+            sound_with_extension(audio, "tones/purple", "wav").await,
+            sound_with_extension(audio, "tones/pink", "wav").await,
+        ]);
+    }
+}
+
+pub struct Shaders {
+    pub pattern_beam: Material,
+    pub noise: Material,
+    /// Post-processing for the whole canvas to make it look like an old screen
+    pub crt: Material,
+    /// Blurs and greys out the game behind the pause screen
+    pub pause: Material,
+    /// Some shader wouldn't compile, so the fancy effects are off.
+    /// Anything that failed got swapped for one that draws things as-is.
+    pub reduced_effects: bool,
+}
+
+impl Shaders {
+    /// Whether to draw with these at all, or skip the effects they're for.
+    pub fn enabled(&self) -> bool {
+        !self.reduced_effects && heavy_effects()
+    }
+
+    async fn init() -> Self {
+        let mut reduced_effects = false;
+        Self {
+            pattern_beam: material_or_fallback(
+                "standard",
+                "pattern_beam",
+                "standard",
+                "pattern_beam",
+                MaterialParams {
+                    textures: Vec::new(),
+                    uniforms: vec![("Width".to_owned(), UniformType::Float1)],
+                    pipeline_params: PipelineParams {
+                        color_blend: Some(BlendState::new(
+                            Equation::Add,
+                            BlendFactor::Value(BlendValue::SourceAlpha),
+                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                        )),
+                        ..Default::default()
+                    },
+                },
+                &mut reduced_effects,
+            )
+            .await,
+            noise: material_or_fallback(
+                "standard",
+                "noise",
+                MaterialParams {
+                    textures: Vec::new(),
+                    uniforms: Vec::new(),
+                    pipeline_params: PipelineParams {
+                        color_blend: Some(BlendState::new(
+                            Equation::Add,
+                            BlendFactor::Value(BlendValue::SourceAlpha),
+                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                        )),
+                        ..Default::default()
+                    },
+                },
+                &mut reduced_effects,
+            )
+            .await,
+            crt: material_or_fallback(
+                "standard",
+                "crt",
+                MaterialParams {
+                    textures: Vec::new(),
+                    uniforms: vec![("CanvasSize".to_owned(), UniformType::Float2)],
+                    pipeline_params: PipelineParams::default(),
+                },
+                &mut reduced_effects,
+            )
+            .await,
+            pause: material_or_fallback(
+                "standard",
+                "pause",
+                MaterialParams {
+                    textures: Vec::new(),
+                    uniforms: vec![("CanvasSize".to_owned(), UniformType::Float2)],
+                    pipeline_params: PipelineParams::default(),
+                },
+                &mut reduced_effects,
+            )
+            .await,
+            reduced_effects,
+        }
+    }
+}
+
+/// Path to the assets root
+static ASSETS_ROOT: Lazy<PathBuf> = Lazy::new(|| {
+    if let Some(path) = &CONFIG.assets_path {
+        path.clone()
+    } else if cfg!(target_arch = "wasm32") {
+        PathBuf::from("./assets")
+    } else if cfg!(target_os = "android") {
+        // does have to be "" and not "."
+        // i guess android doesn't like dots in its paths
+        // probably rolls its own filesystem path impl
+        PathBuf::from("")
+    } else if cfg!(debug_assertions) {
+        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"))
+    } else {
+        todo!("assets path for release hasn't been finalized yet ;-;")
+    }
+});
+
+async fn texture(path: &str) -> Texture2D {
+    let with_extension = path.to_owned() + ".png";
+    if manifest::is_broken(&format!("textures/{}", with_extension)) {
+        return placeholder_texture();
+    }
+    let tex = match load_texture(
+        ASSETS_ROOT
+            .join("textures")
+            .join(&with_extension)
+            .to_string_lossy()
+            .as_ref(),
+    )
+    .await
+    {
+        Ok(tex) => tex,
+        Err(oh_no) => {
+            warn!(
+                "Couldn't load texture {}! Using a placeholder...\n{:?}",
+                with_extension, oh_no
+            );
+            return placeholder_texture();
+        }
+    };
+    tex.set_filter(FilterMode::Nearest);
+    tex
+}
+
+async fn sound(audio: &Audio, path: &str) -> Sound {
+    sound_with_extension(audio, path, "ogg").await
+}
+
+async fn sound_with_extension(audio: &Audio, path: &str, extension: &str) -> Sound {
+    sound_file(audio, &format!("{}.{}", path, extension)).await
+}
+
+/// Load a sound from the path, including the extension
+async fn sound_file(audio: &Audio, path: &str) -> Sound {
+    if manifest::is_broken(&format!("sounds/{}", path)) {
+        return placeholder_sound(audio).await;
+    }
+    let bytes = load_file(
+        ASSETS_ROOT
+            .join("sounds")
+            .join(path)
+            .to_string_lossy()
+            .as_ref(),
+    )
+    .await
+    .map_err(anyhow::Error::from);
+    let sound = match bytes {
+        Ok(bytes) => audio.load(&bytes).await,
+        Err(oh_no) => Err(oh_no),
+    };
+    match sound {
+        Ok(sound) => sound,
+        Err(oh_no) => {
+            warn!(
+                "Couldn't load sound {}! Using a placeholder...\n{:?}",
+                path, oh_no
+            );
+            placeholder_sound(audio).await
+        }
+    }
+}
+
+/// A magenta and black checkerboard, to stand in for a broken texture.
+/// It's hard to miss, which is the point.
+fn placeholder_texture() -> Texture2D {
+    const SIZE: u16 = 16;
+    let bytes = (0..SIZE * SIZE)
+        .flat_map(|idx| {
+            let (x, y) = (idx % SIZE, idx / SIZE);
+            if (x / 4 + y / 4) % 2 == 0 {
+                [0xff, 0x00, 0xff, 0xff]
+            } else {
+                [0x00, 0x00, 0x00, 0xff]
+            }
+        })
+        .collect::<Vec<_>>();
+    let tex = Texture2D::from_rgba8(SIZE, SIZE, &bytes);
+    tex.set_filter(FilterMode::Nearest);
+    tex
+}
+
+/// A moment of silence, to stand in for a broken sound.
+async fn placeholder_sound(audio: &Audio) -> Sound {
+    const SAMPLE_RATE: u32 = 22050;
+    // A tenth of a second of 16-bit mono
+    const SAMPLES: u32 = SAMPLE_RATE / 10;
+    const DATA_LEN: u32 = SAMPLES * 2;
+
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + DATA_LEN).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, 1 channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // Bytes per second, bytes per sample, bits per sample
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&DATA_LEN.to_le_bytes());
+    wav.resize(wav.len() + DATA_LEN as usize, 0);
+
+    audio
+        .load(&wav)
+        .await
+        .expect("the placeholder sound should always load")
+}
+
+/// Shaders that just draw things as they are, for when the real ones won't compile.
+/// These are built in so there's nothing to fail to load.
+const FALLBACK_VERT: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1.0);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+const FALLBACK_FRAG: &str = r#"#version 100
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}"#;
+
+/// Load a material, or the do-nothing fallback if it won't load or compile.
+/// If it falls back `reduced_effects` gets set.
+async fn material_or_fallback(
+    vert_stub: &str,
+    frag_stub: &str,
+    params: MaterialParams,
+    reduced_effects: &mut bool,
+) -> Material {
+    let broken = [(vert_stub, "vert"), (frag_stub, "frag")]
+        .iter()
+        .any(|(stub, ext)| manifest::is_broken(&format!("shaders/{}.{}", stub, ext)));
+
+    // `MaterialParams` isn't `Clone`, so keep a copy by hand for the fallback
+    let backup = MaterialParams {
+        textures: params.textures.clone(),
+        uniforms: params.uniforms.clone(),
+        pipeline_params: params.pipeline_params,
+    };
+    let material = if broken {
+        Err("it failed the integrity check".to_owned())
+    } else {
+        material_vert_frag(vert_stub, frag_stub, params).await
+    };
+    match material {
+        Ok(material) => material,
+        Err(oh_no) => {
+            warn!(
+                "Couldn't load shader {}! Turning effects down...\n{}",
+                frag_stub, oh_no
+            );
+            *reduced_effects = true;
+            load_material(FALLBACK_VERT, FALLBACK_FRAG, backup)
+                .expect("the fallback shader should always compile")
+        }
+    }
+}
+
+async fn material_vert_frag(
+    vert_stub: &str,
+    frag_stub: &str,
+    params: MaterialParams,
+) -> Result<Material, String> {
+    let full_stub = ASSETS_ROOT.join("shaders");
+    let vert = load_string(
+        full_stub
+            .join(vert_stub)
+            .with_extension("vert")
+            .to_string_lossy()
+            .as_ref(),
+    )
+    .await
+    .map_err(|e| format!("{:?}", e))?;
+    let frag = load_string(
+        full_stub
+            .join(frag_stub)
+            .with_extension("frag")
+            .to_string_lossy()
+            .as_ref(),
+    )
+    .await
+    .map_err(|e| format!("{:?}", e))?;
+    load_material(&vert, &frag, params).map_err(|e| format!("{:?}", e))
+}
+
+async fn material(path_stub: &str, params: MaterialParams) -> Result<Material, String> {
+    material_vert_frag(path_stub, path_stub, params).await
+}
@@ -0,0 +1,245 @@
+//! Which sound plays for what, loaded from `sounds/sfx.ron`
+//! so the sounds can be swapped out without recompiling.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use macroquad::prelude::*;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::audio::{Audio, AudioBackend, PlayParams, Sound, VoiceLimiter};
+
+use super::{manifest, sound_file, ASSETS_ROOT};
+
+/// The table that ships with the game, for if the one on disk is missing or broken.
+const BUILTIN_TABLE: &str = include_str!("../../assets/sounds/sfx.ron");
+/// Long cascades stop getting higher past here so they don't turn into a whistle
+const MAX_CASCADE_PITCH: f32 = 4.0;
+
+/// Things that happen that make a noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum SfxEvent {
+    /// The mouse moved onto a button
+    ButtonHover,
+    /// A button got clicked
+    ButtonClick,
+    /// Backing out of a menu
+    MenuBack,
+
+    /// The pattern being drawn got longer
+    PatternExtend,
+    /// The pattern being drawn closed into a loop
+    PatternClose,
+    /// Marbles started moving along a loop
+    Cycle,
+    /// A loop got called off because cells on it emptied out before it went
+    Fizzle,
+    /// Blobs got cleared, at the given cascade level starting at 1.
+    ///
+    /// If `Clear(1)` has a `cascade_step`, its sound gets pitched up for each level.
+    /// Otherwise, or if pitching isn't possible, each level plays its own sound
+    /// and levels past the highest one in the table play the highest one.
+    Clear(u32),
+    /// A hexagon deleted every marble of a color
+    DeleteColor,
+    /// A clear's biggest blob reached the given rung of the blob size ladder, starting at 1
+    LadderTier(u32),
+
+    /// The run ended
+    GameOver,
+    /// The run beat the high score
+    NewBest,
+}
+
+impl SfxEvent {
+    /// Does this only come up now and then, well into a run?
+    /// The sounds for these load in the background after the title shows up.
+    fn is_rare(&self) -> bool {
+        matches!(
+            self,
+            SfxEvent::DeleteColor
+                | SfxEvent::LadderTier(_)
+                | SfxEvent::GameOver
+                | SfxEvent::NewBest
+        )
+    }
+}
+
+/// One line of the table, as written in the file.
+#[derive(Debug, Clone, Deserialize)]
+struct SfxEntry {
+    /// Path from the `sounds` folder, with the extension
+    sound: String,
+    #[serde(default = "default_volume")]
+    volume: f32,
+    /// A random playback speed is picked from this range each time
+    #[serde(default = "default_pitch")]
+    pitch: (f32, f32),
+    /// How much faster the sound plays for each cascade level past the first.
+    /// Only means anything on `Clear(1)`.
+    #[serde(default)]
+    cascade_step: Option<f32>,
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_pitch() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LoadedSfx {
+    sound: Sound,
+    volume: f32,
+    pitch: (f32, f32),
+    cascade_step: Option<f32>,
+}
+
+pub struct SfxTable {
+    entries: HashMap<SfxEvent, LoadedSfx>,
+    /// Sounds for the rare events, once they're loaded
+    rare: OnceCell<HashMap<SfxEvent, LoadedSfx>>,
+    /// Rare events waiting to be loaded into `rare`
+    pending: Mutex<Vec<(SfxEvent, SfxEntry)>>,
+    /// Every file loaded so far, so the rare events can share them too
+    files: Mutex<HashMap<String, Sound>>,
+    voices: VoiceLimiter<Sound>,
+}
+
+impl SfxTable {
+    pub(super) async fn init(audio: &Audio) -> Self {
+        let path = ASSETS_ROOT.join("sounds").join("sfx.ron");
+        let src = if manifest::is_broken("sounds/sfx.ron") {
+            Err("it failed the integrity check".to_owned())
+        } else {
+            load_string(path.to_string_lossy().as_ref())
+                .await
+                .map_err(|e| format!("{:?}", e))
+        };
+        let table = match src {
+            Ok(src) => match ron::from_str::<HashMap<SfxEvent, SfxEntry>>(&src) {
+                Ok(table) => table,
+                Err(oh_no) => {
+                    warn!(
+                        "Couldn't parse the sfx table! Using the builtin one...\n{:?}",
+                        oh_no
+                    );
+                    ron::from_str(BUILTIN_TABLE).unwrap()
+                }
+            },
+            Err(oh_no) => {
+                warn!(
+                    "Couldn't load the sfx table! Using the builtin one...\n{}",
+                    oh_no
+                );
+                ron::from_str(BUILTIN_TABLE).unwrap()
+            }
+        };
+
+        let (pending, common): (Vec<_>, Vec<_>) =
+            table.into_iter().partition(|(event, _)| event.is_rare());
+        let mut files = HashMap::new();
+        let entries = load_entries(audio, common, &mut files).await;
+
+        Self {
+            entries,
+            rare: OnceCell::new(),
+            pending: Mutex::new(pending),
+            files: Mutex::new(files),
+            voices: VoiceLimiter::new(),
+        }
+    }
+
+    /// Load the sounds for the rare events. Until this is done, they don't make any noise.
+    pub(super) async fn load_deferred(&self, audio: &Audio) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut files = std::mem::take(&mut *self.files.lock().unwrap());
+        let rare = load_entries(audio, pending, &mut files).await;
+        let _ = self.rare.set(rare);
+    }
+
+    fn get(&self, event: &SfxEvent) -> Option<&LoadedSfx> {
+        self.entries
+            .get(event)
+            .or_else(|| self.rare.get().and_then(|rare| rare.get(event)))
+    }
+
+    /// Play whatever sound goes with the event. If there isn't one, nothing happens.
+    /// `pan` goes from -1.0 on the left to 1.0 on the right.
+    pub fn play(&self, audio: &Audio, event: SfxEvent, pan: f32) {
+        let (sfx, pitch_scale) = match event {
+            SfxEvent::Clear(level) => {
+                let level = level.max(1);
+                let pitched = self
+                    .get(&SfxEvent::Clear(1))
+                    .filter(|_| audio.can_pitch())
+                    .and_then(|sfx| sfx.cascade_step.map(|step| (sfx, step)));
+                match pitched {
+                    Some((sfx, step)) => (
+                        Some(sfx),
+                        step.powi(level as i32 - 1).min(MAX_CASCADE_PITCH),
+                    ),
+                    None => (
+                        (1..=level)
+                            .rev()
+                            .find_map(|level| self.get(&SfxEvent::Clear(level))),
+                        1.0,
+                    ),
+                }
+            }
+            _ => (self.get(&event), 1.0),
+        };
+        if let Some(sfx) = sfx {
+            let volume = match self.voices.admit(sfx.sound, sfx.volume) {
+                Some(it) => it,
+                // Too many of it are going off already
+                None => return,
+            };
+            let (lo, hi) = sfx.pitch;
+            let pitch = if lo < hi { rand::gen_range(lo, hi) } else { lo };
+            audio.play(
+                sfx.sound,
+                PlayParams {
+                    volume,
+                    pitch: pitch * pitch_scale,
+                    pan,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
+/// Load the sound for each entry in the table.
+///
+/// Lots of events share sounds, so `files` keeps track of what's been loaded
+/// and each file only gets loaded once.
+async fn load_entries(
+    audio: &Audio,
+    table: Vec<(SfxEvent, SfxEntry)>,
+    files: &mut HashMap<String, Sound>,
+) -> HashMap<SfxEvent, LoadedSfx> {
+    let mut entries = HashMap::new();
+    for (event, entry) in table {
+        let sound = match files.get(&entry.sound) {
+            Some(sound) => *sound,
+            None => {
+                let sound = sound_file(audio, &entry.sound).await;
+                files.insert(entry.sound.clone(), sound);
+                sound
+            }
+        };
+        entries.insert(
+            event,
+            LoadedSfx {
+                sound,
+                volume: entry.volume,
+                pitch: entry.pitch,
+                cascade_step: entry.cascade_step,
+            },
+        );
+    }
+    entries
+}
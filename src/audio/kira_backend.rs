@@ -0,0 +1,187 @@
+use std::{
+    io::Cursor,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use kira::{
+    instance::{handle::InstanceHandle, InstanceLoopStart, InstanceSettings, StopInstanceSettings},
+    manager::{AudioManager, AudioManagerSettings},
+    sound::{handle::SoundHandle, Sound, SoundSettings},
+};
+use macroquad::{prelude::warn, time::get_time};
+
+use super::{music_volume, sfx_volume, AudioBackend, PlayParams};
+
+/// Index into the backend's list of loaded sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KiraSound(usize);
+
+/// Plays everything through kira, which can do pitch and panning.
+///
+/// Sounds get played from both the update and draw threads, so everything that changes
+/// is behind a lock.
+pub struct KiraBackend {
+    /// `None` if there's no audio device to play on, in which case everything's silent
+    state: Option<Mutex<MixerState>>,
+    /// How far music is ducked, as the bits of an `f32`
+    duck: AtomicU32,
+}
+
+struct MixerState {
+    manager: AudioManager,
+    sounds: Vec<SoundHandle>,
+    /// Looping sounds that are playing, how loud they're supposed to be,
+    /// and when they would have started if they'd started from the beginning
+    looping: Vec<(KiraSound, InstanceHandle, f32, f64)>,
+    /// How far in each looping sound was when it got stopped
+    stopped: Vec<(KiraSound, f64)>,
+}
+
+impl KiraBackend {
+    pub fn new() -> Self {
+        // Like macroquad's mixer, stay quiet instead of crashing if there's nothing to play on
+        let state = match AudioManager::new(AudioManagerSettings::default()) {
+            Ok(manager) => Some(Mutex::new(MixerState {
+                manager,
+                sounds: Vec::new(),
+                looping: Vec::new(),
+                stopped: Vec::new(),
+            })),
+            Err(oh_no) => {
+                warn!(
+                    "Couldn't start audio! Playing without sound...\n{:?}",
+                    oh_no
+                );
+                None
+            }
+        };
+        Self {
+            state,
+            duck: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    fn duck(&self) -> f32 {
+        f32::from_bits(self.duck.load(Ordering::Relaxed))
+    }
+
+    fn lock(&self) -> Option<MutexGuard<MixerState>> {
+        Some(self.state.as_ref()?.lock().unwrap())
+    }
+
+    /// Load a sound from the bytes of a file. The format is guessed from the first few bytes.
+    pub async fn load(&self, bytes: &[u8]) -> anyhow::Result<KiraSound> {
+        if self.state.is_none() {
+            // Nothing's ever going to play it, so don't bother decoding it
+            return Ok(KiraSound(0));
+        }
+        let reader = Cursor::new(bytes.to_vec());
+        let settings = SoundSettings::default();
+        let sound = if bytes.starts_with(b"OggS") {
+            Sound::from_ogg_reader(reader, settings)?
+        } else if bytes.starts_with(b"RIFF") {
+            Sound::from_wav_reader(reader, settings)?
+        } else if bytes.starts_with(b"fLaC") {
+            Sound::from_flac_reader(reader, settings)?
+        } else {
+            Sound::from_mp3_reader(reader, settings)?
+        };
+        let mut state = self.lock().unwrap();
+        let handle = state.manager.add_sound(sound)?;
+        state.sounds.push(handle);
+        Ok(KiraSound(state.sounds.len() - 1))
+    }
+}
+
+impl AudioBackend for KiraBackend {
+    type Sound = KiraSound;
+
+    fn play(&self, sound: KiraSound, params: PlayParams) {
+        let mut guard = match self.lock() {
+            Some(it) => it,
+            None => return,
+        };
+        let volume = if params.looped {
+            params.volume * self.duck() * music_volume()
+        } else {
+            params.volume * sfx_volume()
+        };
+        let mut settings = InstanceSettings::new()
+            .volume(volume as f64)
+            .playback_rate(params.pitch as f64)
+            // kira goes from 0 to 1 with the middle at 0.5
+            .panning((params.pan as f64 + 1.0) / 2.0);
+        if params.looped {
+            settings = settings.loop_start(InstanceLoopStart::Custom(0.0));
+        }
+        let state = &mut *guard;
+        let handle = &mut state.sounds[sound.0];
+        if params.start_at > 0.0 {
+            // It might have looped around a few times since then
+            settings = settings.start_position(params.start_at % handle.duration());
+        }
+
+        match handle.play(settings) {
+            Ok(instance) if params.looped => {
+                state.looping.retain(|(other, _, _, _)| *other != sound);
+                state
+                    .looping
+                    .push((sound, instance, params.volume, get_time() - params.start_at));
+            }
+            Ok(_) => {}
+            Err(oh_no) => warn!("Couldn't play sound!\n{:?}", oh_no),
+        }
+    }
+
+    fn stop(&self, sound: KiraSound) {
+        let mut guard = match self.lock() {
+            Some(it) => it,
+            None => return,
+        };
+        let state = &mut *guard;
+        if let Some((_, _, _, started)) = state
+            .looping
+            .iter()
+            .find(|(other, _, _, _)| *other == sound)
+        {
+            state.stopped.retain(|(other, _)| *other != sound);
+            state.stopped.push((sound, get_time() - started));
+        }
+        state.looping.retain(|(other, _, _, _)| *other != sound);
+        if let Err(oh_no) = state.sounds[sound.0].stop(StopInstanceSettings::new()) {
+            warn!("Couldn't stop sound!\n{:?}", oh_no);
+        }
+    }
+
+    fn can_pitch(&self) -> bool {
+        true
+    }
+
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    fn stopped_at(&self, sound: KiraSound) -> Option<f64> {
+        self.lock()?
+            .stopped
+            .iter()
+            .find(|(other, _)| *other == sound)
+            .map(|(_, at)| *at)
+    }
+
+    fn duck_music(&self, amount: f32) {
+        self.duck.store(amount.to_bits(), Ordering::Relaxed);
+        let mut state = match self.lock() {
+            Some(it) => it,
+            None => return,
+        };
+        for (_, instance, volume, _) in state.looping.iter_mut() {
+            if let Err(oh_no) = instance.set_volume((*volume * amount * music_volume()) as f64) {
+                warn!("Couldn't set volume!\n{:?}", oh_no);
+            }
+        }
+    }
+}
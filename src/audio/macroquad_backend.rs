@@ -0,0 +1,106 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+};
+
+use macroquad::{
+    audio::{self as mq, PlaySoundParams},
+    time::get_time,
+};
+
+use super::{music_volume, sfx_volume, AudioBackend, PlayParams};
+
+/// Plays everything through macroquad's mixer. It can't change pitch or panning,
+/// or start partway through a sound.
+///
+/// Sounds get played from both the update and draw threads, so everything that changes
+/// is behind a lock.
+pub struct MacroquadBackend {
+    state: Mutex<MixerState>,
+    /// How far music is ducked, as the bits of an `f32`
+    duck: AtomicU32,
+}
+
+#[derive(Default)]
+struct MixerState {
+    /// Looping sounds that are playing, how loud they're supposed to be, and when they started
+    looping: Vec<(mq::Sound, f32, f64)>,
+    /// How far in each looping sound was when it got stopped
+    stopped: Vec<(mq::Sound, f64)>,
+}
+
+impl MacroquadBackend {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MixerState::default()),
+            duck: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    /// Load a sound from the bytes of a file.
+    pub async fn load(&self, bytes: &[u8]) -> anyhow::Result<mq::Sound> {
+        Ok(mq::load_sound_from_bytes(bytes).await?)
+    }
+
+    fn duck(&self) -> f32 {
+        f32::from_bits(self.duck.load(Ordering::Relaxed))
+    }
+}
+
+impl AudioBackend for MacroquadBackend {
+    type Sound = mq::Sound;
+
+    fn play(&self, sound: mq::Sound, params: PlayParams) {
+        let volume = if params.looped {
+            let mut state = self.state.lock().unwrap();
+            state.looping.retain(|(other, _, _)| *other != sound);
+            state.looping.push((sound, params.volume, get_time()));
+            params.volume * self.duck() * music_volume()
+        } else {
+            params.volume * sfx_volume()
+        };
+        mq::play_sound(
+            sound,
+            PlaySoundParams {
+                looped: params.looped,
+                volume,
+            },
+        );
+    }
+
+    fn stop(&self, sound: mq::Sound) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if let Some((_, _, started)) = state.looping.iter().find(|(other, _, _)| *other == sound) {
+            state.stopped.retain(|(other, _)| *other != sound);
+            state.stopped.push((sound, get_time() - started));
+        }
+        state.looping.retain(|(other, _, _)| *other != sound);
+        mq::stop_sound(sound);
+    }
+
+    fn can_pitch(&self) -> bool {
+        false
+    }
+
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    fn stopped_at(&self, sound: mq::Sound) -> Option<f64> {
+        self.state
+            .lock()
+            .unwrap()
+            .stopped
+            .iter()
+            .find(|(other, _)| *other == sound)
+            .map(|(_, at)| *at)
+    }
+
+    fn duck_music(&self, amount: f32) {
+        self.duck.store(amount.to_bits(), Ordering::Relaxed);
+        for (sound, volume, _) in self.state.lock().unwrap().looping.iter() {
+            mq::set_sound_volume(*sound, volume * amount * music_volume());
+        }
+    }
+}
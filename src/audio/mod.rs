@@ -0,0 +1,207 @@
+//! Everything that makes noise goes through here, so the mixer underneath can be swapped out.
+//!
+//! By default that's macroquad's. Building with the `kira` feature uses kira instead,
+//! which can also do pitch and panning.
+
+#[cfg(feature = "kira")]
+mod kira_backend;
+#[cfg(not(feature = "kira"))]
+mod macroquad_backend;
+
+#[cfg(feature = "kira")]
+pub use kira_backend::KiraBackend as Audio;
+#[cfg(not(feature = "kira"))]
+pub use macroquad_backend::MacroquadBackend as Audio;
+
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+use macroquad::{rand, time::get_time};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Cyclable;
+
+/// The same sound can't start again until this many seconds after it last did
+const MIN_REPLAY_GAP: f64 = 0.06;
+/// How long a sound counts as still playing, for limiting voices, in seconds
+const VOICE_WINDOW: f64 = 0.5;
+/// How many copies of one sound can be playing at once
+const MAX_VOICES: usize = 3;
+/// Each copy of a sound plays up to this fraction quieter, so repeats don't sound robotic
+const VOLUME_JITTER: f32 = 0.15;
+
+/// How loud music is, as an index into `Volume::ALL`.
+/// The backends read this every time they start or duck a looping sound.
+static MUSIC_VOLUME: AtomicU8 = AtomicU8::new(Volume::Full as u8);
+/// How loud everything that isn't music is, as an index into `Volume::ALL`.
+static SFX_VOLUME: AtomicU8 = AtomicU8::new(Volume::Full as u8);
+
+/// Set how loud music is. Music that's already playing gets changed the next time it's ducked.
+pub fn set_music_volume(volume: Volume) {
+    MUSIC_VOLUME.store(volume as u8, Ordering::Relaxed);
+}
+
+/// Set how loud sound effects are, starting with the next one.
+pub fn set_sfx_volume(volume: Volume) {
+    SFX_VOLUME.store(volume as u8, Ordering::Relaxed);
+}
+
+/// What to multiply the volume of looping sounds by
+pub fn music_volume() -> f32 {
+    Volume::ALL[MUSIC_VOLUME.load(Ordering::Relaxed) as usize].gain()
+}
+
+/// What to multiply the volume of everything else by
+pub fn sfx_volume() -> f32 {
+    Volume::ALL[SFX_VOLUME.load(Ordering::Relaxed) as usize].gain()
+}
+
+/// A volume setting. There are only a few steps so they fit on one button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Volume {
+    Off,
+    Low,
+    Medium,
+    Full,
+}
+
+impl Volume {
+    /// What to multiply volumes by
+    pub fn gain(&self) -> f32 {
+        match self {
+            Volume::Off => 0.0,
+            Volume::Low => 0.3,
+            Volume::Medium => 0.6,
+            Volume::Full => 1.0,
+        }
+    }
+}
+
+impl Cyclable for Volume {
+    const ALL: &'static [Self] = &[Volume::Off, Volume::Low, Volume::Medium, Volume::Full];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Volume::Off => "OFF",
+            Volume::Low => "LOW",
+            Volume::Medium => "MED",
+            Volume::Full => "FULL",
+        }
+    }
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume::Full
+    }
+}
+
+/// A loaded sound for whichever backend is in use.
+pub type Sound = <Audio as AudioBackend>::Sound;
+
+/// How to play a sound.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayParams {
+    pub volume: f32,
+    pub looped: bool,
+    /// Playback speed; 2.0 is an octave up. Does nothing unless the backend `can_pitch`.
+    // Macroquad's mixer ignores this and `pan`
+    #[cfg_attr(not(feature = "kira"), allow(dead_code))]
+    pub pitch: f32,
+    /// -1.0 is all the way left and 1.0 is all the way right.
+    #[cfg_attr(not(feature = "kira"), allow(dead_code))]
+    pub pan: f32,
+    /// How many seconds into the sound to start. Does nothing unless the backend `can_seek`.
+    #[cfg_attr(not(feature = "kira"), allow(dead_code))]
+    pub start_at: f64,
+}
+
+impl Default for PlayParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            looped: false,
+            pitch: 1.0,
+            pan: 0.0,
+            start_at: 0.0,
+        }
+    }
+}
+
+/// Something that can play sounds.
+///
+/// Loading is async on some backends, so it's an inherent `load` method on each of them
+/// instead of being in here.
+pub trait AudioBackend {
+    type Sound: Debug + Clone + Copy + PartialEq;
+
+    fn play(&self, sound: Self::Sound, params: PlayParams);
+    /// Stop every copy of the sound that's playing.
+    fn stop(&self, sound: Self::Sound);
+
+    /// Whether `PlayParams::pitch` does anything
+    fn can_pitch(&self) -> bool;
+    /// Whether `PlayParams::start_at` does anything
+    fn can_seek(&self) -> bool;
+
+    /// If the sound was looping the last time it got stopped, how many seconds in it was.
+    ///
+    /// This goes by the clock, so it can be longer than the sound if it looped around.
+    fn stopped_at(&self, sound: Self::Sound) -> Option<f64>;
+
+    /// Turn looping sounds (so, music) down to this fraction of their volume.
+    /// Pass 1.0 to put them back.
+    ///
+    /// This also picks up changes to the music volume setting.
+    fn duck_music(&self, amount: f32);
+
+    fn play_once(&self, sound: Self::Sound) {
+        self.play(sound, PlayParams::default());
+    }
+}
+
+/// Keeps sounds that go off over and over, like clears in a long cascade,
+/// from piling up on top of each other and clipping.
+#[derive(Debug)]
+pub struct VoiceLimiter<S> {
+    /// When each sound last started, for every copy that might still be playing
+    recent: Mutex<Vec<(S, f64)>>,
+}
+
+impl<S: Copy + PartialEq> VoiceLimiter<S> {
+    pub fn new() -> Self {
+        Self {
+            recent: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Check whether another copy of the sound can start right now.
+    ///
+    /// Returns how loud to play it, or `None` to drop it.
+    pub fn admit(&self, sound: S, volume: f32) -> Option<f32> {
+        let now = get_time();
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|(_, start)| now - start < VOICE_WINDOW);
+
+        let mut voices = 0;
+        for (other, start) in recent.iter() {
+            if *other == sound {
+                if now - start < MIN_REPLAY_GAP {
+                    return None;
+                }
+                voices += 1;
+            }
+        }
+        if voices >= MAX_VOICES {
+            return None;
+        }
+
+        recent.push((sound, now));
+        Some(volume * rand::gen_range(1.0 - VOLUME_JITTER, 1.0))
+    }
+}
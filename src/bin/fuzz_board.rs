@@ -0,0 +1,189 @@
+//! Plays random moves on boards for as long as it's told to, checking nothing impossible
+//! ever happens. No window opens; it's just the rules running as fast as they can.
+//!
+//! ```text
+//! cargo run --release --features fuzz --bin fuzz_board -- [--seed N] [--runs N] [--ticks N]
+//! ```
+//!
+//! Every run gets its own seed, counting up from `--seed`, and rolls a random mode and
+//! random mutators from it. If a run breaks `Board::check_invariants` or panics, its seed
+//! gets printed; run again with `--seed` set to it and `--runs 1` to watch it happen again.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use quad_rand::compat::QuadRand;
+use rand::{seq::SliceRandom, Rng};
+
+use haxagon::model::{
+    Board, BoardAction, BoardSettingsModeKey, GravityMode, Marble, Mutator, MutatorSet,
+};
+
+/// A run gets cut off here even if the board never fills, so one good run can't hog the budget
+const MAX_RUN_TICKS: u32 = 200_000;
+/// The pretend player makes a move about once every this many ticks
+const MOVE_INTERVAL: u32 = 20;
+/// One in this many moves is a hexagon instead of a loop
+const HEXAGON_ODDS: u32 = 40;
+/// Each mutator has a one in this many chance of being on for a run
+const MUTATOR_ODDS: u32 = 4;
+
+/// Something that went wrong in a run.
+struct Failure {
+    tick: u32,
+    reason: String,
+}
+
+fn main() {
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let mut max_runs = None;
+    let mut tick_budget: u64 = 10_000_000;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|n| n.parse::<u64>().ok());
+        match (arg.as_str(), value) {
+            ("--seed", Some(n)) => seed = n,
+            ("--runs", Some(n)) => max_runs = Some(n),
+            ("--ticks", Some(n)) => tick_budget = n,
+            _ => {
+                eprintln!("Usage: fuzz_board [--seed N] [--runs N] [--ticks N]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    println!("Fuzzing from seed {}...", seed);
+    let mut ticks_run = 0;
+    let mut runs = 0;
+    let mut failed = Vec::new();
+    while ticks_run < tick_budget && max_runs.map_or(true, |max| runs < max) {
+        let run_seed = seed.wrapping_add(runs);
+        // The panic hook has already printed where it went off
+        match panic::catch_unwind(AssertUnwindSafe(|| fuzz_run(run_seed))) {
+            Ok(Ok(ticks)) => ticks_run += ticks as u64,
+            Ok(Err(failure)) => {
+                eprintln!(
+                    "Seed {} broke on tick {}: {}",
+                    run_seed, failure.tick, failure.reason
+                );
+                ticks_run += failure.tick as u64;
+                failed.push(run_seed);
+            }
+            Err(_) => {
+                eprintln!("Seed {} panicked", run_seed);
+                failed.push(run_seed);
+            }
+        }
+        runs += 1;
+    }
+
+    println!("Ran {} ticks over {} runs", ticks_run, runs);
+    if !failed.is_empty() {
+        println!("{} failed, with seeds {:?}", failed.len(), failed);
+        std::process::exit(1);
+    }
+}
+
+/// Play one board with this seed until it fills up or runs out of time.
+/// Return how many ticks it lasted.
+fn fuzz_run(seed: u64) -> Result<u32, Failure> {
+    quad_rand::srand(seed);
+    let mode = *BoardSettingsModeKey::ALL.choose(&mut QuadRand).unwrap();
+    let mut mutators = MutatorSet::empty();
+    for &mutator in Mutator::ALL.iter() {
+        if QuadRand.gen_range(0..MUTATOR_ODDS) == 0 {
+            mutators.toggle(mutator);
+        }
+    }
+    let mut board = Board::new(mode.settings().with_mutators(mutators));
+
+    let describe = |reason: String| {
+        let names = mutators.iter().map(|m| m.name()).collect::<Vec<_>>();
+        format!("{} {:?}: {}", mode.name(), names, reason)
+    };
+
+    let mut last_score = 0;
+    for tick in 0..MAX_RUN_TICKS {
+        if QuadRand.gen_range(0..MOVE_INTERVAL) == 0 {
+            // A misclick is the only thing allowed to take points away
+            let charged = random_move(&mut board);
+            last_score = last_score.saturating_sub(charged);
+        }
+        let filled = board.tick();
+
+        let check = board.check_invariants().and_then(|()| {
+            if board.score() < last_score {
+                return Err(format!(
+                    "score went down from {} to {}",
+                    last_score,
+                    board.score()
+                ));
+            }
+            // When marbles can spawn anywhere, the board is only full when it's really full
+            let spawns_anywhere = matches!(
+                board.settings().gravity,
+                GravityMode::Outward | GravityMode::None
+            );
+            if filled && spawns_anywhere && board.get_marbles().len() < board.cells().len() {
+                return Err("filled up with empty cells left".to_owned());
+            }
+            Ok(())
+        });
+        if let Err(reason) = check {
+            return Err(Failure {
+                tick,
+                reason: format!("{}\n{}", describe(reason), board.ascii_dump()),
+            });
+        }
+        last_score = board.score();
+
+        if filled {
+            return Ok(tick + 1);
+        }
+    }
+    Ok(MAX_RUN_TICKS)
+}
+
+/// Do something a player could have done: spin a loop, or every now and then set off a hexagon.
+/// Return how many points it cost, if it was a misclick.
+fn random_move(board: &mut Board) -> u32 {
+    // Draw on the board like the player would, with everything queued already played out
+    let future = board.simulate_queue();
+    let action = if QuadRand.gen_range(0..HEXAGON_ODDS) == 0 {
+        let colors = future
+            .get_marbles()
+            .values()
+            .filter(|marble| **marble != Marble::Stone)
+            .collect::<Vec<_>>();
+        match colors.choose(&mut QuadRand) {
+            Some(&color) => BoardAction::DeleteColor(color.clone(), Vec::new()),
+            None => return 0,
+        }
+    } else {
+        match future.candidate_loops(true).choose(&mut QuadRand) {
+            Some(path) => BoardAction::Cycle(path.clone()),
+            None => return 0,
+        }
+    };
+
+    // Charge for loops that won't clear anything the same way the game does
+    let before = board.score();
+    let penalized = board.settings().scoring.misclick_penalty > 0;
+    if penalized
+        && matches!(action, BoardAction::Cycle(_))
+        && future.simulate_action(&action).is_empty()
+    {
+        board.charge_misclick();
+    }
+    let charged = before - board.score();
+
+    board.push_action(action);
+    let premult = board.settings().scoring.player_start_multiplier;
+    board.push_action(BoardAction::ClearBlobs(premult));
+    charged
+}
@@ -20,6 +20,11 @@ pub trait Gamemode: Any {
     /// Gather information about how to draw this state.
     fn get_draw_info(&mut self) -> DrawerBox;
 
+    /// What to call this mode in crash reports
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// Called when the state newly comes on top of the stack,
     /// either from being pushed there or revealed after a pop.
     ///
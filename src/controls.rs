@@ -1,101 +1,166 @@
-use ahash::AHashMap;
-use cogs_gamedev::controls::EventInputHandler;
-use enum_map::Enum;
-use macroquad::{
-    miniquad::{self, Context, KeyMods},
-    prelude::{
-        utils::{register_input_subscriber, repeat_all_miniquad_input},
-        KeyCode, MouseButton,
-    },
-};
-
-/// The controls
-#[derive(Enum, Copy, Clone)]
-pub enum Control {
-    Click,
-    Pause,
-}
-
-/// Combo keycode and mouse button code
-#[derive(Hash, PartialEq, Eq, Copy, Clone)]
-pub enum InputCode {
-    Key(KeyCode),
-    Mouse(MouseButton),
-}
-
-/// Event handler to hook into miniquad and get inputs
-#[derive(Clone)]
-pub struct InputSubscriber {
-    controls: EventInputHandler<InputCode, Control>,
-    subscriber_id: usize,
-}
-
-impl InputSubscriber {
-    pub fn new() -> Self {
-        // the science kid
-        let sid = register_input_subscriber();
-
-        InputSubscriber {
-            controls: EventInputHandler::new(Self::default_controls()),
-            subscriber_id: sid,
-        }
-    }
-
-    pub fn default_controls() -> AHashMap<InputCode, Control> {
-        let mut controls = AHashMap::new();
-
-        // Put your controls here
-        controls.insert(InputCode::Mouse(MouseButton::Left), Control::Click);
-        controls.insert(InputCode::Key(KeyCode::Escape), Control::Pause);
-
-        controls
-    }
-
-    pub fn update(&mut self) {
-        repeat_all_miniquad_input(self, self.subscriber_id);
-        self.controls.update();
-    }
-}
-
-impl std::ops::Deref for InputSubscriber {
-    type Target = EventInputHandler<InputCode, Control>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.controls
-    }
-}
-
-impl miniquad::EventHandler for InputSubscriber {
-    fn update(&mut self, _ctx: &mut Context) {}
-
-    fn draw(&mut self, _ctx: &mut Context) {}
-
-    fn key_down_event(
-        &mut self,
-        _ctx: &mut Context,
-        keycode: KeyCode,
-        _keymods: KeyMods,
-        repeat: bool,
-    ) {
-        if !repeat {
-            self.controls.input_down(InputCode::Key(keycode));
-        }
-    }
-
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
-        self.controls.input_up(InputCode::Key(keycode));
-    }
-
-    fn mouse_button_down_event(
-        &mut self,
-        _ctx: &mut Context,
-        button: MouseButton,
-        _x: f32,
-        _y: f32,
-    ) {
-        self.controls.input_down(InputCode::Mouse(button));
-    }
-    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
-        self.controls.input_up(InputCode::Mouse(button));
-    }
-}
+use ahash::AHashMap;
+use cogs_gamedev::controls::EventInputHandler;
+use enum_map::Enum;
+use macroquad::{
+    miniquad::{self, Context, KeyMods},
+    prelude::{
+        utils::{register_input_subscriber, repeat_all_miniquad_input},
+        KeyCode, MouseButton,
+    },
+};
+
+/// The controls
+#[derive(Enum, Copy, Clone)]
+pub enum Control {
+    Click,
+    Pause,
+    /// Hold to rewind in practice mode
+    Rewind,
+    /// Turn the view of the board 60 degrees
+    RotateLeft,
+    RotateRight,
+    /// Move the second player's cursor in co-op, laid out around J like a hexagon
+    PartnerUpLeft,
+    PartnerUpRight,
+    PartnerLeft,
+    PartnerRight,
+    PartnerDownLeft,
+    PartnerDownRight,
+    /// Hold to draw with the second player's cursor
+    PartnerDraw,
+    /// Arm a magnet, if there's one to spend
+    Magnet,
+    /// Arm a ring clear, if there's one to spend
+    RingClear,
+    /// Use the special action, if the charge meter is full
+    Special,
+    /// Move the keyboard focus around lists on menus
+    MenuUp,
+    MenuDown,
+    /// Pick whatever has the keyboard focus
+    MenuSelect,
+}
+
+/// Combo keycode and mouse button code
+#[derive(Hash, PartialEq, Eq, Copy, Clone)]
+pub enum InputCode {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Event handler to hook into miniquad and get inputs
+#[derive(Clone)]
+pub struct InputSubscriber {
+    controls: EventInputHandler<InputCode, Control>,
+    subscriber_id: usize,
+    /// When the player last touched anything, in seconds since the epoch
+    last_input: f64,
+}
+
+impl InputSubscriber {
+    pub fn new() -> Self {
+        // the science kid
+        let sid = register_input_subscriber();
+
+        InputSubscriber {
+            controls: EventInputHandler::new(Self::default_controls()),
+            subscriber_id: sid,
+            last_input: miniquad::date::now(),
+        }
+    }
+
+    pub fn default_controls() -> AHashMap<InputCode, Control> {
+        let mut controls = AHashMap::new();
+
+        // Put your controls here
+        controls.insert(InputCode::Mouse(MouseButton::Left), Control::Click);
+        controls.insert(InputCode::Key(KeyCode::Escape), Control::Pause);
+        controls.insert(InputCode::Key(KeyCode::R), Control::Rewind);
+        controls.insert(InputCode::Key(KeyCode::Backspace), Control::Rewind);
+        controls.insert(InputCode::Mouse(MouseButton::Right), Control::Rewind);
+        controls.insert(InputCode::Key(KeyCode::Q), Control::RotateLeft);
+        controls.insert(InputCode::Key(KeyCode::Left), Control::RotateLeft);
+        controls.insert(InputCode::Key(KeyCode::E), Control::RotateRight);
+        controls.insert(InputCode::Key(KeyCode::Right), Control::RotateRight);
+        controls.insert(InputCode::Key(KeyCode::U), Control::PartnerUpLeft);
+        controls.insert(InputCode::Key(KeyCode::I), Control::PartnerUpRight);
+        controls.insert(InputCode::Key(KeyCode::H), Control::PartnerLeft);
+        controls.insert(InputCode::Key(KeyCode::K), Control::PartnerRight);
+        controls.insert(InputCode::Key(KeyCode::N), Control::PartnerDownLeft);
+        controls.insert(InputCode::Key(KeyCode::M), Control::PartnerDownRight);
+        controls.insert(InputCode::Key(KeyCode::Space), Control::PartnerDraw);
+        controls.insert(InputCode::Key(KeyCode::F), Control::Magnet);
+        controls.insert(InputCode::Key(KeyCode::G), Control::RingClear);
+        controls.insert(InputCode::Key(KeyCode::S), Control::Special);
+        controls.insert(InputCode::Key(KeyCode::Up), Control::MenuUp);
+        controls.insert(InputCode::Key(KeyCode::Down), Control::MenuDown);
+        controls.insert(InputCode::Key(KeyCode::Enter), Control::MenuSelect);
+
+        controls
+    }
+
+    pub fn update(&mut self) {
+        repeat_all_miniquad_input(self, self.subscriber_id);
+        self.controls.update();
+    }
+
+    /// How many seconds it's been since the player last pressed, released, or moved anything
+    pub fn idle_time(&self) -> f64 {
+        miniquad::date::now() - self.last_input
+    }
+
+    fn poke(&mut self) {
+        self.last_input = miniquad::date::now();
+    }
+}
+
+impl std::ops::Deref for InputSubscriber {
+    type Target = EventInputHandler<InputCode, Control>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.controls
+    }
+}
+
+impl miniquad::EventHandler for InputSubscriber {
+    fn update(&mut self, _ctx: &mut Context) {}
+
+    fn draw(&mut self, _ctx: &mut Context) {}
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if !repeat {
+            self.poke();
+            self.controls.input_down(InputCode::Key(keycode));
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        self.poke();
+        self.controls.input_up(InputCode::Key(keycode));
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {
+        self.poke();
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.poke();
+        self.controls.input_down(InputCode::Mouse(button));
+    }
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        self.poke();
+        self.controls.input_up(InputCode::Mouse(button));
+    }
+}
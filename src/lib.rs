@@ -0,0 +1,7 @@
+//! The rules of the game: boards, what can happen on them, and how they score.
+//!
+//! None of this opens a window, draws, or plays sounds, so anything that only needs the
+//! rules, like `fuzz_board`, can run them headless. The game itself is `main.rs`.
+
+pub mod model;
+pub mod weekly;
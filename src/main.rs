@@ -1,10 +1,12 @@
 mod assets;
+mod audio;
 mod boilerplates;
 mod controls;
 mod modes;
 mod utils;
 
-mod model;
+// The rules are in the library half of the crate, next to `fuzz_board`
+use haxagon::{model, weekly};
 
 // `getrandom` doesn't support WASM so we use quadrand's rng for it.
 #[cfg(target_arch = "wasm32")]
@@ -13,28 +15,55 @@ mod wasm_random_impl;
 use std::convert::TryInto;
 
 use crate::{
-    assets::Assets,
-    boilerplates::{FrameInfo, Gamemode},
+    assets::{manifest, Assets},
+    audio::{set_music_volume, set_sfx_volume},
+    boilerplates::{FrameInfo, Gamemode, GamemodeBox},
     controls::InputSubscriber,
-    modes::ModeSplash,
-    utils::draw::width_height_deficit,
+    modes::{start_game, ModeAssetReport, ModeCrashReport, ModeCrossfade, ModeSplash, ModeTitle},
+    utils::{
+        config::{BootTarget, CONFIG},
+        coord_overlay, crash,
+        draw::{crt_filter, set_crt_filter, width_height_deficit},
+        effects::{heavy_effects, GpuTier},
+        layout::set_mirrored,
+        perf::{record_update, PerfOverlay},
+        profile::Profile,
+        resolution::Resolution,
+    },
 };
 
 use macroquad::{miniquad::conf::Icon, prelude::*};
+use model::TICKS_PER_SECOND;
 use utils::draw::hexcolor;
 
-const WIDTH: f32 = 160.0;
-const HEIGHT: f32 = 144.0;
-const ASPECT_RATIO: f32 = WIDTH / HEIGHT;
+/// Width of the virtual canvas. This can change when the player picks a new resolution.
+fn width() -> f32 {
+    Resolution::current().width()
+}
+/// Height of the virtual canvas. This can change when the player picks a new resolution.
+fn height() -> f32 {
+    Resolution::current().height()
+}
+fn aspect_ratio() -> f32 {
+    width() / height()
+}
 
-const UPDATES_PER_DRAW: u64 = 1;
-const UPDATE_DT: f32 = 1.0 / (30.0 * UPDATES_PER_DRAW as f32);
+/// Updates run at a fixed rate, one board tick each, however fast frames get drawn.
+const UPDATE_DT: f32 = 1.0 / TICKS_PER_SECOND as f32;
+/// If updates fall further behind than this, skip the rest instead of trying to catch up
+const MAX_UPDATES_PER_DRAW: u32 = 8;
 
 /// The `macroquad::main` macro uses this.
 fn window_conf() -> Conf {
     let small = Image::from_file_with_format(include_bytes!("../icons/16.png"), None);
     let medium = Image::from_file_with_format(include_bytes!("../icons/32.png"), None);
     let big = Image::from_file_with_format(include_bytes!("../icons/64.png"), None);
+    // The GPU can't be checked until there's a window, so go by what it was last time
+    let msaa = {
+        let profile = Profile::get();
+        let tier = profile.gpu_tier.unwrap_or(GpuTier::Capable);
+        profile.settings.effects.heavy(tier)
+    };
     Conf {
         window_title: if cfg!(debug_assertions) {
             concat!(env!("CARGO_CRATE_NAME"), " v", env!("CARGO_PKG_VERSION"))
@@ -42,8 +71,10 @@ fn window_conf() -> Conf {
             "Haxagon"
         }
         .to_owned(),
-        fullscreen: false,
-        sample_count: 64,
+        fullscreen: CONFIG.fullscreen,
+        window_width: CONFIG.window_width.unwrap_or(800),
+        window_height: CONFIG.window_height.unwrap_or(600),
+        sample_count: if msaa { 64 } else { 1 },
         icon: Some(Icon {
             small: small.bytes.try_into().unwrap(),
             medium: medium.bytes.try_into().unwrap(),
@@ -55,7 +86,19 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
+    crash::install_hook();
     macroquad::rand::srand(macroquad::miniquad::date::now().to_bits());
+    {
+        let settings = Profile::get().settings;
+        settings.resolution.apply();
+        set_crt_filter(settings.crt_filter);
+        set_mirrored(settings.mirrored_ui);
+        settings.ui_scale.apply();
+        settings.effects.apply();
+        set_music_volume(settings.music_volume);
+        set_sfx_volume(settings.sfx_volume);
+        Profile::modify(|profile| profile.gpu_tier = Some(GpuTier::detected()));
+    }
 
     let loading = Texture2D::from_file_with_format(
         include_bytes!("../assets/textures/splash/loading.png"),
@@ -67,6 +110,8 @@ async fn main() {
     let _loading_coroutine = coroutines::start_coroutine(async move {
         // Yield one frame so that we can draw the loading screen
         next_frame().await;
+        // This decides what gets a placeholder, so it has to go first
+        manifest::verify().await;
         let assets = Assets::init().await;
         assets_tx.send(assets).unwrap();
     });
@@ -74,8 +119,8 @@ async fn main() {
     let assets = loop {
         let (miss_x, miss_y) = width_height_deficit();
         // How big do the textures actually display on the screen?
-        let real_width = loading.width() * (screen_width() - miss_x) / WIDTH;
-        let real_height = loading.height() * (screen_height() - miss_y) / HEIGHT;
+        let real_width = loading.width() * (screen_width() - miss_x) / width();
+        let real_height = loading.height() * (screen_height() - miss_y) / height();
 
         // Simulate the border effect
         clear_background(BLACK);
@@ -105,16 +150,52 @@ async fn main() {
         next_frame().await;
     };
     let assets = Box::leak(Box::new(assets)) as &'static Assets;
-    gameloop(assets).await;
+    // The title can come up now, and everything else loads behind it
+    let _deferred_coroutine = coroutines::start_coroutine(assets.load_deferred());
+    gameloop(assets, loading).await;
+}
+
+/// What the mode stack starts out with.
+///
+/// The loading screen fades out over whatever ends up on top.
+fn first_modes(assets: &Assets, loading: Texture2D) -> Vec<GamemodeBox> {
+    let mut modes: Vec<GamemodeBox> = match CONFIG.boot {
+        BootTarget::Splash => vec![Box::new(ModeSplash::new())],
+        BootTarget::Title => vec![Box::new(ModeTitle::new())],
+        BootTarget::LastMode => {
+            let mut modes: Vec<GamemodeBox> = vec![Box::new(ModeTitle::new())];
+            let (last_mode, settings) = {
+                let profile = Profile::get();
+                (profile.last_mode, profile.settings)
+            };
+            if let Some(mode) = last_mode {
+                modes.push(start_game(mode.settings(), settings, assets));
+            }
+            modes
+        }
+    };
+    if let Some(place) = crash::take_last_crash() {
+        modes.push(Box::new(ModeCrashReport::new(place)));
+    }
+    let problems = manifest::problems();
+    if !problems.is_empty() {
+        modes.push(Box::new(ModeAssetReport::new(problems.to_vec())));
+    }
+    // Nothing got pushed onto the stack, so whatever's on top doesn't know it's up yet.
+    // The crossfade lets it know once it's done, which is when the title starts its music.
+    // The splash screen starts its jingle on its own.
+    let top = modes.pop().unwrap();
+    modes.push(Box::new(ModeCrossfade::new(top, loading)));
+    modes
 }
 
 /// Threaded version of main.
 ///
 /// This updates and draws at the same time.
 #[cfg(not(any(target_arch = "wasm32", not(feature = "thread_loop"))))]
-async fn gameloop(assets: &'static Assets) {
+async fn gameloop(assets: &'static Assets, loading: Texture2D) {
     use crossbeam::channel::TryRecvError;
-    use std::thread;
+    use std::{thread, time::Instant};
 
     let mut controls = InputSubscriber::new();
 
@@ -123,35 +204,43 @@ async fn gameloop(assets: &'static Assets) {
     // Drawing must happen on the main thread (thanks macroquad...)
     // so updating goes over here
     let _update_handle = thread::spawn(move || {
-        let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeSplash::new())];
+        let mut mode_stack = first_modes(assets, loading);
         let mut frame_info = FrameInfo {
             dt: UPDATE_DT,
             frames_ran: 0,
         };
+        let mut clock = UpdateClock::default();
+        let mut last_check = Instant::now();
 
         loop {
-            controls.update();
-            // Update the current state.
-            // To change state, return a non-None transition.
-            let transition = mode_stack
-                .last_mut()
-                .unwrap()
-                .update(&controls, frame_info, assets);
-            transition.apply(&mut mode_stack, assets);
-
-            #[allow(clippy::modulo_one)]
-            if frame_info.frames_ran % UPDATES_PER_DRAW == 0 {
-                let drawer = mode_stack.last_mut().unwrap().get_draw_info();
-                // Wait on the draw thread to finish up drawing, then send.
-                // Ignore the error
-                let _ = draw_tx.send(drawer);
+            let now = Instant::now();
+            let due = clock.updates_due((now - last_check).as_secs_f32());
+            last_check = now;
+            for _ in 0..due {
+                controls.update();
+                record_update();
+                // Update the current state.
+                // To change state, return a non-None transition.
+                let transition = mode_stack
+                    .last_mut()
+                    .unwrap()
+                    .update(&controls, frame_info, assets);
+                transition.apply(&mut mode_stack, assets);
+                crash::set_mode(mode_stack.last().unwrap().name());
+                frame_info.frames_ran += 1;
             }
-            frame_info.frames_ran += 1;
+
+            let drawer = mode_stack.last_mut().unwrap().get_draw_info();
+            // Wait on the draw thread to finish up drawing, then send.
+            // Ignore the error
+            let _ = draw_tx.send(drawer);
         }
     });
 
-    let canvas = render_target(WIDTH as u32, HEIGHT as u32);
-    canvas.texture.set_filter(FilterMode::Nearest);
+    let mut resolution = Resolution::current();
+    let mut canvas = make_canvas(resolution);
+
+    let mut perf = PerfOverlay::new();
 
     // Draw loop
     let mut frame_info = FrameInfo {
@@ -159,23 +248,34 @@ async fn gameloop(assets: &'static Assets) {
         frames_ran: 0,
     };
     loop {
+        let frame_start = get_time();
         frame_info.dt = macroquad::time::get_frame_time();
 
+        let wait_start = get_time();
         let drawer = match draw_rx.try_recv() {
             Ok(it) => it,
             Err(TryRecvError::Empty) => {
-                eprintln!("Waiting on updates!");
+                if CONFIG.debug.log_update_waits {
+                    eprintln!("Waiting on updates!");
+                }
                 draw_rx.recv().unwrap()
             }
             Err(TryRecvError::Disconnected) => panic!("The draw channel closed!"),
         };
+        perf.record_frame(frame_info.dt, (get_time() - wait_start) as f32);
+
+        if resolution != Resolution::current() {
+            canvas.delete();
+            resolution = Resolution::current();
+            canvas = make_canvas(resolution);
+        }
 
         // Draw the state.
         push_camera_state();
         set_camera(&Camera2D {
             render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
+            zoom: vec2(width().recip() * 2.0, height().recip() * 2.0),
+            target: vec2(width() / 2.0, height() / 2.0),
             ..Default::default()
         });
 
@@ -188,65 +288,70 @@ async fn gameloop(assets: &'static Assets) {
 
         clear_background(BLACK);
 
-        // Figure out the drawbox.
-        // these are how much wider/taller the window is than the content
-        let (width_deficit, height_deficit) = width_height_deficit();
-        draw_texture_ex(
-            canvas.texture,
-            width_deficit / 2.0,
-            height_deficit / 2.0,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(
-                    screen_width() - width_deficit,
-                    screen_height() - height_deficit,
-                )),
-                ..Default::default()
-            },
-        );
+        draw_canvas(canvas, resolution, assets);
+        coord_overlay::draw_labels();
+        perf.draw();
 
         frame_info.frames_ran += 1;
+        wait_for_frame_cap(frame_start);
         next_frame().await
     }
 }
 
 /// Unthreaded version of main.
 #[cfg(any(target_arch = "wasm32", not(feature = "thread_loop")))]
-async fn gameloop(assets: &'static Assets) {
+async fn gameloop(assets: &'static Assets, loading: Texture2D) {
     let mut controls = InputSubscriber::new();
-    let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeSplash::new())];
+    let mut mode_stack = first_modes(assets, loading);
 
-    let canvas = render_target(WIDTH as u32, HEIGHT as u32);
-    canvas.texture.set_filter(FilterMode::Nearest);
+    let mut resolution = Resolution::current();
+    let mut canvas = make_canvas(resolution);
+    let mut perf = PerfOverlay::new();
 
     let mut frame_info = FrameInfo {
         dt: UPDATE_DT,
         frames_ran: 0,
     };
+    let mut update_info = FrameInfo {
+        dt: UPDATE_DT,
+        frames_ran: 0,
+    };
+    let mut clock = UpdateClock::default();
     loop {
-        frame_info.dt = UPDATE_DT;
+        let frame_start = get_time();
+        frame_info.dt = macroquad::time::get_frame_time();
 
         // Update the current state.
         // To change state, return a non-None transition.
-        for _ in 0..UPDATES_PER_DRAW {
+        for _ in 0..clock.updates_due(frame_info.dt) {
             controls.update();
+            record_update();
 
             let transition = mode_stack
                 .last_mut()
                 .unwrap()
-                .update(&controls, frame_info, assets);
+                .update(&controls, update_info, assets);
             transition.apply(&mut mode_stack, assets);
+            crash::set_mode(mode_stack.last().unwrap().name());
+            update_info.frames_ran += 1;
         }
 
-        frame_info.dt = macroquad::time::get_frame_time();
+        // Updates happen right here, so there's never any waiting on them
+        perf.record_frame(frame_info.dt, 0.0);
+
+        if resolution != Resolution::current() {
+            canvas.delete();
+            resolution = Resolution::current();
+            canvas = make_canvas(resolution);
+        }
 
         push_camera_state();
         // These divides and multiplies are required to get the camera in the center of the screen
         // and having it fill everything.
         set_camera(&Camera2D {
             render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
+            zoom: vec2(width().recip() * 2.0, height().recip() * 2.0),
+            target: vec2(width() / 2.0, height() / 2.0),
             ..Default::default()
         });
         clear_background(WHITE);
@@ -259,24 +364,85 @@ async fn gameloop(assets: &'static Assets) {
         pop_camera_state();
         clear_background(BLACK);
 
-        // Figure out the drawbox.
-        // these are how much wider/taller the window is than the content
-        let (width_deficit, height_deficit) = width_height_deficit();
-        draw_texture_ex(
-            canvas.texture,
-            width_deficit / 2.0,
-            height_deficit / 2.0,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(
-                    screen_width() - width_deficit,
-                    screen_height() - height_deficit,
-                )),
-                ..Default::default()
-            },
-        );
+        draw_canvas(canvas, resolution, assets);
+        coord_overlay::draw_labels();
+        perf.draw();
 
         frame_info.frames_ran += 1;
+        wait_for_frame_cap(frame_start);
         next_frame().await
     }
 }
+
+/// Keeps track of how far the updates are behind the clock.
+#[derive(Default)]
+struct UpdateClock {
+    /// Time that's passed without being updated for yet, in seconds
+    behind: f32,
+}
+
+impl UpdateClock {
+    /// Count `dt` seconds as passed, and say how many updates it takes to catch back up.
+    fn updates_due(&mut self, dt: f32) -> u32 {
+        self.behind += dt;
+        let due = (self.behind / UPDATE_DT) as u32;
+        if due > MAX_UPDATES_PER_DRAW {
+            // Something stalled for a while, so let that time go
+            self.behind = 0.0;
+            return MAX_UPDATES_PER_DRAW;
+        }
+        self.behind -= due as f32 * UPDATE_DT;
+        due
+    }
+}
+
+/// If there's a frame cap, sleep off whatever's left of this frame.
+fn wait_for_frame_cap(frame_start: f64) {
+    // Can't sleep on the web, but the browser caps the frame rate anyways
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(cap) = CONFIG.frame_cap.filter(|cap| *cap > 0) {
+        let left = 1.0 / cap as f64 - (get_time() - frame_start);
+        if left > 0.0 {
+            std::thread::sleep(std::time::Duration::from_secs_f64(left));
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = frame_start;
+}
+
+/// Make the render target everything gets drawn to before being scaled up to the window.
+fn make_canvas(resolution: Resolution) -> RenderTarget {
+    let canvas = render_target(resolution.width() as u32, resolution.height() as u32);
+    canvas.texture.set_filter(FilterMode::Nearest);
+    canvas
+}
+
+/// Scale the canvas up to fit the window and draw it.
+fn draw_canvas(canvas: RenderTarget, resolution: Resolution, assets: &Assets) {
+    if crt_filter() && assets.shaders.enabled() {
+        assets
+            .shaders
+            .crt
+            .set_uniform("CanvasSize", (resolution.width(), resolution.height()));
+        gl_use_material(assets.shaders.crt);
+    }
+
+    // Figure out the drawbox.
+    // these are how much wider/taller the window is than the content
+    let (width_deficit, height_deficit) = width_height_deficit();
+    draw_texture_ex(
+        canvas.texture,
+        width_deficit / 2.0,
+        height_deficit / 2.0,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(vec2(
+                screen_width() - width_deficit,
+                screen_height() - height_deficit,
+            )),
+            ..Default::default()
+        },
+    );
+
+    gl_use_default_material();
+}
@@ -4,29 +4,111 @@ use ahash::{AHashMap, AHashSet};
 use enum_map::Enum;
 use hex2d::{Angle, Coordinate, Direction, Spin};
 use quad_rand::compat::QuadRand;
-use rand::Rng;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::StdRng,
+    seq::SliceRandom,
+    Rng, SeedableRng,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::weekly::WeeklyChallenge;
+
+/// How many times the board ticks per second. All timers are counted in ticks.
+///
+/// Nothing about the board goes by the wall clock, so a board run headless with
+/// `Board::advance` ends up exactly where it would have played out in real time.
+pub const TICKS_PER_SECOND: u32 = 60;
 pub const SCORE_TIMER: u32 = 30;
+/// How many times to try generating a fair board before giving up and using whatever we got
+const FAIR_START_ATTEMPTS: usize = 100;
+/// A starting board with this many adjacent same-color pairs counts as fair,
+/// even if no single triangle clears anything
+const FAIR_START_PAIRS: usize = 8;
+/// With the stone rain mutator, every this-many-th spawn is stone
+const STONE_RAIN_PERIOD: u32 = 8;
+/// How many upcoming marble colors are known ahead of time, for the preview
+pub const SPAWN_PREVIEW_LENGTH: usize = 3;
+/// With weighted spawns, even the most common color keeps at least this much of a chance
+const MIN_SPAWN_WEIGHT: f32 = 0.05;
+/// With the squeeze mutator, marbles against the edge this long fuse into the wall
+const EDGE_FUSE_TICKS: u32 = TICKS_PER_SECOND * 30;
+/// Ticks after one cell fuses before the next one can, so the board shrinks a cell at a time
+const EDGE_FUSE_COOLDOWN: u32 = TICKS_PER_SECOND * 4;
+/// The squeeze never eats cells this close to the center, so there's always somewhere to play
+const EDGE_FUSE_MIN_DISTANCE: i32 = 2;
+/// With the misclick mutator, how many points a loop that clears nothing costs
+const MISCLICK_PENALTY: u32 = 5;
+
+/// Format a tick count as minutes, seconds, and hundredths.
+pub fn format_ticks(ticks: u32) -> String {
+    let seconds = ticks / TICKS_PER_SECOND;
+    let hundredths = ticks % TICKS_PER_SECOND * 100 / TICKS_PER_SECOND;
+    format!("{}:{:02}.{:02}", seconds / 60, seconds % 60, hundredths)
+}
 
 /// Board full of marbles to play on
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Board {
     marbles: AHashMap<Coordinate, Marble>,
+    /// Every cell that exists on the board
+    cells: AHashSet<Coordinate>,
+    /// How many ticks each marble has gone without the player moving it.
+    /// Marbles not in here are brand new.
+    ages: AHashMap<Coordinate, u32>,
+    /// With the squeeze mutator, the marble resting on each edge cell and for how many ticks
+    edge_pressure: AHashMap<Coordinate, (Marble, u32)>,
+    /// Count down until another cell can fuse into the wall
+    squeeze_cooldown: u32,
+    /// How many of the score milestones for growing the board have been passed
+    growth_milestones: u32,
     score: u32,
     /// Each time we gain points, push the points to here.
     score_queue: VecDeque<ScorePacket>,
     score_timer: u32,
 
     action_queue: VecDeque<BoardAction>,
+    /// How many actions at the back of the queue the player pushed
+    player_actions: usize,
     /// Time counting up until we do the next action
     action_timer: u32,
 
     /// Count up until we spawn the next marble
     next_spawn_timer: u32,
     planned_next_spawn_pos: Option<Coordinate>,
+    /// How many marbles have spawned since the start
+    spawn_count: u32,
+    /// How many marbles have spawned at each spawnpoint, for checking spawns are spread out
+    spawn_tally: AHashMap<Coordinate, u32>,
+    /// Colors of the next marbles to spawn, front first
+    upcoming: VecDeque<Marble>,
+    /// Stones an opponent sent over, which drop in place of the next spawns
+    pending_garbage: u32,
+    /// Someone's picking where the next marble goes, so it waits once its timer is up
+    spawn_held: bool,
+    /// The held marble got let go, so don't hold it again
+    spawn_released: bool,
+    /// The player picked the spawnpoint, so leave it where it is until something lands there
+    spawn_placed: bool,
+    /// While this counts down, the board only runs every other tick
+    slow_ticks: u32,
+
+    /// Which way marbles fall under `GravityMode::Directional`
+    gravity_direction: Direction,
+    /// Count up until we rotate the gravity direction
+    gravity_rotate_timer: u32,
 
     tick_count: u32,
+    /// Marbles cleared since the last time someone took them
+    clear_events: Vec<ClearEvent>,
+    /// How many cycles fizzled since the last time someone took them
+    fizzles: u32,
+    /// Points scored since the last time someone took them
+    score_events: Vec<ScoreEvent>,
+
+    /// Where this board gets its colors and shuffles from.
+    /// It's its own so nothing else rolling dice can change what a seeded board does.
+    rng: StdRng,
 
     settings: BoardSettings,
 }
@@ -34,58 +116,229 @@ pub struct Board {
 impl Board {
     /// Create a new Board with the given size. There will be the given number of "rings"
     /// of marbles around the outside.
+    ///
+    /// If `fair_start` is set, this rerolls the board until there's something to do.
     pub fn new(settings: BoardSettings) -> Self {
+        let rng = match settings.weekly {
+            // Everyone playing this week starts on the same board
+            Some(weekly) => StdRng::seed_from_u64(weekly.seed()),
+            None => StdRng::seed_from_u64(QuadRand.gen()),
+        };
+        let mut out = Board::generate(settings.clone(), rng);
+        if settings.fair_start {
+            for _ in 0..FAIR_START_ATTEMPTS {
+                if out.is_fair_start() {
+                    break;
+                }
+                // Keep rolling the same RNG so the reroll comes out different
+                let rng = out.rng;
+                out = Board::generate(settings.clone(), rng);
+            }
+        }
+        out
+    }
+
+    fn generate(settings: BoardSettings, rng: StdRng) -> Self {
         let pad = settings.radius - settings.border_width;
+        let first_spawn = match settings.gravity {
+            // Inward gravity piles marbles up in the middle, so spawn on the rim
+            GravityMode::Inward => Coordinate::new(settings.radius as i32, 0),
+            // This gets moved onto the ceiling once we know where it is
+            GravityMode::Directional { .. } => Coordinate::new(0, 0),
+            GravityMode::Outward | GravityMode::None => Coordinate::new(pad as i32, 0),
+        };
+        let gravity_direction = match settings.gravity {
+            GravityMode::Directional { start, .. } => start,
+            _ => Direction::YX,
+        };
+        let cells = settings.cells();
         let mut out = Board {
             marbles: AHashMap::new(),
+            cells,
+            ages: AHashMap::new(),
+            edge_pressure: AHashMap::new(),
+            squeeze_cooldown: 0,
+            growth_milestones: 0,
             score: 0,
             score_timer: 0,
             score_queue: VecDeque::new(),
             action_queue: VecDeque::new(),
+            player_actions: 0,
             action_timer: 0,
             next_spawn_timer: 0,
 
             // we're about to set this in
-            planned_next_spawn_pos: Some(Coordinate::new(pad as i32, 0)),
+            planned_next_spawn_pos: Some(first_spawn),
+            spawn_count: 0,
+            spawn_tally: AHashMap::new(),
+            upcoming: VecDeque::new(),
+            pending_garbage: 0,
+            spawn_held: false,
+            spawn_released: false,
+            spawn_placed: false,
+            slow_ticks: 0,
+            gravity_direction,
+            gravity_rotate_timer: 0,
             tick_count: 0,
+            clear_events: Vec::new(),
+            score_events: Vec::new(),
+            fizzles: 0,
+            rng,
             settings,
         };
 
-        for dist in pad..=out.radius() {
-            for c in Coordinate::new(0, 0).ring_iter(dist as i32 + 1, Spin::CW(Direction::XY)) {
-                out.spawn_marble(&c);
+        match out.settings.gravity {
+            GravityMode::Directional { .. } => {
+                // Fill up the floor
+                for c in out.cells.clone() {
+                    if out.height_above_floor(&c) < out.settings.border_width {
+                        out.spawn_marble(&c);
+                    }
+                }
+                out.planned_next_spawn_pos = out.resettle_spawnpoint();
+            }
+            GravityMode::Inward => {
+                // Start with a lump in the middle instead of a crust on the outside
+                for c in out.cells.clone() {
+                    if c.distance(Coordinate::new(0, 0)) < out.settings.border_width as i32 {
+                        out.spawn_marble(&c);
+                    }
+                }
+            }
+            GravityMode::Outward | GravityMode::None => {
+                for dist in pad..=out.radius() {
+                    for c in
+                        Coordinate::new(0, 0).ring_iter(dist as i32 + 1, Spin::CW(Direction::XY))
+                    {
+                        out.spawn_marble(&c);
+                    }
+                }
             }
         }
 
         out
     }
 
+    /// Run the board for `n_ticks` ticks, stopping early if it fills up.
+    /// Return `true` if it did.
+    pub fn advance(&mut self, n_ticks: u32) -> bool {
+        (0..n_ticks).any(|_| self.tick())
+    }
+
     /// Run one frame of the board. Return `true` if we die.
     pub fn tick(&mut self) -> bool {
-        self.next_spawn_timer += 1;
-        if self.next_spawn_timer >= self.timer_max() {
+        if self.slow_ticks > 0 {
+            self.slow_ticks -= 1;
+            if self.slow_ticks % 2 == 1 {
+                return false;
+            }
+        }
+
+        let frozen = self.settings.freeze_spawns_during_actions && !self.action_queue.is_empty();
+        if !frozen && !self.spawn_waiting() {
+            self.next_spawn_timer += 1;
+        }
+        if !frozen && !self.spawn_held && self.next_spawn_timer >= self.timer_max() {
             self.next_spawn_timer = 0;
+            self.spawn_released = false;
+            self.spawn_placed = false;
 
             if let Some(sp) = self.planned_next_spawn_pos {
-                self.spawn_marble(&sp);
+                self.spawn_count += 1;
+                *self.spawn_tally.entry(sp).or_insert(0) += 1;
+                if self.pending_garbage > 0 {
+                    self.pending_garbage -= 1;
+                    self.spawn_stone(&sp);
+                } else if self.settings.mutators.contains(Mutator::StoneRain)
+                    && self.spawn_count % STONE_RAIN_PERIOD == 0
+                {
+                    self.spawn_stone(&sp);
+                } else {
+                    self.spawn_marble(&sp);
+                }
                 self.gravitate();
-                self.action_queue.push_back(BoardAction::ClearBlobs(1));
+                self.queue_board_action(BoardAction::ClearBlobs(
+                    self.settings.scoring.spawn_start_multiplier,
+                ));
                 self.planned_next_spawn_pos = self.find_next_spawnpoint(sp);
             } else {
                 // oh no we couldn't find a place to be.
                 // reify all the pending score packets
                 while let Some(pkt) = self.score_queue.pop_front() {
-                    self.score += pkt.base * pkt.multiplier;
+                    self.score = self.score.saturating_add(pkt.points());
                 }
                 return true;
             }
         }
 
+        if let Some(limit) = self.settings.petrify_after {
+            let moving = self.moving_cells().to_vec();
+            for (pos, marble) in self.marbles.iter_mut() {
+                if *marble == Marble::Stone || moving.contains(pos) {
+                    continue;
+                }
+                let age = self.ages.entry(*pos).or_insert(0);
+                *age += 1;
+                if *age >= limit {
+                    *marble = Marble::Stone;
+                    self.ages.remove(pos);
+                }
+            }
+        }
+
+        if self.settings.mutators.contains(Mutator::Squeeze) {
+            self.squeeze();
+        }
+
+        if let GravityMode::Directional {
+            rotate_period: Some(period),
+            ..
+        } = self.settings.gravity
+        {
+            self.gravity_rotate_timer += 1;
+            if self.gravity_rotate_timer >= period {
+                self.gravity_rotate_timer = 0;
+                self.gravity_direction = self.gravity_direction + Angle::Right;
+                self.gravitate();
+                // Everything just got shaken up, maybe something lined up
+                self.queue_board_action(BoardAction::ClearBlobs(
+                    self.settings.scoring.spawn_start_multiplier,
+                ));
+                self.planned_next_spawn_pos = self.resettle_spawnpoint();
+            }
+        }
+
+        self.resolve_actions();
+
+        if let Some(expansion) = self.settings.expansion {
+            let next_milestone =
+                (self.growth_milestones + 1).saturating_mul(expansion.every_points);
+            if self.score >= next_milestone {
+                self.growth_milestones += 1;
+                if self.grow() {
+                    self.gravitate();
+                    self.queue_board_action(BoardAction::ClearBlobs(
+                        self.settings.scoring.spawn_start_multiplier,
+                    ));
+                    self.planned_next_spawn_pos = self.resettle_spawnpoint();
+                }
+            }
+        }
+
+        self.tick_count += 1;
+
+        false
+    }
+
+    /// Run one frame of queued actions and scoring, without spawning anything or running
+    /// any other timers. `tick` does this too; call it directly to let a board built by hand
+    /// play out.
+    pub fn resolve_actions(&mut self) {
         if !self.score_queue.is_empty() {
             self.score_timer += 1;
             if self.score_timer >= SCORE_TIMER {
                 let packet = self.score_queue.pop_front().unwrap();
-                self.score += packet.base * packet.multiplier;
+                self.score = self.score.saturating_add(packet.points());
                 self.score_timer = 0;
             }
         }
@@ -93,17 +346,34 @@ impl Board {
         let do_action = loop {
             let do_action = match self.action_queue.front() {
                 Some(it) => {
-                    if let BoardAction::ClearBlobs(_) = it {
-                        let blobs = self.find_blobs();
-                        if blobs.is_empty() {
-                            // Skip clearing blobs if we didn't find any blobs.
+                    match it {
+                        BoardAction::ClearBlobs(_) => {
+                            let blobs = self.find_blobs();
+                            if blobs.is_empty() {
+                                // Skip clearing blobs if we didn't find any blobs.
+                                self.action_queue.pop_front();
+                                self.action_timer = 0;
+                                self.player_actions =
+                                    self.player_actions.min(self.action_queue.len());
+                                continue;
+                            }
+                        }
+                        BoardAction::Cycle(poses)
+                            if self.action_timer == 0 && self.cycle_fizzles(poses) =>
+                        {
+                            // Something earlier in the queue pulled the rug out from under it.
+                            // Drop it before it starts moving instead of halfway through.
                             self.action_queue.pop_front();
+                            self.action_timer = 0;
+                            self.player_actions = self.player_actions.min(self.action_queue.len());
+                            self.fizzles += 1;
                             continue;
                         }
+                        _ => {}
                     }
 
                     self.action_timer += 1;
-                    self.action_timer >= it.time()
+                    self.action_timer >= it.time(&self.settings.action_times)
                 }
                 _ => false,
             };
@@ -111,23 +381,14 @@ impl Board {
         };
         if do_action {
             let action = self.action_queue.pop_front().unwrap();
+            self.player_actions = self.player_actions.min(self.action_queue.len());
             self.execute_action(action);
             self.action_timer = 0;
             self.gravitate();
 
             // This action likely moved some marbles, so let's reposition the spawnpoint
-            // If we don't currently have a spawnpoint (aka, we *just* saved ourselves from losing),
-            // pretend it was at the center of the board.
-            let present_sp = self
-                .planned_next_spawn_pos
-                .unwrap_or_else(|| Coordinate::new(0, 0));
-            let shunted = self.gravity_all(present_sp);
-            self.planned_next_spawn_pos = Some(shunted);
+            self.planned_next_spawn_pos = self.resettle_spawnpoint();
         }
-
-        self.tick_count += 1;
-
-        false
     }
 
     /// Find all the blobs of marbles with size >= the given.
@@ -149,18 +410,158 @@ impl Board {
         out
     }
 
+    /// Predict which blobs would get cleared if this action ran right now, without
+    /// touching the real board.
+    ///
+    /// Only looks at the first clear, not at any cascades after it.
+    pub fn simulate_action(&self, action: &BoardAction) -> Vec<Vec<Coordinate>> {
+        if let BoardAction::Cycle(poses) = action {
+            if self.cycle_fizzles(poses) {
+                return Vec::new();
+            }
+        }
+        let mut sim = self.clone();
+        sim.execute_action(action.clone());
+        sim.gravitate();
+        sim.find_blobs()
+    }
+
+    /// A copy of the board with everything queued up played out, so anything the player
+    /// draws in the meantime can be checked against where the marbles are going to be.
+    ///
+    /// Nothing spawns and no other timers run, since there's no knowing where new marbles
+    /// would land by then.
+    pub fn simulate_queue(&self) -> Board {
+        let mut sim = self.clone();
+        sim.play_out_queue();
+        sim
+    }
+
+    /// Play out everything queued up right away, like `simulate_queue` but in place.
+    pub fn play_out_queue(&mut self) {
+        while self.next_action().is_some() {
+            self.resolve_actions();
+        }
+    }
+
     pub fn next_spawn_point(&self) -> Option<Coordinate> {
         self.planned_next_spawn_pos
     }
 
-    /// Return if the coordinate lies within the board
+    /// Does the player get to pick where the next marble spawns, and hasn't yet?
+    pub fn needs_placement(&self) -> bool {
+        let manual = self
+            .settings
+            .manual_spawns
+            .map_or(false, |manual| (self.spawn_count + 1) % manual.every == 0);
+        manual && !self.spawn_released
+    }
+
+    /// Make the next marble wait to spawn once its timer is up, until `release_spawn`.
+    pub fn hold_spawn(&mut self) {
+        self.spawn_held = true;
+    }
+
+    /// Is the next marble ready to spawn but being held back?
+    pub fn spawn_waiting(&self) -> bool {
+        self.spawn_held && self.next_spawn_timer >= self.timer_max()
+    }
+
+    /// Can the next marble be placed here?
+    pub fn can_place_spawn(&self, pos: &Coordinate) -> bool {
+        self.is_in_bounds(pos) && self.get_marble(pos).is_none()
+    }
+
+    /// Let a held marble spawn, at `pos` if there's room there and wherever
+    /// the spawnpoint was going otherwise.
+    pub fn release_spawn(&mut self, pos: Option<Coordinate>) {
+        self.spawn_held = false;
+        self.spawn_released = true;
+        if let Some(pos) = pos.filter(|pos| self.can_place_spawn(pos)) {
+            self.planned_next_spawn_pos = Some(pos);
+            self.spawn_placed = true;
+        }
+    }
+
+    /// Return if the coordinate lies within the board (and isn't a hole)
     pub fn is_in_bounds(&self, c: &Coordinate) -> bool {
-        c.distance(Coordinate::new(0, 0)) <= self.radius() as i32
+        self.cells.contains(c)
+    }
+
+    /// Get every cell that exists on the board
+    pub fn cells(&self) -> &AHashSet<Coordinate> {
+        &self.cells
     }
 
     /// The player has done a thing and the board needs to update
     pub fn push_action(&mut self, action: BoardAction) {
         self.action_queue.push_back(action);
+        self.player_actions += 1;
+    }
+
+    /// The board has done a thing on its own and needs to update.
+    ///
+    /// The player can't take back anything queued before this.
+    fn queue_board_action(&mut self, action: BoardAction) {
+        self.action_queue.push_back(action);
+        self.player_actions = 0;
+    }
+
+    /// Every action waiting to happen, starting with the one happening now.
+    pub fn queued_actions(&self) -> impl Iterator<Item = &BoardAction> + '_ {
+        self.action_queue.iter()
+    }
+
+    /// How many actions at the back of the queue the player pushed and could still take back.
+    ///
+    /// Once an action starts playing out it's too late, and so is anything the board
+    /// queued up on its own in between, like clearing after a spawn.
+    pub fn cancelable_actions(&self) -> usize {
+        let started = self.action_timer > 0 && self.player_actions == self.action_queue.len();
+        self.player_actions.saturating_sub(started as usize)
+    }
+
+    /// Take back the player's actions that haven't started yet, newest last.
+    pub fn cancel_player_actions(&mut self) -> Vec<BoardAction> {
+        let keep = self.action_queue.len() - self.cancelable_actions();
+        self.player_actions = 0;
+        self.action_queue.split_off(keep).into()
+    }
+
+    /// Put a marble on the board, or take one off with `None`, for building boards by hand.
+    ///
+    /// Nothing falls or clears until `settle` is called.
+    pub fn set_marble(&mut self, pos: Coordinate, marble: Option<Marble>) {
+        if !self.is_in_bounds(&pos) {
+            return;
+        }
+        self.ages.remove(&pos);
+        match marble {
+            Some(marble) => {
+                self.marbles.insert(pos, marble);
+            }
+            None => {
+                self.marbles.remove(&pos);
+            }
+        }
+    }
+
+    /// Take every marble off the board and forget anything that was about to happen.
+    pub fn clear_marbles(&mut self) {
+        self.marbles.clear();
+        self.ages.clear();
+        self.action_queue.clear();
+        self.player_actions = 0;
+        self.action_timer = 0;
+    }
+
+    /// Let everything fall and queue up clearing whatever lines up, like after a spawn.
+    pub fn settle(&mut self) {
+        self.gravitate();
+        self.queue_board_action(BoardAction::ClearBlobs(
+            self.settings.scoring.spawn_start_multiplier,
+        ));
+        self.planned_next_spawn_pos = self.resettle_spawnpoint();
     }
 
     /// The action we're going to execute.
@@ -168,6 +569,14 @@ impl Board {
         self.action_queue.front()
     }
 
+    /// If we're in the middle of clearing a cascade, the multiplier carried over into the next clear.
+    pub fn active_multiplier(&self) -> Option<u32> {
+        match self.action_queue.front() {
+            Some(BoardAction::ClearBlobs(premult)) if *premult > 0 => Some(*premult),
+            _ => None,
+        }
+    }
+
     /// Get all the marbles in the board
     pub fn get_marbles(&self) -> &AHashMap<Coordinate, Marble> {
         &self.marbles
@@ -178,6 +587,193 @@ impl Board {
         self.marbles.get(pos)
     }
 
+    /// Which way marbles currently fall under directional gravity, or `None` if
+    /// the gravity isn't directional.
+    pub fn gravity_direction(&self) -> Option<Direction> {
+        match self.settings.gravity {
+            GravityMode::Directional { .. } => Some(self.gravity_direction),
+            _ => None,
+        }
+    }
+
+    /// If the gravity is going to rotate, how many ticks until it does?
+    pub fn ticks_until_gravity_rotation(&self) -> Option<u32> {
+        match self.settings.gravity {
+            GravityMode::Directional {
+                rotate_period: Some(period),
+                ..
+            } => Some(period - self.gravity_rotate_timer),
+            _ => None,
+        }
+    }
+
+    /// How close the marble at the position is to turning to stone, from 0 to 1,
+    /// or `None` if marbles don't petrify.
+    pub fn petrify_progress(&self, pos: &Coordinate) -> Option<f32> {
+        let limit = self.settings.petrify_after?;
+        let age = self.ages.get(pos).copied().unwrap_or(0);
+        Some(age as f32 / limit as f32)
+    }
+
+    /// With the squeeze mutator, how close each edge cell is to fusing into the wall,
+    /// from 0 to 1.
+    pub fn edge_pressure(&self) -> impl Iterator<Item = (Coordinate, f32)> + '_ {
+        self.edge_pressure
+            .iter()
+            .map(|(pos, (_, ticks))| (*pos, *ticks as f32 / EDGE_FUSE_TICKS as f32))
+    }
+
+    /// Make the board one ring bigger, if it's allowed to grow any more.
+    /// Return whether it did.
+    fn grow(&mut self) -> bool {
+        let max_radius = match self.settings.expansion {
+            Some(expansion) => expansion.max_radius,
+            None => return false,
+        };
+        if self.radius() >= max_radius {
+            return false;
+        }
+        self.settings.radius += 1;
+        // The new ring is all there even if the board has a mask; the mask only covers
+        // the size the board started at
+        let ring = Coordinate::new(0, 0).ring_iter(self.radius() as i32, Spin::CW(Direction::XY));
+        self.cells.extend(ring);
+        true
+    }
+
+    /// Does this cover every cell of some ring around the middle?
+    fn completes_ring(&self, cleared: &[Coordinate]) -> bool {
+        let origin = Coordinate::new(0, 0);
+        let cleared = cleared.iter().copied().collect::<AHashSet<_>>();
+        cleared
+            .iter()
+            .map(|pos| pos.distance(origin))
+            .filter(|dist| *dist > 0)
+            .any(|dist| {
+                self.cells
+                    .iter()
+                    .filter(|pos| pos.distance(origin) == dist)
+                    .all(|pos| cleared.contains(pos))
+            })
+    }
+
+    /// Marbles sitting against the edge of the board build up pressure,
+    /// until they fuse into the wall and take their cell with them.
+    fn squeeze(&mut self) {
+        let origin = Coordinate::new(0, 0);
+        let moving = self.moving_cells();
+        let mut pressure = AHashMap::new();
+        for (pos, marble) in self.marbles.iter() {
+            let on_edge = pos.neighbors().iter().any(|n| !self.cells.contains(n));
+            if !on_edge || pos.distance(origin) <= EDGE_FUSE_MIN_DISTANCE || moving.contains(pos) {
+                continue;
+            }
+            // Only the same marble sitting there the whole time counts
+            let ticks = match self.edge_pressure.get(pos) {
+                Some((prev, ticks)) if prev == marble => (ticks + 1).min(EDGE_FUSE_TICKS),
+                _ => 1,
+            };
+            pressure.insert(*pos, (marble.clone(), ticks));
+        }
+        self.edge_pressure = pressure;
+
+        if self.squeeze_cooldown > 0 {
+            self.squeeze_cooldown -= 1;
+            return;
+        }
+        // Only one goes at a time; the rest wait their turn
+        let fused = self
+            .edge_pressure
+            .iter()
+            .filter(|(_, (_, ticks))| *ticks >= EDGE_FUSE_TICKS)
+            .map(|(pos, _)| *pos)
+            .min_by_key(|pos| (pos.x, pos.y));
+        if let Some(pos) = fused {
+            self.marbles.remove(&pos);
+            self.ages.remove(&pos);
+            self.cells.remove(&pos);
+            self.edge_pressure.remove(&pos);
+            self.squeeze_cooldown = EDGE_FUSE_COOLDOWN;
+            self.planned_next_spawn_pos = self.resettle_spawnpoint();
+        }
+    }
+
+    /// For every empty cell a marble would fall out of, where it would fall to next.
+    pub fn fall_directions(&self) -> Vec<(Coordinate, Coordinate)> {
+        if self.settings.gravity == GravityMode::None {
+            return Vec::new();
+        }
+        self.cells
+            .iter()
+            .filter(|c| !self.marbles.contains_key(*c))
+            .filter_map(|c| Some((*c, self.gravity_step(c)?)))
+            .collect()
+    }
+
+    /// Check the things about the board that should always hold, no matter what's happened.
+    /// If one doesn't, say what's wrong.
+    ///
+    /// This is for hunting down bugs in the rules; `fuzz_board` runs it after every tick.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if let Some(pos) = self.marbles.keys().find(|pos| !self.cells.contains(pos)) {
+            return Err(format!("marble at {:?} is off the board", pos));
+        }
+        if self.player_actions > self.action_queue.len() {
+            return Err(format!(
+                "{} player actions but only {} queued",
+                self.player_actions,
+                self.action_queue.len()
+            ));
+        }
+        if let Some(sp) = self.planned_next_spawn_pos {
+            if !self.is_in_bounds(&sp) {
+                return Err(format!("spawnpoint {:?} is off the board", sp));
+            }
+        }
+        // Once nothing's going on, everything should have fallen as far as it can
+        if self.action_queue.is_empty() {
+            let mut settled = self.clone();
+            settled.gravitate();
+            if *settled.marbles != *self.marbles {
+                return Err("marbles are floating with nothing queued".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the board as text, one letter per marble and `.` for empty cells.
+    pub fn ascii_dump(&self) -> String {
+        if self.cells.is_empty() {
+            return String::new();
+        }
+        let min_x = self.cells.iter().map(|c| c.x).min().unwrap();
+        let min_y = self.cells.iter().map(|c| c.y).min().unwrap();
+        let max_y = self.cells.iter().map(|c| c.y).max().unwrap();
+        // Each step in x is two columns over, and each row down is one column over,
+        // which makes the rows interleave like the hexagons do
+        let column = |c: &Coordinate| (2 * (c.x - min_x) + (c.y - min_y)) as usize;
+
+        let mut out = String::new();
+        for y in min_y..=max_y {
+            let mut row: Vec<char> = Vec::new();
+            for cell in self.cells.iter().filter(|c| c.y == y) {
+                let col = column(cell);
+                if row.len() <= col {
+                    row.resize(col + 1, ' ');
+                }
+                row[col] = self.marbles.get(cell).map_or('.', Marble::letter);
+            }
+            out.extend(row);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// How many ticks the board has run for.
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
     /// Get a reference to the board's action timer.
     pub fn action_timer(&self) -> u32 {
         self.action_timer
@@ -198,6 +794,27 @@ impl Board {
         &self.settings
     }
 
+    /// The ring with the most marbles in it, counting out from the center.
+    /// Ties go to the outermost one.
+    pub fn fullest_ring(&self) -> i32 {
+        let origin = Coordinate::new(0, 0);
+        (0..=self.radius() as i32)
+            .max_by_key(|&dist| {
+                self.marbles
+                    .iter()
+                    .filter(|&(pos, marble)| {
+                        *marble != Marble::Stone && pos.distance(origin) == dist
+                    })
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    /// How many more ticks the board runs at half speed
+    pub fn slow_ticks(&self) -> u32 {
+        self.slow_ticks
+    }
+
     /// Get a reference to the board's score.
     pub fn score(&self) -> u32 {
         self.score
@@ -208,16 +825,148 @@ impl Board {
         !self.is_in_bounds(c) || self.get_marble(c).is_some()
     }
 
+    /// Does the board have a reasonable first move?
+    ///
+    /// That means either some triangle loop clears a blob, or there's a good amount of
+    /// same-colored marbles already touching to build off of.
+    fn is_fair_start(&self) -> bool {
+        let pairs = self
+            .marbles
+            .iter()
+            .map(|(pos, marble)| {
+                pos.neighbors()
+                    .iter()
+                    .filter(|n| self.get_marble(n) == Some(marble))
+                    .count()
+            })
+            .sum::<usize>()
+            // each pair got counted from both ends
+            / 2;
+        if pairs >= FAIR_START_PAIRS {
+            return true;
+        }
+
+        self.candidate_loops(false)
+            .into_iter()
+            .any(|tri| !self.simulate_action(&BoardAction::Cycle(tri)).is_empty())
+    }
+
+    /// Every small loop worth trying: every triangle both ways around,
+    /// and if `rings` is set, every ring around a cell both ways around too.
+    ///
+    /// Loops with stone in them are left out, since stone can't move.
+    pub fn candidate_loops(&self, rings: bool) -> Vec<Vec<Coordinate>> {
+        let movable = |c: &Coordinate| {
+            self.get_marble(c)
+                .map_or(false, |marble| *marble != Marble::Stone)
+        };
+        let mut out = Vec::new();
+
+        for &pos in self.marbles.keys() {
+            if !movable(&pos) {
+                continue;
+            }
+            for &dir in Direction::all() {
+                let b = pos + dir;
+                let c = pos + (dir + Angle::Right);
+                // Each triangle gets found from all three corners; only keep it from one
+                let corner = |c: Coordinate| (c.x, c.y);
+                if !movable(&b)
+                    || !movable(&c)
+                    || corner(pos) > corner(b)
+                    || corner(pos) > corner(c)
+                {
+                    continue;
+                }
+                out.push(vec![pos, b, c]);
+                out.push(vec![pos, c, b]);
+            }
+        }
+
+        if rings {
+            for &center in self.cells.iter() {
+                let ring: Vec<_> = Direction::all().iter().map(|&dir| center + dir).collect();
+                if ring.iter().all(movable) {
+                    let mut reversed = ring.clone();
+                    reversed.reverse();
+                    out.push(ring);
+                    out.push(reversed);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Find a loop that would clear something right away, if there is one.
+    ///
+    /// If there isn't, the player is probably stuck.
+    pub fn find_useful_move(&self) -> Option<Vec<Coordinate>> {
+        self.candidate_loops(true)
+            .into_iter()
+            .find(|path| self.cycle_clears(path))
+    }
+
+    /// Would going around this loop make a blob big enough to clear?
+    ///
+    /// This is `simulate_action` without copying the board, for checking lots of loops at once.
+    /// Every cell on a candidate loop is full, so nothing falls after it goes around,
+    /// and any new blob has to touch the loop.
+    fn cycle_clears(&self, path: &[Coordinate]) -> bool {
+        // Everything on the loop moves one step along it
+        let after = |c: &Coordinate| match path.iter().position(|p| p == c) {
+            Some(idx) => self.get_marble(&path[(idx + path.len() - 1) % path.len()]),
+            None => self.get_marble(c),
+        };
+        path.iter()
+            .any(|c| floodfill_by(c, after).len() >= self.settings.clear_blob_size)
+    }
+
+    /// Does this loop have to be called off instead of going around?
+    ///
+    /// Someone else's pattern, a clear, or a power-up might have emptied out cells on it
+    /// since it was drawn, and stone still can't be moved. Rather than shuffling the marbles
+    /// that are left into the gaps, the whole loop fizzles and nothing on it moves.
+    pub fn cycle_fizzles(&self, poses: &[Coordinate]) -> bool {
+        poses
+            .iter()
+            .any(|pos| matches!(self.get_marble(pos), None | Some(Marble::Stone)))
+    }
+
+    /// The cells of the loop that's partway through going around, if there is one.
+    /// It was checked for fizzling when it started, so nothing else gets to touch them
+    /// until it lands.
+    fn moving_cells(&self) -> &[Coordinate] {
+        match self.action_queue.front() {
+            Some(BoardAction::Cycle(poses)) if self.action_timer > 0 => poses,
+            _ => &[],
+        }
+    }
+
     /// If the previous spawnpoint was here, wehere is the next spawnpoint?
     fn find_next_spawnpoint(&self, prev: Coordinate) -> Option<Coordinate> {
+        if let Some(edge) = self.spawn_edge() {
+            return self.find_next_edge_spawnpoint(&edge, prev);
+        }
+
+        // With fair spawns, start looking from a different side each time so the spawnpoint
+        // doesn't keep favoring whichever way comes first when it has a choice
+        let dirs = Direction::all();
+        let first = if self.settings.fair_spawns {
+            self.spawn_count as usize % dirs.len()
+        } else {
+            0
+        };
+
         // clockwise iter
         let maybe_pos = (|| {
-            for dir in Direction::all() {
+            for i in 0..dirs.len() {
+                let dir = dirs[(first + i) % dirs.len()];
                 // Use a maze algorithm: always keep your left hand on the wall.
-                let ahead = prev + *dir;
-                let wallfinder = prev + (*dir + Angle::Left);
+                let ahead = prev + dir;
+                let wallfinder = prev + (dir + Angle::Left);
 
-                if !self.is_solid(&ahead) && self.is_solid(&wallfinder) {
+                if !self.is_solid(&ahead) && self.is_crawl_wall(&wallfinder) {
                     // here's our pos! but let's gravitate it to avoid jank
                     return Some(ahead);
                 }
@@ -228,24 +977,114 @@ impl Board {
             Some(it) => Some(it),
             None => {
                 // uh oh ... look for the closest empty spot
-                Coordinate::new(0, 0)
-                    .range_iter(self.radius() as i32)
-                    .filter(|pos| self.get_marble(pos).is_none())
-                    .min_by_key(|pos| pos.distance(prev))
+                let empty = self
+                    .cells
+                    .iter()
+                    .filter(|pos| self.get_marble(pos).is_none());
+                if self.settings.fair_spawns {
+                    // Take turns between the spots that are tied for closest
+                    let closest = empty.clone().map(|pos| pos.distance(prev)).min();
+                    let mut ties = empty
+                        .filter(|pos| Some(pos.distance(prev)) == closest)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    ties.sort_by_key(|pos| (pos.x, pos.y));
+                    ties.get(first % ties.len().max(1)).copied()
+                } else {
+                    empty
+                        .min_by_key(|pos| (pos.distance(prev), pos.x, pos.y))
+                        .copied()
+                }
             }
         };
         // Shunt the spawnpoint to the outside, even if there's no gravity.
         maybe_pos.map(|pos| self.gravity_all(pos))
     }
 
+    /// If marbles don't pile up from the outside in, get the cells along the edge of the
+    /// board that the spawnpoint walks along, in clockwise order.
+    fn spawn_edge(&self) -> Option<Vec<Coordinate>> {
+        let rim = Coordinate::new(0, 0).ring_iter(self.radius() as i32, Spin::CW(Direction::XY));
+        match self.settings.gravity {
+            GravityMode::Inward => Some(rim.collect()),
+            GravityMode::Directional { .. } => {
+                // Only the cells on the ceiling
+                let up = self.gravity_direction + Angle::Back;
+                Some(
+                    rim.filter(|c| self.is_in_bounds(c) && !self.is_in_bounds(&(*c + up)))
+                        .collect(),
+                )
+            }
+            GravityMode::Outward | GravityMode::None => None,
+        }
+    }
+
+    /// Can the spawnpoint keep its hand on this position when crawling around?
+    ///
+    /// Holes inside the board don't count, or on boards with a hole in the middle
+    /// the spawnpoint would get stuck circling around the inner edge instead of
+    /// following the marbles.
+    fn is_crawl_wall(&self, c: &Coordinate) -> bool {
+        self.get_marble(c).is_some() || c.distance(Coordinate::new(0, 0)) > self.radius() as i32
+    }
+
+    /// Walk clockwise along the edge from the previous spawnpoint to the next empty edge cell.
+    fn find_next_edge_spawnpoint(&self, edge: &[Coordinate], prev: Coordinate) -> Option<Coordinate> {
+        let start = edge
+            .iter()
+            .position(|c| *c == prev)
+            .map_or(0, |idx| idx + 1);
+        edge.iter()
+            .cycle()
+            .skip(start)
+            .take(edge.len())
+            .find(|c| !self.is_solid(c))
+            .copied()
+    }
+
+    /// Marbles just moved around, so figure out where the spawnpoint should be now.
+    fn resettle_spawnpoint(&self) -> Option<Coordinate> {
+        match self.planned_next_spawn_pos {
+            Some(sp) if self.spawn_placed && !self.is_solid(&sp) => return Some(sp),
+            _ => {}
+        }
+        if let Some(edge) = self.spawn_edge() {
+            return match self.planned_next_spawn_pos {
+                Some(sp) if edge.contains(&sp) && !self.is_solid(&sp) => Some(sp),
+                Some(sp) => self.find_next_edge_spawnpoint(&edge, sp),
+                None => self.find_next_edge_spawnpoint(&edge, Coordinate::new(0, 0)),
+            };
+        }
+
+        // If we don't currently have a spawnpoint (aka, we *just* saved ourselves from losing),
+        // pretend it was at the center of the board.
+        let present_sp = self
+            .planned_next_spawn_pos
+            .unwrap_or_else(|| Coordinate::new(0, 0));
+        let shunted = self.gravity_all(present_sp);
+        if self.is_solid(&shunted) {
+            // The center might be a hole, or we fell onto something; go find a real spot
+            self.find_next_spawnpoint(shunted)
+        } else {
+            Some(shunted)
+        }
+    }
+
     fn timer_max(&self) -> u32 {
-        let out = match self.tick_count {
-            it if it < 60 * 10 => 60,
-            it if it < 60 * 20 => 50,
-            it if it < 60 * 40 => 40,
-            it if it < 60 * 60 => 30,
-            it if it < 60 * 120 => 40,
-            it => 40u32.saturating_sub(it / (60 * 30)).max(20),
+        let out = match self.settings.spawn_ramp {
+            SpawnRamp::Stepped => match self.tick_count {
+                it if it < 60 * 10 => 60,
+                it if it < 60 * 20 => 50,
+                it if it < 60 * 40 => 40,
+                it if it < 60 * 60 => 30,
+                it if it < 60 * 120 => 40,
+                it => 40u32.saturating_sub(it / (60 * 30)).max(20),
+            },
+            SpawnRamp::Exponential {
+                start,
+                half_life,
+                min,
+            } => ((start * 0.5f32.powf(self.tick_count as f32 / half_life)) as u32).max(min),
         };
         (out as f32 / self.settings.spawn_multiplier) as u32
     }
@@ -255,6 +1094,10 @@ impl Board {
         match &action {
             BoardAction::Cycle(poses) => {
                 if poses.len() >= 2 {
+                    // The player touched these so they get to stay fresh
+                    for pos in poses {
+                        self.ages.remove(pos);
+                    }
                     // Swap in a reversed order to end up with rotation in the right order.
                     for pair in poses.windows(2).rev() {
                         let a = self.marbles.remove(&pair[0]);
@@ -268,32 +1111,187 @@ impl Board {
                     }
                 }
             }
-            BoardAction::DeleteColor(color) => {
+            BoardAction::DeleteColor(color, _) => {
                 let score = self.get_score_from_action(&action).unwrap();
-                self.score_queue.push_back(score);
+                let cleared = self
+                    .marbles
+                    .iter()
+                    .filter(|&(_, marble)| marble == color)
+                    .map(|(pos, _)| *pos)
+                    .collect::<Vec<_>>();
+                self.push_score(score, &cleared, None);
+                for (pos, marble) in self.marbles.iter() {
+                    if marble == color {
+                        self.clear_events.push(ClearEvent {
+                            pos: *pos,
+                            marble: marble.clone(),
+                        });
+                    }
+                }
                 self.marbles.retain(|_, marble| marble != color);
+                let marbles = &self.marbles;
+                self.ages.retain(|pos, _| marbles.contains_key(pos));
             }
             BoardAction::ClearBlobs(_) => {
                 let blobs = self.find_blobs();
                 if !blobs.is_empty() {
                     let score = self.get_score_from_action(&action).unwrap();
-                    self.score_queue.push_back(score);
+                    let cleared = blobs.iter().flatten().copied().collect::<Vec<_>>();
+                    let biggest = blobs.iter().map(|blob| blob.len()).max().unwrap_or(0);
+                    let tier = self.settings.scoring.ladder_tier(biggest);
+                    self.push_score(score, &cleared, tier);
+                    if self.completes_ring(&cleared) {
+                        self.grow();
+                    }
                     // This might cause a cascade: immediately try again.
                     self.action_queue
                         .push_front(BoardAction::ClearBlobs(score.multiplier));
 
                     for c in blobs.into_iter().flatten() {
-                        self.marbles.remove(&c);
+                        if let Some(marble) = self.marbles.remove(&c) {
+                            self.clear_events.push(ClearEvent { pos: c, marble });
+                        }
+                        self.ages.remove(&c);
+                        // Clearing next to stone crumbles it
+                        for neighbor in c.neighbors().iter() {
+                            if self.get_marble(neighbor) == Some(&Marble::Stone) {
+                                self.marbles.remove(neighbor);
+                                self.clear_events.push(ClearEvent {
+                                    pos: *neighbor,
+                                    marble: Marble::Stone,
+                                });
+                            }
+                        }
                     }
                 }
             }
+            BoardAction::Attract(color, toward) => {
+                let distance = |c: &Coordinate| {
+                    toward
+                        .iter()
+                        .map(|target| target.distance(*c))
+                        .min()
+                        .unwrap_or(0)
+                };
+                // Closest first, so the marbles nearest the pattern get first pick of the cells
+                let mut pulled = self
+                    .marbles
+                    .iter()
+                    .filter(|&(pos, marble)| marble == color && !toward.contains(pos))
+                    .map(|(pos, _)| *pos)
+                    .collect::<Vec<_>>();
+                pulled.sort_by_key(|pos| (distance(pos), pos.x, pos.y));
+
+                // Nothing moves twice, and the pattern itself stays put
+                let mut moved = AHashSet::new();
+                for pos in pulled {
+                    let step = pos
+                        .neighbors()
+                        .iter()
+                        .copied()
+                        .filter(|next| {
+                            self.is_in_bounds(next)
+                                && !toward.contains(next)
+                                && !moved.contains(next)
+                                && distance(next) < distance(&pos)
+                                && self
+                                    .get_marble(next)
+                                    .map_or(true, |other| other != color && *other != Marble::Stone)
+                        })
+                        .min_by_key(|next| (next.x, next.y));
+                    if let Some(step) = step {
+                        let a = self.marbles.remove(&pos);
+                        let b = self.marbles.remove(&step);
+                        if let Some(a) = a {
+                            self.marbles.insert(step, a);
+                        }
+                        if let Some(b) = b {
+                            self.marbles.insert(pos, b);
+                        }
+                        self.ages.remove(&pos);
+                        self.ages.remove(&step);
+                        moved.insert(pos);
+                        moved.insert(step);
+                    }
+                }
+            }
+            &BoardAction::ClearRing(dist) => {
+                let score = self.get_score_from_action(&action).unwrap();
+                let ring = self
+                    .marbles
+                    .keys()
+                    .filter(|pos| pos.distance(Coordinate::new(0, 0)) == dist)
+                    .copied()
+                    .collect::<Vec<_>>();
+                self.push_score(score, &ring, None);
+                for pos in ring {
+                    if let Some(marble) = self.marbles.remove(&pos) {
+                        self.clear_events.push(ClearEvent { pos, marble });
+                    }
+                    self.ages.remove(&pos);
+                }
+                // Gravity runs once the action's done, so with outward gravity
+                // everything falls into the new ring
+                self.grow();
+            }
+            BoardAction::Shuffle => {
+                let poses = self
+                    .marbles
+                    .iter()
+                    .filter(|&(_, marble)| *marble != Marble::Stone)
+                    .map(|(pos, _)| *pos)
+                    .collect::<Vec<_>>();
+                let mut shuffled = poses
+                    .iter()
+                    .filter_map(|pos| self.marbles.remove(pos))
+                    .collect::<Vec<_>>();
+                shuffled.shuffle(&mut self.rng);
+                for (pos, marble) in poses.into_iter().zip(shuffled) {
+                    self.marbles.insert(pos, marble);
+                    self.ages.remove(&pos);
+                }
+            }
+            &BoardAction::SlowTime(ticks) => self.slow_ticks = ticks,
         }
     }
 
+    /// Queue up points to add to the score, and tell whoever's listening where they came from.
+    fn push_score(&mut self, packet: ScorePacket, cleared: &[Coordinate], tier: Option<usize>) {
+        self.score_queue.push_back(packet);
+
+        // Label it on the cell closest to the middle of the clear
+        let n = cleared.len().max(1) as f32;
+        let mid_x = cleared.iter().map(|c| c.x as f32).sum::<f32>() / n;
+        let mid_y = cleared.iter().map(|c| c.y as f32).sum::<f32>() / n;
+        let dist = |c: &Coordinate| (c.x as f32 - mid_x).powi(2) + (c.y as f32 - mid_y).powi(2);
+        let pos = cleared
+            .iter()
+            .copied()
+            .min_by(|a, b| dist(a).partial_cmp(&dist(b)).unwrap())
+            .unwrap_or_else(|| Coordinate::new(0, 0));
+        self.score_events.push(ScoreEvent {
+            pos,
+            size: cleared.len() as u32,
+            packet,
+            tier,
+        });
+    }
+
     pub fn get_score_from_action(&self, action: &BoardAction) -> Option<ScorePacket> {
+        let mut packet = self.get_unmutated_score_from_action(action)?;
+        if self.settings.mutators.contains(Mutator::DoubleScore) {
+            packet.base = packet.base.saturating_mul(2);
+        }
+        Some(packet)
+    }
+
+    fn get_unmutated_score_from_action(&self, action: &BoardAction) -> Option<ScorePacket> {
         match action {
-            BoardAction::Cycle(_) => None,
-            BoardAction::DeleteColor(color) => {
+            BoardAction::Cycle(_)
+            | BoardAction::Attract(..)
+            | BoardAction::Shuffle
+            | BoardAction::SlowTime(_) => None,
+            BoardAction::DeleteColor(color, _) => {
                 let remove_ct = self
                     .marbles
                     .values()
@@ -301,21 +1299,49 @@ impl Board {
                     .count();
                 Some(ScorePacket {
                     base: remove_ct as u32,
-                    multiplier: 1,
+                    multiplier: self.settings.scoring.delete_color_multiplier,
+                })
+            }
+            &BoardAction::ClearRing(dist) => {
+                // Stones go too, but they aren't worth anything
+                let remove_ct = self
+                    .marbles
+                    .iter()
+                    .filter(|&(pos, marble)| {
+                        *marble != Marble::Stone && pos.distance(Coordinate::new(0, 0)) == dist
+                    })
+                    .count();
+                Some(ScorePacket {
+                    base: remove_ct as u32,
+                    multiplier: self.settings.scoring.ring_clear_multiplier,
                 })
             }
             &BoardAction::ClearBlobs(premult) => {
                 let blobs = self.find_blobs();
                 if !blobs.is_empty() {
+                    let scoring = &self.settings.scoring;
                     let (base, multiplier) =
                         blobs
                             .into_iter()
                             .fold((0u32, premult), |(base, mult), blob| {
+                                let bonus = if scoring.blob_ladder.is_empty() {
+                                    let big = blob.len() >= scoring.big_blob_size;
+                                    big as u32 * scoring.big_blob_bonus
+                                } else {
+                                    scoring
+                                        .ladder_tier(blob.len())
+                                        .map_or(0, |tier| scoring.blob_ladder[tier].1)
+                                };
                                 (
-                                    base + blob.len() as u32,
-                                    mult + 1 + (blob.len() >= 6) as u32,
+                                    base.saturating_add(blob.len() as u32),
+                                    mult.saturating_add(scoring.multiplier_per_blob)
+                                        .saturating_add(bonus),
                                 )
                             });
+                    let multiplier = match scoring.max_multiplier {
+                        Some(max) => multiplier.min(max),
+                        None => multiplier,
+                    };
                     Some(ScorePacket { base, multiplier })
                 } else {
                     None
@@ -325,7 +1351,7 @@ impl Board {
     }
 
     fn gravitate(&mut self) {
-        if self.settings.gravity {
+        if self.settings.gravity != GravityMode::None {
             loop {
                 let mut shunted_any = false;
 
@@ -335,6 +1361,9 @@ impl Board {
                     if let Some(target) = target {
                         let m = self.marbles.remove(&pos).unwrap();
                         self.marbles.insert(target, m);
+                        if let Some(age) = self.ages.remove(&pos) {
+                            self.ages.insert(target, age);
+                        }
                         shunted_any = true;
                     }
                 }
@@ -348,7 +1377,17 @@ impl Board {
 
     /// Find the place the coordinate falls to under gravity, or None if it doesn't.
     fn gravity_step(&self, c: &Coordinate) -> Option<Coordinate> {
-        let gravity = c.direction_from_center_cw().unwrap_or(Direction::YX);
+        let gravity = match self.settings.gravity {
+            // Even with no gravity, the spawnpoint gets shunted outwards with this
+            GravityMode::Outward | GravityMode::None => {
+                c.direction_from_center_cw().unwrap_or(Direction::YX)
+            }
+            GravityMode::Inward => {
+                // The center is the bottom of the well
+                c.direction_from_center_cw()? + Angle::Back
+            }
+            GravityMode::Directional { .. } => self.gravity_direction,
+        };
 
         let mut shunt = None;
         let mut solid_poses = 0;
@@ -375,6 +1414,17 @@ impl Board {
         }
     }
 
+    /// How many cells you can go in the gravity direction before hitting the edge of the board.
+    fn height_above_floor(&self, c: &Coordinate) -> usize {
+        let mut c = *c;
+        let mut height = 0;
+        while self.is_in_bounds(&(c + self.gravity_direction)) {
+            c = c + self.gravity_direction;
+            height += 1;
+        }
+        height
+    }
+
     /// Repeatedly apply gravity to this point and return where it moves to.
     fn gravity_all(&self, mut c: Coordinate) -> Coordinate {
         while let Some(newpos) = self.gravity_step(&c) {
@@ -384,58 +1434,225 @@ impl Board {
     }
 
     /// Get all coordinates connected by color to the given coordinate (ignoring None)
+    ///
+    /// Stone never forms blobs.
     fn floodfill(&self, c: &Coordinate) -> Vec<Coordinate> {
-        let color = match self.get_marble(c) {
-            Some(it) => it,
-            None => return Vec::new(),
-        };
-
-        let mut seen = AHashSet::new();
-        let mut todo = vec![*c];
-        let mut blob = Vec::new();
-        while let Some(c) = todo.pop() {
-            if !seen.contains(&c) && Some(color) == self.get_marble(&c) {
-                seen.insert(c);
-                todo.push(c);
-                blob.push(c);
-                todo.extend_from_slice(&c.neighbors());
-            }
-        }
-        blob
+        floodfill_by(c, |c| self.get_marble(c))
     }
 
-    /// Spawn a new random marble at the given position. Won't clobber existing marbles
-    /// or form blobs big enough to score.
+    /// Spawn a new marble at the given position, with the next color in line.
+    /// Won't clobber existing marbles or form blobs big enough to score.
     /// Return `false` if it can't do it.
     fn spawn_marble(&mut self, c: &Coordinate) -> bool {
         if !self.is_in_bounds(c) || self.marbles.contains_key(c) {
             return false;
         }
-
-        let mut marble = Marble::random(self.settings.marble_color_count);
-        loop {
+        self.refill_upcoming();
+
+        // Try the color next in line, then the rest of the line,
+        // then every color we're allowed to spawn starting from a random one.
+        // Colors skipped over stay in line for later.
+        let palette = Marble::palette_size(self.settings.marble_color_count);
+        let start = self.rng.gen_range(0..palette);
+        let tries = self
+            .upcoming
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(idx, marble)| (Some(idx), marble))
+            .chain((0..palette).map(|offset| (None, Marble::nth_color((start + offset) % palette))))
+            .collect::<Vec<_>>();
+        let mut least_bad = None;
+        for (idx, marble) in tries {
             self.marbles.insert(*c, marble.clone());
-            if self.floodfill(c).len() < self.settings.clear_blob_size {
+            let blob_size = self.floodfill(c).len();
+            if blob_size < self.settings.clear_blob_size {
                 // no overflow here!
+                if let Some(idx) = idx {
+                    self.upcoming.remove(idx);
+                }
                 return true;
             }
-            // There are 7 marble colors and only 6 possible neighbors,
-            // so something will always happen eventually
-            marble = marble.another();
+            if least_bad
+                .as_ref()
+                .map_or(true, |(_, _, size)| blob_size < *size)
+            {
+                least_bad = Some((idx, marble, blob_size));
+            }
+        }
+        // With only a few colors, every one of them might make a blob.
+        // Go with the smallest one rather than sneaking in a color that isn't in play.
+        if let Some((idx, marble, _)) = least_bad {
+            if let Some(idx) = idx {
+                self.upcoming.remove(idx);
+            }
+            self.marbles.insert(*c, marble);
         }
+        true
     }
 
-    /// Get a reference to the board's score queue.
+    /// Make sure there's enough colors in line to preview.
+    fn refill_upcoming(&mut self) {
+        let palette = Marble::palette_size(self.settings.marble_color_count);
+        while self.upcoming.len() < SPAWN_PREVIEW_LENGTH {
+            match self.settings.spawn_policy {
+                SpawnPolicy::Random => self
+                    .upcoming
+                    .push_back(Marble::nth_color(self.rng.gen_range(0..palette))),
+                SpawnPolicy::Bag => {
+                    let mut bag = (0..palette).map(Marble::nth_color).collect::<Vec<_>>();
+                    bag.shuffle(&mut self.rng);
+                    self.upcoming.extend(bag);
+                }
+                SpawnPolicy::Weighted { bias } => {
+                    let counts = self.color_counts(palette);
+                    let total = counts.iter().sum::<usize>().max(1) as f32;
+                    let weights = counts
+                        .iter()
+                        .map(|&count| (1.0 - bias * count as f32 / total).max(MIN_SPAWN_WEIGHT));
+                    // The weights are never all zero, so this can't fail
+                    let dist = WeightedIndex::new(weights).unwrap();
+                    self.upcoming
+                        .push_back(Marble::nth_color(dist.sample(&mut self.rng)));
+                }
+            }
+        }
+    }
+
+    /// How many marbles of each of the first `palette` colors are on the board.
+    fn color_counts(&self, palette: usize) -> Vec<usize> {
+        let mut counts = vec![0; palette];
+        for marble in self.marbles.values() {
+            if let Some(idx) = marble.color_idx() {
+                if let Some(count) = counts.get_mut(idx) {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// The colors of the next few marbles to spawn, front first.
     ///
-    /// The score about to be added is at the bottom.
-    pub fn score_queue(&self) -> &VecDeque<ScorePacket> {
-        &self.score_queue
+    /// A color can get skipped over for a while if it would make a blob where it lands.
+    pub fn upcoming(&self) -> impl Iterator<Item = &Marble> + '_ {
+        self.upcoming.iter().take(SPAWN_PREVIEW_LENGTH)
+    }
+
+    /// Drop a stone at the given position. Won't clobber existing marbles.
+    /// Return `false` if it can't do it.
+    fn spawn_stone(&mut self, c: &Coordinate) -> bool {
+        if !self.is_in_bounds(c) || self.marbles.contains_key(c) {
+            return false;
+        }
+        self.marbles.insert(*c, Marble::Stone);
+        true
+    }
+
+    /// Queue up stones to drop in place of the next spawns.
+    pub fn send_garbage(&mut self, count: u32) {
+        self.pending_garbage += count;
+    }
+
+    /// How many marbles have spawned at each spawnpoint so far
+    pub fn spawn_tally(&self) -> &AHashMap<Coordinate, u32> {
+        &self.spawn_tally
+    }
+
+    /// Take points off for a loop that won't clear anything, if the scoring says to.
+    ///
+    /// The score stops at zero instead of going negative.
+    pub fn charge_misclick(&mut self) {
+        self.score = self
+            .score
+            .saturating_sub(self.settings.scoring.misclick_penalty);
+    }
+
+    /// How many stones are waiting to drop
+    pub fn pending_garbage(&self) -> u32 {
+        self.pending_garbage
+    }
+
+    /// Take every marble cleared since the last call, for keeping stats.
+    pub fn take_clear_events(&mut self) -> Vec<ClearEvent> {
+        std::mem::take(&mut self.clear_events)
+    }
+
+    /// Take how many loops fizzled since the last call, so the player gets their moves back.
+    pub fn take_fizzles(&mut self) -> u32 {
+        std::mem::take(&mut self.fizzles)
+    }
+
+    /// Take every bunch of points scored since the last call, for showing off.
+    ///
+    /// Points land in the score a little while after they're scored;
+    /// this is when they're scored.
+    pub fn take_score_events(&mut self) -> Vec<ScoreEvent> {
+        std::mem::take(&mut self.score_events)
+    }
+}
+
+/// Rolling history of board states, for rewinding and undoing.
+/// Snapshots can carry more than the board, for whatever has to rewind along with it.
+///
+/// The oldest snapshots get forgotten when it fills up.
+#[derive(Debug, Clone)]
+pub struct BoardHistory<T = Board> {
+    snapshots: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Clone> BoardHistory<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Save a copy of the snapshot.
+    pub fn record(&mut self, snapshot: &T) {
+        if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot.clone());
+    }
+
+    /// Take the most recent snapshot out of the history.
+    pub fn pop(&mut self) -> Option<T> {
+        self.snapshots.pop_back()
+    }
+
+    /// Look at the most recent snapshot without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        self.snapshots.back()
+    }
+
+    /// All the snapshots, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.snapshots.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
     }
 }
 
 /// Pieces that go on the board.
 /// This is purposely *not* `Copy` to hopefully cut down on duplication.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Marble {
     Red,
     Green,
@@ -444,13 +1661,54 @@ pub enum Marble {
     Cyan,
     Purple,
     Pink,
+    /// Dead marble that doesn't clear and can't be moved by the player.
+    /// It crumbles when a blob next to it is cleared.
+    Stone,
 }
 
 impl Marble {
-    /// Make a random marble.
-    pub fn random(max: usize) -> Self {
+    pub const ALL: [Marble; 8] = [
+        Marble::Red,
+        Marble::Green,
+        Marble::Blue,
+        Marble::Yellow,
+        Marble::Cyan,
+        Marble::Purple,
+        Marble::Pink,
+        Marble::Stone,
+    ];
+
+    /// One letter standing for this marble, for printing boards out as text
+    pub fn letter(&self) -> char {
+        match self {
+            Marble::Red => 'R',
+            Marble::Green => 'G',
+            Marble::Blue => 'B',
+            Marble::Yellow => 'Y',
+            Marble::Cyan => 'C',
+            Marble::Purple => 'P',
+            Marble::Pink => 'K',
+            Marble::Stone => '#',
+        }
+    }
+
+    /// How many colors actually spawn when asked for up to `max` of them.
+    fn palette_size(max: usize) -> usize {
+        max.clamp(1, Marble::Pink as usize)
+    }
+
+    /// The index `nth_color` would take to get this marble, or None for stone.
+    fn color_idx(&self) -> Option<usize> {
+        match self {
+            Marble::Stone => None,
+            other => Some(other.clone() as usize),
+        }
+    }
+
+    /// Get a colored marble by its index.
+    fn nth_color(idx: usize) -> Self {
         use Marble::*;
-        match QuadRand.gen_range(0..max.min(Marble::Pink as usize)) {
+        match idx {
             0 => Red,
             1 => Green,
             2 => Blue,
@@ -461,21 +1719,25 @@ impl Marble {
             _ => panic!(),
         }
     }
+}
 
-    /// Give another color that isn't this one, for use after random generation
-    /// doesn't go right.
-    fn another(&self) -> Self {
-        use Marble::*;
-        match self {
-            Red => Green,
-            Green => Blue,
-            Blue => Yellow,
-            Yellow => Cyan,
-            Cyan => Purple,
-            Purple => Pink,
-            Pink => Red,
-        }
-    }
+/// A marble that got taken off the board by clearing it.
+#[derive(Debug, Clone)]
+pub struct ClearEvent {
+    pub pos: Coordinate,
+    pub marble: Marble,
+}
+
+/// Points scored by one clear.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreEvent {
+    /// The cell nearest the middle of what got cleared
+    pub pos: Coordinate,
+    /// How many marbles got cleared
+    pub size: u32,
+    pub packet: ScorePacket,
+    /// How far up the blob size ladder the biggest blob got, from 0, if the scoring has one
+    pub tier: Option<usize>,
 }
 
 /// Abstract actions that can happen on the board.
@@ -487,23 +1749,61 @@ pub enum BoardAction {
     ///
     /// DO NOT make the last the same as the first, this cycles it itself
     Cycle(Vec<Coordinate>),
-    /// Delete all marbles of the given color
-    DeleteColor(Marble),
+    /// Delete all marbles of the given color.
+    ///
+    /// The coords are the hexagon that set it off, which the marbles get pulled into
+    /// on their way out. If there aren't any they go to the middle of the board instead.
+    DeleteColor(Marble, Vec<Coordinate>),
     /// Clear all the large enough blobs of marbles, with the given additional score multiplier
     ClearBlobs(u32),
+    /// Move every marble of the given color one step closer to the given coords,
+    /// swapping places with whatever's in the way
+    Attract(Marble, Vec<Coordinate>),
+    /// Clear every marble (and stone) the given distance from the center
+    ClearRing(i32),
+    /// Scramble where all the marbles are.
+    ///
+    /// Every cell with a marble in it still has one afterwards, so nothing needs to fall.
+    /// Stone stays put.
+    Shuffle,
+    /// Run the board at half speed for the given number of ticks
+    SlowTime(u32),
 }
 
 impl BoardAction {
-    pub const CYCLE_TIME: u32 = 10;
-    pub const DELETE_COLOR_TIME: u32 = 30;
-    pub const CLEAR_BLOBS_TIME: u32 = 20;
+    pub const ATTRACT_TIME: u32 = 15;
+    pub const CLEAR_RING_TIME: u32 = 30;
+    pub const SHUFFLE_TIME: u32 = 20;
+    pub const SLOW_TIME_TIME: u32 = 1;
 
     /// How many frames should it take to finish this action?
-    pub fn time(&self) -> u32 {
+    pub fn time(&self, times: &ActionTimes) -> u32 {
         match self {
-            BoardAction::Cycle(_) => Self::CYCLE_TIME,
-            BoardAction::DeleteColor(_) => Self::DELETE_COLOR_TIME,
-            BoardAction::ClearBlobs(_) => Self::CLEAR_BLOBS_TIME,
+            BoardAction::Cycle(_) => times.cycle,
+            BoardAction::DeleteColor(..) => times.delete_color,
+            BoardAction::ClearBlobs(_) => times.clear_blobs,
+            BoardAction::Attract(..) => Self::ATTRACT_TIME,
+            BoardAction::ClearRing(_) => Self::CLEAR_RING_TIME,
+            BoardAction::Shuffle => Self::SHUFFLE_TIME,
+            BoardAction::SlowTime(_) => Self::SLOW_TIME_TIME,
+        }
+    }
+}
+
+/// How many ticks the actions that animate the most take to play out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionTimes {
+    pub cycle: u32,
+    pub delete_color: u32,
+    pub clear_blobs: u32,
+}
+
+impl Default for ActionTimes {
+    fn default() -> Self {
+        Self {
+            cycle: 10,
+            delete_color: 30,
+            clear_blobs: 20,
         }
     }
 }
@@ -512,18 +1812,88 @@ impl BoardAction {
 ///
 /// Each marble removed from the board contributes one base point.
 ///
-/// The multiplier starts at 1.
+/// With the default `ScoringConfig`:
 ///
-/// - Clearing more than one blob adds 1 to the multiplier for each blob.
-/// - Each blob with a size more than 6 adds 1 to the multiplier.
+/// - Clears the player causes start at a multiplier of 0, and clears caused by spawning start at 1.
+/// - Each blob cleared adds 1 to the multiplier.
+/// - Each blob with a size of 6 or more adds 1 to the multiplier.
 /// - "Cascading", where clearing a blob leads to marbles falling and clearing more marbles,
 ///   makes the next clear start at this multiplier.
+/// - The multiplier can't go above 16.
 #[derive(Debug, Clone, Copy)]
 pub struct ScorePacket {
     pub base: u32,
     pub multiplier: u32,
 }
 
+impl ScorePacket {
+    /// How much this adds to the score. Marathon runs can't overflow it; it just stops.
+    pub fn points(&self) -> u32 {
+        self.base.saturating_mul(self.multiplier)
+    }
+}
+
+/// Knobs for how scoring works.
+#[derive(Debug, Clone)]
+pub struct ScoringConfig {
+    /// Multiplier a clear caused by the player drawing a pattern starts at
+    pub player_start_multiplier: u32,
+    /// Multiplier a clear caused by a marble spawning (or other board events) starts at
+    pub spawn_start_multiplier: u32,
+    /// How much each blob in a clear adds to the multiplier
+    pub multiplier_per_blob: u32,
+    /// Blobs at least this big add `big_blob_bonus` to the multiplier
+    pub big_blob_size: usize,
+    pub big_blob_bonus: u32,
+    /// Multiplier for clearing a color with a hexagon
+    pub delete_color_multiplier: u32,
+    /// Multiplier for clearing a ring with a power-up
+    pub ring_clear_multiplier: u32,
+    /// The multiplier can't go above this, if set
+    pub max_multiplier: Option<u32>,
+    /// Blob sizes, smallest first, and how much blobs at least that big add to the multiplier.
+    /// If there are any, blobs get the bonus for the biggest one they reach
+    /// instead of `big_blob_bonus`.
+    pub blob_ladder: Vec<(usize, u32)>,
+    /// Points taken off for drawing a loop that doesn't clear anything, or 0 for none
+    pub misclick_penalty: u32,
+}
+
+impl ScoringConfig {
+    /// Small clears barely score, but bigger and bigger blobs climb a ladder of bonuses.
+    /// The top rungs add up fast, so the multiplier tops out.
+    pub fn blob_ladder() -> Self {
+        Self {
+            big_blob_bonus: 0,
+            blob_ladder: vec![(6, 2), (8, 4), (10, 8)],
+            max_multiplier: Some(16),
+            ..Self::default()
+        }
+    }
+
+    /// Which rung of the blob size ladder a blob this big reaches, if any.
+    pub fn ladder_tier(&self, size: usize) -> Option<usize> {
+        self.blob_ladder.iter().rposition(|&(min, _)| size >= min)
+    }
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            player_start_multiplier: 0,
+            spawn_start_multiplier: 1,
+            multiplier_per_blob: 1,
+            big_blob_size: 6,
+            big_blob_bonus: 1,
+            delete_color_multiplier: 1,
+            ring_clear_multiplier: 1,
+            max_multiplier: None,
+            blob_ladder: Vec::new(),
+            misclick_penalty: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BoardSettings {
     /// How many marbles to the edge from the center.
@@ -531,14 +1901,62 @@ pub struct BoardSettings {
     pub radius: usize,
     /// How many outside layers of marble to start
     pub border_width: usize,
-    /// Whether gravity is on (it will point to the outside)
-    pub gravity: bool,
+    /// Which way marbles fall
+    pub gravity: GravityMode,
     /// How many marbles need to be next to each other to clear
     pub clear_blob_size: usize,
     /// Multiplier on marble spawn rate
     pub spawn_multiplier: f32,
+    /// How spawns speed up over the run
+    pub spawn_ramp: SpawnRamp,
+    /// How the colors of new marbles get picked
+    pub spawn_policy: SpawnPolicy,
+    /// Hold off on spawning while anything's still playing out,
+    /// so a long clear doesn't eat into the time before the next marble
+    pub freeze_spawns_during_actions: bool,
     /// How many colors of marbles try to spawn
     pub marble_color_count: usize,
+    /// If set, only these cells within the radius exist. Everything else is a hole.
+    pub cell_mask: Option<AHashSet<Coordinate>>,
+    /// How points are handed out
+    pub scoring: ScoringConfig,
+    /// If set, marbles the player doesn't move for this many ticks turn to stone
+    pub petrify_after: Option<u32>,
+    /// If set, the run ends after the player draws this many patterns
+    pub move_limit: Option<u32>,
+    /// Practice runs let you rewind, but don't get highscores
+    pub practice: bool,
+    /// A second player draws on the same board with the keyboard. Co-op runs don't get highscores.
+    pub coop: bool,
+    /// If set, a CPU plays its own board alongside the player's and they send each other stones.
+    /// These runs don't get highscores either.
+    pub versus: Option<CpuDifficulty>,
+    /// If set, clearing marbles charges up a meter that does this when it's full.
+    /// These runs don't get highscores.
+    pub special: Option<SpecialAction>,
+    /// How fast the board runs. Slowed down runs rank separately.
+    pub game_speed: GameSpeed,
+    /// If set, the player picks where some of the marbles spawn
+    pub manual_spawns: Option<ManualSpawns>,
+    /// If set, the board grows as the run goes on
+    pub expansion: Option<Expansion>,
+    /// If set, this is the weekly challenge. The board's RNG is seeded from it,
+    /// and highscores go in their own bucket for the week instead of on the leaderboard.
+    pub weekly: Option<WeeklyChallenge>,
+    /// How long the slower actions take to play out.
+    /// This doesn't change what anything scores, so runs rank together no matter what it is.
+    pub action_times: ActionTimes,
+    /// Modifiers turned on for this run.
+    ///
+    /// Don't set this directly; use `with_mutators` so the settings they change get changed.
+    pub mutators: MutatorSet,
+    /// If set, reroll the starting board until there's a reasonable first move
+    pub fair_start: bool,
+    /// If set, the spawnpoint takes turns between the ways it could go instead of always
+    /// preferring the same one, so spawns don't drift toward one side over a long run
+    pub fair_spawns: bool,
+    /// Pop up hints about how to play during the run
+    pub show_tips: bool,
 
     /// A key associated with this gamemode for storing scores, or None
     /// if it's a custom mode.
@@ -546,14 +1964,116 @@ pub struct BoardSettings {
 }
 
 impl BoardSettings {
+    /// Apply the given mutators to these settings.
+    pub fn with_mutators(mut self, mutators: MutatorSet) -> Self {
+        for mutator in mutators.iter() {
+            match mutator {
+                Mutator::FastSpawns => self.spawn_multiplier *= 1.5,
+                Mutator::ThreeColors => self.marble_color_count = self.marble_color_count.min(3),
+                Mutator::ColorBag => self.spawn_policy = SpawnPolicy::Bag,
+                Mutator::Misclicks => self.scoring.misclick_penalty = MISCLICK_PENALTY,
+                Mutator::Growth => {
+                    self.expansion = Some(Expansion {
+                        max_radius: self.radius + 3,
+                        every_points: 50,
+                    })
+                }
+                // These are checked while playing
+                Mutator::InvisibleSigils
+                | Mutator::StoneRain
+                | Mutator::DoubleScore
+                | Mutator::PowerUps
+                | Mutator::Squeeze => {}
+            }
+        }
+        self.mutators = self.mutators.union(mutators);
+        self
+    }
+
+    pub fn with_fair_spawns(mut self, fair_spawns: bool) -> Self {
+        self.fair_spawns = fair_spawns;
+        self
+    }
+
+    pub fn with_game_speed(mut self, game_speed: GameSpeed) -> Self {
+        self.game_speed = game_speed;
+        self
+    }
+
+    pub fn with_game_feel(mut self, game_feel: GameFeel) -> Self {
+        self.action_times = game_feel.action_times();
+        self
+    }
+
+    /// The key to store highscores under, or None if this is a custom mode,
+    /// a practice run, a co-op run, a versus run, a run with a special action,
+    /// or the weekly challenge.
+    pub fn leaderboard_key(&self) -> Option<LeaderboardKey> {
+        if self.practice
+            || self.coop
+            || self.versus.is_some()
+            || self.special.is_some()
+            || self.weekly.is_some()
+        {
+            return None;
+        }
+        self.mode_key.map(|mode| LeaderboardKey {
+            mode,
+            mutators: self.mutators,
+            speed: self.game_speed,
+        })
+    }
+
+    /// Do runs with these settings save a highscore anywhere,
+    /// either on the leaderboard or for the weekly challenge?
+    pub fn keeps_highscores(&self) -> bool {
+        self.leaderboard_key().is_some() || self.weekly.is_some()
+    }
+
+    /// What runs with these settings are ranked by. Custom modes go by score.
+    pub fn ranking(&self) -> Ranking {
+        self.mode_key.map_or(Ranking::Score, |mode| mode.ranking())
+    }
+
+    /// Get every cell on a board with these settings.
+    pub fn cells(&self) -> AHashSet<Coordinate> {
+        Coordinate::new(0, 0)
+            .range_iter(self.radius as i32)
+            .filter(|c| match &self.cell_mask {
+                Some(mask) => mask.contains(c),
+                None => true,
+            })
+            .collect()
+    }
+
     pub fn classic() -> Self {
         Self {
             radius: 5,
             border_width: 2,
             spawn_multiplier: 1.0,
-            gravity: true,
+            spawn_ramp: SpawnRamp::Stepped,
+            gravity: GravityMode::Outward,
             clear_blob_size: 4,
             marble_color_count: 6,
+            cell_mask: None,
+            scoring: ScoringConfig::default(),
+            fair_start: false,
+            fair_spawns: false,
+            show_tips: false,
+            petrify_after: None,
+            move_limit: None,
+            practice: false,
+            coop: false,
+            versus: None,
+            special: None,
+            spawn_policy: SpawnPolicy::Random,
+            freeze_spawns_during_actions: false,
+            game_speed: GameSpeed::Full,
+            action_times: ActionTimes::default(),
+            manual_spawns: None,
+            expansion: None,
+            weekly: None,
+            mutators: MutatorSet::empty(),
             mode_key: Some(BoardSettingsModeKey::Classic),
         }
     }
@@ -563,45 +2083,1069 @@ impl BoardSettings {
             radius: 6,
             border_width: 3,
             spawn_multiplier: 1.2,
-            gravity: true,
-            clear_blob_size: 4,
             marble_color_count: 7,
             mode_key: Some(BoardSettingsModeKey::Advanced),
+            ..BoardSettings::classic()
         }
     }
 
     pub fn no_gravity() -> Self {
         Self {
             radius: 3,
-            border_width: 2,
             spawn_multiplier: 0.8,
-            gravity: false,
-            clear_blob_size: 4,
+            gravity: GravityMode::None,
             marble_color_count: 4,
             mode_key: Some(BoardSettingsModeKey::NoGravity),
+            ..BoardSettings::classic()
         }
     }
+
+    pub fn rotating_gravity() -> Self {
+        Self {
+            spawn_multiplier: 0.9,
+            gravity: GravityMode::Directional {
+                start: Direction::ZY,
+                rotate_period: Some(60 * 20),
+            },
+            mode_key: Some(BoardSettingsModeKey::RotatingGravity),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn pegboard() -> Self {
+        let radius = 5;
+        // Knock out a ring of pegs for the marbles to pile up on
+        let pegs = Coordinate::new(0, 0)
+            .ring_iter(2, Spin::CW(Direction::XY))
+            .step_by(2)
+            .collect::<AHashSet<_>>();
+        let mask = Coordinate::new(0, 0)
+            .range_iter(radius as i32)
+            .filter(|c| !pegs.contains(c))
+            .collect();
+        Self {
+            radius,
+            cell_mask: Some(mask),
+            mode_key: Some(BoardSettingsModeKey::Pegboard),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn donut() -> Self {
+        let radius = 6;
+        let mask = Coordinate::new(0, 0)
+            .range_iter(radius as i32)
+            .filter(|c| c.distance(Coordinate::new(0, 0)) > 2)
+            .collect();
+        Self {
+            radius,
+            cell_mask: Some(mask),
+            mode_key: Some(BoardSettingsModeKey::Donut),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn fossil() -> Self {
+        Self {
+            petrify_after: Some(60 * 40),
+            mode_key: Some(BoardSettingsModeKey::Fossil),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn beginner() -> Self {
+        Self {
+            spawn_multiplier: 0.6,
+            clear_blob_size: 5,
+            marble_color_count: 3,
+            show_tips: true,
+            freeze_spawns_during_actions: true,
+            // New players shouldn't start on a board with nothing to do
+            fair_start: true,
+            mode_key: Some(BoardSettingsModeKey::Beginner),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn placement() -> Self {
+        Self {
+            manual_spawns: Some(ManualSpawns {
+                every: 5,
+                time_limit: TICKS_PER_SECOND * 3,
+            }),
+            mode_key: Some(BoardSettingsModeKey::Placement),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn ladder() -> Self {
+        Self {
+            scoring: ScoringConfig::blob_ladder(),
+            mode_key: Some(BoardSettingsModeKey::Ladder),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn limited_moves() -> Self {
+        Self {
+            spawn_multiplier: 0.8,
+            move_limit: Some(30),
+            mode_key: Some(BoardSettingsModeKey::LimitedMoves),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn sudden_death() -> Self {
+        Self {
+            spawn_ramp: SpawnRamp::Exponential {
+                start: 60.0,
+                half_life: (TICKS_PER_SECOND * 45) as f32,
+                min: 8,
+            },
+            mode_key: Some(BoardSettingsModeKey::SuddenDeath),
+            ..BoardSettings::classic()
+        }
+    }
+
+    pub fn center_gravity() -> Self {
+        Self {
+            gravity: GravityMode::Inward,
+            mode_key: Some(BoardSettingsModeKey::CenterGravity),
+            ..BoardSettings::classic()
+        }
+    }
+}
+
+/// How the time between spawns shrinks over a run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnRamp {
+    /// Speeds up in steps, then backs off a bit so long runs stay playable.
+    Stepped,
+    /// Starts at `start` ticks between spawns and halves every `half_life` ticks,
+    /// never going below `min`.
+    Exponential {
+        start: f32,
+        half_life: f32,
+        min: u32,
+    },
+}
+
+/// How the colors of new marbles get picked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnPolicy {
+    /// Every marble is a fresh random color.
+    Random,
+    /// Colors get dealt from a shuffled bag with one of each color in it,
+    /// so no color goes missing for long.
+    Bag,
+    /// Colors that already cover a lot of the board spawn less often,
+    /// so one color can't flood it.
+    ///
+    /// A color's chance gets scaled down by `bias` times its share of the marbles on the board;
+    /// 0 is the same as `Random`, and 1 means a color covering the whole board hardly ever spawns.
+    Weighted { bias: f32 },
+}
+
+/// Which way marbles fall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GravityMode {
+    /// Away from the center. Spawns crawl around the inside of the pile.
+    Outward,
+    /// Towards the center. Spawns walk around the rim.
+    Inward,
+    /// Everything falls in the same direction, like rain.
+    Directional {
+        /// Which way things fall at the start
+        start: Direction,
+        /// If set, the direction rotates clockwise by 60 degrees this often in ticks
+        rotate_period: Option<u32>,
+    },
+    /// Marbles stay where they are put.
+    None,
 }
 
 #[non_exhaustive]
 #[derive(Enum, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BoardSettingsModeKey {
     Classic,
+    Beginner,
     Advanced,
     NoGravity,
+    CenterGravity,
+    RotatingGravity,
+    Pegboard,
+    Donut,
+    Fossil,
+    LimitedMoves,
+    SuddenDeath,
+    Placement,
+    Ladder,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct PlaySettings {
-    pub funni_background: bool,
-    pub animations: bool,
+impl BoardSettingsModeKey {
+    /// Every mode, in the order they show up on the mode select screen.
+    pub const ALL: [BoardSettingsModeKey; 13] = [
+        BoardSettingsModeKey::Classic,
+        BoardSettingsModeKey::Beginner,
+        BoardSettingsModeKey::Advanced,
+        BoardSettingsModeKey::NoGravity,
+        BoardSettingsModeKey::CenterGravity,
+        BoardSettingsModeKey::RotatingGravity,
+        BoardSettingsModeKey::Pegboard,
+        BoardSettingsModeKey::Donut,
+        BoardSettingsModeKey::Fossil,
+        BoardSettingsModeKey::LimitedMoves,
+        BoardSettingsModeKey::SuddenDeath,
+        BoardSettingsModeKey::Placement,
+        BoardSettingsModeKey::Ladder,
+    ];
+
+    /// Get the settings this mode is played with.
+    pub fn settings(&self) -> BoardSettings {
+        match self {
+            BoardSettingsModeKey::Classic => BoardSettings::classic(),
+            BoardSettingsModeKey::Beginner => BoardSettings::beginner(),
+            BoardSettingsModeKey::Advanced => BoardSettings::advanced(),
+            BoardSettingsModeKey::NoGravity => BoardSettings::no_gravity(),
+            BoardSettingsModeKey::CenterGravity => BoardSettings::center_gravity(),
+            BoardSettingsModeKey::RotatingGravity => BoardSettings::rotating_gravity(),
+            BoardSettingsModeKey::Pegboard => BoardSettings::pegboard(),
+            BoardSettingsModeKey::Donut => BoardSettings::donut(),
+            BoardSettingsModeKey::Fossil => BoardSettings::fossil(),
+            BoardSettingsModeKey::LimitedMoves => BoardSettings::limited_moves(),
+            BoardSettingsModeKey::SuddenDeath => BoardSettings::sudden_death(),
+            BoardSettingsModeKey::Placement => BoardSettings::placement(),
+            BoardSettingsModeKey::Ladder => BoardSettings::ladder(),
+        }
+    }
+
+    /// Name to show to the player.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoardSettingsModeKey::Classic => "CLASSIC",
+            BoardSettingsModeKey::Beginner => "BEGINNER",
+            BoardSettingsModeKey::Advanced => "ADVANCED",
+            BoardSettingsModeKey::NoGravity => "NO GRAVITY",
+            BoardSettingsModeKey::CenterGravity => "BLACK HOLE",
+            BoardSettingsModeKey::RotatingGravity => "TILT",
+            BoardSettingsModeKey::Pegboard => "PEGBOARD",
+            BoardSettingsModeKey::Donut => "DONUT",
+            BoardSettingsModeKey::Fossil => "FOSSIL",
+            BoardSettingsModeKey::LimitedMoves => "30 MOVES",
+            BoardSettingsModeKey::SuddenDeath => "SUDDEN DEATH",
+            BoardSettingsModeKey::Placement => "PLACEMENT",
+            BoardSettingsModeKey::Ladder => "LADDER",
+        }
+    }
+
+    /// Blurb for the mode select screen.
+    pub fn description(&self) -> &'static str {
+        match self {
+            BoardSettingsModeKey::Classic => "THE NORMAL GAME.",
+            BoardSettingsModeKey::Beginner => {
+                "ONLY 3 COLORS,\nSLOWER SPAWNS,\nAND TIPS ON HOW\nTO PLAY. CLEAR\nGROUPS OF 5."
+            }
+            BoardSettingsModeKey::Advanced => "BIGGER BOARD,\nMORE COLORS,\nFASTER SPAWNS.",
+            BoardSettingsModeKey::NoGravity => "SMALL BOARD.\nMARBLES STAY\nWHERE THEY ARE.",
+            BoardSettingsModeKey::CenterGravity => {
+                "MARBLES FALL\nTOWARDS THE\nCENTER AND SPAWN\nON THE RIM."
+            }
+            BoardSettingsModeKey::RotatingGravity => {
+                "MARBLES FALL\nSIDEWAYS. EVERY\n20 SECONDS THE\nBOARD TILTS."
+            }
+            BoardSettingsModeKey::Pegboard => {
+                "THE BOARD HAS\nHOLES IN IT.\nMARBLES CAN'T\nGO THROUGH THEM."
+            }
+            BoardSettingsModeKey::Donut => "A RING-SHAPED\nBOARD WITH NOTHING\nIN THE MIDDLE.",
+            BoardSettingsModeKey::Fossil => {
+                "MARBLES YOU DON'T\nMOVE FOR A WHILE\nTURN TO STONE.\nCLEAR NEXT TO\nSTONE TO BREAK IT."
+            }
+            BoardSettingsModeKey::LimitedMoves => {
+                "YOU ONLY GET TO\nDRAW 30 PATTERNS.\nMAKE THEM COUNT!"
+            }
+            BoardSettingsModeKey::SuddenDeath => {
+                "SPAWNS GET FASTER\nAND FASTER AND\nFASTER. RANKED BY\nHOW LONG YOU LAST."
+            }
+            BoardSettingsModeKey::Placement => {
+                "EVERY 5TH MARBLE,\nCLICK AN EMPTY\nCELL TO PICK WHERE\nIT SPAWNS. BE\nQUICK ABOUT IT!"
+            }
+            BoardSettingsModeKey::Ladder => {
+                "GROUPS OF 4 BARELY\nSCORE. BUILD UP TO\n6, 8, AND 10 FOR\nBIGGER AND BIGGER\nBONUSES."
+            }
+        }
+    }
+
+    /// What the leaderboard for this mode ranks runs by.
+    pub fn ranking(&self) -> Ranking {
+        match self {
+            BoardSettingsModeKey::SuddenDeath => Ranking::SurvivalTime,
+            _ => Ranking::Score,
+        }
+    }
 }
 
-impl Default for PlaySettings {
+/// What a leaderboard ranks runs by. Bigger numbers are better either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    Score,
+    /// How many ticks the run lasted
+    SurvivalTime,
+}
+
+impl Ranking {
+    /// The number to rank the run on this board by.
+    pub fn value(&self, board: &Board) -> u32 {
+        match self {
+            Ranking::Score => board.score(),
+            Ranking::SurvivalTime => board.tick_count(),
+        }
+    }
+
+    /// Show a ranked number to the player.
+    pub fn format(&self, value: u32) -> String {
+        match self {
+            Ranking::Score => format!("{}", value as u64 * 100),
+            Ranking::SurvivalTime => format_ticks(value),
+        }
+    }
+
+    /// What to call the best run.
+    pub fn best_label(&self) -> &'static str {
+        match self {
+            Ranking::Score => "HISCORE",
+            Ranking::SurvivalTime => "BEST TIME",
+        }
+    }
+}
+
+/// Where highscores get filed. Runs with different modifiers or speeds rank separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LeaderboardKey {
+    pub mode: BoardSettingsModeKey,
+    pub mutators: MutatorSet,
+    pub speed: GameSpeed,
+}
+
+/// A setting with a handful of choices, which a button steps through.
+pub trait Cyclable: Copy + PartialEq + 'static {
+    /// Every choice, in the order the button goes through them
+    const ALL: &'static [Self];
+
+    /// What the button calls this choice
+    fn name(&self) -> &'static str;
+
+    /// The one after this one, wrapping around at the end
+    fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|it| it == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// How fast the board runs, for players who need more time to think.
+///
+/// This slows down the board's timers and spawns; everything still draws at the normal rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameSpeed {
+    Full,
+    ThreeQuarters,
+    Half,
+}
+
+impl GameSpeed {
+    /// Whether the board should tick on this frame.
+    pub fn ticks_on(&self, frame: u32) -> bool {
+        match self {
+            GameSpeed::Full => true,
+            GameSpeed::ThreeQuarters => frame % 4 != 3,
+            GameSpeed::Half => frame % 2 == 0,
+        }
+    }
+}
+
+impl Cyclable for GameSpeed {
+    const ALL: &'static [Self] = &[GameSpeed::Full, GameSpeed::ThreeQuarters, GameSpeed::Half];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GameSpeed::Full => "100%",
+            GameSpeed::ThreeQuarters => "75%",
+            GameSpeed::Half => "50%",
+        }
+    }
+}
+
+impl Default for GameSpeed {
     fn default() -> Self {
-        Self {
-            funni_background: true,
-            animations: true,
+        GameSpeed::Full
+    }
+}
+
+/// How often the player picks where a marble spawns, and how long they have to do it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManualSpawns {
+    /// Every this-many-th marble gets placed by hand
+    pub every: u32,
+    /// Ticks to pick a cell before the marble spawns wherever it was going to anyways
+    pub time_limit: u32,
+}
+
+/// When the board grows, and how big it can get.
+///
+/// It grows a ring each time a whole ring gets cleared and every `every_points` points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expansion {
+    /// The board stops growing once it's this big
+    pub max_radius: usize,
+    pub every_points: u32,
+}
+
+/// How quickly moves and clears play out on the board.
+///
+/// Unlike `GameSpeed` this only changes how long the animations take, not spawns or timers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameFeel {
+    Snappy,
+    Normal,
+    Cinematic,
+}
+
+impl GameFeel {
+    pub fn action_times(&self) -> ActionTimes {
+        match self {
+            GameFeel::Snappy => ActionTimes {
+                cycle: 6,
+                delete_color: 18,
+                clear_blobs: 12,
+            },
+            GameFeel::Normal => ActionTimes::default(),
+            GameFeel::Cinematic => ActionTimes {
+                cycle: 15,
+                delete_color: 45,
+                clear_blobs: 30,
+            },
+        }
+    }
+}
+
+impl Cyclable for GameFeel {
+    const ALL: &'static [Self] = &[GameFeel::Snappy, GameFeel::Normal, GameFeel::Cinematic];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GameFeel::Snappy => "SNAPPY",
+            GameFeel::Normal => "NORMAL",
+            GameFeel::Cinematic => "CINEMATIC",
+        }
+    }
+}
+
+impl Default for GameFeel {
+    fn default() -> Self {
+        GameFeel::Normal
+    }
+}
+
+/// How close the mouse has to get to a cell for a pattern to reach it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SnapRadius {
+    /// Only the cell the mouse is actually in
+    Off,
+    Near,
+    Far,
+}
+
+impl SnapRadius {
+    /// How far from a cell's middle still counts, as a fraction of the distance between cells.
+    ///
+    /// The cell under the mouse always counts, so anything under one half does nothing.
+    pub fn reach(&self) -> f32 {
+        match self {
+            SnapRadius::Off => 0.0,
+            SnapRadius::Near => 0.65,
+            SnapRadius::Far => 0.85,
+        }
+    }
+}
+
+impl Cyclable for SnapRadius {
+    const ALL: &'static [Self] = &[SnapRadius::Off, SnapRadius::Near, SnapRadius::Far];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SnapRadius::Off => "OFF",
+            SnapRadius::Near => "NEAR",
+            SnapRadius::Far => "FAR",
+        }
+    }
+}
+
+impl Default for SnapRadius {
+    fn default() -> Self {
+        SnapRadius::Near
+    }
+}
+
+/// What a full charge meter does, picked before the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpecialAction {
+    /// Scramble every marble on the board
+    Shuffle,
+    /// Run the board at half speed for a few seconds
+    SlowTime,
+    /// Clear the ring with the most marbles in it
+    RingClear,
+}
+
+impl SpecialAction {
+    /// How long slow time lasts
+    pub const SLOW_TIME_TICKS: u32 = TICKS_PER_SECOND * 5;
+
+    /// Blurb for the mode select screen.
+    pub fn description(&self) -> &'static str {
+        match self {
+            SpecialAction::Shuffle => "SCRAMBLES EVERY\nMARBLE ON THE\nBOARD.",
+            SpecialAction::SlowTime => "RUNS THE BOARD AT\nHALF SPEED FOR\n5 SECONDS.",
+            SpecialAction::RingClear => "CLEARS THE RING\nWITH THE MOST\nMARBLES IN IT.",
+        }
+    }
+}
+
+impl Cyclable for SpecialAction {
+    const ALL: &'static [Self] = &[
+        SpecialAction::Shuffle,
+        SpecialAction::SlowTime,
+        SpecialAction::RingClear,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SpecialAction::Shuffle => "SHUFFLE",
+            SpecialAction::SlowTime => "SLOW TIME",
+            SpecialAction::RingClear => "RING CLEAR",
+        }
+    }
+}
+
+/// How good the CPU opponent in versus runs is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CpuDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl CpuDifficulty {
+    /// How many ticks the CPU waits between patterns
+    pub fn think_ticks(&self) -> u32 {
+        match self {
+            CpuDifficulty::Easy => TICKS_PER_SECOND * 3,
+            CpuDifficulty::Normal => TICKS_PER_SECOND * 2,
+            CpuDifficulty::Hard => TICKS_PER_SECOND,
+        }
+    }
+
+    /// How likely the CPU is to draw some random pattern instead of the best one it found
+    pub fn mistake_rate(&self) -> f32 {
+        match self {
+            CpuDifficulty::Easy => 0.5,
+            CpuDifficulty::Normal => 0.2,
+            CpuDifficulty::Hard => 0.05,
+        }
+    }
+
+    /// Whether the CPU also looks at rings around a marble, not just triangles
+    pub fn searches_rings(&self) -> bool {
+        matches!(self, CpuDifficulty::Hard)
+    }
+}
+
+impl Cyclable for CpuDifficulty {
+    const ALL: &'static [Self] = &[
+        CpuDifficulty::Easy,
+        CpuDifficulty::Normal,
+        CpuDifficulty::Hard,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            CpuDifficulty::Easy => "EASY",
+            CpuDifficulty::Normal => "NORMAL",
+            CpuDifficulty::Hard => "HARD",
+        }
+    }
+}
+
+/// Optional modifiers the player can turn on before a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Mutator {
+    /// Marbles spawn 50% faster
+    FastSpawns,
+    /// At most 3 colors of marble
+    ThreeColors,
+    /// Marbles are drawn without the symbols on them
+    InvisibleSigils,
+    /// Every so often a stone drops instead of a marble
+    StoneRain,
+    /// All points are doubled
+    DoubleScore,
+    /// Clearing marbles earns power-ups to spend later
+    PowerUps,
+    /// Colors get dealt from a bag instead of rolled
+    ColorBag,
+    /// Marbles left against the edge fuse into it, shrinking the board
+    Squeeze,
+    /// The board grows as the run goes on
+    Growth,
+    /// Loops that don't clear anything cost points
+    Misclicks,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 10] = [
+        Mutator::FastSpawns,
+        Mutator::ThreeColors,
+        Mutator::InvisibleSigils,
+        Mutator::StoneRain,
+        Mutator::DoubleScore,
+        Mutator::PowerUps,
+        Mutator::ColorBag,
+        Mutator::Squeeze,
+        Mutator::Growth,
+        Mutator::Misclicks,
+    ];
+
+    /// Name to show to the player.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mutator::FastSpawns => "FAST SPAWNS",
+            Mutator::ThreeColors => "3 COLORS",
+            Mutator::InvisibleSigils => "NO SYMBOLS",
+            Mutator::StoneRain => "STONE RAIN",
+            Mutator::DoubleScore => "DOUBLE SCORE",
+            Mutator::PowerUps => "POWER-UPS",
+            Mutator::ColorBag => "COLOR BAG",
+            Mutator::Squeeze => "SQUEEZE",
+            Mutator::Growth => "GROWTH",
+            Mutator::Misclicks => "NO MISCLICKS",
+        }
+    }
+
+    /// Blurb for the mutator select screen.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Mutator::FastSpawns => "MARBLES SPAWN\n50% FASTER.",
+            Mutator::ThreeColors => "ONLY 3 COLORS\nOF MARBLE SPAWN.",
+            Mutator::InvisibleSigils => "MARBLES DON'T\nHAVE SYMBOLS ON\nTHEM. GOOD LUCK.",
+            Mutator::StoneRain => "EVERY 8TH SPAWN\nIS A STONE.",
+            Mutator::DoubleScore => "ALL POINTS ARE\nDOUBLED.",
+            Mutator::PowerUps => "CLEARING MARBLES\nEARNS POWER-UPS.\nARM THEM WITH THE\nHUD BUTTONS.\n\nMAGNET (F) PULLS\nTHE NEXT LOOP'S\nCOLOR IN.\n\nRING (G) CLEARS\nTHE NEXT LOOP'S\nRING. DRAWING A\nWHOLE RING DOES\nTOO!",
+            Mutator::ColorBag => "COLORS ARE DEALT\nFROM A BAG WITH\nONE OF EACH, SO\nNONE GO MISSING.\n\nTHE NEXT FEW SHOW\nUP ON THE LEFT.",
+            Mutator::Squeeze => "MARBLES LEFT\nAGAINST THE EDGE\nTOO LONG FUSE INTO\nTHE WALL, AND THE\nBOARD SHRINKS.",
+            Mutator::Growth => "CLEARING A WHOLE\nRING AND EVERY 5K\nPOINTS MAKE THE\nBOARD A RING\nBIGGER, UP TO 3\nTIMES.",
+            Mutator::Misclicks => "LOOPS THAT DON'T\nCLEAR ANYTHING\nCOST 500 POINTS.\n\nYOUR SCORE CAN'T\nGO BELOW ZERO.",
+        }
+    }
+
+    fn bit(&self) -> u32 {
+        1 << (*self as u32)
+    }
+}
+
+/// A set of mutators, stored as bitflags so it's compact to use as a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MutatorSet(u32);
+
+impl MutatorSet {
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, mutator: Mutator) -> bool {
+        self.0 & mutator.bit() != 0
+    }
+
+    /// Turn the mutator on if it's off and off if it's on.
+    pub fn toggle(&mut self, mutator: Mutator) {
+        self.0 ^= mutator.bit();
+    }
+
+    pub fn union(&self, other: MutatorSet) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Mutator> + '_ {
+        Mutator::ALL.iter().copied().filter(move |m| self.contains(*m))
+    }
+}
+
+/// All the marbles the same color as the one at `c` and touching it, going by `marble_at`
+/// for what's where.
+fn floodfill_by<'a>(
+    c: &Coordinate,
+    marble_at: impl Fn(&Coordinate) -> Option<&'a Marble>,
+) -> Vec<Coordinate> {
+    let color = match marble_at(c) {
+        Some(Marble::Stone) | None => return Vec::new(),
+        Some(it) => it,
+    };
+
+    let mut seen = AHashSet::new();
+    let mut todo = vec![*c];
+    let mut blob = Vec::new();
+    while let Some(c) = todo.pop() {
+        if !seen.contains(&c) && Some(color) == marble_at(&c) {
+            seen.insert(c);
+            todo.push(c);
+            blob.push(c);
+            todo.extend_from_slice(&c.neighbors());
+        }
+    }
+    blob
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with nothing on it and nothing pulling marbles around
+    fn empty_board() -> Board {
+        let mut board = Board::new(BoardSettings::no_gravity());
+        board.clear_marbles();
+        board
+    }
+
+    /// Three cells around the middle that all touch each other
+    fn triangle() -> Vec<Coordinate> {
+        let pos = Coordinate::new(0, 0);
+        vec![
+            pos,
+            pos + Direction::XY,
+            pos + (Direction::XY + Angle::Right),
+        ]
+    }
+
+    fn fill(board: &mut Board, poses: &[Coordinate], marbles: &[Option<Marble>]) {
+        for (pos, marble) in poses.iter().zip(marbles) {
+            board.set_marble(*pos, marble.clone());
+        }
+    }
+
+    fn marbles_at(board: &Board, poses: &[Coordinate]) -> Vec<Option<Marble>> {
+        poses
+            .iter()
+            .map(|pos| board.get_marble(pos).cloned())
+            .collect()
+    }
+
+    fn play_out(board: &mut Board) {
+        while board.next_action().is_some() {
+            board.resolve_actions();
+        }
+    }
+
+    #[test]
+    fn loop_goes_around() {
+        let mut board = empty_board();
+        let tri = triangle();
+        fill(
+            &mut board,
+            &tri,
+            &[Some(Marble::Red), Some(Marble::Green), Some(Marble::Blue)],
+        );
+
+        board.push_action(BoardAction::Cycle(tri.clone()));
+        play_out(&mut board);
+
+        assert_eq!(board.take_fizzles(), 0);
+        assert_ne!(
+            marbles_at(&board, &tri),
+            vec![Some(Marble::Red), Some(Marble::Green), Some(Marble::Blue)]
+        );
+    }
+
+    #[test]
+    fn loop_with_empty_cell_fizzles() {
+        let mut board = empty_board();
+        let tri = triangle();
+        let before = [Some(Marble::Red), None, Some(Marble::Blue)];
+        fill(&mut board, &tri, &before);
+
+        board.push_action(BoardAction::Cycle(tri.clone()));
+        play_out(&mut board);
+
+        assert_eq!(board.take_fizzles(), 1);
+        assert_eq!(marbles_at(&board, &tri), before.to_vec());
+    }
+
+    #[test]
+    fn loop_with_stone_fizzles() {
+        let mut board = empty_board();
+        let tri = triangle();
+        let before = [Some(Marble::Red), Some(Marble::Stone), Some(Marble::Blue)];
+        fill(&mut board, &tri, &before);
+
+        board.push_action(BoardAction::Cycle(tri.clone()));
+        play_out(&mut board);
+
+        assert_eq!(board.take_fizzles(), 1);
+        assert_eq!(marbles_at(&board, &tri), before.to_vec());
+    }
+
+    #[test]
+    fn fizzle_is_refunded_once() {
+        let mut board = empty_board();
+        let tri = triangle();
+        fill(&mut board, &tri, &[Some(Marble::Red), None, None]);
+
+        board.push_action(BoardAction::Cycle(tri));
+        board.resolve_actions();
+
+        // It never got to start, so it isn't the player's to cancel any more either
+        assert!(board.next_action().is_none());
+        assert_eq!(board.cancelable_actions(), 0);
+        // The fizzle gets handed out once, so the move only comes back once
+        assert_eq!(board.take_fizzles(), 1);
+        assert_eq!(board.take_fizzles(), 0);
+    }
+
+    fn misclick_board(score: u32) -> Board {
+        let mut mutators = MutatorSet::empty();
+        mutators.toggle(Mutator::Misclicks);
+        let mut board = Board::new(BoardSettings::classic().with_mutators(mutators));
+        board.score = score;
+        board
+    }
+
+    #[test]
+    fn misclick_stops_at_zero() {
+        let mut board = misclick_board(0);
+        board.charge_misclick();
+        assert_eq!(board.score(), 0);
+
+        let mut board = misclick_board(MISCLICK_PENALTY - 1);
+        board.charge_misclick();
+        assert_eq!(board.score(), 0);
+    }
+
+    #[test]
+    fn misclick_at_max_score() {
+        let mut board = misclick_board(u32::MAX);
+        board.charge_misclick();
+        assert_eq!(board.score(), u32::MAX - MISCLICK_PENALTY);
+    }
+
+    #[test]
+    fn score_packet_saturates() {
+        let points = |base, multiplier| ScorePacket { base, multiplier }.points();
+        assert_eq!(points(u32::MAX, 1), u32::MAX);
+        assert_eq!(points(u32::MAX, 0), 0);
+        assert_eq!(points(u32::MAX / 2, 2), u32::MAX - 1);
+        assert_eq!(points(u32::MAX / 2 + 1, 2), u32::MAX);
+        assert_eq!(points(u32::MAX, 16), u32::MAX);
+    }
+
+    #[test]
+    fn score_stops_at_max() {
+        let mut board = misclick_board(u32::MAX - 1);
+        board.score_queue.push_back(ScorePacket {
+            base: 10,
+            multiplier: 2,
+        });
+        for _ in 0..SCORE_TIMER {
+            board.resolve_actions();
+        }
+        assert!(board.score_queue.is_empty());
+        assert_eq!(board.score(), u32::MAX);
+    }
+
+    fn growth_board() -> Board {
+        let mut mutators = MutatorSet::empty();
+        mutators.toggle(Mutator::Growth);
+        let mut board = Board::new(BoardSettings::no_gravity().with_mutators(mutators));
+        board.clear_marbles();
+        board
+    }
+
+    #[test]
+    fn clearing_a_ring_grows_the_board() {
+        let mut board = growth_board();
+        let radius = board.radius();
+        for pos in Coordinate::new(0, 0).ring_iter(1, Spin::CW(Direction::XY)) {
+            board.set_marble(pos, Some(Marble::Red));
+        }
+
+        board.settle();
+        play_out(&mut board);
+
+        assert!(board.get_marbles().is_empty());
+        assert_eq!(board.radius(), radius + 1);
+    }
+
+    #[test]
+    fn clearing_part_of_a_ring_does_not_grow_the_board() {
+        let mut board = growth_board();
+        let radius = board.radius();
+        for pos in Coordinate::new(0, 0)
+            .ring_iter(1, Spin::CW(Direction::XY))
+            .take(5)
+        {
+            board.set_marble(pos, Some(Marble::Red));
+        }
+
+        board.settle();
+        play_out(&mut board);
+
+        assert!(board.get_marbles().is_empty());
+        assert_eq!(board.radius(), radius);
+    }
+
+    #[test]
+    fn loop_after_fizzle_starts_fresh() {
+        let mut board = empty_board();
+        let tri = triangle();
+        let other = tri
+            .iter()
+            .map(|pos| *pos + Direction::YZ)
+            .collect::<Vec<_>>();
+        fill(
+            &mut board,
+            &other,
+            &[Some(Marble::Red), Some(Marble::Green), Some(Marble::Blue)],
+        );
+
+        board.push_action(BoardAction::Cycle(tri));
+        board.push_action(BoardAction::Cycle(other));
+        board.resolve_actions();
+
+        // The dead loop got dropped and the next one is on its first frame
+        assert_eq!(board.take_fizzles(), 1);
+        assert_eq!(board.action_timer(), 1);
+    }
+
+    #[test]
+    fn weekly_board_ignores_global_rng() {
+        let settings = WeeklyChallenge {
+            year: 2023,
+            week: 42,
+        }
+        .settings();
+
+        quad_rand::srand(1);
+        let first = Board::new(settings.clone());
+        quad_rand::srand(2);
+        let second = Board::new(settings);
+
+        assert_eq!(first.get_marbles(), second.get_marbles());
+        assert!(first.upcoming().eq(second.upcoming()));
+    }
+
+    #[test]
+    fn board_with_nothing_to_clear_is_stuck() {
+        let mut board = empty_board();
+        fill(
+            &mut board,
+            &triangle(),
+            &[Some(Marble::Red), Some(Marble::Green), Some(Marble::Blue)],
+        );
+        assert!(board.find_useful_move().is_none());
+    }
+
+    #[test]
+    fn useful_moves_match_simulation() {
+        for _ in 0..8 {
+            let board = Board::new(BoardSettings::no_gravity());
+            for path in board.candidate_loops(true) {
+                let simulated = board.simulate_action(&BoardAction::Cycle(path.clone()));
+                assert_eq!(board.cycle_clears(&path), !simulated.is_empty());
+            }
+        }
+    }
+
+    /// A loop in the middle with three colors on it, and one next to it that's empty
+    fn loop_and_empty_loop() -> (Board, Vec<Coordinate>, Vec<Coordinate>) {
+        let mut board = empty_board();
+        let tri = triangle();
+        fill(
+            &mut board,
+            &tri,
+            &[Some(Marble::Red), Some(Marble::Green), Some(Marble::Blue)],
+        );
+        let empty = tri
+            .iter()
+            .map(|pos| *pos + Direction::YZ + Direction::YZ)
+            .collect::<Vec<_>>();
+        (board, tri, empty)
+    }
+
+    #[test]
+    fn started_action_cannot_be_canceled() {
+        let (mut board, tri, _) = loop_and_empty_loop();
+        board.push_action(BoardAction::Cycle(tri));
+        board.push_action(BoardAction::ClearBlobs(0));
+        assert_eq!(board.cancelable_actions(), 2);
+
+        board.resolve_actions();
+        assert_eq!(board.action_timer(), 1);
+        assert_eq!(board.cancelable_actions(), 1);
+
+        let canceled = board.cancel_player_actions();
+        assert!(matches!(canceled[..], [BoardAction::ClearBlobs(0)]));
+        assert!(matches!(board.next_action(), Some(BoardAction::Cycle(_))));
+        assert_eq!(board.cancelable_actions(), 0);
+    }
+
+    #[test]
+    fn board_action_in_between_cannot_be_canceled() {
+        let (mut board, tri, _) = loop_and_empty_loop();
+        board.push_action(BoardAction::Cycle(tri.clone()));
+        board.queue_board_action(BoardAction::ClearBlobs(1));
+        board.push_action(BoardAction::Cycle(tri));
+        assert_eq!(board.cancelable_actions(), 1);
+
+        let canceled = board.cancel_player_actions();
+        assert!(matches!(canceled[..], [BoardAction::Cycle(_)]));
+        assert_eq!(board.action_queue.len(), 2);
+        assert_eq!(board.cancelable_actions(), 0);
+    }
+
+    #[test]
+    fn fizzles_and_skips_keep_player_actions_in_step() {
+        let (mut board, tri, empty) = loop_and_empty_loop();
+        board.push_action(BoardAction::Cycle(empty));
+        board.push_action(BoardAction::ClearBlobs(0));
+        board.push_action(BoardAction::Cycle(tri));
+        assert_eq!(board.cancelable_actions(), 3);
+
+        // The empty loop fizzles, there's nothing to clear, and the last loop starts
+        board.resolve_actions();
+        assert_eq!(board.take_fizzles(), 1);
+        assert_eq!(board.action_timer(), 1);
+        assert_eq!(board.action_queue.len(), 1);
+        assert_eq!(board.player_actions, 1);
+        assert_eq!(board.cancelable_actions(), 0);
+        assert!(board.cancel_player_actions().is_empty());
+        assert!(matches!(board.next_action(), Some(BoardAction::Cycle(_))));
+    }
+
+    #[test]
+    fn fair_start_rerolls_until_fair() {
+        let settings = BoardSettings::beginner();
+        assert!(settings.fair_start);
+        for _ in 0..8 {
+            assert!(Board::new(settings.clone()).is_fair_start());
+        }
+    }
+
+    #[test]
+    fn cycling_goes_through_everything_and_wraps() {
+        let mut speed = GameSpeed::ALL[0];
+        for expected in GameSpeed::ALL.iter().skip(1).chain(&GameSpeed::ALL[..1]) {
+            speed = speed.next();
+            assert_eq!(speed, *expected);
         }
     }
 }
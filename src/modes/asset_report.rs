@@ -0,0 +1,136 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::clear_background;
+
+use crate::{
+    assets::{manifest::AssetProblem, Assets, SfxEvent},
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics},
+        text::TextAlign,
+        theme,
+    },
+    width,
+};
+
+/// How many broken files to list before giving up and just counting the rest
+const SHOWN_PROBLEMS: usize = 6;
+
+/// Shown on launch if some of the assets are missing or don't match the manifest.
+///
+/// The broken ones have already been swapped for placeholders by the time this is up,
+/// so skipping it carries on with those.
+#[derive(Debug, Clone)]
+pub struct ModeAssetReport {
+    problems: Vec<AssetProblem>,
+    b_skip: Button,
+    /// There's nothing to quit to in a browser
+    b_quit: Option<Button>,
+}
+
+impl ModeAssetReport {
+    pub fn new(problems: Vec<AssetProblem>) -> Self {
+        let m = MenuMetrics::current();
+        let w = m.px(4.0 * 12.0);
+        let h = m.px(9.0);
+
+        Self {
+            problems,
+            b_skip: Button::new(width() - w - m.px(3.0), height() - h - m.px(3.0), w, h),
+            b_quit: if cfg!(target_arch = "wasm32") {
+                None
+            } else {
+                Some(Button::new(m.px(3.0), height() - h - m.px(3.0), w, h))
+            },
+        }
+    }
+
+    fn buttons(&self) -> impl Iterator<Item = (&Button, &'static str)> {
+        std::iter::once((&self.b_skip, "SKIP")).chain(self.b_quit.iter().map(|b| (b, "QUIT")))
+    }
+}
+
+impl Gamemode for ModeAssetReport {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if let Some(b_quit) = &self.b_quit {
+            if b_quit.mouse_hovering() && controls.clicked_down(Control::Click) {
+                std::process::exit(1);
+            }
+        }
+        if (self.b_skip.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::Pop;
+        }
+
+        let mut play_enter = false;
+        for b in std::iter::once(&mut self.b_skip).chain(self.b_quit.as_mut()) {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeAssetReport {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let mut list = self
+            .problems
+            .iter()
+            .take(SHOWN_PROBLEMS)
+            .map(|problem| format!("{} {}", problem.kind.name(), problem.path.to_uppercase()))
+            .collect::<Vec<_>>();
+        if self.problems.len() > SHOWN_PROBLEMS {
+            list.push(format!("AND {} MORE", self.problems.len() - SHOWN_PROBLEMS));
+        }
+        draw_menu_text(
+            &format!(
+                "SOME GAME FILES ARE\nBROKEN:\n\n{}\n\nTRY REINSTALLING.\nSKIP TO PLAY WITH\nPLACEHOLDERS.",
+                list.join("\n")
+            ),
+            m.px(3.0),
+            m.px(3.0),
+            TextAlign::Left,
+            blight,
+            assets.textures.fonts.small,
+        );
+
+        for (b, text) in self.buttons() {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                b.text_color(border, blight),
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::clear_background;
+
+use crate::{
+    assets::{Assets, SfxEvent},
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics},
+        text::TextAlign,
+        theme,
+    },
+    width,
+};
+
+/// Shown on launch if the game crashed last time, saying where the report went.
+#[derive(Debug, Clone)]
+pub struct ModeCrashReport {
+    place: String,
+    b_ok: Button,
+}
+
+impl ModeCrashReport {
+    pub fn new(place: String) -> Self {
+        let m = MenuMetrics::current();
+        let w = m.px(4.0 * 12.0);
+        let h = m.px(9.0);
+
+        Self {
+            place,
+            b_ok: Button::new(width() - w - m.px(3.0), height() - h - m.px(3.0), w, h),
+        }
+    }
+}
+
+impl Gamemode for ModeCrashReport {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if (self.b_ok.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::Pop;
+        }
+        if self.b_ok.mouse_entered() {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+        self.b_ok.post_update();
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeCrashReport {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        draw_menu_text(
+            &format!(
+                "SORRY, THE GAME\nCRASHED LAST TIME.\n\nA REPORT WAS\nSAVED TO\n{}\n\nPLEASE SEND IT\nALONG IF YOU\nREPORT THE BUG!",
+                self.place.to_uppercase()
+            ),
+            m.px(3.0),
+            m.px(3.0),
+            TextAlign::Left,
+            blight,
+            assets.textures.fonts.small,
+        );
+
+        self.b_ok.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            "OK",
+            self.b_ok.x() + self.b_ok.w() / 2.0,
+            self.b_ok.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_ok.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+    }
+}
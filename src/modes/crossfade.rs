@@ -0,0 +1,99 @@
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Assets,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeBox, GamemodeDrawer, Transition},
+    controls::InputSubscriber,
+    height,
+    utils::draw::{hexcolor, width_height_deficit},
+    width,
+};
+
+/// How many frames the loading screen takes to fade out
+const CROSSFADE_TIME: u32 = 15;
+
+/// Fades the loading screen out over the first mode, so startup doesn't jump.
+///
+/// The mode underneath runs like normal the whole time.
+/// Once the fade is done this swaps itself out for it.
+pub struct ModeCrossfade {
+    /// The mode being faded into. This is only `None` once it's been handed off.
+    inner: Option<GamemodeBox>,
+    loading: Texture2D,
+    timer: u32,
+}
+
+impl ModeCrossfade {
+    pub fn new(inner: GamemodeBox, loading: Texture2D) -> Self {
+        Self {
+            inner: Some(inner),
+            loading,
+            timer: 0,
+        }
+    }
+}
+
+impl Gamemode for ModeCrossfade {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        let inner = self.inner.as_mut().unwrap();
+        self.timer += 1;
+        match inner.update(controls, frame_info, assets) {
+            Transition::None if self.timer < CROSSFADE_TIME => Transition::None,
+            Transition::None => Transition::Swap(self.inner.take().unwrap()),
+            // Pushing would leave us stuck under the new mode, so put the inner mode back first
+            Transition::Push(new) => {
+                let mut inner = self.inner.take().unwrap();
+                inner.on_reveal(None, assets);
+                Transition::PopNAndPush(1, vec![inner, new])
+            }
+            // Everything else gets rid of the inner mode, which is the same as getting rid of us
+            trans => trans,
+        }
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(CrossfadeDrawer {
+            inner: self.inner.as_mut().unwrap().get_draw_info(),
+            loading: self.loading,
+            alpha: 1.0 - self.timer as f32 / CROSSFADE_TIME as f32,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner
+            .as_ref()
+            .map_or("ModeCrossfade", |inner| inner.name())
+    }
+}
+
+struct CrossfadeDrawer {
+    inner: DrawerBox,
+    loading: Texture2D,
+    alpha: f32,
+}
+
+impl GamemodeDrawer for CrossfadeDrawer {
+    fn draw(&self, assets: &Assets, frame_info: FrameInfo) {
+        self.inner.draw(assets, frame_info);
+
+        // Draw the loading screen from `main` again, but on the canvas
+        let mut bg = hexcolor(0x21181b_ff);
+        bg.a = self.alpha;
+        draw_rectangle(0.0, 0.0, width(), height(), bg);
+
+        // The loading screen keeps its texture this far from the corner, in window pixels
+        let (miss_x, _) = width_height_deficit();
+        let margin = (screen_width() * 0.05 * width() / (screen_width() - miss_x)).floor();
+        draw_texture(
+            self.loading,
+            width() - self.loading.width() - margin,
+            height() - self.loading.height() - margin,
+            Color::new(1.0, 1.0, 1.0, self.alpha),
+        );
+    }
+}
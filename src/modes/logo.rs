@@ -1,10 +1,12 @@
 use crate::{
     assets::Assets,
+    audio::AudioBackend,
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
+    height,
     modes::ModeTitle,
     utils::draw::{self, hexcolor},
-    HEIGHT, WIDTH,
+    width,
 };
 
 use cogs_gamedev::{chance::WeightedPicker, controls::InputHandler};
@@ -88,13 +90,13 @@ impl Gamemode for ModeSplash {
         if self.first_frame {
             self.first_frame = false;
             self.start_time = macroquad::time::get_time();
-            macroquad::audio::play_sound_once(assets.sounds.splash_jingle);
+            assets.audio.play_once(assets.sounds.splash_jingle);
         }
 
         if macroquad::time::get_time() - self.start_time > 5.0
             || controls.clicked_down(Control::Click)
         {
-            macroquad::audio::stop_sound(assets.sounds.splash_jingle);
+            assets.audio.stop(assets.sounds.splash_jingle);
 
             // Put your next state here!
             Transition::Swap(Box::new(ModeTitle::new()))
@@ -133,9 +135,9 @@ impl GamemodeDrawer for ModeSplash {
                 let theta2 =
                     (2 * idx + 1) as f32 / blade_span * TAU + time_ran as f32 * self.rotation_speed;
 
-                let v1 = Vec2::from(theta1.sin_cos()) * WIDTH * 2.0;
-                let v2 = Vec2::from(theta2.sin_cos()) * WIDTH * 2.0;
-                let vc = Vec2::new(WIDTH / 2.0, HEIGHT / 2.0);
+                let v1 = Vec2::from(theta1.sin_cos()) * width() * 2.0;
+                let v2 = Vec2::from(theta2.sin_cos()) * width() * 2.0;
+                let vc = Vec2::new(width() / 2.0, height() / 2.0);
 
                 draw_triangle(v1, v2, vc, self.blade_light);
             }
@@ -149,8 +151,8 @@ impl GamemodeDrawer for ModeSplash {
         let sx = banner_idx as f32 * 64.0;
         draw_texture_ex(
             assets.textures.title_banner,
-            WIDTH / 2.0 - BANNER_DISPLAY_SIZE / 2.0,
-            HEIGHT / 2.0 - BANNER_DISPLAY_SIZE / 2.0,
+            width() / 2.0 - BANNER_DISPLAY_SIZE / 2.0,
+            height() / 2.0 - BANNER_DISPLAY_SIZE / 2.0,
             WHITE,
             DrawTextureParams {
                 source: Some(Rect::new(sx, 0.0, 64.0, 64.0)),
@@ -1,7 +1,16 @@
-mod logo;
-mod playing;
-mod title;
-
-pub use logo::ModeSplash;
-pub use playing::ModePlaying;
-pub use title::ModeTitle;
+mod asset_report;
+mod crash_report;
+mod crossfade;
+mod logo;
+mod playing;
+mod title;
+
+pub use asset_report::ModeAssetReport;
+pub use crash_report::ModeCrashReport;
+pub use crossfade::ModeCrossfade;
+pub use logo::ModeSplash;
+pub use playing::{
+    draw_background_hexes, draw_board_preview, start_game, CpuOpponent, ModePlaying, ModeSandbox,
+    Tip, MARBLE_SIZE, SPLIT_COUNT,
+};
+pub use title::{quick_settings, ModeTitle, Setting};
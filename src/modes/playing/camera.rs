@@ -0,0 +1,92 @@
+use hex2d::{Angle, Coordinate};
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+use crate::{height, utils::hexmath::HexLayout, width};
+
+use super::{board_center, MARBLE_SIZE, MARBLE_SPAN_X, MARBLE_SPAN_Y};
+
+/// Room to leave around the board on the left and right
+const MARGIN_X: f32 = 2.0;
+/// Room to leave around the board on the top and bottom; the score goes up there
+const MARGIN_Y: f32 = 12.0;
+
+/// Converts between board coordinates and canvas pixels.
+///
+/// Boards up to radius 7 fit on the canvas at full size.
+/// Anything bigger gets zoomed out until it fits.
+#[derive(Debug, Clone, Copy)]
+pub struct BoardCamera {
+    /// Where the center hex goes on the canvas
+    pub center: Vec2,
+    /// How much to scale marble spacing and size by. Never more than 1.
+    pub zoom: f32,
+    /// How far the view is turned clockwise. Only the view; the board's coordinates stay put.
+    pub rotation: Angle,
+}
+
+impl BoardCamera {
+    pub fn for_radius(radius: usize) -> Self {
+        Self::for_shown_radius(radius as f32)
+    }
+
+    /// Like `for_radius`, but in between sizes, for zooming smoothly while the board grows.
+    pub fn for_shown_radius(radius: f32) -> Self {
+        let half_w = radius * MARBLE_SPAN_X as f32 + MARBLE_SIZE / 2.0;
+        let half_h = radius * MARBLE_SPAN_Y as f32 + MARBLE_SIZE / 2.0;
+        let zoom = ((width() / 2.0 - MARGIN_X) / half_w)
+            .min((height() / 2.0 - MARGIN_Y) / half_h)
+            .min(1.0);
+        Self {
+            center: board_center(),
+            zoom,
+            rotation: Angle::Forward,
+        }
+    }
+
+    /// A camera that fits the whole board inside `area`, for drawing boards small
+    /// outside of a run.
+    pub fn fitting(radius: usize, area: Rect) -> Self {
+        let half_w = radius as f32 * MARBLE_SPAN_X as f32 + MARBLE_SIZE / 2.0;
+        let half_h = radius as f32 * MARBLE_SPAN_Y as f32 + MARBLE_SIZE / 2.0;
+        let zoom = (area.w / 2.0 / half_w).min(area.h / 2.0 / half_h).min(1.0);
+        Self {
+            center: vec2(area.x + area.w / 2.0, area.y + area.h / 2.0).round(),
+            zoom,
+            rotation: Angle::Forward,
+        }
+    }
+
+    /// The same camera, but turned
+    pub fn rotated(self, rotation: Angle) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// How big a marble is drawn
+    pub fn marble_size(&self) -> f32 {
+        MARBLE_SIZE * self.zoom
+    }
+
+    /// Where the board's hexes go, not counting the rotation
+    pub fn layout(&self) -> HexLayout {
+        HexLayout::new(MARBLE_SPAN_X, MARBLE_SPAN_Y, self.center).zoomed(self.zoom)
+    }
+
+    /// Pixel position of the middle of the hex
+    pub fn hex_to_pixel(&self, pos: Coordinate) -> Vec2 {
+        self.layout()
+            .hex_to_pixel(pos.rotate_around_zero(self.rotation))
+    }
+
+    /// Pixel position of the top left corner of the marble at the hex,
+    /// snapped to the pixel grid
+    pub fn hex_to_corner(&self, pos: Coordinate) -> Vec2 {
+        self.layout()
+            .hex_to_corner(pos.rotate_around_zero(self.rotation), self.marble_size())
+    }
+
+    /// Which hex the pixel is over
+    pub fn pixel_to_hex(&self, px: Vec2) -> Coordinate {
+        let unturn = Angle::from_int(6 - self.rotation.to_int::<i32>());
+        self.layout().pixel_to_hex(px).rotate_around_zero(unturn)
+    }
+}
@@ -0,0 +1,107 @@
+use cogs_gamedev::controls::InputHandler;
+use hex2d::Coordinate;
+use macroquad::prelude::vec2;
+
+use crate::{
+    controls::{Control, InputSubscriber},
+    model::Board,
+};
+
+use super::{
+    camera::BoardCamera, is_pattern_valid, PatternExtensionValidity, MARBLE_SPAN_X, MARBLE_SPAN_Y,
+};
+
+/// Which way on screen each of the partner's movement keys goes, in unzoomed pixels.
+///
+/// Going by the screen instead of by board directions keeps the keys right
+/// when the view is turned.
+const CURSOR_MOVES: [(Control, (i32, i32)); 6] = [
+    (Control::PartnerUpLeft, (-MARBLE_SPAN_X / 2, -MARBLE_SPAN_Y)),
+    (Control::PartnerUpRight, (MARBLE_SPAN_X / 2, -MARBLE_SPAN_Y)),
+    (Control::PartnerLeft, (-MARBLE_SPAN_X, 0)),
+    (Control::PartnerRight, (MARBLE_SPAN_X, 0)),
+    (
+        Control::PartnerDownLeft,
+        (-MARBLE_SPAN_X / 2, MARBLE_SPAN_Y),
+    ),
+    (
+        Control::PartnerDownRight,
+        (MARBLE_SPAN_X / 2, MARBLE_SPAN_Y),
+    ),
+];
+
+/// The second player in co-op, who moves a cursor around the board with the keyboard
+/// and holds a key to draw.
+#[derive(Debug, Clone)]
+pub struct Partner {
+    pub cursor: Coordinate,
+    pub pattern: Option<Vec<Coordinate>>,
+}
+
+impl Partner {
+    /// Start the cursor on the cell closest to the middle of the board
+    pub fn new(board: &Board) -> Self {
+        let origin = Coordinate::new(0, 0);
+        let cursor = board
+            .cells()
+            .iter()
+            .copied()
+            .min_by_key(|c| (c.distance(origin), c.x, c.y))
+            .unwrap_or(origin);
+        Self {
+            cursor,
+            pattern: None,
+        }
+    }
+
+    /// Move the cursor and draw with it.
+    ///
+    /// Returns the pattern if the partner just let go of a finished loop.
+    pub fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        board: &Board,
+        camera: &BoardCamera,
+    ) -> Option<Vec<Coordinate>> {
+        let mut moved = false;
+        for &(control, (dx, dy)) in CURSOR_MOVES.iter() {
+            if controls.clicked_down(control) {
+                let offset = vec2(dx as f32, dy as f32) * camera.zoom;
+                let next = camera.pixel_to_hex(camera.hex_to_pixel(self.cursor) + offset);
+                if board.is_in_bounds(&next) {
+                    self.cursor = next;
+                    moved = true;
+                }
+            }
+        }
+
+        if !controls.pressed(Control::PartnerDraw) {
+            let pat = self.pattern.take()?;
+            return match is_pattern_valid(&pat, board.get_marbles()) {
+                PatternExtensionValidity::Finished => Some(pat),
+                _ => None,
+            };
+        }
+
+        match &mut self.pattern {
+            None => self.pattern = Some(vec![self.cursor]),
+            Some(pat) if moved => {
+                let mut maybe_pat = pat.clone();
+                maybe_pat.push(self.cursor);
+                let extendable = matches!(
+                    is_pattern_valid(pat, board.get_marbles()),
+                    PatternExtensionValidity::Continue
+                );
+                let valid = !matches!(
+                    is_pattern_valid(&maybe_pat, board.get_marbles()),
+                    PatternExtensionValidity::Invalid
+                );
+                if extendable && valid {
+                    *pat = maybe_pat;
+                }
+            }
+            Some(_) => {}
+        }
+        None
+    }
+}
@@ -1,43 +1,101 @@
+use std::collections::hash_map::Entry;
+
 use ahash::AHashMap;
 use cogs_gamedev::controls::InputHandler;
-use hex2d::{Coordinate, IntegerSpacing};
-use macroquad::{
-    audio::{play_sound, play_sound_once, PlaySoundParams},
-    prelude::*,
-};
+use hex2d::Coordinate;
+use macroquad::prelude::*;
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, SfxEvent},
     boilerplates::*,
     controls::{Control, InputSubscriber},
-    model::{BoardSettings, Marble, PlaySettings},
-    modes::playing::{BOARD_CENTER_X, BOARD_CENTER_Y, MARBLE_SIZE, MARBLE_SPAN_X, MARBLE_SPAN_Y},
+    height,
+    model::{format_ticks, BoardSettings, Marble, Ranking, TICKS_PER_SECOND},
+    modes::playing::{board_center, MARBLE_SIZE, MARBLE_SPAN_X, MARBLE_SPAN_Y},
     utils::{
         button::Button,
-        draw::hexcolor,
-        profile::Profile,
+        draw::{draw_mini_board, hexcolor, marble_color, MARBLE_COLORS},
+        hexmath::HexLayout,
+        profile::{PlaySettings, Profile, RUN_HISTORY_LENGTH},
+        score_counter::ScoreCounter,
         text::{draw_pixel_text, TextAlign},
+        theme,
     },
-    HEIGHT, WIDTH,
+    width,
 };
 
-use super::ModePlaying;
+use super::{
+    camera::BoardCamera, postmortem::PostMortem, start_game, ModePlaying, SCORE_COUNT_TIME,
+};
 
-/// Transition between having just lost the game and the losing screen
+/// Why the run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverReason {
+    /// The spawnpoint had nowhere to go
+    BoardFilled,
+    /// The player used up all their moves
+    OutOfMoves,
+    /// In a versus run, the CPU's board filled up first
+    OpponentFilled,
+}
+
+/// The board as it was when the run ended, so the losing screen can show how you died.
 #[derive(Clone)]
-pub struct ModeLosingTransition {
+struct BoardSnapshot {
+    cells: Vec<Coordinate>,
     marbles: AHashMap<Coordinate, Marble>,
     radius: usize,
+}
+
+impl BoardSnapshot {
+    /// Draw a tiny version of the board, centered horizontally at `cx`
+    /// and fitting between `top` and `bottom`.
+    ///
+    /// Whatever the post-mortem found gets picked out, with the blocked spawn blinking.
+    fn draw(&self, cx: f32, top: f32, bottom: f32, postmortem: &PostMortem, blink: bool) {
+        let empty = theme::shadow();
+        draw_mini_board(
+            self.cells.iter().copied(),
+            self.radius,
+            cx,
+            top,
+            bottom,
+            |pos| {
+                if blink && postmortem.blocked == Some(pos) {
+                    theme::accent()
+                } else if postmortem.missed_loop.contains(&pos) {
+                    theme::text()
+                } else {
+                    self.marbles.get(&pos).map_or(empty, marble_color)
+                }
+            },
+        );
+    }
+}
+
+/// Transition between having just lost the game and the losing screen
+#[derive(Clone)]
+pub struct ModeLosingTransition {
+    snapshot: BoardSnapshot,
     time: u32,
     /// Score to pass on to the next stage
     score: u32,
-    /// if there was a previous score it's here
+    /// The number the leaderboard cares about, which might not be the score
+    ranked: u32,
+    ranking: Ranking,
+    /// if there was a previous best it's here
     prev_score: Option<u32>,
 
     board_settings: BoardSettings,
     play_settings: PlaySettings,
 
-    playtime: f64,
+    /// How many frames the run was played for, not counting pauses
+    playtime: u32,
+    reason: GameOverReason,
+    /// How many ticks the run lasted, if the speedrun timer is on
+    run_ticks: Option<u32>,
+    /// What went wrong, if the board filled up
+    postmortem: PostMortem,
 }
 
 impl Gamemode for ModeLosingTransition {
@@ -48,13 +106,7 @@ impl Gamemode for ModeLosingTransition {
         assets: &Assets,
     ) -> Transition {
         if self.time == 0 {
-            play_sound(
-                assets.sounds.end_jingle,
-                PlaySoundParams {
-                    looped: false,
-                    volume: 0.8,
-                },
-            );
+            assets.play_sfx(SfxEvent::GameOver);
         }
         self.time += 1;
 
@@ -72,23 +124,24 @@ impl Gamemode for ModeLosingTransition {
 
 impl GamemodeDrawer for ModeLosingTransition {
     fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
-        clear_background(hexcolor(0x14182e_ff));
+        clear_background(theme::bg());
         // No need to draw background ticks cause they'll all be filled.
 
-        for (pos, marble) in self.marbles.iter() {
-            let dark = hexcolor(0x291d2b_ff);
+        let atlas = self.play_settings.skin.atlas();
+        for (pos, marble) in self.snapshot.marbles.iter() {
+            let dark = theme::shadow();
 
-            let scale = self.scale();
+            let zoom = BoardCamera::for_radius(self.snapshot.radius).zoom;
+            let scale = self.scale() * zoom;
             let distance = pos.distance(Coordinate::new(0, 0));
-            let (ox, oy) =
-                pos.to_pixel_integer(IntegerSpacing::PointyTop(MARBLE_SPAN_X, MARBLE_SPAN_Y));
-            let swirl_angle = self.swirl(distance) + (oy as f32).atan2(ox as f32);
-            let px_distance = (ox as f32).hypot(oy as f32) * self.spread(distance);
+            let offset = HexLayout::new(MARBLE_SPAN_X, MARBLE_SPAN_Y, board_center()).offset(*pos);
+            let swirl_angle = self.swirl(distance) + offset.y.atan2(offset.x);
+            let px_distance = offset.length() * self.spread(distance);
 
             let corner_x = (swirl_angle.cos() * px_distance as f32 - MARBLE_SIZE / 2.0) * scale
-                + BOARD_CENTER_X;
+                + board_center().x;
             let corner_y = (swirl_angle.sin() * px_distance as f32 - MARBLE_SIZE / 2.0) * scale
-                + BOARD_CENTER_Y;
+                + board_center().y;
 
             let sx = marble.clone() as u32 as f32 * MARBLE_SIZE;
             draw_texture_ex(
@@ -97,7 +150,7 @@ impl GamemodeDrawer for ModeLosingTransition {
                 corner_y,
                 WHITE,
                 DrawTextureParams {
-                    source: Some(Rect::new(sx, 8.0, MARBLE_SIZE, MARBLE_SIZE)),
+                    source: Some(Rect::new(sx, atlas.body_y, MARBLE_SIZE, MARBLE_SIZE)),
                     dest_size: Some(MARBLE_SIZE * vec2(scale, scale)),
                     ..Default::default()
                 },
@@ -108,54 +161,90 @@ impl GamemodeDrawer for ModeLosingTransition {
                 corner_y,
                 dark,
                 DrawTextureParams {
-                    source: Some(Rect::new(sx, 0.0, MARBLE_SIZE, MARBLE_SIZE)),
+                    source: Some(Rect::new(sx, atlas.sigil_y, MARBLE_SIZE, MARBLE_SIZE)),
                     dest_size: Some(MARBLE_SIZE * vec2(scale, scale)),
                     ..Default::default()
                 },
             );
         }
 
-        gl_use_material(assets.shaders.noise);
-        let mut fg = hexcolor(0x14182e_ff);
+        if assets.shaders.enabled() {
+            gl_use_material(assets.shaders.noise);
+        }
+        let mut fg = theme::bg();
         fg.a = (self.time as f32 / 120.0).powi(4).clamp(0.0, 1.0);
-        draw_rectangle(0.0, 0.0, WIDTH, HEIGHT, fg);
+        draw_rectangle(0.0, 0.0, width(), height(), fg);
         gl_use_default_material();
     }
 }
 
 impl ModeLosingTransition {
     /// also saves the score
-    pub fn new(prev: &ModePlaying) -> Self {
+    pub fn new(prev: &ModePlaying, reason: GameOverReason) -> Self {
         let board_settings = prev.board.settings().clone();
 
         let mut profile = Profile::get();
 
-        let prev_score = if let Some(mk) = board_settings.mode_key {
-            match profile.highscores.get_mut(&mk) {
-                Some(prev_score) => {
-                    // save it so we can return it
-                    let save = *prev_score;
-                    *prev_score = save.max(prev.board.score());
-                    Some(save)
-                }
-                None => {
-                    profile.highscores.insert(mk, prev.board.score());
-                    None
-                }
-            }
+        let ranking = board_settings.ranking();
+        let ranked = ranking.value(&prev.board);
+        let prev_score = if let Some(mk) = board_settings.leaderboard_key() {
+            record_best(profile.highscores.entry(mk), ranked)
+        } else if let Some(weekly) = board_settings.weekly {
+            record_best(profile.weekly_highscores.entry(weekly.id()), ranked)
         } else {
             None
         };
 
+        if let Some(key) = board_settings.leaderboard_key() {
+            profile
+                .clears
+                .entry(key.mode)
+                .or_default()
+                .merge(&prev.run_clears);
+            profile.last_run_clears = Some((key.mode, prev.run_clears.clone()));
+
+            let mut curve = prev.run_curve.clone();
+            curve.finish(prev.board.score());
+            let history = profile.run_history.entry(key.mode).or_default();
+            history.push_back(curve);
+            while history.len() > RUN_HISTORY_LENGTH {
+                history.pop_front();
+            }
+
+            let best_splits = profile.best_splits.entry(key).or_default();
+            for (best, split) in best_splits.iter_mut().zip(prev.splits.iter()) {
+                if let Some(split) = split {
+                    *best = Some(best.map_or(*split, |best| best.min(*split)));
+                }
+            }
+        }
+
         Self {
-            marbles: prev.board.get_marbles().clone(),
-            radius: prev.board.radius(),
+            snapshot: BoardSnapshot {
+                cells: prev.board.cells().iter().copied().collect(),
+                marbles: prev.board.get_marbles().clone(),
+                radius: prev.board.radius(),
+            },
             time: 0,
             score: prev.board.score(),
+            ranked,
+            ranking,
             prev_score,
             board_settings,
             play_settings: prev.settings,
-            playtime: macroquad::time::get_time() - prev.start_time,
+            playtime: prev.frame_count,
+            reason,
+            run_ticks: if prev.settings.speedrun_timer {
+                Some(prev.board.tick_count())
+            } else {
+                None
+            },
+            postmortem: match reason {
+                GameOverReason::BoardFilled => {
+                    PostMortem::analyze(&prev.recent_boards, &prev.board)
+                }
+                _ => PostMortem::default(),
+            },
         }
     }
 
@@ -165,24 +254,45 @@ impl ModeLosingTransition {
     }
 
     fn swirl(&self, distance: i32) -> f32 {
-        let rank = distance as f32 / self.radius as f32;
+        let rank = distance as f32 / self.snapshot.radius as f32;
         let x = self.time as f32 + rank * 60.0;
         (x / 10.0 - 10.0).exp().ln_1p() * 0.5 * if distance % 2 == 0 { 1.0 } else { -1.0 }
     }
 
     fn spread(&self, distance: i32) -> f32 {
-        let rank = distance as f32 / self.radius as f32;
+        let rank = distance as f32 / self.snapshot.radius as f32;
         let x = self.time as f32 + rank * 60.0;
         (x / 10.0 - 10.0).exp().ln_1p() * 0.5 + 1.0
     }
 }
 
+/// How many ticks it takes a new best score to count up.
+/// Normal scores count up as fast as they do on the HUD.
+const NEW_BEST_COUNT_TIME: u32 = 120;
+/// How many ticks the blocked spawn on the final board blinks on and off for
+const POSTMORTEM_BLINK_SPEED: u32 = 15;
+/// How many bits of confetti to throw for a new best
+const CONFETTI_COUNT: usize = 80;
+const CONFETTI_GRAVITY: f32 = 0.03;
+
+/// One bit of confetti for celebrating a new best
+#[derive(Clone)]
+struct Confetti {
+    pos: Vec2,
+    vel: Vec2,
+    color: Color,
+    /// Spins the confetti between wide and tall
+    spin: f32,
+}
+
 /// Losing screen, sadde
 #[derive(Clone)]
 pub struct ModeLosingScreen {
     time: u32,
 
     score: u32,
+    ranked: u32,
+    ranking: Ranking,
     prev_score: Option<u32>,
     /// Settings so we can play again with the same settings if you want
     board_settings: BoardSettings,
@@ -191,7 +301,18 @@ pub struct ModeLosingScreen {
     b_again: Button,
     b_quit: Button,
 
-    playtime: f64,
+    /// How many frames the run was played for, not counting pauses
+    playtime: u32,
+    reason: GameOverReason,
+    run_ticks: Option<u32>,
+
+    /// Counts the score up from zero
+    score_counter: ScoreCounter,
+    /// Only thrown if there's a new best
+    confetti: Vec<Confetti>,
+    /// What the board looked like at the end
+    snapshot: BoardSnapshot,
+    postmortem: PostMortem,
 }
 
 impl Gamemode for ModeLosingScreen {
@@ -201,19 +322,31 @@ impl Gamemode for ModeLosingScreen {
         _frame_info: FrameInfo,
         assets: &Assets,
     ) -> Transition {
+        if self.time == 0 && self.is_new_best() {
+            assets.play_sfx(SfxEvent::NewBest);
+        }
         self.time += 1;
+        self.score_counter.update(self.score);
+
+        for bit in self.confetti.iter_mut() {
+            bit.vel.y += CONFETTI_GRAVITY;
+            bit.vel *= 0.99;
+            bit.pos += bit.vel;
+            bit.spin += 0.15;
+        }
+        self.confetti.retain(|bit| bit.pos.y < height() + 2.0);
 
         if self.b_again.mouse_hovering() && controls.clicked_down(Control::Click) {
-            play_sound_once(assets.sounds.close_loop);
-            return Transition::Swap(Box::new(ModePlaying::new(
+            assets.play_sfx(SfxEvent::ButtonClick);
+            return Transition::Swap(start_game(
                 self.board_settings.clone(),
                 self.play_settings,
                 assets,
-            )));
+            ));
         } else if self.b_quit.mouse_hovering() && controls.clicked_down(Control::Click)
             || controls.clicked_down(Control::Pause)
         {
-            play_sound_once(assets.sounds.shunt);
+            assets.play_sfx(SfxEvent::MenuBack);
             return Transition::Pop; // back to the title screen
         }
 
@@ -225,7 +358,7 @@ impl Gamemode for ModeLosingScreen {
             b.post_update();
         }
         if play_sound {
-            play_sound_once(assets.sounds.select);
+            assets.play_sfx(SfxEvent::ButtonHover);
         }
 
         Transition::None
@@ -238,36 +371,61 @@ impl Gamemode for ModeLosingScreen {
 
 impl GamemodeDrawer for ModeLosingScreen {
     fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
-        clear_background(hexcolor(0x14182e_ff));
-
-        let color = hexcolor(0x4b1d52_ff);
-        let highlight = hexcolor(0x692464_ff);
-        let border = hexcolor(0xcc2f7b_ff);
-        let blight = hexcolor(0xff5277_ff);
-
-        let text = match self.prev_score {
-            _ if cfg!(target_arch = "wasm32") => format!("GAME OVER\nSCORE: {}", self.score * 100,),
-            Some(prev) if prev < self.score => format!(
-                "GAME OVER\nSCORE: {}\nNEW BEST! PREVIOUS: {}",
-                self.score * 100,
-                prev * 100
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let title = match self.reason {
+            GameOverReason::BoardFilled => "GAME OVER",
+            GameOverReason::OutOfMoves => "OUT OF MOVES",
+            GameOverReason::OpponentFilled => "YOU WIN!",
+        };
+        let score = format!("SCORE: {}", self.score_counter.shown() as u64 * 100);
+        let headline = match self.ranking {
+            Ranking::Score => score.clone(),
+            Ranking::SurvivalTime => format!("SURVIVED: {}", format_ticks(self.ranked)),
+        };
+        let mut text = match self.prev_score {
+            _ if cfg!(target_arch = "wasm32") || !self.board_settings.keeps_highscores() => {
+                format!("{}\n{}", title, headline)
+            }
+            Some(prev) if prev < self.ranked => format!(
+                "{}\n{}\nNEW BEST! PREVIOUS: {}",
+                title,
+                headline,
+                self.ranking.format(prev)
             ),
             Some(prev) => format!(
-                "GAME OVER\nSCORE: {}\nHISCORE: {}",
-                self.score * 100,
-                prev * 100
+                "{}\n{}\n{}: {}",
+                title,
+                headline,
+                self.ranking.best_label(),
+                self.ranking.format(prev)
             ),
-            None => format!("GAME OVER\nSCORE: {}\n NEW BEST!", self.score * 100),
-        } + &format!(
-            "\n\nPLAY TIME: {}m {}s",
-            self.playtime as u32 / 60,
-            self.playtime as u32 % 60
-        );
+            None => format!("{}\n{}\n NEW BEST!", title, headline),
+        };
+        match self.ranking {
+            Ranking::Score => {
+                text += &format!(
+                    "\n\nPLAY TIME: {}m {}s",
+                    self.playtime / TICKS_PER_SECOND / 60,
+                    self.playtime / TICKS_PER_SECOND % 60
+                );
+                if let Some(ticks) = self.run_ticks {
+                    text += &format!("\nRUN TIME: {}", format_ticks(ticks));
+                }
+            }
+            // The play time is the headline already, so just tack on the score
+            Ranking::SurvivalTime => text += &format!("\n\n{}", score),
+        }
 
         draw_pixel_text(
             &text,
-            WIDTH / 2.0,
-            HEIGHT * 0.25,
+            width() / 2.0,
+            height() * 0.25,
             TextAlign::Center,
             blight,
             assets.textures.fonts.small,
@@ -300,10 +458,42 @@ impl GamemodeDrawer for ModeLosingScreen {
             assets.textures.fonts.small,
         );
 
-        gl_use_material(assets.shaders.noise);
-        let mut fg = hexcolor(0x14182e_ff);
+        let mut snapshot_top = self.b_quit.bounds().bottom() + 4.0;
+        if !self.postmortem.explanation.is_empty() {
+            draw_pixel_text(
+                &self.postmortem.explanation,
+                width() / 2.0,
+                snapshot_top,
+                TextAlign::Center,
+                border,
+                assets.textures.fonts.small,
+            );
+            let line_height = assets.textures.fonts.small.height() + 1.0;
+            snapshot_top += line_height * self.postmortem.explanation.lines().count() as f32 + 2.0;
+        }
+        self.snapshot.draw(
+            width() / 2.0,
+            snapshot_top,
+            height() - 3.0,
+            &self.postmortem,
+            self.time / POSTMORTEM_BLINK_SPEED % 2 == 0,
+        );
+
+        for bit in self.confetti.iter() {
+            let (w, h) = if bit.spin.sin() > 0.0 {
+                (2.0, 1.0)
+            } else {
+                (1.0, 2.0)
+            };
+            draw_rectangle(bit.pos.x.round(), bit.pos.y.round(), w, h, bit.color);
+        }
+
+        if assets.shaders.enabled() {
+            gl_use_material(assets.shaders.noise);
+        }
+        let mut fg = theme::bg();
         fg.a = (1.0 - self.time as f32 / 150.0).clamp(0.0, 1.0);
-        draw_rectangle(0.0, 0.0, WIDTH, HEIGHT, fg);
+        draw_rectangle(0.0, 0.0, width(), height(), fg);
         gl_use_default_material();
     }
 }
@@ -311,16 +501,72 @@ impl GamemodeDrawer for ModeLosingScreen {
 impl ModeLosingScreen {
     pub fn new(prev: &ModeLosingTransition) -> Self {
         let w = 12.0 * 4.0 + 4.0;
-        let x = WIDTH / 2.0 - w / 2.0;
-        Self {
+        let x = width() / 2.0 - w / 2.0;
+        let mut out = Self {
             score: prev.score,
+            ranked: prev.ranked,
+            ranking: prev.ranking,
             prev_score: prev.prev_score,
             board_settings: prev.board_settings.clone(),
             play_settings: prev.play_settings,
             time: 0,
-            b_again: Button::new(x, HEIGHT / 2.0 + 3.0, w, 9.0),
-            b_quit: Button::new(x, HEIGHT / 2.0 + 14.0, w, 9.0),
+            b_again: Button::new(x, height() / 2.0 + 3.0, w, 9.0),
+            b_quit: Button::new(x, height() / 2.0 + 14.0, w, 9.0),
             playtime: prev.playtime,
+            reason: prev.reason,
+            run_ticks: prev.run_ticks,
+            score_counter: ScoreCounter::new(0, SCORE_COUNT_TIME),
+            confetti: Vec::new(),
+            snapshot: prev.snapshot.clone(),
+            postmortem: prev.postmortem.clone(),
+        };
+        if out.is_new_best() {
+            out.score_counter = ScoreCounter::new(0, NEW_BEST_COUNT_TIME);
+            out.confetti = (0..CONFETTI_COUNT)
+                .map(|idx| {
+                    // Half from each bottom corner, shooting up and inwards
+                    let left = idx % 2 == 0;
+                    let x_speed = rand::gen_range(0.3, 1.5);
+                    Confetti {
+                        pos: vec2(if left { 0.0 } else { width() }, height()),
+                        vel: vec2(
+                            if left { x_speed } else { -x_speed },
+                            -rand::gen_range(1.5, 3.0),
+                        ),
+                        // No gray confetti, it's a party
+                        color: hexcolor(MARBLE_COLORS[idx % Marble::Stone as usize]),
+                        spin: rand::gen_range(0.0, std::f32::consts::TAU),
+                    }
+                })
+                .collect();
+        }
+        out
+    }
+
+    /// Did this run beat the high score?
+    fn is_new_best(&self) -> bool {
+        if cfg!(target_arch = "wasm32") || !self.board_settings.keeps_highscores() {
+            return false;
+        }
+        match self.prev_score {
+            Some(prev) => prev < self.ranked,
+            None => self.ranked > 0,
+        }
+    }
+}
+
+/// Save the score if it beats the one in the entry, and return what was there before.
+fn record_best<K>(entry: Entry<K, u32>, ranked: u32) -> Option<u32> {
+    match entry {
+        Entry::Occupied(mut prev_score) => {
+            // save it so we can return it
+            let save = *prev_score.get();
+            prev_score.insert(save.max(ranked));
+            Some(save)
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(ranked);
+            None
         }
     }
 }
@@ -1,26 +1,63 @@
+use std::cell::Cell;
+
+use ahash::AHashMap;
 use cogs_gamedev::ease::Interpolator;
-use hex2d::{Coordinate, IntegerSpacing};
+use hex2d::{Angle, Coordinate, Direction};
 use macroquad::prelude::*;
 
 use crate::{
     assets::Assets,
     boilerplates::{FrameInfo, GamemodeDrawer},
-    model::{BoardAction, Marble, PlaySettings, ScorePacket},
+    height,
+    model::{format_ticks, ActionTimes, Board, BoardAction, Marble, ScoreEvent, TICKS_PER_SECOND},
     utils::{
-        draw::{hexcolor, mouse_position_pixel},
+        button::Button,
+        coord_overlay::{coord_overlay, label},
+        draw::{hexcolor, marble_color, mouse_position_pixel},
+        effects::heavy_effects,
+        layout::{flip_align, flip_x},
+        profile::PlaySettings,
         text::{draw_pixel_text, Billboard, Markup, TextAlign, TextSpan},
+        theme,
     },
-    HEIGHT, WIDTH,
+    width,
 };
 
-use super::{BOARD_CENTER_X, BOARD_CENTER_Y, MARBLE_SIZE, MARBLE_SPAN_X, MARBLE_SPAN_Y};
+use super::{
+    board_center, camera::BoardCamera, MARBLE_SIZE, MARBLE_SPAN_X, MARBLE_SPAN_Y, SCORE_POPUP_TIME,
+    SCORE_TICKER_LENGTH, SCORE_TICKER_TIME,
+};
 
 /// Speed for one on or off of the blink
 const CLEAR_ALL_BLINK_SPEED: u32 = 10;
-/// How many bg timer points to one hexagon
-const BG_HEX_SPEED: u32 = 20;
 /// How many hexagons there are
 const BG_HEX_COUNT: u32 = 6;
+/// How many ticks it takes for the cells the player can spawn a marble in to pulse once
+const PLACEMENT_PULSE_TIME: u32 = 30;
+/// Colors of the score popups for each rung of the blob size ladder
+const LADDER_TIER_COLORS: [u32; 3] = [0x99e550_ff, 0x5fcde4_ff, 0xfbf236_ff];
+/// How far through a color clear's wind-up the marbles finish getting pulled in and burst
+const DELETE_COLOR_BURST_START: f32 = 0.75;
+/// How many ticks the score ticker takes to scroll up a line for a new gain
+const SCORE_TICKER_SCROLL_TIME: u32 = 6;
+/// Marbles start looking stony once they're this close to petrifying
+const PETRIFY_TINT_START: f32 = 0.5;
+/// How close to fusing an edge cell has to be before it starts crumbling
+const EDGE_CRUMBLE_START: f32 = 0.6;
+/// The most chips a crumbling edge sheds at once
+const EDGE_CRUMBLE_CHIPS: u32 = 4;
+/// Speed for one on or off of the gravity warning blink
+const GRAVITY_WARNING_BLINK_SPEED: u32 = 15;
+/// How thick the pattern beam is with the high-visibility cursor on
+const HIGH_VIS_BEAM_WIDTH: f32 = 3.0;
+/// How far each arm of the high-visibility crosshair reaches from the mouse
+const CROSSHAIR_REACH: f32 = 7.0;
+/// Color of the second player's cursor and pattern in co-op
+const PARTNER_COLOR: u32 = 0x8fde5d_ff;
+/// How small the CPU's board is drawn in versus runs
+const OPPONENT_ZOOM: f32 = 0.2;
+/// How far the middle of the CPU's board is from the corner of the canvas
+const OPPONENT_INSET: f32 = 15.0;
 
 pub struct Drawer {
     pub marbles: Vec<(Coordinate, Marble)>,
@@ -29,139 +66,842 @@ pub struct Drawer {
     /// All the coordinates of marbles in blobs big enough to be removed,
     /// if next on the agenda is to clear blobs (otherwise it will be empty)
     pub to_remove: Vec<Coordinate>,
+    /// Marbles that would get cleared if the player let go of the loop they're holding
+    pub preview: Vec<Coordinate>,
+    /// The pattern the player just submitted, and how opaque to draw it
+    pub ghost: Option<(Vec<Coordinate>, f32)>,
+    /// A hexagon waiting for confirmation, and how much of its time is left
+    pub unconfirmed_hexagon: Option<(Vec<Coordinate>, f32)>,
+    /// Cells the player can pick for the next marble to spawn in, and how many ticks they have left
+    pub placing: Option<(Vec<Coordinate>, u32)>,
     pub radius: usize,
+    /// The radius the camera frames, which lags behind `radius` while the board grows
+    pub shown_radius: f32,
+    /// Every cell that exists on the board
+    pub cells: Vec<Coordinate>,
+    /// Empty cells and where a marble there would fall to, if the gravity arrows are on
+    pub falls: Vec<(Coordinate, Coordinate)>,
+    /// How close each marble is to turning to stone, if they can
+    pub petrify_progress: AHashMap<Coordinate, f32>,
+    /// How close each edge cell is to fusing into the wall, with the squeeze mutator
+    pub edge_pressure: Vec<(Coordinate, f32)>,
     pub next_spawn_point: Option<Coordinate>,
     /// The action we're about to do and time ticking up until it's completed
     pub next_action: Option<(BoardAction, u32)>,
+    /// How long the board's actions take to complete
+    pub action_times: ActionTimes,
 
-    pub bg_funni_timer: f32,
+    /// How many background pulses have gone by, plus how far we are through the current one
+    pub bg_phase: f32,
 
+    /// The score to show, which counts up towards the real one
     pub score: u32,
-    pub score_queue: Vec<ScorePacket>,
+    /// How brightly the score is flashing from a big gain, from 0 to 1
+    pub score_flash: f32,
+    /// Points scored lately, newest last, and how many ticks ago
+    pub recent_scores: Vec<(ScoreEvent, u32)>,
+    /// The multiplier carried along by the current cascade, if there is one
+    pub active_multiplier: Option<u32>,
+    /// How many more patterns the player can draw, if there's a limit
+    pub moves_left: Option<u32>,
+    /// Don't draw the symbols on the marbles
+    pub hide_sigils: bool,
+    /// Speedrun timer, if it's turned on
+    pub run_timer: Option<RunTimer>,
+    /// How long the run has lasted, if that's what it's ranked by
+    pub survival_time: Option<u32>,
+    /// Hint to show and how opaque it is
+    pub tip: Option<(String, f32)>,
+    /// Rewind controls, if this is practice mode
+    pub rewind: Option<RewindInfo>,
+
+    /// If the gravity is about to rotate, the direction it's rotating to and how many ticks until it does
+    pub gravity_warning: Option<(Direction, u32)>,
+    /// How far the player has turned their view of the board
+    pub view_rotation: Angle,
+    /// The hex the mouse is over, as far as input is concerned, for the coordinate overlay
+    pub hovered: Coordinate,
+    /// How many marbles have spawned at each spawnpoint, for the coordinate overlay
+    pub spawn_tally: Vec<(Coordinate, u32)>,
+    /// The CPU's board, if this is a versus run
+    pub opponent: Option<OpponentInfo>,
+    /// The second player's cursor and pattern, if this is a co-op run
+    pub partner: Option<(Coordinate, Option<Vec<Coordinate>>)>,
+    /// HUD buttons for power-ups, if the run has them
+    pub powerups: Vec<PowerUpInfo>,
+    /// The special action's charge meter, if the run has one
+    pub charge: Option<ChargeInfo>,
+    /// Is the board running at half speed?
+    pub slowed: bool,
+    /// Button for a free shuffle, if the player is out of useful moves
+    pub shuffle_offer: Option<Button>,
+    /// The next few marble colors, if they're dealt from a bag
+    pub upcoming: Vec<Marble>,
 
     pub paused: bool,
+    /// Buttons on the pause menu's quick-settings bar, and what they say
+    pub quick_settings: Vec<(Button, String)>,
 
     pub settings: PlaySettings,
 }
 
-impl GamemodeDrawer for Drawer {
-    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
-        clear_background(hexcolor(0x14182e_ff));
-
-        if self.settings.funni_background {
-            for hex_idx in (0..BG_HEX_COUNT).rev() {
-                let radius = (hex_idx as f32 + (self.bg_funni_timer / BG_HEX_SPEED as f32).fract())
-                    * WIDTH
-                    / BG_HEX_COUNT as f32
-                    * 1.1;
-                let color = if (self.bg_funni_timer.trunc() as u32 / BG_HEX_SPEED + hex_idx)
-                    % BG_HEX_COUNT
-                    % 2
-                    == 0
-                {
-                    hexcolor(0x14182e_ff)
-                } else {
-                    hexcolor(0x4b1d52_ff)
-                };
+/// Speedrun timer info
+pub struct RunTimer {
+    /// Ticks since the run started
+    pub ticks: u32,
+    /// Score milestone, tick it was reached on this run, and the best tick it's ever been reached on
+    pub splits: Vec<(u32, Option<u32>, Option<u32>)>,
+}
 
-                draw_hexagon(
-                    BOARD_CENTER_X,
-                    BOARD_CENTER_Y,
-                    radius,
-                    2.0,
-                    false,
-                    hexcolor(0xcc2f7b_ff),
-                    color,
-                );
-            }
+/// The CPU's side of a versus run
+pub struct OpponentInfo {
+    pub marbles: AHashMap<Coordinate, Marble>,
+    pub cells: Vec<Coordinate>,
+    /// Stones waiting to drop on the player's board
+    pub incoming: u32,
+    /// Stones waiting to drop on the CPU's board
+    pub outgoing: u32,
+}
+
+impl OpponentInfo {
+    /// Draw the CPU's board small in the bottom corner, with flat colors for the marbles,
+    /// and how many stones are headed each way.
+    fn draw(&self, assets: &Assets) {
+        let camera = BoardCamera {
+            center: vec2(
+                flip_x(width() - OPPONENT_INSET, 0.0),
+                height() - OPPONENT_INSET,
+            ),
+            zoom: OPPONENT_ZOOM,
+            rotation: Angle::Forward,
+        };
+        let size = camera.marble_size().ceil();
+        for &cell in self.cells.iter() {
+            let corner = camera.hex_to_corner(cell);
+            let color = match self.marbles.get(&cell) {
+                Some(marble) => marble_color(marble),
+                None => hexcolor(0xdfe0e8_40),
+            };
+            draw_rectangle(corner.x, corner.y, size, size, color);
+        }
+
+        let label = if self.outgoing > 0 {
+            format!("CPU +{}", self.outgoing)
+        } else {
+            "CPU".to_owned()
+        };
+        draw_pixel_text(
+            &label,
+            camera.center.x,
+            height() - 2.0 * OPPONENT_INSET - 6.0,
+            TextAlign::Center,
+            WHITE,
+            assets.textures.fonts.small,
+        );
+        if self.incoming > 0 {
+            draw_pixel_text(
+                &format!("+{} STONES", self.incoming),
+                flip_x(width() - 2.0 * OPPONENT_INSET - 3.0, 0.0),
+                height() - 9.0,
+                flip_align(TextAlign::Right),
+                theme::accent(),
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
+
+/// Practice mode rewind info
+pub struct RewindInfo {
+    /// How full the history is, from 0 to 1
+    pub fill: f32,
+    pub rewinding: bool,
+    pub button: Button,
+}
+
+/// A power-up's HUD button
+pub struct PowerUpInfo {
+    pub button: Button,
+    pub name: &'static str,
+    /// Where the icon is in the marble atlas
+    pub icon: Rect,
+    /// How many the player has to spend
+    pub held: u32,
+    /// Will it go off with the next pattern?
+    pub armed: bool,
+    /// How close the next power-up is, from 0 to 1
+    pub progress: f32,
+}
+
+/// The charge meter, which doubles as the button to use the special action
+pub struct ChargeInfo {
+    pub button: Button,
+    pub name: &'static str,
+    /// How full the meter is, from 0 to 1
+    pub fill: f32,
+    pub full: bool,
+}
+
+impl Drawer {
+    /// Draw everything except the pause overlay
+    fn draw_game(&self, assets: &Assets) {
+        clear_background(theme::bg());
+
+        if self.settings.funni_background && heavy_effects() {
+            draw_background_hexes(board_center(), width() * 1.1, self.bg_phase);
         }
 
+        let camera = BoardCamera::for_shown_radius(self.shown_radius).rotated(self.view_rotation);
         draw_marble_board(
-            vec2(BOARD_CENTER_X, BOARD_CENTER_Y),
-            self.radius,
+            &camera,
+            &self.cells,
+            &self.falls,
             &self.marbles,
+            &self.petrify_progress,
             self.next_action.as_ref(),
+            self.action_times,
             &self.to_remove,
+            &self.preview,
             self.next_spawn_point,
             self.pattern
                 .as_ref()
                 .map(|v| (v.as_slice(), mouse_position_pixel().into())),
             self.settings,
+            self.hide_sigils,
             assets,
         );
 
-        let score = format!("{}", self.score * 100);
-        let text_x = BOARD_CENTER_X - 5.0 * (score.len() as f32 - 1.0) / 2.0;
-        let text_y = BOARD_CENTER_Y - (self.radius as i32 * MARBLE_SPAN_Y) as f32 - 10.0;
+        if coord_overlay() {
+            self.draw_coord_overlay(&camera);
+        }
+
+        if let Some((cursor, pattern)) = &self.partner {
+            let color = hexcolor(PARTNER_COLOR);
+            if let Some(pattern) = pattern {
+                let thickness = if self.settings.high_vis_cursor {
+                    HIGH_VIS_BEAM_WIDTH
+                } else {
+                    1.0
+                };
+                draw_pattern(
+                    pattern,
+                    camera.hex_to_pixel(*cursor),
+                    &camera,
+                    thickness,
+                    color,
+                    assets,
+                );
+            }
+            let corner = camera.hex_to_corner(*cursor);
+            let size = camera.marble_size();
+            draw_rectangle_lines(
+                corner.x - 1.0,
+                corner.y - 1.0,
+                size + 2.0,
+                size + 2.0,
+                1.0,
+                color,
+            );
+        }
+
+        if let Some((ghost, fade)) = &self.ghost {
+            let mut color = theme::text();
+            color.a = *fade;
+            for span in ghost.windows(2) {
+                let start = camera.hex_to_pixel(span[0]);
+                let end = camera.hex_to_pixel(span[1]);
+                draw_line(start.x, start.y, end.x, end.y, 1.0, color);
+            }
+        }
+
+        if let Some((hexagon, left)) = &self.unconfirmed_hexagon {
+            let color = theme::text();
+            for span in hexagon.windows(2) {
+                let start = camera.hex_to_pixel(span[0]);
+                let end = camera.hex_to_pixel(span[1]);
+                draw_line(start.x, start.y, end.x, end.y, 1.0, color);
+            }
+            // A ring around it shrinks down to nothing as time runs out.
+            // The loop ends where it starts, so skip the repeat.
+            let corners = hexagon[1..].iter().map(|c| camera.hex_to_pixel(*c));
+            let center =
+                corners.clone().fold(vec2(0.0, 0.0), |acc, p| acc + p) / (hexagon.len() - 1) as f32;
+            let reach = corners.map(|p| (p - center).length()).fold(0.0, f32::max);
+            let radius = (reach + camera.marble_size()) * left;
+            draw_circle_lines(center.x, center.y, radius, 1.0, color);
+        }
+
+        // Edges about to fuse into the wall crack and shed chips
+        for (pos, pressure) in self.edge_pressure.iter() {
+            if *pressure < EDGE_CRUMBLE_START {
+                continue;
+            }
+            let t = (pressure - EDGE_CRUMBLE_START) / (1.0 - EDGE_CRUMBLE_START);
+            let center = camera.hex_to_pixel(*pos);
+            let radius = camera.marble_size() / 2.0;
+            let mut color = theme::shadow();
+            color.a = t;
+            draw_circle_lines(center.x, center.y, radius, 1.0, color);
+            let chips = (t * EDGE_CRUMBLE_CHIPS as f32).ceil() as u32;
+            for chip in 0..chips {
+                // Spread them around the cell differently for each cell
+                let angle = (chip as f32 + (pos.x * 7 + pos.y * 3) as f32 * 0.37)
+                    * std::f32::consts::TAU
+                    / EDGE_CRUMBLE_CHIPS as f32;
+                let drift = radius + 1.0 + 2.0 * (self.bg_phase + chip as f32 * 0.25).fract();
+                let chip_pos = center + vec2(angle.cos(), angle.sin()) * drift;
+                draw_rectangle(chip_pos.x.round(), chip_pos.y.round(), 1.0, 1.0, color);
+            }
+        }
+
+        if let Some((cells, ticks)) = &self.placing {
+            let seconds = (ticks + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND;
+            let pulse = (*ticks % PLACEMENT_PULSE_TIME) as f32 / PLACEMENT_PULSE_TIME as f32;
+            let mut color = theme::accent();
+            color.a = 0.3 + 0.7 * pulse;
+            for &cell in cells {
+                let center = camera.hex_to_pixel(cell);
+                draw_circle_lines(center.x, center.y, camera.marble_size() / 2.0, 1.0, color);
+            }
+            draw_pixel_text(
+                &format!("PLACE A MARBLE {}", seconds),
+                width() / 2.0,
+                3.0,
+                TextAlign::Center,
+                theme::accent(),
+                assets.textures.fonts.small,
+            );
+        }
+
+        // Each clear's points float up off of it
+        for (event, age) in self.recent_scores.iter() {
+            if *age >= SCORE_POPUP_TIME {
+                continue;
+            }
+            let t = *age as f32 / SCORE_POPUP_TIME as f32;
+            let pos = camera.hex_to_pixel(event.pos) - vec2(0.0, 2.0 + 8.0 * t);
+            let mut color = match event.tier {
+                Some(tier) => hexcolor(LADDER_TIER_COLORS[tier.min(LADDER_TIER_COLORS.len() - 1)]),
+                None => theme::text(),
+            };
+            color.a = 1.0 - t * t;
+            draw_pixel_text(
+                &format!("+{}", event.packet.points() as u64 * 100),
+                pos.x,
+                pos.y - 3.0,
+                TextAlign::Center,
+                color,
+                assets.textures.fonts.small,
+            );
+        }
+
+        if let Some((dir, ticks)) = self.gravity_warning {
+            if ticks / GRAVITY_WARNING_BLINK_SPEED % 2 == 0 {
+                draw_gravity_arrow(
+                    &camera,
+                    self.radius,
+                    dir,
+                    theme::text(),
+                );
+            }
+        }
+
+        let score = format!("{}", self.score as u64 * 100);
+        let text_x = camera.center.x - 5.0 * (score.len() as f32 - 1.0) / 2.0;
+        let text_y =
+            camera.center.y - self.radius as f32 * MARBLE_SPAN_Y as f32 * camera.zoom - 10.0;
+        let flash = theme::text();
+        let score_color = Color::new(
+            1.0 + (flash.r - 1.0) * self.score_flash,
+            1.0 + (flash.g - 1.0) * self.score_flash,
+            1.0 + (flash.b - 1.0) * self.score_flash,
+            1.0,
+        );
         draw_pixel_text(
             &score,
             text_x,
             text_y,
             TextAlign::Left,
-            WHITE,
+            score_color,
             assets.textures.fonts.small,
         );
-        for (idx, packet) in self.score_queue.iter().enumerate() {
+        if let Some(mult) = self.active_multiplier {
+            draw_pixel_text(
+                &format!("x{}", mult),
+                text_x + 5.0 * score.len() as f32 + 3.0,
+                text_y,
+                TextAlign::Left,
+                theme::text(),
+                assets.textures.fonts.small,
+            );
+        }
+        // The ticker of recent gains scrolls up from the score, newest at the bottom
+        let ticker = self
+            .recent_scores
+            .iter()
+            .rev()
+            .take(SCORE_TICKER_LENGTH)
+            .filter(|(_, age)| *age < SCORE_TICKER_TIME);
+        let scroll = self.recent_scores.last().map_or(0.0, |(_, age)| {
+            1.0 - (*age as f32 / SCORE_TICKER_SCROLL_TIME as f32).min(1.0)
+        });
+        for (idx, (event, age)) in ticker.enumerate() {
             // we want the score part to line up with the main score.
             // and the 1 char plus sign to hang over the edge.
             // so we subtract 1
             let text_x = text_x - 1.0 * 4.0;
-            let text_y = text_y - 6.0 * (1 + idx) as f32;
+            let text_y = (text_y - 6.0 * (1.0 + idx as f32 - scroll)).round();
+            let packet = event.packet;
             let text = if packet.multiplier == 1 {
-                format!("+{}", packet.base * 100)
+                format!("+{}", packet.base as u64 * 100)
             } else {
-                format!("+{:2}x{}", packet.multiplier, packet.base * 100)
+                format!("+{:2}x{}", packet.multiplier, packet.base as u64 * 100)
             };
+            // Fade out over the last third of its time
+            let mut color = theme::accent();
+            color.a = (3.0 * (1.0 - *age as f32 / SCORE_TICKER_TIME as f32)).min(1.0);
             draw_pixel_text(
                 &text,
                 text_x,
                 text_y,
                 TextAlign::Left,
-                hexcolor(0xff5277_ff),
+                color,
                 assets.textures.fonts.small,
             );
         }
 
-        if self.paused {
-            draw_rectangle(0.0, 0.0, WIDTH, HEIGHT, hexcolor(0x291d2b_a0));
+        if let Some(moves_left) = self.moves_left {
+            draw_pixel_text(
+                &format!("MOVES\n{}", moves_left),
+                flip_x(3.0, 0.0),
+                3.0,
+                flip_align(TextAlign::Left),
+                if moves_left <= 5 {
+                    theme::accent()
+                } else {
+                    WHITE
+                },
+                assets.textures.fonts.small,
+            );
+        }
 
-            Billboard::draw_now(
-                vec![TextSpan {
-                    text: "PAUSED".to_owned(),
-                    markup: Markup {
-                        color: WHITE,
-                        font: assets.textures.fonts.small,
-                        kerning: 1.0,
-                        vert_space: 1.0,
-                        wave: None,
+        if !self.upcoming.is_empty() {
+            draw_pixel_text(
+                "NEXT",
+                flip_x(3.0, 0.0),
+                20.0,
+                flip_align(TextAlign::Left),
+                WHITE,
+                assets.textures.fonts.small,
+            );
+            let atlas = self.settings.skin.atlas();
+            for (idx, marble) in self.upcoming.iter().enumerate() {
+                let x = flip_x(3.0 + (MARBLE_SIZE + 1.0) * idx as f32, MARBLE_SIZE);
+                let sx = marble.clone() as u32 as f32 * MARBLE_SIZE;
+                draw_texture_ex(
+                    assets.textures.marble_atlas,
+                    x,
+                    26.0,
+                    WHITE,
+                    DrawTextureParams {
+                        source: Some(Rect::new(sx, atlas.body_y, MARBLE_SIZE, MARBLE_SIZE)),
+                        ..Default::default()
+                    },
+                );
+                draw_texture_ex(
+                    assets.textures.marble_atlas,
+                    x,
+                    26.0,
+                    theme::shadow(),
+                    DrawTextureParams {
+                        source: Some(Rect::new(sx, atlas.sigil_y, MARBLE_SIZE, MARBLE_SIZE)),
+                        ..Default::default()
                     },
-                }],
-                vec2(WIDTH / 2.0 - 10.0, HEIGHT / 2.0),
-                vec2(0.0, -5.0),
-                None,
+                );
+            }
+        }
+
+        if let Some((tip, alpha)) = &self.tip {
+            let mut color = WHITE;
+            color.a = *alpha;
+            draw_pixel_text(
+                tip,
+                width() / 2.0,
+                height() - 15.0,
+                TextAlign::Center,
+                color,
+                assets.textures.fonts.small,
+            );
+        }
+
+        if let Some(ticks) = self.survival_time {
+            draw_pixel_text(
+                &format_ticks(ticks),
+                flip_x(3.0, 0.0),
+                3.0,
+                flip_align(TextAlign::Left),
+                WHITE,
+                assets.textures.fonts.medium,
+            );
+        }
+
+        if let Some(timer) = &self.run_timer {
+            draw_pixel_text(
+                &format_ticks(timer.ticks),
+                flip_x(width() - 3.0, 0.0),
+                3.0,
+                flip_align(TextAlign::Right),
+                WHITE,
+                assets.textures.fonts.small,
+            );
+            for (idx, (milestone, split, best)) in timer.splits.iter().enumerate() {
+                let y = 10.0 + 6.0 * idx as f32;
+                let label = format!("{}K", milestone / 10);
+                let (text, color) = match (split, best) {
+                    (Some(split), Some(best)) if split <= best => (
+                        format!("{} -{}", label, format_ticks(best - split)),
+                        hexcolor(0x8fde5d_ff),
+                    ),
+                    (Some(split), Some(best)) => (
+                        format!("{} +{}", label, format_ticks(split - best)),
+                        theme::accent(),
+                    ),
+                    (Some(split), None) => {
+                        (format!("{} {}", label, format_ticks(*split)), WHITE)
+                    }
+                    (None, Some(best)) => (
+                        format!("{} {}", label, format_ticks(*best)),
+                        theme::dim(),
+                    ),
+                    (None, None) => (format!("{} -", label), theme::dim()),
+                };
+                draw_pixel_text(
+                    &text,
+                    flip_x(width() - 3.0, 0.0),
+                    y,
+                    flip_align(TextAlign::Right),
+                    color,
+                    assets.textures.fonts.small,
+                );
+            }
+        }
+
+        if let Some(rewind) = &self.rewind {
+            let color = theme::panel();
+            let highlight = theme::panel_highlight();
+            let border = theme::border();
+            let blight = theme::accent();
+
+            let b = &rewind.button;
+            b.draw(color, border, highlight, blight, 1.01);
+            // Meter of how much history is left
+            draw_rectangle(
+                b.x() + 1.0,
+                b.y() + b.h() - 2.0,
+                ((b.w() - 2.0) * rewind.fill).round(),
+                1.0,
+                blight,
+            );
+            draw_pixel_text(
+                "REWIND",
+                b.x() + b.w() / 2.0,
+                b.y() + 2.0,
+                TextAlign::Center,
+                if rewind.rewinding { WHITE } else { border },
+                assets.textures.fonts.small,
+            );
+            if rewind.rewinding {
+                draw_rectangle(0.0, 0.0, width(), height(), hexcolor(0x4b1d52_40));
+            }
+        }
+
+        for powerup in self.powerups.iter() {
+            let color = theme::panel();
+            let highlight = theme::panel_highlight();
+            let border = theme::border();
+            let blight = theme::accent();
+
+            let b = &powerup.button;
+            b.draw(color, border, highlight, blight, 1.01);
+            // Meter of how close the next one is
+            draw_rectangle(
+                b.x() + 1.0,
+                b.y() + b.h() - 2.0,
+                ((b.w() - 2.0) * powerup.progress).round(),
+                1.0,
+                blight,
+            );
+            let text_color = if powerup.armed {
+                WHITE
+            } else if powerup.held == 0 {
+                theme::dim()
+            } else {
+                border
+            };
+            draw_texture_ex(
+                assets.textures.marble_atlas,
+                b.x() + 1.0,
+                b.y() + 1.0,
+                text_color,
+                DrawTextureParams {
+                    source: Some(powerup.icon),
+                    ..Default::default()
+                },
+            );
+            draw_pixel_text(
+                &format!("{} {}", powerup.name, powerup.held),
+                b.x() + MARBLE_SIZE + 3.0,
+                b.y() + 2.0,
+                TextAlign::Left,
+                text_color,
+                assets.textures.fonts.small,
             );
         }
+
+        if let Some(charge) = &self.charge {
+            let color = theme::panel();
+            let highlight = theme::panel_highlight();
+            let border = theme::border();
+            let blight = theme::accent();
+
+            let b = &charge.button;
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_rectangle(
+                b.x() + 1.0,
+                b.y() + 1.0,
+                ((b.w() - 2.0) * charge.fill).round(),
+                b.h() - 2.0,
+                if charge.full { blight } else { highlight },
+            );
+            draw_pixel_text(
+                charge.name,
+                b.x() + b.w() / 2.0,
+                b.y() + 2.0,
+                TextAlign::Center,
+                if charge.full { WHITE } else { border },
+                assets.textures.fonts.small,
+            );
+        }
+        if self.slowed {
+            draw_rectangle(0.0, 0.0, width(), height(), hexcolor(0x639bff_20));
+        }
+
+        if let Some(b) = &self.shuffle_offer {
+            let color = theme::panel();
+            let highlight = theme::panel_highlight();
+            let border = theme::border();
+            let blight = theme::accent();
+
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_pixel_text(
+                "NO MOVES? SHUFFLE",
+                b.x() + b.w() / 2.0,
+                b.y() + 2.0,
+                TextAlign::Center,
+                if b.mouse_hovering() { WHITE } else { blight },
+                assets.textures.fonts.small,
+            );
+        }
+
+        if let Some(opponent) = &self.opponent {
+            opponent.draw(assets);
+        }
+
+        if self.settings.high_vis_cursor {
+            draw_crosshair(mouse_position_pixel().into());
+        }
+    }
+
+    /// Label every cell with its coordinates and box the one the mouse is over.
+    fn draw_coord_overlay(&self, camera: &BoardCamera) {
+        // Shade spawnpoints by how often they've been used, so lopsided spawns stand out
+        let size = camera.marble_size();
+        let most = self.spawn_tally.iter().map(|(_, count)| *count).max();
+        let mut sides = [0; 6];
+        for &(cell, count) in self.spawn_tally.iter() {
+            let corner = camera.hex_to_corner(cell);
+            let heat = count as f32 / most.unwrap_or(1) as f32;
+            draw_rectangle(
+                corner.x,
+                corner.y,
+                size,
+                size,
+                Color::new(1.0, 0.0, 0.0, heat * 0.6),
+            );
+            if let Some(dir) = cell.direction_from_center_cw() {
+                sides[dir.to_int::<i32>() as usize] += count;
+            }
+        }
+        label(
+            vec2(width() / 2.0, 4.0),
+            format!(
+                "SPAWNS BY SIDE {}",
+                sides
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            RED,
+        );
+
+        for &cell in self.cells.iter() {
+            let color = if cell == self.hovered { YELLOW } else { WHITE };
+            label(
+                camera.hex_to_pixel(cell),
+                format!("{},{}", cell.x, cell.y),
+                color,
+            );
+        }
+
+        let corner = camera.hex_to_corner(self.hovered);
+        draw_rectangle_lines(
+            corner.x - 1.0,
+            corner.y - 1.0,
+            size + 2.0,
+            size + 2.0,
+            1.0,
+            YELLOW,
+        );
+
+        // Where the mouse is on the canvas and where that lands, in case they don't line up
+        let (mx, my) = mouse_position_pixel();
+        label(
+            vec2(width() / 2.0, height() - 4.0),
+            format!(
+                "MOUSE {:.1},{:.1} -> {},{}",
+                mx, my, self.hovered.x, self.hovered.y
+            ),
+            YELLOW,
+        );
     }
 }
 
+impl GamemodeDrawer for Drawer {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        if !self.paused {
+            self.draw_game(assets);
+            return;
+        }
+
+        if !assets.shaders.enabled() {
+            // No blur, so just darken it
+            self.draw_game(assets);
+            let mut dim = theme::bg();
+            dim.a = 0.6;
+            draw_rectangle(0.0, 0.0, width(), height(), dim);
+        } else {
+            // Draw the game off to the side so it can go through the pause shader
+            let snapshot = pause_snapshot_target();
+            push_camera_state();
+            set_camera(&Camera2D {
+                render_target: Some(snapshot),
+                zoom: vec2(width().recip() * 2.0, height().recip() * 2.0),
+                target: vec2(width() / 2.0, height() / 2.0),
+                ..Default::default()
+            });
+            self.draw_game(assets);
+            pop_camera_state();
+
+            assets
+                .shaders
+                .pause
+                .set_uniform("CanvasSize", (width(), height()));
+            gl_use_material(assets.shaders.pause);
+            draw_texture_ex(
+                snapshot.texture,
+                0.0,
+                0.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(width(), height())),
+                    ..Default::default()
+                },
+            );
+            gl_use_default_material();
+        }
+
+        Billboard::draw_now(
+            vec![TextSpan {
+                text: "PAUSED".to_owned(),
+                markup: Markup {
+                    color: WHITE,
+                    font: assets.textures.fonts.small,
+                    kerning: 1.0,
+                    vert_space: 1.0,
+                    wave: None,
+                },
+            }],
+            vec2(width() / 2.0 - 10.0, height() / 2.0),
+            vec2(0.0, -5.0),
+            None,
+        );
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+        for (b, text) in self.quick_settings.iter() {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_pixel_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + 2.0,
+                TextAlign::Center,
+                if b.mouse_hovering() { WHITE } else { blight },
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
+
+thread_local! {
+    /// Where the game gets drawn while paused, so it can be blurred.
+    /// Drawing always happens on the main thread, so this only ever gets made once per resolution.
+    static PAUSE_SNAPSHOT: Cell<Option<RenderTarget>> = Cell::new(None);
+}
+
+/// Get a render target the size of the canvas to draw the paused game onto
+fn pause_snapshot_target() -> RenderTarget {
+    PAUSE_SNAPSHOT.with(|cell| match cell.get() {
+        Some(target)
+            if target.texture.width() == width() && target.texture.height() == height() =>
+        {
+            target
+        }
+        old => {
+            if let Some(old) = old {
+                old.delete();
+            }
+            let target = render_target(width() as u32, height() as u32);
+            target.texture.set_filter(FilterMode::Nearest);
+            cell.set(Some(target));
+            target
+        }
+    })
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn draw_marble_board(
-    center: Vec2,
-    radius: usize,
+    camera: &BoardCamera,
+    cells: &[Coordinate],
+    falls: &[(Coordinate, Coordinate)],
     marbles: &[(Coordinate, Marble)],
+    petrify_progress: &AHashMap<Coordinate, f32>,
     next_action: Option<&(BoardAction, u32)>,
+    action_times: ActionTimes,
     to_remove: &[Coordinate],
+    preview: &[Coordinate],
     spawnpoint: Option<Coordinate>,
     path: Option<(&[Coordinate], Vec2)>,
     settings: PlaySettings,
+    hide_sigils: bool,
     assets: &Assets,
 ) {
-    for bg_pos in Coordinate::new(0, 0).range_iter(radius as _) {
-        let (ox, oy) =
-            bg_pos.to_pixel_integer(IntegerSpacing::PointyTop(MARBLE_SPAN_X, MARBLE_SPAN_Y));
-
-        let corner_x = ox as f32 - MARBLE_SIZE / 2.0 + center.x;
-        let corner_y = oy as f32 - MARBLE_SIZE / 2.0 + center.y;
+    let marble_size = camera.marble_size();
+    let atlas = settings.skin.atlas();
+    for &bg_pos in cells {
+        let corner = camera.hex_to_corner(bg_pos);
 
         let (sx, color) = if spawnpoint == Some(bg_pos) {
             (1, hexcolor(0xff4538_a0))
@@ -171,8 +911,8 @@ pub fn draw_marble_board(
 
         draw_texture_ex(
             assets.textures.marble_atlas,
-            corner_x,
-            corner_y,
+            corner.x,
+            corner.y,
             color,
             DrawTextureParams {
                 source: Some(Rect::new(
@@ -181,18 +921,35 @@ pub fn draw_marble_board(
                     MARBLE_SIZE,
                     MARBLE_SIZE,
                 )),
+                dest_size: Some(vec2(marble_size, marble_size)),
                 ..Default::default()
             },
         );
     }
 
+    for &(from, to) in falls {
+        draw_fall_arrow(camera, from, to, hexcolor(0xdfe0e8_40));
+    }
+
     for (pos, marble) in marbles.iter() {
-        let dark = hexcolor(0x291d2b_ff);
+        let dark = theme::shadow();
         let sigil_color = match next_action {
             Some((BoardAction::ClearBlobs(_), _)) if to_remove.contains(pos) => WHITE,
-            Some((BoardAction::DeleteColor(col), timer)) if col == marble => {
+            _ if preview.contains(pos) => theme::text(),
+            Some((BoardAction::Attract(col, _), _)) if col == marble => theme::text(),
+            Some((BoardAction::Shuffle, _)) if *marble != Marble::Stone => WHITE,
+            Some((BoardAction::ClearRing(dist), timer))
+                if pos.distance(Coordinate::new(0, 0)) == *dist =>
+            {
                 if *timer / CLEAR_ALL_BLINK_SPEED % 2 == 0 {
-                    hexcolor(0xffee83_ff)
+                    theme::text()
+                } else {
+                    WHITE
+                }
+            }
+            Some((BoardAction::DeleteColor(col, _), timer)) if col == marble => {
+                if *timer / CLEAR_ALL_BLINK_SPEED % 2 == 0 {
+                    theme::text()
                 } else {
                     WHITE
                 }
@@ -219,16 +976,59 @@ pub fn draw_marble_board(
                     .unwrap();
                 let next = path[(idx + 1) % path.len()];
 
-                let start = pos_to_marble_corner(*pos, center);
-                let start = [start.0, start.1];
-                let end = pos_to_marble_corner(next, center);
-                let end = [end.0, end.1];
+                let start = camera.hex_to_corner(*pos);
+                let start = [start.x, start.y];
+                let end = camera.hex_to_corner(next);
+                let end = [end.x, end.y];
 
-                let t = *timer as f32 / BoardAction::CYCLE_TIME as f32;
+                let t = *timer as f32 / action_times.cycle as f32;
                 let middle = Interpolator::lerp(t, start, end);
                 (middle[0].round(), middle[1].round())
             }
-            _ => pos_to_marble_corner(*pos, center),
+            Some((BoardAction::DeleteColor(col, hexagon), timer))
+                if settings.animations && col == marble =>
+            {
+                let t = *timer as f32 / action_times.delete_color as f32;
+                let t = (t / DELETE_COLOR_BURST_START).min(1.0);
+                let start = camera.hex_to_corner(*pos);
+                let end = delete_color_target(camera, hexagon) - Vec2::splat(marble_size / 2.0);
+                // Start slow and get sucked in faster and faster
+                let middle = start + (end - start) * t * t;
+                (middle.x.round(), middle.y.round())
+            }
+            _ => camera.hex_to_corner(*pos).into(),
+        };
+
+        // Fade old marbles towards the color of stone
+        let body_color = match petrify_progress.get(pos) {
+            Some(&progress) if progress > PETRIFY_TINT_START => {
+                let t = (progress - PETRIFY_TINT_START) / (1.0 - PETRIFY_TINT_START);
+                let stone = hexcolor(0x8c8a99_ff);
+                Color::new(
+                    1.0 + (stone.r - 1.0) * t,
+                    1.0 + (stone.g - 1.0) * t,
+                    1.0 + (stone.b - 1.0) * t,
+                    1.0,
+                )
+            }
+            _ => WHITE,
+        };
+        // Ring clears fade the ring out as they go
+        let body_color = match next_action {
+            Some((BoardAction::ClearRing(dist), timer))
+                if settings.animations && pos.distance(Coordinate::new(0, 0)) == *dist =>
+            {
+                let t = *timer as f32 / BoardAction::CLEAR_RING_TIME as f32;
+                Color::new(body_color.r, body_color.g, body_color.b, 1.0 - t)
+            }
+            // Color clears fade the marbles out once they've all piled up
+            Some((BoardAction::DeleteColor(col, _), timer))
+                if settings.animations && col == marble =>
+            {
+                let t = delete_color_burst(*timer, action_times);
+                Color::new(body_color.r, body_color.g, body_color.b, 1.0 - t)
+            }
+            _ => body_color,
         };
 
         let sx = marble.clone() as u32 as f32 * MARBLE_SIZE;
@@ -236,64 +1036,209 @@ pub fn draw_marble_board(
             assets.textures.marble_atlas,
             corner_x,
             corner_y,
-            WHITE,
+            body_color,
             DrawTextureParams {
-                source: Some(Rect::new(sx, 8.0, MARBLE_SIZE, MARBLE_SIZE)),
-                ..Default::default()
-            },
-        );
-        draw_texture_ex(
-            assets.textures.marble_atlas,
-            corner_x,
-            corner_y,
-            sigil_color,
-            DrawTextureParams {
-                source: Some(Rect::new(sx, 0.0, MARBLE_SIZE, MARBLE_SIZE)),
+                source: Some(Rect::new(sx, atlas.body_y, MARBLE_SIZE, MARBLE_SIZE)),
+                dest_size: Some(vec2(marble_size, marble_size)),
                 ..Default::default()
             },
         );
+        // Stone doesn't have a color so it always gets its cracks,
+        // and we still want to show what's about to be cleared
+        if !hide_sigils || *marble == Marble::Stone || sigil_color != dark {
+            draw_texture_ex(
+                assets.textures.marble_atlas,
+                corner_x,
+                corner_y,
+                sigil_color,
+                DrawTextureParams {
+                    source: Some(Rect::new(sx, atlas.sigil_y, MARBLE_SIZE, MARBLE_SIZE)),
+                    dest_size: Some(vec2(marble_size, marble_size)),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    if let Some((BoardAction::DeleteColor(col, hexagon), timer)) = next_action {
+        let t = delete_color_burst(*timer, action_times);
+        if settings.animations && t > 0.0 {
+            let center = delete_color_target(camera, hexagon);
+            let color = marble_color(col);
+            draw_circle_lines(
+                center.x,
+                center.y,
+                marble_size * (0.5 + 1.5 * t),
+                1.0,
+                Color::new(color.r, color.g, color.b, 1.0 - t),
+            );
+        }
     }
 
     if let Some((path, terminus)) = path {
-        draw_pattern(path, terminus, center, WHITE, assets);
+        let thickness = if settings.high_vis_cursor {
+            HIGH_VIS_BEAM_WIDTH
+        } else {
+            1.0
+        };
+        draw_pattern(path, terminus, camera, thickness, WHITE, assets);
     }
 }
 
-/// give the corner x/y poses of the marble at the given position
-fn pos_to_marble_corner(pos: Coordinate, center: Vec2) -> (f32, f32) {
-    let (ox, oy) = pos.to_pixel_integer(IntegerSpacing::PointyTop(MARBLE_SPAN_X, MARBLE_SPAN_Y));
-    let corner_x = ox as f32 - MARBLE_SIZE / 2.0 + center.x;
-    let corner_y = oy as f32 - MARBLE_SIZE / 2.0 + center.y;
-    (corner_x, corner_y)
+/// Where the marbles of a color clear get pulled to: the middle of the hexagon
+/// that set it off, or the middle of the board.
+fn delete_color_target(camera: &BoardCamera, hexagon: &[Coordinate]) -> Vec2 {
+    if hexagon.is_empty() {
+        camera.center
+    } else {
+        hexagon
+            .iter()
+            .map(|&pos| camera.hex_to_pixel(pos))
+            .fold(vec2(0.0, 0.0), |acc, px| acc + px)
+            / hexagon.len() as f32
+    }
 }
 
-fn draw_pattern(pat: &[Coordinate], terminus: Vec2, center: Vec2, color: Color, assets: &Assets) {
-    gl_use_material(assets.shaders.pattern_beam);
+/// How far along the burst at the end of a color clear is, from 0 before it starts to 1.
+fn delete_color_burst(timer: u32, action_times: ActionTimes) -> f32 {
+    let t = timer as f32 / action_times.delete_color as f32;
+    ((t - DELETE_COLOR_BURST_START) / (1.0 - DELETE_COLOR_BURST_START)).max(0.0)
+}
 
-    for span in pat.windows(2) {
-        let (x1, y1) = pos_to_marble_corner(span[0], center);
-        let (x2, y2) = pos_to_marble_corner(span[1], center);
-
-        draw_line_but_with_uvs(
-            x1 + MARBLE_SIZE / 2.0,
-            y1 + MARBLE_SIZE / 2.0,
-            x2 + MARBLE_SIZE / 2.0,
-            y2 + MARBLE_SIZE / 2.0,
-            1.0,
+/// Draw the rings of hexagons that spread out behind the board.
+///
+/// They pulse outwards once per whole number of `phase`, with the biggest as big as `max_radius`.
+pub fn draw_background_hexes(center: Vec2, max_radius: f32, phase: f32) {
+    for hex_idx in (0..BG_HEX_COUNT).rev() {
+        let radius = (hex_idx as f32 + phase.fract()) * max_radius / BG_HEX_COUNT as f32;
+        let color = if (phase.trunc() as u32 + hex_idx) % BG_HEX_COUNT % 2 == 0 {
+            theme::bg()
+        } else {
+            theme::panel()
+        };
+
+        draw_hexagon(
+            center.x,
+            center.y,
+            radius,
+            2.0,
+            false,
+            theme::border(),
             color,
         );
     }
+}
 
-    let (x1, y1) = pos_to_marble_corner(*pat.last().unwrap(), center);
-    let (x2, y2) = terminus.into();
-    draw_line_but_with_uvs(
-        x1 + MARBLE_SIZE / 2.0,
-        y1 + MARBLE_SIZE / 2.0,
-        x2,
-        y2,
-        1.0,
-        color,
+/// Draw a board shrunk down to fit in `area`, without any of the HUD.
+///
+/// With `arrows`, empty cells get arrows no matter the settings, so it's clear which way
+/// things fall.
+pub fn draw_board_preview(
+    board: &Board,
+    area: Rect,
+    arrows: bool,
+    settings: PlaySettings,
+    assets: &Assets,
+) {
+    let camera = BoardCamera::fitting(board.radius(), area);
+    let falls = if arrows {
+        board.fall_directions()
+    } else {
+        Vec::new()
+    };
+    let cells = board.cells().iter().copied().collect::<Vec<_>>();
+    let marbles = board
+        .get_marbles()
+        .iter()
+        .map(|(c, m)| (*c, m.clone()))
+        .collect::<Vec<_>>();
+    let next_action = board
+        .next_action()
+        .cloned()
+        .map(|action| (action, board.action_timer()));
+    draw_marble_board(
+        &camera,
+        &cells,
+        &falls,
+        &marbles,
+        &AHashMap::new(),
+        next_action.as_ref(),
+        board.settings().action_times,
+        &[],
+        &[],
+        board.next_spawn_point(),
+        None,
+        settings,
+        false,
+        assets,
     );
+    if let Some(dir) = board.gravity_direction() {
+        draw_gravity_arrow(&camera, board.radius(), dir, hexcolor(0xdfe0e8_a0));
+    }
+}
+
+/// Draw an arrow just outside the board pointing in the given direction
+fn draw_gravity_arrow(camera: &BoardCamera, radius: usize, dir: Direction, color: Color) {
+    let forward = (camera.hex_to_pixel(Coordinate::new(0, 0) + dir) - camera.center).normalize();
+    let side = vec2(-forward.y, forward.x);
+
+    let base =
+        camera.center + forward * ((radius + 1) as f32 * MARBLE_SPAN_X as f32 * camera.zoom);
+    let tip = base + forward * 6.0;
+    draw_triangle(base + side * 5.0, base - side * 5.0, tip, color);
+}
+
+/// Draw an outlined crosshair, for players who have trouble seeing the mouse
+fn draw_crosshair(pos: Vec2) {
+    let outline = theme::bg();
+    let arms = [vec2(1.0, 0.0), vec2(0.0, 1.0)];
+    for &arm in arms.iter() {
+        let (a, b) = (pos - arm * CROSSHAIR_REACH, pos + arm * CROSSHAIR_REACH);
+        draw_line(a.x, a.y, b.x, b.y, 3.0, outline);
+    }
+    draw_circle_lines(pos.x, pos.y, CROSSHAIR_REACH - 2.0, 3.0, outline);
+    for &arm in arms.iter() {
+        let (a, b) = (pos - arm * CROSSHAIR_REACH, pos + arm * CROSSHAIR_REACH);
+        draw_line(a.x, a.y, b.x, b.y, 1.0, WHITE);
+    }
+    draw_circle_lines(pos.x, pos.y, CROSSHAIR_REACH - 2.0, 1.0, WHITE);
+}
+
+/// Draw a little arrow in a cell pointing towards the cell a marble there would fall to
+fn draw_fall_arrow(camera: &BoardCamera, from: Coordinate, to: Coordinate, color: Color) {
+    let start = camera.hex_to_pixel(from);
+    let forward = (camera.hex_to_pixel(to) - start).normalize();
+    let side = vec2(-forward.y, forward.x);
+
+    let size = camera.marble_size() / 4.0;
+    let tip = start + forward * size;
+    let back = start - forward * size;
+    draw_triangle(back + side * size, back - side * size, tip, color);
+}
+
+fn draw_pattern(
+    pat: &[Coordinate],
+    terminus: Vec2,
+    camera: &BoardCamera,
+    thickness: f32,
+    color: Color,
+    assets: &Assets,
+) {
+    if assets.shaders.enabled() {
+        assets.shaders.pattern_beam.set_uniform("Width", thickness);
+        gl_use_material(assets.shaders.pattern_beam);
+    }
+
+    for span in pat.windows(2) {
+        let (x1, y1) = camera.hex_to_pixel(span[0]).into();
+        let (x2, y2) = camera.hex_to_pixel(span[1]).into();
+
+        draw_line_but_with_uvs(x1, y1, x2, y2, thickness, color);
+    }
+
+    let (x1, y1) = camera.hex_to_pixel(*pat.last().unwrap()).into();
+    let (x2, y2) = terminus.into();
+    draw_line_but_with_uvs(x1, y1, x2, y2, thickness, color);
 
     gl_use_default_material();
 }
@@ -320,9 +1265,9 @@ pub fn draw_line_but_with_uvs(x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32
     context.quad_gl.geometry(
         &[
             Vertex::new(x1 + tx, y1 + ty, 0., 0., 0., color),
-            Vertex::new(x1 - tx, y1 - ty, 0., 0., 0., color),
+            Vertex::new(x1 - tx, y1 - ty, 0., 0., 1., color),
             Vertex::new(x2 + tx, y2 + ty, 0., 1., 0., color),
-            Vertex::new(x2 - tx, y2 - ty, 0., 1., 0., color),
+            Vertex::new(x2 - tx, y2 - ty, 0., 1., 1., color),
         ],
         &[0, 1, 2, 2, 1, 3],
     );
@@ -0,0 +1,200 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::{Assets, SfxEvent},
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        puzzle::Puzzle,
+        text::TextAlign,
+        theme,
+    },
+};
+
+const MOVE_LIMITS: [Option<u32>; 6] = [None, Some(3), Some(5), Some(10), Some(15), Some(20)];
+/// In board points, so 100 times smaller than what the HUD shows
+const TARGET_SCORES: [Option<u32>; 6] = [None, Some(5), Some(10), Some(25), Some(50), Some(100)];
+const REQUIRED_CLEARS: [Option<u32>; 6] = [None, Some(8), Some(16), Some(30), Some(50), Some(100)];
+
+/// Set a sandbox board's goals and export it as a puzzle.
+///
+/// Pops with the edited `PuzzleGoals`.
+#[derive(Debug, Clone)]
+pub struct ModeLevelEditor {
+    puzzle: Puzzle,
+    /// How the last export went
+    status: Option<String>,
+
+    b_moves: Button,
+    b_score: Button,
+    b_clears: Button,
+    b_export: Button,
+
+    b_back: Button,
+}
+
+impl ModeLevelEditor {
+    pub fn new(puzzle: Puzzle) -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+        let y_stride = h + m.px(2.0);
+        let y = m.px(5.0);
+
+        Self {
+            puzzle,
+            status: None,
+
+            b_moves: Button::new(x, y, w, h),
+            b_score: Button::new(x, y + y_stride, w, h),
+            b_clears: Button::new(x, y + 2.0 * y_stride, w, h),
+            b_export: Button::new(x, y + 4.0 * y_stride, w, h),
+
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        }
+    }
+
+    /// Every button, what it says, and what it says when hovered
+    fn buttons(&self) -> Vec<(&Button, String, String)> {
+        let goals = self.puzzle.goals;
+        let show = |goal: Option<u32>, scale: u32| match goal {
+            Some(it) => (it * scale).to_string(),
+            None => "ANY".to_owned(),
+        };
+        vec![
+            (
+                &self.b_moves,
+                format!("MOVES {}", show(goals.move_limit, 1)),
+                "HOW MANY PATTERNS\nTHE PLAYER GETS\nTO DRAW.".to_owned(),
+            ),
+            (
+                &self.b_score,
+                format!("SCORE {}", show(goals.target_score, 100)),
+                "THE SCORE THE\nPLAYER HAS TO\nREACH.".to_owned(),
+            ),
+            (
+                &self.b_clears,
+                format!("CLEARS {}", show(goals.required_clears, 1)),
+                "HOW MANY MARBLES\nTHE PLAYER HAS\nTO CLEAR.".to_owned(),
+            ),
+            (
+                &self.b_export,
+                "EXPORT".to_owned(),
+                match &self.status {
+                    Some(status) => status.clone(),
+                    None => "SAVE THE BOARD AND\nGOALS AS A PUZZLE\nCODE TO SHARE.".to_owned(),
+                },
+            ),
+            (&self.b_back, "RETURN".to_owned(), String::new()),
+        ]
+    }
+}
+
+/// The option after `current` in the list, wrapping around
+fn next_option(options: &[Option<u32>], current: Option<u32>) -> Option<u32> {
+    let idx = options.iter().position(|o| *o == current).unwrap_or(0);
+    options[(idx + 1) % options.len()]
+}
+
+impl Gamemode for ModeLevelEditor {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Click) {
+            let goals = &mut self.puzzle.goals;
+            if self.b_moves.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                goals.move_limit = next_option(&MOVE_LIMITS, goals.move_limit);
+            } else if self.b_score.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                goals.target_score = next_option(&TARGET_SCORES, goals.target_score);
+            } else if self.b_clears.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                goals.required_clears = next_option(&REQUIRED_CLEARS, goals.required_clears);
+            } else if self.b_export.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.status = Some(match self.puzzle.export() {
+                    Ok(place) => format!("SAVED TO\n{}", place.to_uppercase()),
+                    Err(oh_no) => {
+                        warn!("Couldn't export puzzle!\n{:?}", oh_no);
+                        "COULDN'T SAVE\nTHE PUZZLE.".to_owned()
+                    }
+                });
+            }
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(self.puzzle.goals));
+        }
+
+        let mut play_enter = false;
+        for b in [
+            &mut self.b_moves,
+            &mut self.b_score,
+            &mut self.b_clears,
+            &mut self.b_export,
+            &mut self.b_back,
+        ] {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeLevelEditor {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let panel = SidePanel::beside(self.b_moves.bounds());
+        panel.draw_line(border);
+
+        for (b, text, desc) in self.buttons() {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                &text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                if b.mouse_hovering() { blight } else { border },
+                assets.textures.fonts.small,
+            );
+            if b.mouse_hovering() {
+                draw_menu_text(
+                    &desc,
+                    panel.text_x(),
+                    m.px(5.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+            }
+        }
+    }
+}
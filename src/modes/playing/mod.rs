@@ -1,53 +1,228 @@
 use ahash::AHashMap;
 use cogs_gamedev::{controls::InputHandler};
-use hex2d::{Angle, Coordinate};
+use hex2d::{Angle, Coordinate, Direction};
 use itertools::Itertools;
-use macroquad::{
-    audio::{play_sound, stop_sound, PlaySoundParams, Sound},
-    prelude::{vec2, Mat2},
-};
+use macroquad::prelude::{vec2, Vec2};
 use quad_rand::compat::QuadRand;
 use rand::Rng;
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, SfxEvent},
+    audio::{set_music_volume, set_sfx_volume, AudioBackend, PlayParams, Sound},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
-    model::{Board, BoardAction, BoardSettings, Marble, PlaySettings},
+    model::{
+        Board, BoardAction, BoardHistory, BoardSettings, Cyclable, GravityMode, Marble, Mutator,
+        Ranking, ScoreEvent, SnapRadius, SpawnPolicy, TICKS_PER_SECOND,
+    },
+    utils::{
+        button::Button,
+        coord_overlay::coord_overlay,
+        crash,
+        music::{BeatPulse, MusicClock, GAME_BPMS, TITLE_BPM},
+        profile::{ClearStats, PlaySettings, Profile, RunCurve},
+        score_counter::ScoreCounter,
+    },
     utils::draw::mouse_position_pixel,
-    HEIGHT, WIDTH,
+    height, width,
 };
 
-use self::{denoument::ModeLosingTransition, draw::Drawer};
+use super::{quick_settings, Setting};
+
+pub use self::{
+    draw::{draw_background_hexes, draw_board_preview},
+    ready::start_game,
+    sandbox::ModeSandbox,
+    tips::Tip,
+    versus::CpuOpponent,
+};
 
+use self::{
+    camera::BoardCamera,
+    coop::Partner,
+    denoument::{GameOverReason, ModeLosingTransition},
+    draw::{ChargeInfo, Drawer, OpponentInfo, PowerUpInfo, RewindInfo, RunTimer},
+    postmortem::{POSTMORTEM_INTERVAL, POSTMORTEM_LENGTH},
+    powerups::{ChargeMeter, Inventory, PowerUp},
+    tips::Tips,
+};
+
+mod camera;
+mod coop;
 mod denoument;
 mod draw;
+mod level_editor;
+mod postmortem;
+mod powerups;
+mod ready;
+mod sandbox;
+mod tips;
+mod versus;
 
-const BOARD_CENTER_X: f32 = WIDTH / 2.0;
-const BOARD_CENTER_Y: f32 = HEIGHT / 2.0;
+/// Where the middle of the board goes on the canvas
+fn board_center() -> Vec2 {
+    vec2(width() / 2.0, height() / 2.0)
+}
 
 /// Diameter of the marble itself
-const MARBLE_SIZE: f32 = 8.0;
+pub const MARBLE_SIZE: f32 = 8.0;
 /// Horizontal distance between marbles
 const MARBLE_SPAN_X: i32 = 10;
 /// Vertical distance between marbles
 const MARBLE_SPAN_Y: i32 = 8;
 
+/// How many ticks before the gravity rotates to start warning the player
+const GRAVITY_WARNING_TIME: u32 = 120;
+
+/// How many beats apart the background pulses normally are
+const BG_CALM_PULSE_BEATS: f64 = 2.0;
+/// How many beats apart the background pulses are when the spawnpoint is close to the center
+const BG_DANGER_PULSE_BEATS: f64 = 1.0;
+/// How close to the center the spawnpoint needs to be to speed up the pulses, from 0 to 1
+const BG_DANGER_THRESHOLD: f32 = 0.6;
+
+/// How many ticks the HUD score takes to count up to the real score
+const SCORE_COUNT_TIME: u32 = TICKS_PER_SECOND / 2;
+
+/// How many ticks a clear's points float above it
+const SCORE_POPUP_TIME: u32 = 40;
+/// How many ticks recent gains stay on the ticker above the score
+const SCORE_TICKER_TIME: u32 = TICKS_PER_SECOND * 3;
+/// The most gains the ticker shows at once
+const SCORE_TICKER_LENGTH: usize = 4;
+
+/// How many patterns to draw before hearing about hexagons
+const HEXAGON_TIP_MOVES: u32 = 5;
+
+/// How many ticks the camera takes to zoom out a ring when the board grows
+const BOARD_GROW_TIME: u32 = 30;
+
+/// How many ticks the ghost of the last pattern takes to fade out
+const GHOST_TIME: u32 = 40;
+/// How many ticks the player has to click again to set off a hexagon, with confirmation on
+const HEXAGON_CONFIRM_TIME: u32 = TICKS_PER_SECOND;
+/// How far apart to check along the mouse's path for cells a pattern passed over, in pixels
+const MOUSE_PATH_STEP: f32 = 2.0;
+
+/// In practice mode, save a snapshot of the run this often in ticks
+const HISTORY_INTERVAL: u32 = 6;
+/// How many snapshots to keep, for about 10 seconds of history
+const HISTORY_LENGTH: usize = (TICKS_PER_SECOND * 10 / HISTORY_INTERVAL) as usize;
+
+/// Scores (in board points, not displayed points) to record speedrun splits at
+const SPLIT_MILESTONES: [u32; SPLIT_COUNT] = [10, 50, 100];
+pub const SPLIT_COUNT: usize = 3;
+
+/// How often to check whether the player is out of useful moves, in ticks
+const STUCK_CHECK_INTERVAL: u32 = TICKS_PER_SECOND;
+
+/// How often to write the board down again for crash reports, in updates
+const CRASH_DUMP_INTERVAL: u32 = TICKS_PER_SECOND;
+
+/// How loud the music is while paused
+const PAUSED_MUSIC_VOLUME: f32 = 0.3;
+/// Color tones are quiet so they don't drown out everything else
+const COLOR_TONE_VOLUME: f32 = 0.4;
+
+/// Everything a practice rewind puts back the way it was,
+/// so the HUD and the stats saved at the end agree with the board.
+#[derive(Clone)]
+pub struct RewindSnapshot {
+    board: Board,
+    moves_made: u32,
+    score_counter: ScoreCounter,
+    splits: [Option<u32>; SPLIT_COUNT],
+    run_clears: ClearStats,
+    run_curve: RunCurve,
+}
+
 pub struct ModePlaying {
     pub board: Board,
+    /// The board once everything queued up plays out, which patterns get drawn against.
+    /// This is worked out once an update and kept in step with anything queued after that.
+    pub future: Board,
     pub pattern: Option<Vec<Coordinate>>,
 
-    pub bg_funni_timer: f32,
+    /// Where we are in the music, so the background can pulse in time
+    pub music_clock: MusicClock,
+    /// Background hexagon pulses
+    pub bg_pulse: BeatPulse,
 
     /// Did we start the music yet?
     pub played_music: bool,
+    /// Counts down to writing the board down again for crash reports
+    pub crash_dump_timer: u32,
     pub music: Sound,
 
     pub paused: bool,
+    /// Settings that can be changed on the pause menu, like the music volume
+    pub b_quick_settings: Vec<(Button, &'static Setting)>,
 
     pub settings: PlaySettings,
 
-    pub start_time: f64,
+    /// The score shown on the HUD, which counts up to the real one
+    pub score_counter: ScoreCounter,
+    /// Points scored lately, newest last, and how many ticks ago
+    pub recent_scores: Vec<(ScoreEvent, u32)>,
+
+    /// How many patterns the player has submitted
+    pub moves_made: u32,
+    /// The last pattern submitted and how many ticks it has left to fade out
+    pub ghost: Option<(Vec<Coordinate>, u32)>,
+    /// A hexagon waiting for the player to click on it again, what it's going to do,
+    /// and how many ticks they have left
+    pub unconfirmed_hexagon: Option<(Vec<Coordinate>, BoardAction, u32)>,
+    /// If a marble is waiting for the player to pick where it spawns, how many ticks they have left
+    pub placing: Option<u32>,
+
+    /// The tick each split milestone was reached on
+    pub splits: [Option<u32>; SPLIT_COUNT],
+    /// Best times for each split, to compare against
+    pub best_splits: [Option<u32>; SPLIT_COUNT],
+
+    /// What's been cleared this run, to save to the stats at the end
+    pub run_clears: ClearStats,
+    /// How the score has gone this run, to save to the history at the end
+    pub run_curve: RunCurve,
+    /// Hints on how to play, if this mode shows them
+    pub tips: Option<Tips>,
+    /// How many frames have been played, not counting pauses.
+    /// This slows down the board and times the run, so it goes at `TICKS_PER_SECOND`.
+    pub frame_count: u32,
+    /// How far the player has turned their view of the board
+    pub view_rotation: Angle,
+    /// The radius the camera is framing, which catches up to the board's when it grows
+    pub shown_radius: f32,
+    /// Where the mouse was last frame, to fill in cells a fast drag skipped over
+    pub last_mouse: Option<Vec2>,
+
+    /// In practice mode, recent moments in the run to rewind to
+    pub history: Option<BoardHistory<RewindSnapshot>>,
+    /// The last few seconds of boards, to work out what went wrong if the run ends
+    pub recent_boards: BoardHistory,
+    /// Are we rewinding right now?
+    pub rewinding: bool,
+    /// Hold to rewind, for touch screens
+    pub b_rewind: Button,
+
+    /// In versus runs, the CPU and its board
+    pub opponent: Option<CpuOpponent>,
+    /// In co-op runs, the second player drawing with the keyboard
+    pub partner: Option<Partner>,
+
+    /// Power-ups earned and not spent yet, if the run has them
+    pub inventory: Option<Inventory>,
+    /// Click to arm each power-up
+    pub b_powerups: Vec<(Button, PowerUp)>,
+    /// Fills up to use the special action, if the run has one
+    pub charge: Option<ChargeMeter>,
+    /// Click to use the special action when it's charged
+    pub b_charge: Button,
+
+    /// Is the board getting full with no loop that clears anything?
+    pub stuck: bool,
+    /// Click for a free shuffle when stuck
+    pub b_shuffle: Button,
 }
 
 impl Gamemode for ModePlaying {
@@ -57,26 +232,56 @@ impl Gamemode for ModePlaying {
         _frame_info: FrameInfo,
         assets: &Assets,
     ) -> Transition {
+        // Drawing the board out is too slow to do every update just in case,
+        // so the one in a crash report can be up to a second old
+        if self.crash_dump_timer == 0 {
+            crash::set_board(self.board.ascii_dump());
+            self.crash_dump_timer = CRASH_DUMP_INTERVAL;
+        }
+        self.crash_dump_timer -= 1;
+
         if !self.played_music {
             self.played_music = true;
-            play_sound(
+            assets.audio.play(
                 self.music,
-                PlaySoundParams {
+                PlayParams {
                     looped: true,
                     volume: 0.5,
+                    ..Default::default()
                 },
             );
-            self.start_time = macroquad::time::get_time();
+            self.music_clock = MusicClock::start(self.music_clock.bpm());
         }
 
         if self.paused {
             let (mx, my) = mouse_position_pixel();
-            let unpause = controls.clicked_down(Control::Pause)
-                || controls.clicked_down(Control::Click)
-                    && (0.0..=WIDTH).contains(&mx)
-                    && (0.0..=HEIGHT).contains(&my);
-            if unpause {
-                self.paused = false;
+            let quick_setting = self
+                .b_quick_settings
+                .iter()
+                .find(|(b, _)| b.mouse_hovering())
+                .map(|(_, setting)| *setting);
+            if let (Some(setting), true) = (quick_setting, controls.clicked_down(Control::Click)) {
+                self.change_quick_setting(setting, assets);
+            } else {
+                let unpause = controls.clicked_down(Control::Pause)
+                    || controls.clicked_down(Control::Click)
+                        && (0.0..=width()).contains(&mx)
+                        && (0.0..=height()).contains(&my);
+                if unpause {
+                    self.paused = false;
+                    assets.audio.duck_music(1.0);
+                }
+            }
+
+            let mut play_enter = false;
+            for (b, _) in self.b_quick_settings.iter_mut() {
+                if b.mouse_entered() {
+                    play_enter = true;
+                }
+                b.post_update();
+            }
+            if play_enter {
+                assets.play_sfx(SfxEvent::ButtonHover);
             }
 
             Transition::None
@@ -92,6 +297,12 @@ impl Gamemode for ModePlaying {
             .iter()
             .map(|(c, m)| (*c, m.clone()))
             .collect();
+        let petrify_progress = self
+            .board
+            .get_marbles()
+            .keys()
+            .filter_map(|c| Some((*c, self.board.petrify_progress(c)?)))
+            .collect();
         let next_action = self.board.next_action().cloned();
         let to_remove = if let Some(BoardAction::ClearBlobs(_)) = &next_action {
             self.board.find_blobs().into_iter().flatten().collect()
@@ -99,28 +310,158 @@ impl Gamemode for ModePlaying {
             Vec::new()
         };
         let next_action = next_action.map(|action| (action, self.board.action_timer()));
+        let preview = if self.settings.cascade_preview {
+            self.cascade_preview()
+        } else {
+            Vec::new()
+        };
 
-        let mut scores = next_action
-            .as_ref()
-            .and_then(|(action, _)| {
-                self.board
-                    .get_score_from_action(action)
-                    .map(|score| vec![score])
-            })
-            .unwrap_or_default();
-        scores.extend(self.board.score_queue().iter().copied());
+        let gravity_warning = self
+            .board
+            .gravity_direction()
+            .zip(self.board.ticks_until_gravity_rotation())
+            .and_then(|(dir, ticks)| {
+                if ticks <= GRAVITY_WARNING_TIME {
+                    Some((dir + Angle::Right, ticks))
+                } else {
+                    None
+                }
+            });
 
         Box::new(Drawer {
             marbles,
             pattern: self.pattern.clone(),
             next_spawn_point: self.board.next_spawn_point(),
             radius: self.board.radius(),
+            shown_radius: self.shown_radius,
+            cells: self.board.cells().iter().copied().collect(),
+            falls: if self.settings.gravity_arrows {
+                self.board.fall_directions()
+            } else {
+                Vec::new()
+            },
+            petrify_progress,
+            edge_pressure: self.board.edge_pressure().collect(),
             next_action,
+            action_times: self.board.settings().action_times,
             to_remove,
-            bg_funni_timer: self.bg_funni_timer,
-            score: self.board.score(),
-            score_queue: scores,
+            preview,
+            ghost: self
+                .ghost
+                .as_ref()
+                .map(|(pat, timer)| (pat.clone(), *timer as f32 / GHOST_TIME as f32)),
+            unconfirmed_hexagon: self
+                .unconfirmed_hexagon
+                .as_ref()
+                .map(|(pat, _, timer)| (pat.clone(), *timer as f32 / HEXAGON_CONFIRM_TIME as f32)),
+            placing: self.placing.map(|timer| {
+                let cells = self
+                    .board
+                    .cells()
+                    .iter()
+                    .filter(|c| self.board.can_place_spawn(c))
+                    .copied()
+                    .collect();
+                (cells, timer)
+            }),
+            bg_phase: self.bg_pulse.phase(self.music_clock.beats()) as f32,
+            score: self.score_counter.shown(),
+            score_flash: self.score_counter.flash(),
+            recent_scores: self.recent_scores.clone(),
+            active_multiplier: self.board.active_multiplier(),
+            moves_left: self.moves_left(),
+            rewind: self.history.as_ref().map(|history| RewindInfo {
+                fill: history.len() as f32 / history.capacity() as f32,
+                rewinding: self.rewinding,
+                button: self.b_rewind.clone(),
+            }),
+            tip: self
+                .tips
+                .as_ref()
+                .and_then(|tips| tips.current(self.board.settings())),
+            survival_time: match self.board.settings().ranking() {
+                Ranking::SurvivalTime => Some(self.board.tick_count()),
+                Ranking::Score => None,
+            },
+            run_timer: if self.settings.speedrun_timer {
+                Some(RunTimer {
+                    ticks: self.board.tick_count(),
+                    splits: SPLIT_MILESTONES
+                        .iter()
+                        .zip(self.splits.iter().zip(self.best_splits.iter()))
+                        .map(|(milestone, (split, best))| (*milestone, *split, *best))
+                        .collect(),
+                })
+            } else {
+                None
+            },
+            hide_sigils: self
+                .board
+                .settings()
+                .mutators
+                .contains(Mutator::InvisibleSigils),
+            gravity_warning,
+            view_rotation: self.view_rotation,
+            hovered: self.mouse_to_hex(),
+            spawn_tally: if coord_overlay() {
+                self.board
+                    .spawn_tally()
+                    .iter()
+                    .map(|(pos, count)| (*pos, *count))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            opponent: self.opponent.as_ref().map(|opponent| OpponentInfo {
+                marbles: opponent.board.get_marbles().clone(),
+                cells: opponent.board.cells().iter().copied().collect(),
+                incoming: self.board.pending_garbage(),
+                outgoing: opponent.board.pending_garbage(),
+            }),
+            partner: self
+                .partner
+                .as_ref()
+                .map(|partner| (partner.cursor, partner.pattern.clone())),
+            powerups: match &self.inventory {
+                Some(inventory) => self
+                    .b_powerups
+                    .iter()
+                    .map(|(button, powerup)| PowerUpInfo {
+                        button: button.clone(),
+                        name: powerup.name(),
+                        icon: powerup.icon(),
+                        held: inventory.held(*powerup),
+                        armed: inventory.armed() == Some(*powerup),
+                        progress: inventory.progress(),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            },
+            charge: self.charge.as_ref().map(|charge| ChargeInfo {
+                button: self.b_charge.clone(),
+                name: charge.special.name(),
+                fill: charge.fill(),
+                full: charge.is_full(),
+            }),
+            slowed: self.board.slow_ticks() > 0,
+            shuffle_offer: if self.stuck {
+                Some(self.b_shuffle.clone())
+            } else {
+                None
+            },
+            upcoming: match self.board.settings().spawn_policy {
+                SpawnPolicy::Bag => self.board.upcoming().cloned().collect(),
+                SpawnPolicy::Random | SpawnPolicy::Weighted { .. } => Vec::new(),
+            },
             paused: self.paused,
+            quick_settings: if self.paused {
+                self.b_quick_settings
+                    .iter()
+                    .map(|(b, setting)| (b.clone(), setting.button_text(&self.settings)))
+                    .collect()
+            } else {
+                Vec::new()
+            },
             settings: self.settings,
         })
     }
@@ -132,134 +473,501 @@ impl ModePlaying {
         play_settings: PlaySettings,
         assets: &Assets,
     ) -> Self {
+        let board_settings = board_settings
+            .with_game_speed(play_settings.game_speed)
+            .with_fair_spawns(play_settings.fair_spawns)
+            .with_game_feel(play_settings.game_feel);
+        // The game music loads in the background, so pick from whatever's in so far
         let tracks = [
-            assets.sounds.music0,
-            assets.sounds.music1,
-            assets.sounds.music2,
-        ];
-        let music = tracks[QuadRand.gen_range(0..tracks.len())];
+            &assets.sounds.music0,
+            &assets.sounds.music1,
+            &assets.sounds.music2,
+        ]
+        .iter()
+        .zip(GAME_BPMS.iter())
+        .filter_map(|(track, bpm)| track.get().map(|track| (track, *bpm)))
+        .collect::<Vec<_>>();
+        let (music, bpm) = if tracks.is_empty() {
+            (assets.sounds.title_music, TITLE_BPM)
+        } else {
+            tracks[QuadRand.gen_range(0..tracks.len())]
+        };
+        // Loading the profile isn't free, so get everything out of it at once
+        let (best_splits, seen_tips) = {
+            let profile = Profile::get();
+            let best_splits = board_settings
+                .leaderboard_key()
+                .and_then(|key| profile.best_splits.get(&key).copied())
+                .unwrap_or_default();
+            (best_splits, profile.seen_tips.clone())
+        };
+        let history = if board_settings.practice {
+            Some(BoardHistory::new(HISTORY_LENGTH))
+        } else {
+            None
+        };
+        let board = Board::new(board_settings.clone());
+        let partner = if board_settings.coop {
+            Some(Partner::new(&board))
+        } else {
+            None
+        };
+        let opponent = board_settings
+            .versus
+            .map(|difficulty| CpuOpponent::new(board_settings.clone(), difficulty));
+        let inventory = if board_settings.mutators.contains(Mutator::PowerUps) {
+            Some(Inventory::new())
+        } else {
+            None
+        };
+        let b_powerups = PowerUp::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, powerup)| {
+                let y = height() - 25.0 - 13.0 * idx as f32;
+                (Button::new(3.0, y, 4.0 * 14.0, 10.0), *powerup)
+            })
+            .collect();
+        let charge = board_settings.special.map(ChargeMeter::new);
+        let mut tips = if board_settings.show_tips {
+            Some(Tips::new(Vec::new()))
+        } else if play_settings.tips {
+            Some(Tips::new(seen_tips))
+        } else {
+            None
+        };
+        if let Some(tips) = &mut tips {
+            tips.trigger(Tip::DrawLoop);
+        }
+        // Two columns of them under the pause text
+        let b_quick_settings = quick_settings()
+            .enumerate()
+            .map(|(idx, setting)| {
+                let x = width() / 2.0 + if idx % 2 == 0 { -58.0 } else { 2.0 };
+                let y = height() / 2.0 + 12.0 + 13.0 * (idx / 2) as f32;
+                (Button::new(x, y, 4.0 * 14.0, 10.0), setting)
+            })
+            .collect();
         Self {
-            board: Board::new(board_settings),
+            future: board.simulate_queue(),
+            board,
             pattern: None,
-            bg_funni_timer: 0.0,
+            music_clock: MusicClock::start(bpm),
+            bg_pulse: BeatPulse::new(BG_CALM_PULSE_BEATS),
             played_music: false,
+            crash_dump_timer: 0,
             music,
             paused: false,
+            b_quick_settings,
             settings: play_settings,
-            start_time: 0.0,
+            score_counter: ScoreCounter::new(0, SCORE_COUNT_TIME),
+            recent_scores: Vec::new(),
+            moves_made: 0,
+            ghost: None,
+            unconfirmed_hexagon: None,
+            placing: None,
+            splits: [None; SPLIT_COUNT],
+            best_splits,
+            run_clears: ClearStats::default(),
+            run_curve: RunCurve::default(),
+            tips,
+            frame_count: 0,
+            view_rotation: Angle::Forward,
+            shown_radius: board_settings.radius as f32,
+            last_mouse: None,
+            history,
+            recent_boards: BoardHistory::new(POSTMORTEM_LENGTH),
+            rewinding: false,
+            b_rewind: Button::new(3.0, height() - 12.0, 4.0 * 7.0, 9.0),
+            opponent,
+            partner,
+            inventory,
+            b_powerups,
+            charge,
+            b_charge: Button::new(3.0, height() - 51.0, 4.0 * 14.0, 10.0),
+            stuck: false,
+            b_shuffle: Button::new(width() / 2.0 - 32.0, height() - 29.0, 64.0, 10.0),
         }
     }
 
+    /// Change one of the settings on the pause menu, and save it right away
+    /// so it sticks even if the game gets closed before the run ends.
+    fn change_quick_setting(&mut self, setting: &Setting, assets: &Assets) {
+        setting.advance(&mut self.settings);
+        let settings = self.settings;
+        Profile::modify(|profile| profile.settings = settings);
+
+        set_music_volume(settings.music_volume);
+        set_sfx_volume(settings.sfx_volume);
+        // Turn the playing music to match, still ducked for the pause
+        assets.audio.duck_music(PAUSED_MUSIC_VOLUME);
+        // After changing the volume, so the click is as loud as everything else will be
+        assets.play_sfx(SfxEvent::ButtonClick);
+    }
+
     /// The actual update code when not paused
     fn actually_update(&mut self, controls: &InputSubscriber, assets: &Assets) -> Transition {
         let (mx, my) = mouse_position_pixel();
         let pause = controls.clicked_down(Control::Pause)
-            || (controls.clicked_down(Control::Click) && !(0.0..=WIDTH).contains(&mx)
-                || !(0.0..=HEIGHT).contains(&my));
-        if pause {
+            || (controls.clicked_down(Control::Click) && !(0.0..=width()).contains(&mx)
+                || !(0.0..=height()).contains(&my));
+        let idle = match self.settings.auto_pause {
+            // Don't pause out from under someone holding a pattern still
+            Some(secs) => self.pattern.is_none() && controls.idle_time() > secs as f64,
+            None => false,
+        };
+        if pause || idle {
             self.paused = true;
+            assets.audio.duck_music(PAUSED_MUSIC_VOLUME);
             return Transition::None;
         }
 
+        if self.history.is_some() {
+            let holding_rewind = controls.pressed(Control::Rewind)
+                || (controls.pressed(Control::Click) && self.b_rewind.mouse_hovering());
+            self.b_rewind.post_update();
+            if holding_rewind {
+                self.pattern = None;
+                self.ghost = None;
+                self.unconfirmed_hexagon = None;
+                self.placing = None;
+                if let Some(snapshot) = self.history.as_mut().and_then(BoardHistory::pop) {
+                    self.rewind_to(snapshot);
+                }
+                self.rewinding = true;
+                return Transition::None;
+            }
+            self.rewinding = false;
+        }
+
+        let turn = if controls.clicked_down(Control::RotateLeft) {
+            Some(Angle::Left)
+        } else if controls.clicked_down(Control::RotateRight) {
+            Some(Angle::Right)
+        } else {
+            None
+        };
+        if let Some(turn) = turn {
+            self.view_rotation =
+                Angle::from_int(self.view_rotation.to_int::<i32>() + turn.to_int::<i32>());
+        }
+
+        if let Some((_, timer)) = &mut self.ghost {
+            *timer = timer.saturating_sub(1);
+            if *timer == 0 {
+                self.ghost = None;
+            }
+        }
+
+        let out_of_moves = self.moves_left() == Some(0);
+
+        // A hexagon waiting on confirmation goes off with a click inside it, or fizzles out.
+        // It does exactly what it showed, even if the board has moved on since.
+        let mut confirmed_hexagon = false;
+        if let Some((pat, action, timer)) = self.unconfirmed_hexagon.take() {
+            if controls.clicked_down(Control::Click) {
+                // Clicking anywhere else lets it go
+                if hexagon_contains(&pat, self.mouse_to_hex()) {
+                    self.submit_action(pat, action);
+                    confirmed_hexagon = true;
+                }
+            } else if timer > 1 {
+                self.unconfirmed_hexagon = Some((pat, action, timer - 1));
+            }
+        }
+
+        let dismissed_tip = controls.clicked_down(Control::Click)
+            && Tip::toast_bounds().contains(vec2(mx, my))
+            && self.tips.as_mut().map_or(false, |tips| tips.dismiss());
+
+        let mut clicked_hud = false;
+        if let Some(inventory) = &mut self.inventory {
+            for (button, powerup) in self.b_powerups.iter_mut() {
+                let clicked = button.mouse_hovering() && controls.clicked_down(Control::Click);
+                clicked_hud |= clicked;
+                if (clicked || controls.clicked_down(powerup.control()))
+                    && inventory.toggle(*powerup)
+                {
+                    assets.play_sfx(SfxEvent::ButtonClick);
+                }
+                button.post_update();
+            }
+        }
+        if let Some(charge) = &mut self.charge {
+            let clicked = self.b_charge.mouse_hovering() && controls.clicked_down(Control::Click);
+            clicked_hud |= clicked;
+            if clicked || controls.clicked_down(Control::Special) {
+                if let Some(action) = charge.spend(&self.board) {
+                    assets.play_sfx(SfxEvent::ButtonClick);
+                    self.board.push_action(action);
+                    let premult = self.board.settings().scoring.player_start_multiplier;
+                    self.board.push_action(BoardAction::ClearBlobs(premult));
+                }
+            }
+            self.b_charge.post_update();
+        }
+        if self.stuck {
+            if self.b_shuffle.mouse_hovering() && controls.clicked_down(Control::Click) {
+                clicked_hud = true;
+                self.stuck = false;
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.board.push_action(BoardAction::Shuffle);
+                let premult = self.board.settings().scoring.player_start_multiplier;
+                self.board.push_action(BoardAction::ClearBlobs(premult));
+            }
+            self.b_shuffle.post_update();
+        }
+
+        // Patterns drawn while the last one is still playing out get queued up after it,
+        // so check them against where the marbles are going to be
+        self.future = self.board.simulate_queue();
+        let marbles = self.future.get_marbles();
+        let camera = self.camera();
+        let pos = self.mouse_to_hex();
+
+        // A held marble spawns wherever the player clicks,
+        // or where it was going anyways if they run out of time
+        let mut placed_spawn = false;
+        if let Some(timer) = &mut self.placing {
+            if controls.clicked_down(Control::Click) && self.board.can_place_spawn(&pos) {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.board.release_spawn(Some(pos));
+                self.placing = None;
+                placed_spawn = true;
+            } else {
+                *timer = timer.saturating_sub(1);
+                if *timer == 0 {
+                    self.board.release_spawn(None);
+                    self.placing = None;
+                }
+            }
+        }
+
+        // Normally the pattern follows the mouse while the button's held and finishes
+        // when it's let go. With sticky dragging it follows the mouse until the next click.
+        let drawing = if self.settings.sticky_drag {
+            !controls.clicked_down(Control::Click)
+        } else {
+            controls.pressed(Control::Click)
+        };
         match &mut self.pattern {
+            _ if out_of_moves => {
+                self.pattern = None;
+            }
+            None if dismissed_tip || clicked_hud || confirmed_hexagon || placed_spawn => {}
             None if controls.clicked_down(Control::Click) => {
-                let pos = mouse_to_hex();
                 if self.board.is_in_bounds(&pos) {
+                    if let (true, Some(marble)) = (self.settings.color_tones, marbles.get(&pos)) {
+                        play_color_tone(marble, assets);
+                    }
                     self.pattern = Some(vec![pos])
                 }
             }
-            Some(pat) if controls.pressed(Control::Click) => {
-                let pos = mouse_to_hex();
-                if self.board.is_in_bounds(&pos) {
-                    let mut maybe_pat = pat.clone();
-                    if matches!(
-                        is_pattern_valid(&maybe_pat, self.board.get_marbles()),
-                        PatternExtensionValidity::Continue
-                    ) {
-                        // Only look at this next possibility if we can actually extend it.
-                        maybe_pat.push(pos);
-                        match is_pattern_valid(&maybe_pat, self.board.get_marbles()) {
-                            validity
-                            @
-                            (PatternExtensionValidity::Continue
-                            | PatternExtensionValidity::Finished) => {
-                                *pat = maybe_pat;
-                                let sound =
-                                    if matches!(validity, PatternExtensionValidity::Continue) {
-                                        assets.sounds.select
-                                    } else {
-                                        assets.sounds.close_loop
-                                    };
-                                play_sound(
-                                    sound,
-                                    PlaySoundParams {
-                                        looped: false,
-                                        volume: 1.0,
-                                    },
-                                );
-                            }
-                            PatternExtensionValidity::Invalid => {}
-                        }
+            Some(pat) if drawing => {
+                // Walk from where the mouse was to where it is,
+                // so a fast drag doesn't skip over cells
+                let mouse = vec2(mx, my);
+                let from = self.last_mouse.unwrap_or(mouse);
+                let steps = ((mouse - from).length() / MOUSE_PATH_STEP).ceil().max(1.0) as usize;
+                for step in 1..=steps {
+                    let px = from.lerp(mouse, step as f32 / steps as f32);
+                    let next = match pattern_step(pat, px, &camera, self.settings.snap, marbles) {
+                        Some(it) if self.board.is_in_bounds(&it) => it,
+                        _ => continue,
+                    };
+                    pat.push(next);
+                    assets.play_sfx_at(
+                        if matches!(
+                            is_pattern_valid(pat, marbles),
+                            PatternExtensionValidity::Continue
+                        ) {
+                            SfxEvent::PatternExtend
+                        } else {
+                            SfxEvent::PatternClose
+                        },
+                        px.x,
+                    );
+                    if let (true, Some(marble)) = (self.settings.color_tones, marbles.get(&next)) {
+                        play_color_tone(marble, assets);
                     }
                 }
             }
-            // mouse up but with pattern
+            // done drawing but with pattern
             Some(pat) => {
                 if matches!(
-                    is_pattern_valid(pat, self.board.get_marbles()),
+                    is_pattern_valid(pat, marbles),
                     PatternExtensionValidity::Finished
                 ) {
                     let pat = std::mem::take(pat);
-                    let action = self.pattern_to_action(pat);
-
-                    self.board.push_action(action);
-                    // We start with an add'l multiplier of 0
-                    self.board.push_action(BoardAction::ClearBlobs(0));
+                    // An armed power-up changes what the pattern does,
+                    // so only plain hexagons wait to be confirmed
+                    let armed = self.inventory.as_ref().and_then(Inventory::armed).is_some();
+                    let action = self.pattern_to_action(pat.clone(), &self.future);
+                    if self.settings.confirm_hexagons
+                        && matches!(action, BoardAction::DeleteColor(..))
+                        && !armed
+                    {
+                        self.unconfirmed_hexagon = Some((pat, action, HEXAGON_CONFIRM_TIME));
+                    } else {
+                        self.submit_pattern(pat);
+                    }
                 }
                 // if we're not pressing gotta clear it
                 self.pattern = None;
             }
             None => {}
         }
+        self.last_mouse = Some(vec2(mx, my));
 
-        if let Some(next_action) = self.board.next_action() {
+        if let Some(partner) = &mut self.partner {
+            let finished = if out_of_moves {
+                partner.pattern = None;
+                None
+            } else {
+                partner.update(controls, &self.future, &camera)
+            };
+            if let Some(pat) = finished {
+                self.submit_pattern(pat);
+            }
+        }
+
+        let ticking = self.board.settings().game_speed.ticks_on(self.frame_count);
+        self.frame_count = self.frame_count.wrapping_add(1);
+        let next_action = self.board.next_action().filter(|_| ticking);
+        if let Some(next_action) = next_action {
             let timer = self.board.action_timer();
-            let finish_time = next_action.time();
-            let sound = match next_action {
-                BoardAction::Cycle(_) if timer == 0 => Some((assets.sounds.shunt, 1.0)),
-                BoardAction::DeleteColor(_) if timer == 0 => Some((assets.sounds.clear_all, 1.0)),
-                BoardAction::ClearBlobs(_) if timer == finish_time - 1 => {
-                    if let Some(score) = self.board.get_score_from_action(next_action) {
-                        let mult = score.multiplier;
-                        let sound = match mult {
-                            1 => assets.sounds.clear1,
-                            2 => assets.sounds.clear2,
-                            3 => assets.sounds.clear3,
-                            4 => assets.sounds.clear4,
-                            _ => assets.sounds.clear5,
-                        };
-                        Some((sound, 1.0))
-                    } else {
-                        None
-                    }
+            let finish_time = next_action.time(&self.board.settings().action_times);
+            let event = match next_action {
+                // Loops that are about to fizzle make their own noise once they do
+                BoardAction::Cycle(path) if timer == 0 && self.board.cycle_fizzles(path) => None,
+                BoardAction::Cycle(_) | BoardAction::Attract(..) | BoardAction::Shuffle
+                    if timer == 0 =>
+                {
+                    Some(SfxEvent::Cycle)
+                }
+                BoardAction::DeleteColor(..) | BoardAction::ClearRing(_) if timer == 0 => {
+                    Some(SfxEvent::DeleteColor)
                 }
+                BoardAction::ClearBlobs(_) if timer == finish_time - 1 => self
+                    .board
+                    .get_score_from_action(next_action)
+                    .map(|score| SfxEvent::Clear(score.multiplier)),
                 _ => None,
             };
-            if let Some((sound, volume)) = sound {
-                play_sound(
-                    sound,
-                    PlaySoundParams {
-                        looped: false,
-                        volume,
-                    },
-                );
+            if let Some(event) = event {
+                assets.play_sfx(event);
             }
         }
 
-        let failure = self.board.tick();
+        if self.board.needs_placement() {
+            self.board.hold_spawn();
+        }
+        let ticks_before = self.board.tick_count();
+        let (failure, clear_events) = if ticking {
+            (self.board.tick(), self.board.take_clear_events())
+        } else {
+            (false, Vec::new())
+        };
+        // Slowed down boards skip some of their ticks, so go by whether the clock moved
+        let ticked = self.board.tick_count() != ticks_before;
+        // Zoom out to fit the board once it grows
+        let radius = self.board.radius() as f32;
+        if self.shown_radius < radius {
+            self.shown_radius = (self.shown_radius + 1.0 / BOARD_GROW_TIME as f32).min(radius);
+        } else {
+            self.shown_radius = radius;
+        }
+        if self.placing.is_none() && self.board.spawn_waiting() {
+            self.placing = self
+                .board
+                .settings()
+                .manual_spawns
+                .map(|manual| manual.time_limit);
+        }
+        for (_, age) in self.recent_scores.iter_mut() {
+            *age += 1;
+        }
+        self.recent_scores
+            .retain(|(_, age)| *age < SCORE_TICKER_TIME.max(SCORE_POPUP_TIME));
+        let fizzles = self.board.take_fizzles();
+        if fizzles > 0 {
+            assets.play_sfx(SfxEvent::Fizzle);
+            // Nothing moved, so it doesn't count against the move limit
+            self.moves_made = self.moves_made.saturating_sub(fizzles);
+        }
+        let score_events = self.board.take_score_events();
+        for tier in score_events.iter().filter_map(|event| event.tier) {
+            assets.play_sfx(SfxEvent::LadderTier(tier as u32 + 1));
+        }
+        self.recent_scores
+            .extend(score_events.into_iter().map(|event| (event, 0)));
+        for event in clear_events.iter() {
+            self.run_clears.record(event);
+        }
+        if let Some(inventory) = &mut self.inventory {
+            inventory.record_clears(&clear_events);
+        }
+        if let Some(charge) = &mut self.charge {
+            charge.record_clears(&clear_events);
+        }
+        // Only offer a shuffle once things have settled and the board is filling up;
+        // a nearly empty board with nothing to do just needs to wait for spawns
+        if ticking && self.board.tick_count() % STUCK_CHECK_INTERVAL == 0 {
+            let filling_up = self.board.get_marbles().len() * 2 >= self.board.cells().len();
+            self.stuck = filling_up
+                && self.board.next_action().is_none()
+                && self.board.find_useful_move().is_none();
+        }
+        let opponent_failure = match &mut self.opponent {
+            Some(opponent) if ticking => opponent.tick(&mut self.board, &clear_events),
+            _ => false,
+        };
+        if self.settings.color_tones {
+            for marble in clear_events.iter().map(|event| &event.marble).unique() {
+                play_color_tone(marble, assets);
+            }
+        }
+        self.score_counter.update(self.board.score());
+        if ticking {
+            self.run_curve
+                .sample(self.board.tick_count(), self.board.score());
+            if self.board.tick_count() % POSTMORTEM_INTERVAL == 0 {
+                self.recent_boards.record(&self.board);
+            }
+        }
+        for (split, milestone) in self.splits.iter_mut().zip(SPLIT_MILESTONES.iter()) {
+            if split.is_none() && self.board.score() >= *milestone {
+                *split = Some(self.board.tick_count());
+            }
+        }
+        // Everything this tick has been tallied up, so this is a whole moment to go back to
+        if self.history.is_some() && ticked && self.board.tick_count() % HISTORY_INTERVAL == 0 {
+            let snapshot = self.rewind_snapshot();
+            if let Some(history) = &mut self.history {
+                history.record(&snapshot);
+            }
+        }
         if failure {
-            stop_sound(self.music);
-            return Transition::Swap(Box::new(ModeLosingTransition::new(self)));
+            assets.audio.stop(self.music);
+            return Transition::Swap(Box::new(ModeLosingTransition::new(
+                self,
+                GameOverReason::BoardFilled,
+            )));
+        }
+        if opponent_failure {
+            assets.audio.stop(self.music);
+            return Transition::Swap(Box::new(ModeLosingTransition::new(
+                self,
+                GameOverReason::OpponentFilled,
+            )));
+        }
+        // Let the last move finish resolving before ending it
+        if out_of_moves && self.board.next_action().is_none() {
+            assets.audio.stop(self.music);
+            return Transition::Swap(Box::new(ModeLosingTransition::new(
+                self,
+                GameOverReason::OutOfMoves,
+            )));
         }
 
         let dist = if let Some(sp) = self.board.next_spawn_point() {
@@ -267,14 +975,166 @@ impl ModePlaying {
         } else {
             -1.0
         };
-        let speed = 1.0 - ((dist - 1.0) / self.board.radius() as f32);
-        self.bg_funni_timer += speed.sqrt();
+        let danger = 1.0 - ((dist - 1.0) / self.board.radius() as f32);
+        if let Some(tips) = &mut self.tips {
+            if !clear_events.is_empty() {
+                tips.trigger(Tip::Cascades);
+                if self.board.settings().gravity != GravityMode::None {
+                    tips.trigger(Tip::Gravity);
+                }
+            }
+            if danger >= BG_DANGER_THRESHOLD {
+                tips.trigger(Tip::FillingUp);
+            }
+            tips.tick();
+        }
+        // Pulse twice as often when the board is getting full
+        self.bg_pulse.update(self.music_clock.beats(), || {
+            if danger >= BG_DANGER_THRESHOLD {
+                BG_DANGER_PULSE_BEATS
+            } else {
+                BG_CALM_PULSE_BEATS
+            }
+        });
 
         Transition::None
     }
 
-    /// always follow this with a clear blobs sil vous plait
-    fn pattern_to_action(&self, mut pat: Vec<Coordinate>) -> BoardAction {
+    /// Queue up a finished loop from either player.
+    ///
+    /// The loop was drawn against the board as it'll be once the queue plays out,
+    /// so that's what it gets turned into an action against too.
+    fn submit_pattern(&mut self, pat: Vec<Coordinate>) {
+        let ghost = pat.clone();
+        let action = match self.inventory.as_mut().and_then(Inventory::take_armed) {
+            Some(PowerUp::Magnet) => {
+                // Pull in whatever color the loop starts on, before the loop spins
+                let action = self.pattern_to_action(pat.clone(), &self.future);
+                if let Some(color) = self.future.get_marble(&pat[0]).cloned() {
+                    self.queue_action(BoardAction::Attract(color, pat));
+                }
+                action
+            }
+            // Clear the ring the loop starts on instead of spinning it,
+            // so going all the way around one clears that one
+            Some(PowerUp::RingClear) => {
+                BoardAction::ClearRing(pat[0].distance(Coordinate::new(0, 0)))
+            }
+            None => {
+                let action = self.pattern_to_action(pat, &self.future);
+                let penalized = self.board.settings().scoring.misclick_penalty > 0;
+                if penalized
+                    && matches!(action, BoardAction::Cycle(_))
+                    && self.future.simulate_action(&action).is_empty()
+                {
+                    self.board.charge_misclick();
+                }
+                action
+            }
+        };
+        self.submit_action(ghost, action);
+    }
+
+    /// Queue up what a finished loop turned into, and count it as a move.
+    fn submit_action(&mut self, pat: Vec<Coordinate>, action: BoardAction) {
+        self.ghost = Some((pat, GHOST_TIME));
+        self.queue_action(action);
+        let premult = self.board.settings().scoring.player_start_multiplier;
+        self.queue_action(BoardAction::ClearBlobs(premult));
+        self.future.play_out_queue();
+        self.moves_made += 1;
+        if let Some(tips) = &mut self.tips {
+            tips.trigger(Tip::MakeGroups);
+            if self.moves_made >= HEXAGON_TIP_MOVES {
+                tips.trigger(Tip::Hexagon);
+            }
+        }
+    }
+
+    /// Queue up an action on the board, and on its future too so that stays in step.
+    fn queue_action(&mut self, action: BoardAction) {
+        self.future.push_action(action.clone());
+        self.board.push_action(action);
+    }
+
+    /// If the player is holding a closed loop, the marbles that would get cleared
+    /// once it finishes rotating.
+    fn cascade_preview(&self) -> Vec<Coordinate> {
+        let future = &self.future;
+        let pat = match &self.pattern {
+            Some(pat)
+                if matches!(
+                    is_pattern_valid(pat, future.get_marbles()),
+                    PatternExtensionValidity::Finished
+                ) =>
+            {
+                pat
+            }
+            _ => return Vec::new(),
+        };
+        match self.pattern_to_action(pat.clone(), future) {
+            BoardAction::Cycle(path) => {
+                let blobs = future.simulate_action(&BoardAction::Cycle(path.clone()));
+                // The blobs are where the marbles end up, but we want to highlight
+                // them where they are now, so walk anything on the path back one step.
+                blobs
+                    .into_iter()
+                    .flatten()
+                    .map(|pos| match path.iter().position(|p| *p == pos) {
+                        Some(idx) => path[(idx + path.len() - 1) % path.len()],
+                        None => pos,
+                    })
+                    .collect()
+            }
+            // Hexagons already blink everything they're about to delete
+            _ => Vec::new(),
+        }
+    }
+
+    /// How the board is on screen right now
+    fn camera(&self) -> BoardCamera {
+        BoardCamera::for_shown_radius(self.shown_radius).rotated(self.view_rotation)
+    }
+
+    /// The hex the mouse is over
+    fn mouse_to_hex(&self) -> Coordinate {
+        self.camera().pixel_to_hex(mouse_position_pixel().into())
+    }
+
+    /// Copy down where the run is, for practice rewinds.
+    fn rewind_snapshot(&self) -> RewindSnapshot {
+        RewindSnapshot {
+            board: self.board.clone(),
+            moves_made: self.moves_made,
+            score_counter: self.score_counter.clone(),
+            splits: self.splits,
+            run_clears: self.run_clears.clone(),
+            run_curve: self.run_curve.clone(),
+        }
+    }
+
+    /// Put the run back how it was when the snapshot was taken.
+    fn rewind_to(&mut self, snapshot: RewindSnapshot) {
+        self.board = snapshot.board;
+        self.future = self.board.simulate_queue();
+        self.moves_made = snapshot.moves_made;
+        self.score_counter = snapshot.score_counter;
+        self.splits = snapshot.splits;
+        self.run_clears = snapshot.run_clears;
+        self.run_curve = snapshot.run_curve;
+    }
+
+    /// How many more patterns the player can draw, if there's a limit
+    fn moves_left(&self) -> Option<u32> {
+        self.board
+            .settings()
+            .move_limit
+            .map(|limit| limit.saturating_sub(self.moves_made))
+    }
+
+    /// always follow this with a clear blobs sil vous plait.
+    /// `future` is the board the pattern was drawn on, from `Board::simulate_queue`.
+    fn pattern_to_action(&self, mut pat: Vec<Coordinate>, future: &Board) -> BoardAction {
         // Chexagon if it's a hexagon
         let is_hexagon = || {
             // Note that everything is already looped
@@ -292,12 +1152,12 @@ impl ModePlaying {
                 .enumerate()
                 .filter_map(|(idx, a)| {
                     if *a == Angle::Left || *a == Angle::Right {
-                        Some(self.board.get_marble(&pat[idx + 1]))
+                        Some(future.get_marble(&pat[idx + 1]))
                     } else {
                         None
                     }
                 })
-                .chain(std::iter::once(self.board.get_marble(&pat[0])))
+                .chain(std::iter::once(future.get_marble(&pat[0])))
                 .all_equal();
             if !all_corners_same {
                 return false;
@@ -335,7 +1195,9 @@ impl ModePlaying {
         };
 
         if is_hexagon() {
-            BoardAction::DeleteColor(self.board.get_marble(&pat[0]).unwrap().clone())
+            // Last == first, so skip one of them to not weigh it twice
+            let color = future.get_marble(&pat[0]).unwrap().clone();
+            BoardAction::DeleteColor(color, pat[1..].to_vec())
         } else {
             // Oh well.
             // Because last == first we need to remove one of them
@@ -346,25 +1208,83 @@ impl ModePlaying {
     }
 }
 
-fn mouse_to_hex() -> Coordinate {
-    let (mx, my) = mouse_position_pixel();
-    let board_x = mx - BOARD_CENTER_X;
-    let board_y = my - BOARD_CENTER_Y;
-
-    // hex2d does not come with a function to convert back from blocky pixel coords to hex.
-    // so we roll our own
-    // also i could const fold all this but lazyyy
-    let forward_transform = Mat2::from_cols_array(&[
-        MARBLE_SPAN_X as f32,
-        0.0,
-        MARBLE_SPAN_X as f32 / 2.0,
-        MARBLE_SPAN_Y as f32,
-    ]);
-    let transform = forward_transform.inverse();
-    let (q, r) = (transform * vec2(board_x, board_y)).into();
-
-    // i hate hexagons, dunno why i need all this awful rotating
-    Coordinate::<i32>::nearest(r, q).rotate_around_zero(Angle::RightBack)
+/// Play the soft tone for the marble's color. Stones don't have one.
+fn play_color_tone(marble: &Marble, assets: &Assets) {
+    let tones = match assets.sounds.tones.get() {
+        Some(it) => it,
+        // Not loaded yet
+        None => return,
+    };
+    if let Some(tone) = tones.get(marble.clone() as usize) {
+        assets.audio.play(
+            *tone,
+            PlayParams {
+                volume: COLOR_TONE_VOLUME,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Is the cell on or inside the hexagon pattern?
+///
+/// A hexagon goes all the way around a ring, so the cell in the middle is the average of it.
+fn hexagon_contains(hexagon: &[Coordinate], pos: Coordinate) -> bool {
+    // The loop ends where it starts, so skip the repeat
+    let ring = &hexagon[1..];
+    let (sum_x, sum_y) = ring
+        .iter()
+        .fold((0, 0), |(x, y), cell| (x + cell.x, y + cell.y));
+    let len = ring.len() as i32;
+    let center = Coordinate::new(sum_x / len, sum_y / len);
+    pos.distance(center) <= ring[0].distance(center)
+}
+
+/// Where the pattern goes next with the mouse at `px`, if it can go anywhere.
+///
+/// That's the cell under the mouse if the pattern can grow into it. Failing that, it's
+/// whichever cell the pattern could grow into has its middle closest to the mouse,
+/// as long as it's within the snap radius.
+fn pattern_step(
+    pattern: &[Coordinate],
+    px: Vec2,
+    camera: &BoardCamera,
+    snap: SnapRadius,
+    marbles: &AHashMap<Coordinate, Marble>,
+) -> Option<Coordinate> {
+    if !matches!(
+        is_pattern_valid(pattern, marbles),
+        PatternExtensionValidity::Continue
+    ) {
+        return None;
+    }
+    let last = *pattern.last()?;
+    let extends = |pos: Coordinate| {
+        let mut maybe_pat = pattern.to_vec();
+        maybe_pat.push(pos);
+        !matches!(
+            is_pattern_valid(&maybe_pat, marbles),
+            PatternExtensionValidity::Invalid
+        )
+    };
+
+    let under = camera.pixel_to_hex(px);
+    if under == last {
+        return None;
+    }
+    if extends(under) {
+        return Some(under);
+    }
+
+    let reach = snap.reach() * MARBLE_SPAN_X as f32 * camera.zoom;
+    last.neighbors()
+        .iter()
+        .copied()
+        .filter(|pos| extends(*pos))
+        .map(|pos| (pos, (camera.hex_to_pixel(pos) - px).length()))
+        .filter(|(_, dist)| *dist <= reach)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(pos, _)| pos)
 }
 
 fn is_pattern_valid(
@@ -377,6 +1297,10 @@ fn is_pattern_valid(
         if !board.contains_key(&a) || !board.contains_key(&b) {
             return PatternExtensionValidity::Invalid;
         }
+        // stone can't be moved
+        if board.get(&a) == Some(&Marble::Stone) || board.get(&b) == Some(&Marble::Stone) {
+            return PatternExtensionValidity::Invalid;
+        }
         if a.distance(b) != 1 {
             return PatternExtensionValidity::Invalid;
         }
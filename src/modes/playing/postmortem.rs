@@ -0,0 +1,67 @@
+use hex2d::Coordinate;
+
+use crate::model::{Board, BoardHistory, TICKS_PER_SECOND};
+
+/// How often to save the board for working out what went wrong, in ticks
+pub const POSTMORTEM_INTERVAL: u32 = TICKS_PER_SECOND / 2;
+/// How many boards to keep, for about 5 seconds
+pub const POSTMORTEM_LENGTH: usize = (TICKS_PER_SECOND * 5 / POSTMORTEM_INTERVAL) as usize;
+
+/// Why the board filled up, worked out from its last few seconds.
+#[derive(Debug, Clone, Default)]
+pub struct PostMortem {
+    /// A line or two for the losing screen
+    pub explanation: String,
+    /// Where the last marble had nowhere to spawn
+    pub blocked: Option<Coordinate>,
+    /// The last loop that would have cleared something, if there was one lately
+    pub missed_loop: Vec<Coordinate>,
+}
+
+impl PostMortem {
+    /// Look back over the recent boards, oldest first, and the one the run ended on.
+    pub fn analyze(recent: &BoardHistory, last: &Board) -> Self {
+        let newest_first = || std::iter::once(last).chain(recent.iter().rev());
+
+        let mut lines = Vec::new();
+
+        let blocked = newest_first().find_map(Board::next_spawn_point);
+        if let Some(pos) = blocked {
+            lines.push(format!(
+                "SPAWN BLOCKED AT RING {}",
+                pos.distance(Coordinate::new(0, 0))
+            ));
+        }
+
+        let missed = newest_first().find_map(|board| {
+            board
+                .find_useful_move()
+                .map(|path| (path, last.tick_count() - board.tick_count()))
+        });
+        let missed_loop = match missed {
+            Some((path, ago)) => {
+                let secs = (ago + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND;
+                lines.push(if secs == 0 {
+                    "A CLEAR WAS OPEN AT THE END".to_owned()
+                } else {
+                    format!("LAST CLEAR WAS OPEN {}S BEFORE", secs)
+                });
+                path
+            }
+            None => {
+                let span = last.tick_count() - recent.iter().next().map_or(0, Board::tick_count);
+                lines.push(format!(
+                    "NO CLEARS IN THE LAST {}S",
+                    (span / TICKS_PER_SECOND).max(1)
+                ));
+                Vec::new()
+            }
+        };
+
+        Self {
+            explanation: lines.join("\n"),
+            blocked,
+            missed_loop,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use enum_map::{Enum, EnumMap};
+use macroquad::prelude::Rect;
+
+use crate::{
+    controls::Control,
+    model::{Board, BoardAction, ClearEvent, Marble, SpecialAction},
+};
+
+use super::MARBLE_SIZE;
+
+/// Every this many marbles cleared earns the next power-up in turn
+const MARBLES_PER_POWERUP: u32 = 30;
+/// The most of any one power-up the player can hold at once
+const MAX_HELD: u32 = 3;
+/// How many marbles need clearing to fill the charge meter
+const MARBLES_PER_CHARGE: u32 = 50;
+
+/// Things the player can earn by clearing marbles and spend to get out of a jam.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUp {
+    /// Pull every marble of one color a step towards the next pattern drawn
+    Magnet,
+    /// Clear the whole ring the next pattern starts on, instead of spinning it
+    RingClear,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 2] = [PowerUp::Magnet, PowerUp::RingClear];
+
+    /// Name for the HUD button
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::Magnet => "MAGNET",
+            PowerUp::RingClear => "RING",
+        }
+    }
+
+    /// The key that arms it
+    pub fn control(&self) -> Control {
+        match self {
+            PowerUp::Magnet => Control::Magnet,
+            PowerUp::RingClear => Control::RingClear,
+        }
+    }
+
+    /// Where its icon is in the marble atlas.
+    /// They're in the spare space after the background ticks.
+    pub fn icon(&self) -> Rect {
+        Rect::new(
+            (3 + *self as usize) as f32 * MARBLE_SIZE,
+            2.0 * MARBLE_SIZE,
+            MARBLE_SIZE,
+            MARBLE_SIZE,
+        )
+    }
+}
+
+/// Power-ups the player has earned and not spent yet.
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    held: EnumMap<PowerUp, u32>,
+    /// Marbles cleared towards the next power-up
+    progress: u32,
+    /// Which power-up gets earned next
+    next_earned: usize,
+    /// The power-up that goes off with the next pattern the player submits
+    armed: Option<PowerUp>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            held: EnumMap::default(),
+            progress: 0,
+            next_earned: 0,
+            armed: None,
+        }
+    }
+
+    /// Count what got cleared towards the next power-up.
+    ///
+    /// Stones crumbling don't count.
+    pub fn record_clears(&mut self, clears: &[ClearEvent]) {
+        self.progress += clears
+            .iter()
+            .filter(|event| event.marble != Marble::Stone)
+            .count() as u32;
+        while self.progress >= MARBLES_PER_POWERUP {
+            self.progress -= MARBLES_PER_POWERUP;
+            let held = &mut self.held[PowerUp::ALL[self.next_earned]];
+            *held = (*held + 1).min(MAX_HELD);
+            self.next_earned = (self.next_earned + 1) % PowerUp::ALL.len();
+        }
+    }
+
+    /// Arm the power-up if there's one to spend, or disarm it if it's already armed.
+    ///
+    /// Return whether anything changed.
+    pub fn toggle(&mut self, powerup: PowerUp) -> bool {
+        if self.armed == Some(powerup) {
+            self.armed = None;
+            true
+        } else if self.held[powerup] > 0 {
+            self.armed = Some(powerup);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Spend the armed power-up, if there is one.
+    pub fn take_armed(&mut self) -> Option<PowerUp> {
+        let powerup = self.armed.take()?;
+        self.held[powerup] -= 1;
+        Some(powerup)
+    }
+
+    pub fn held(&self, powerup: PowerUp) -> u32 {
+        self.held[powerup]
+    }
+
+    pub fn armed(&self) -> Option<PowerUp> {
+        self.armed
+    }
+
+    /// How close the next power-up is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.progress as f32 / MARBLES_PER_POWERUP as f32
+    }
+}
+
+/// Fills up as marbles get cleared, and does the run's special action when it's full.
+#[derive(Debug, Clone)]
+pub struct ChargeMeter {
+    pub special: SpecialAction,
+    /// Marbles cleared since it was last used
+    charge: u32,
+}
+
+impl ChargeMeter {
+    pub fn new(special: SpecialAction) -> Self {
+        Self { special, charge: 0 }
+    }
+
+    /// Count what got cleared towards a full charge.
+    ///
+    /// Stones crumbling don't count.
+    pub fn record_clears(&mut self, clears: &[ClearEvent]) {
+        let cleared = clears
+            .iter()
+            .filter(|event| event.marble != Marble::Stone)
+            .count() as u32;
+        self.charge = (self.charge + cleared).min(MARBLES_PER_CHARGE);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.charge >= MARBLES_PER_CHARGE
+    }
+
+    /// Empty the meter and get what it does, if it was full.
+    pub fn spend(&mut self, board: &Board) -> Option<BoardAction> {
+        if !self.is_full() {
+            return None;
+        }
+        self.charge = 0;
+        Some(match self.special {
+            SpecialAction::Shuffle => BoardAction::Shuffle,
+            SpecialAction::SlowTime => BoardAction::SlowTime(SpecialAction::SLOW_TIME_TICKS),
+            SpecialAction::RingClear => BoardAction::ClearRing(board.fullest_ring()),
+        })
+    }
+
+    /// How full the meter is, from 0 to 1
+    pub fn fill(&self) -> f32 {
+        self.charge as f32 / MARBLES_PER_CHARGE as f32
+    }
+}
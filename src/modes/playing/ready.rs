@@ -0,0 +1,98 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Assets,
+    audio::AudioBackend,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeBox, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{BoardSettings, TICKS_PER_SECOND},
+    utils::{
+        profile::{PlaySettings, Profile},
+        text::{draw_pixel_text, TextAlign},
+        theme,
+    },
+    width,
+};
+
+use super::ModePlaying;
+
+/// How long the countdown before a run is, in frames
+const READY_TIME: u32 = TICKS_PER_SECOND * 3 / 2;
+/// How long "GO!" shows for at the end of the countdown
+const GO_TIME: u32 = TICKS_PER_SECOND / 2;
+
+/// Start a run: stop the title music, roll the board, and count down into it.
+///
+/// Everything that starts a game goes through here, so push or swap to what this returns.
+pub fn start_game(
+    board_settings: BoardSettings,
+    play_settings: PlaySettings,
+    assets: &Assets,
+) -> GamemodeBox {
+    assets.audio.stop(assets.sounds.title_music);
+    // Only runs the player actually starts count, not demos or previews
+    if let Some(mode) = board_settings.mode_key {
+        Profile::modify(|profile| profile.last_mode = Some(mode));
+    }
+    Box::new(ModeReady {
+        playing: Some(ModePlaying::new(board_settings, play_settings, assets)),
+        timer: 0,
+    })
+}
+
+/// A short countdown over the starting board, so the run doesn't start the moment
+/// the button's clicked. Clicking skips it.
+pub struct ModeReady {
+    /// The run to start. This is only `None` once it's been handed off.
+    playing: Option<ModePlaying>,
+    timer: u32,
+}
+
+impl Gamemode for ModeReady {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        _assets: &Assets,
+    ) -> Transition {
+        self.timer += 1;
+        if self.timer >= READY_TIME || controls.clicked_down(Control::Click) {
+            Transition::Swap(Box::new(self.playing.take().unwrap()))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(ReadyDrawer {
+            playing: self.playing.as_mut().unwrap().get_draw_info(),
+            go: self.timer + GO_TIME >= READY_TIME,
+        })
+    }
+}
+
+struct ReadyDrawer {
+    playing: DrawerBox,
+    /// Show "GO!" instead of "READY"
+    go: bool,
+}
+
+impl GamemodeDrawer for ReadyDrawer {
+    fn draw(&self, assets: &Assets, frame_info: FrameInfo) {
+        self.playing.draw(assets, frame_info);
+
+        let mut dim = theme::bg();
+        dim.a = if self.go { 0.2 } else { 0.5 };
+        draw_rectangle(0.0, 0.0, width(), height(), dim);
+        draw_pixel_text(
+            if self.go { "GO!" } else { "READY" },
+            width() / 2.0,
+            height() / 2.0 - 4.0,
+            TextAlign::Center,
+            theme::text(),
+            assets.textures.fonts.medium,
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::any::Any;
+
+use ahash::AHashMap;
+use cogs_gamedev::controls::InputHandler;
+use hex2d::Coordinate;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::{Assets, SfxEvent},
+    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{ActionTimes, Board, BoardAction, BoardHistory, BoardSettingsModeKey, Marble},
+    utils::{
+        button::Button,
+        draw::mouse_position_pixel,
+        profile::PlaySettings,
+        puzzle::{Puzzle, PuzzleGoals},
+        text::{draw_pixel_text, TextAlign},
+        theme,
+    },
+};
+
+use super::{
+    camera::BoardCamera, draw::draw_marble_board, level_editor::ModeLevelEditor, MARBLE_SIZE,
+};
+
+/// How many edits can be undone
+const UNDO_LENGTH: usize = 100;
+
+/// Place and erase marbles by hand, then let the board play out to see what happens.
+///
+/// Nothing spawns here, and nothing gets saved unless it's exported as a puzzle.
+pub struct ModeSandbox {
+    board: Board,
+    settings: PlaySettings,
+
+    /// What clicking on the board puts there; `None` erases
+    tool: Option<Marble>,
+    /// Let marbles fall and clear while editing
+    simulating: bool,
+    /// Are we in the middle of dragging out a stroke?
+    painting: bool,
+    /// Boards from before each edit, to undo back to
+    history: BoardHistory,
+    /// What the board should be played for if it's exported as a puzzle
+    goals: PuzzleGoals,
+
+    b_palette: Vec<(Button, Option<Marble>)>,
+    b_simulate: Button,
+    b_undo: Button,
+    b_clear: Button,
+    b_level: Button,
+    b_back: Button,
+}
+
+impl ModeSandbox {
+    pub fn new(settings: PlaySettings) -> Self {
+        let mut board = Board::new(BoardSettingsModeKey::Classic.settings());
+        board.clear_marbles();
+
+        let b_palette = Marble::ALL
+            .iter()
+            .cloned()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .enumerate()
+            .map(|(idx, tool)| {
+                let size = MARBLE_SIZE + 2.0;
+                (Button::new(3.0, 3.0 + idx as f32 * size, size, size), tool)
+            })
+            .collect();
+
+        let h = 9.0;
+        let y = height() - h - 3.0;
+        Self {
+            board,
+            settings,
+            tool: Some(Marble::Red),
+            simulating: false,
+            painting: false,
+            history: BoardHistory::new(UNDO_LENGTH),
+            goals: PuzzleGoals::default(),
+
+            b_palette,
+            b_back: Button::new(3.0, y, 4.0 * 7.0, h),
+            b_simulate: Button::new(34.0, y, 4.0 * 8.0, h),
+            b_undo: Button::new(69.0, y, 4.0 * 5.0, h),
+            b_clear: Button::new(92.0, y, 4.0 * 6.0, h),
+            b_level: Button::new(119.0, y, 4.0 * 6.0, h),
+        }
+    }
+
+    fn camera(&self) -> BoardCamera {
+        BoardCamera::for_radius(self.board.radius())
+    }
+}
+
+impl Gamemode for ModeSandbox {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::Pop;
+        }
+
+        if controls.clicked_down(Control::Click) {
+            if let Some((_, tool)) = self.b_palette.iter().find(|(b, _)| b.mouse_hovering()) {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.tool = tool.clone();
+            } else if self.b_simulate.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.simulating = !self.simulating;
+                if self.simulating {
+                    self.history.record(&self.board);
+                    self.board.settle();
+                }
+            } else if self.b_undo.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                if let Some(board) = self.history.pop() {
+                    self.board = board;
+                }
+            } else if self.b_clear.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.history.record(&self.board);
+                self.board.clear_marbles();
+            } else if self.b_level.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                let puzzle =
+                    Puzzle::from_board(&self.board, BoardSettingsModeKey::Classic, self.goals);
+                return Transition::Push(Box::new(ModeLevelEditor::new(puzzle)));
+            } else {
+                let pos = self.camera().pixel_to_hex(mouse_position_pixel().into());
+                if self.board.is_in_bounds(&pos) {
+                    self.history.record(&self.board);
+                    self.painting = true;
+                }
+            }
+        }
+
+        if self.painting {
+            if controls.pressed(Control::Click) {
+                let (mx, my) = mouse_position_pixel();
+                let pos = self.camera().pixel_to_hex(vec2(mx, my));
+                if self.board.is_in_bounds(&pos)
+                    && self.board.get_marble(&pos) != self.tool.as_ref()
+                {
+                    self.board.set_marble(pos, self.tool.clone());
+                    assets.play_sfx_at(SfxEvent::PatternExtend, mx);
+                }
+            } else {
+                self.painting = false;
+                if self.simulating {
+                    self.board.settle();
+                }
+            }
+        }
+
+        if self.simulating {
+            if let Some(action @ BoardAction::ClearBlobs(_)) = self.board.next_action() {
+                let finish_time = action.time(&self.board.settings().action_times);
+                if self.board.action_timer() == finish_time - 1 {
+                    if let Some(score) = self.board.get_score_from_action(action) {
+                        assets.play_sfx(SfxEvent::Clear(score.multiplier));
+                    }
+                }
+            }
+            self.board.resolve_actions();
+            // Nobody's keeping track of these here
+            self.board.take_clear_events();
+            self.board.take_score_events();
+        }
+
+        let mut play_enter = false;
+        for b in self.b_palette.iter_mut().map(|(b, _)| b).chain([
+            &mut self.b_simulate,
+            &mut self.b_undo,
+            &mut self.b_clear,
+            &mut self.b_level,
+            &mut self.b_back,
+        ]) {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        let next_action = self.board.next_action().cloned();
+        let to_remove = if let Some(BoardAction::ClearBlobs(_)) = &next_action {
+            self.board.find_blobs().into_iter().flatten().collect()
+        } else {
+            Vec::new()
+        };
+        Box::new(SandboxDrawer {
+            marbles: self
+                .board
+                .get_marbles()
+                .iter()
+                .map(|(c, m)| (*c, m.clone()))
+                .collect(),
+            cells: self.board.cells().iter().copied().collect(),
+            next_action: next_action.map(|action| (action, self.board.action_timer())),
+            action_times: self.board.settings().action_times,
+            to_remove,
+            camera: self.camera(),
+            settings: self.settings,
+            tool: self.tool.clone(),
+            simulating: self.simulating,
+            can_undo: !self.history.is_empty(),
+
+            b_palette: self.b_palette.clone(),
+            b_simulate: self.b_simulate.clone(),
+            b_undo: self.b_undo.clone(),
+            b_clear: self.b_clear.clone(),
+            b_level: self.b_level.clone(),
+            b_back: self.b_back.clone(),
+        })
+    }
+
+    fn on_reveal(&mut self, data: Option<Box<dyn Any>>, _assets: &Assets) {
+        if let Some(goals) = data.and_then(|data| data.downcast_ref::<PuzzleGoals>().copied()) {
+            self.goals = goals;
+        }
+    }
+}
+
+struct SandboxDrawer {
+    marbles: Vec<(Coordinate, Marble)>,
+    cells: Vec<Coordinate>,
+    next_action: Option<(BoardAction, u32)>,
+    action_times: ActionTimes,
+    to_remove: Vec<Coordinate>,
+    camera: BoardCamera,
+    settings: PlaySettings,
+    tool: Option<Marble>,
+    simulating: bool,
+    can_undo: bool,
+
+    b_palette: Vec<(Button, Option<Marble>)>,
+    b_simulate: Button,
+    b_undo: Button,
+    b_clear: Button,
+    b_level: Button,
+    b_back: Button,
+}
+
+impl GamemodeDrawer for SandboxDrawer {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        clear_background(theme::bg());
+
+        draw_marble_board(
+            &self.camera,
+            &self.cells,
+            &[],
+            &self.marbles,
+            &AHashMap::new(),
+            self.next_action.as_ref(),
+            self.action_times,
+            &self.to_remove,
+            &[],
+            None,
+            None,
+            self.settings,
+            false,
+            assets,
+        );
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let atlas = self.settings.skin.atlas();
+        for (b, tool) in self.b_palette.iter() {
+            b.draw(color, border, highlight, blight, 1.01);
+            // Erasing is shown as an empty cell
+            let (sx, sy) = match tool {
+                Some(marble) => (marble.clone() as u32 as f32 * MARBLE_SIZE, atlas.body_y),
+                None => (0.0, 2.0 * MARBLE_SIZE),
+            };
+            draw_texture_ex(
+                assets.textures.marble_atlas,
+                b.x() + 1.0,
+                b.y() + 1.0,
+                WHITE,
+                DrawTextureParams {
+                    source: Some(Rect::new(sx, sy, MARBLE_SIZE, MARBLE_SIZE)),
+                    ..Default::default()
+                },
+            );
+            if *tool == self.tool {
+                draw_rectangle_lines(
+                    b.x() - 1.0,
+                    b.y() - 1.0,
+                    b.w() + 2.0,
+                    b.h() + 2.0,
+                    2.0,
+                    WHITE,
+                );
+            }
+        }
+
+        for (b, text, enabled) in [
+            (
+                &self.b_simulate,
+                if self.simulating { "SIM ON" } else { "SIM OFF" },
+                true,
+            ),
+            (&self.b_undo, "UNDO", self.can_undo),
+            (&self.b_clear, "CLEAR", true),
+            (&self.b_level, "LEVEL", true),
+            (&self.b_back, "RETURN", true),
+        ] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_pixel_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + 2.0,
+                TextAlign::Center,
+                if !enabled {
+                    highlight
+                } else if b.mouse_hovering() {
+                    blight
+                } else {
+                    border
+                },
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
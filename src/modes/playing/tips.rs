@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    height,
+    model::{BoardSettings, GravityMode, TICKS_PER_SECOND},
+    utils::profile::Profile,
+    width,
+};
+
+/// How long each tip stays up
+const TIP_TIME: u32 = TICKS_PER_SECOND * 4;
+/// How long tips take to fade out at the end
+const TIP_FADE_TIME: u32 = TICKS_PER_SECOND / 2;
+
+/// Hints that pop up during runs, until the player has seen them once.
+/// Modes with `show_tips` on show them every time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tip {
+    /// Right at the start
+    DrawLoop,
+    /// After the first move
+    MakeGroups,
+    /// After the first clear
+    Cascades,
+    /// When the board is getting full
+    FillingUp,
+    /// After the first clear, when things fall into the gap
+    Gravity,
+    /// After a few moves
+    Hexagon,
+}
+
+impl Tip {
+    fn text(&self, settings: &BoardSettings) -> String {
+        match self {
+            Tip::DrawLoop => "DRAG IN A LOOP TO\nSPIN THE MARBLES".to_owned(),
+            Tip::MakeGroups => format!(
+                "PUT {} OF A COLOR\nTOGETHER TO CLEAR",
+                settings.clear_blob_size
+            ),
+            Tip::Cascades => "CLEARS CAN CAUSE\nMORE CLEARS!".to_owned(),
+            Tip::FillingUp => "DON'T LET THE\nBOARD FILL UP!".to_owned(),
+            Tip::Gravity => match settings.gravity {
+                GravityMode::Inward => "MARBLES FALL\nTOWARDS THE CENTER",
+                GravityMode::Directional { .. } => "MARBLES FALL\nTO ONE SIDE",
+                GravityMode::Outward | GravityMode::None => "MARBLES FALL AWAY\nFROM THE CENTER",
+            }
+            .to_owned(),
+            Tip::Hexagon => "DRAW A HEXAGON OF\nONE COLOR TO CLEAR\nTHAT WHOLE COLOR".to_owned(),
+        }
+    }
+
+    /// Where tips show up on the screen. Clicking here gets rid of them.
+    pub fn toast_bounds() -> Rect {
+        Rect::new(0.0, height() - 17.0, width(), 15.0)
+    }
+}
+
+/// Keeps track of which tips have been shown, and shows them one at a time.
+#[derive(Debug, Clone, Default)]
+pub struct Tips {
+    seen: Vec<Tip>,
+    waiting: VecDeque<Tip>,
+    /// The tip up right now and how long it's been up
+    current: Option<(Tip, u32)>,
+}
+
+impl Tips {
+    /// Tips that won't show, because they've been seen already.
+    pub fn new(seen: Vec<Tip>) -> Self {
+        Self {
+            seen,
+            ..Default::default()
+        }
+    }
+
+    /// Show the tip, unless it's been shown already.
+    pub fn trigger(&mut self, tip: Tip) {
+        if !self.seen.contains(&tip) {
+            self.seen.push(tip);
+            self.waiting.push_back(tip);
+            Profile::modify(|profile| {
+                if !profile.seen_tips.contains(&tip) {
+                    profile.seen_tips.push(tip);
+                }
+            });
+        }
+    }
+
+    /// Get rid of the tip up right now, if there is one.
+    pub fn dismiss(&mut self) -> bool {
+        self.current.take().is_some()
+    }
+
+    pub fn tick(&mut self) {
+        if let Some((_, time)) = &mut self.current {
+            *time += 1;
+            if *time >= TIP_TIME {
+                self.current = None;
+            }
+        }
+        if self.current.is_none() {
+            self.current = self.waiting.pop_front().map(|tip| (tip, 0));
+        }
+    }
+
+    /// The text of the tip up right now and how opaque it is
+    pub fn current(&self, settings: &BoardSettings) -> Option<(String, f32)> {
+        self.current.map(|(tip, time)| {
+            let left = TIP_TIME - time;
+            let alpha = (left as f32 / TIP_FADE_TIME as f32).min(1.0);
+            (tip.text(settings), alpha)
+        })
+    }
+}
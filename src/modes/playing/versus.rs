@@ -0,0 +1,115 @@
+use hex2d::Coordinate;
+use quad_rand::compat::QuadRand;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::model::{Board, BoardAction, BoardSettings, ClearEvent, CpuDifficulty, Marble};
+
+/// Every this many marbles cleared sends one stone to the other board
+const MARBLES_PER_GARBAGE: u32 = 4;
+
+/// The CPU in a versus run, playing its own board with the same rules as the player's.
+///
+/// It also plays by itself behind the title screen.
+#[derive(Clone)]
+pub struct CpuOpponent {
+    pub board: Board,
+    difficulty: CpuDifficulty,
+    /// Ticks until the CPU draws its next pattern
+    think_timer: u32,
+    /// Marbles the player cleared that haven't added up to a whole stone yet
+    player_carry: u32,
+    /// Marbles the CPU cleared that haven't added up to a whole stone yet
+    cpu_carry: u32,
+}
+
+impl CpuOpponent {
+    pub fn new(settings: BoardSettings, difficulty: CpuDifficulty) -> Self {
+        Self {
+            board: Board::new(settings),
+            difficulty,
+            think_timer: difficulty.think_ticks(),
+            player_carry: 0,
+            cpu_carry: 0,
+        }
+    }
+
+    /// Run the CPU's board for one tick, and trade stones with the player's board
+    /// for whatever each side cleared.
+    ///
+    /// Return `true` if the CPU's board filled up.
+    pub fn tick(&mut self, player: &mut Board, player_clears: &[ClearEvent]) -> bool {
+        self.board
+            .send_garbage(garbage(&mut self.player_carry, player_clears));
+        let failure = self.play();
+        let cpu_clears = self.board.take_clear_events();
+        player.send_garbage(garbage(&mut self.cpu_carry, &cpu_clears));
+        // Nobody shows off the CPU's points
+        self.board.take_score_events();
+        failure
+    }
+
+    /// Think and run the CPU's board for one tick, with nobody to trade stones with.
+    ///
+    /// Return `true` if the CPU's board filled up.
+    /// What it clears piles up in the board's clear events until someone takes them.
+    pub fn play(&mut self) -> bool {
+        // Like a person, wait to see how the last pattern played out before drawing another
+        if self.board.next_action().is_none() {
+            self.think_timer = self.think_timer.saturating_sub(1);
+            if self.think_timer == 0 {
+                self.think_timer = self.difficulty.think_ticks();
+                if let Some(path) = self.pick_move() {
+                    self.board.push_action(BoardAction::Cycle(path));
+                    let premult = self.board.settings().scoring.player_start_multiplier;
+                    self.board.push_action(BoardAction::ClearBlobs(premult));
+                }
+            }
+        }
+
+        self.board.tick()
+    }
+
+    /// Find the pattern that clears the most marbles, unless the CPU slips up
+    /// and picks any old one.
+    ///
+    /// On easier difficulties it only looks at triangles;
+    /// harder ones look at every ring around a marble too.
+    fn pick_move(&self) -> Option<Vec<Coordinate>> {
+        let candidates = self.board.candidate_loops(self.difficulty.searches_rings());
+        if QuadRand.gen::<f32>() < self.difficulty.mistake_rate() {
+            return candidates.choose(&mut QuadRand).cloned();
+        }
+
+        let (best, cleared) = candidates
+            .iter()
+            .map(|path| {
+                let cleared: usize = self
+                    .board
+                    .simulate_action(&BoardAction::Cycle(path.clone()))
+                    .iter()
+                    .map(Vec::len)
+                    .sum();
+                (path, cleared)
+            })
+            .max_by_key(|(_, cleared)| *cleared)?;
+        if cleared > 0 {
+            Some(best.clone())
+        } else {
+            // Nothing clears anything, so shuffle things around and hope
+            candidates.choose(&mut QuadRand).cloned()
+        }
+    }
+}
+
+/// Add what got cleared to the carry, and take out as many whole stones as that makes.
+///
+/// Stones crumbling don't count.
+fn garbage(carry: &mut u32, clears: &[ClearEvent]) -> u32 {
+    *carry += clears
+        .iter()
+        .filter(|event| event.marble != Marble::Stone)
+        .count() as u32;
+    let stones = *carry / MARBLES_PER_GARBAGE;
+    *carry %= MARBLES_PER_GARBAGE;
+    stones
+}
@@ -0,0 +1,115 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics},
+        text::TextAlign,
+        theme,
+    },
+    width, Assets,
+};
+
+/// Passed back down to whoever pushed the `ModeConfirm`.
+#[derive(Debug, Clone, Copy)]
+pub struct Confirmed(pub bool);
+
+/// Asks "are you sure?" and pops with a `Confirmed`.
+#[derive(Debug, Clone)]
+pub struct ModeConfirm {
+    message: String,
+
+    b_yes: Button,
+    b_no: Button,
+}
+
+impl ModeConfirm {
+    pub fn new(message: impl Into<String>) -> Self {
+        let m = MenuMetrics::current();
+        let w = m.px(4.0 * 8.0);
+        let h = m.px(9.0);
+        let y = height() / 2.0 + m.px(10.0);
+
+        Self {
+            message: message.into(),
+            b_yes: Button::new(width() / 2.0 - w - m.px(4.0), y, w, h),
+            b_no: Button::new(width() / 2.0 + m.px(4.0), y, w, h),
+        }
+    }
+}
+
+impl Gamemode for ModeConfirm {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Click) && self.b_yes.mouse_hovering() {
+            assets.play_sfx(SfxEvent::ButtonClick);
+            return Transition::PopWith(Box::new(Confirmed(true)));
+        }
+        if (controls.clicked_down(Control::Click) && self.b_no.mouse_hovering())
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(Confirmed(false)));
+        }
+
+        let mut play_enter = false;
+        for b in [&mut self.b_yes, &mut self.b_no] {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeConfirm {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let lines = self.message.lines().count() as f32;
+        draw_menu_text(
+            &self.message,
+            width() / 2.0,
+            self.b_yes.y() - m.px(8.0 + lines * 6.0),
+            TextAlign::Center,
+            border,
+            assets.textures.fonts.small,
+        );
+
+        for (b, text) in [(&self.b_yes, "YES"), (&self.b_no, "NO")] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                if b.mouse_hovering() { blight } else { border },
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
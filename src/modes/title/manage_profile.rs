@@ -0,0 +1,358 @@
+use std::any::Any;
+
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{BoardSettingsModeKey, Cyclable, LeaderboardKey},
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        profile::Profile,
+        scroll_list::ScrollList,
+        text::TextAlign,
+        theme,
+    },
+    Assets,
+};
+
+use super::confirm::{Confirmed, ModeConfirm};
+
+/// How many highscore rows fit above the reset buttons
+const SCORES_SHOWN: usize = 7;
+
+/// What we asked the player to confirm
+#[derive(Debug, Clone, Copy)]
+enum PendingReset {
+    One(LeaderboardKey),
+    Scores,
+    Stats,
+    Everything,
+}
+
+/// Look at and throw away saved highscores and stats.
+#[derive(Debug, Clone)]
+pub struct ModeManageProfile {
+    /// Copy of the highscores, in a stable order
+    highscores: Vec<(LeaderboardKey, u32)>,
+    /// The highscores have been edited outside the game
+    modified: bool,
+    pending: Option<PendingReset>,
+
+    scores: ScrollList,
+    b_reset_scores: Button,
+    b_reset_stats: Button,
+    b_reset_all: Button,
+    b_back: Button,
+}
+
+impl ModeManageProfile {
+    pub fn new() -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+        let y_stride = h + m.px(2.0);
+        let y = m.px(5.0);
+
+        let gap = y_stride - h;
+        let scores = ScrollList::new(
+            Rect::new(x, y, w, y_stride * SCORES_SHOWN as f32 - gap),
+            h,
+            gap,
+        );
+
+        let mut out = Self {
+            highscores: Vec::new(),
+            modified: false,
+            pending: None,
+
+            scores,
+            b_reset_scores: Button::new(x, height() - 4.0 * (h + m.px(3.0)), w, h),
+            b_reset_stats: Button::new(x, height() - 3.0 * (h + m.px(3.0)), w, h),
+            b_reset_all: Button::new(x, height() - 2.0 * (h + m.px(3.0)), w, h),
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        };
+        out.reload();
+        out
+    }
+
+    /// Re-read the highscores from storage.
+    fn reload(&mut self) {
+        let profile = Profile::get();
+        let mut highscores = profile
+            .highscores
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        highscores.sort_by_key(|(key, _)| {
+            (
+                BoardSettingsModeKey::ALL
+                    .iter()
+                    .position(|mode| *mode == key.mode),
+                key.mutators.iter().count(),
+                key.mutators.iter().map(|m| m.name()).collect::<Vec<_>>(),
+                key.speed as usize,
+            )
+        });
+        self.highscores = highscores;
+        self.modified = profile.modified;
+        self.scores.set_len(self.highscores.len());
+        self.b_reset_scores.disabled = self.highscores.is_empty();
+    }
+
+    /// The rows of the list on screen, with where they go and the score they're for
+    fn shown_scores(&self) -> impl Iterator<Item = (usize, Rect, &(LeaderboardKey, u32))> + '_ {
+        self.scores
+            .rows()
+            .map(move |(idx, rect)| (idx, rect, &self.highscores[idx]))
+    }
+
+    fn ask(&mut self, reset: PendingReset) -> Transition {
+        let message = match reset {
+            PendingReset::One(key) => format!("RESET THE {} HISCORE?", key.mode.name()),
+            PendingReset::Scores => "RESET ALL HISCORES?".to_owned(),
+            PendingReset::Stats => "RESET ALL STATS?\nHISCORES ARE KEPT.".to_owned(),
+            PendingReset::Everything => {
+                "RESET EVERYTHING?\nHISCORES, STATS, AND\nSETTINGS WILL BE LOST.".to_owned()
+            }
+        };
+        self.pending = Some(reset);
+        Transition::Push(Box::new(ModeConfirm::new(message)))
+    }
+}
+
+impl Gamemode for ModeManageProfile {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if let Some(idx) = self.scores.update(controls) {
+            assets.play_sfx(SfxEvent::ButtonClick);
+            let (key, _) = self.highscores[idx];
+            return self.ask(PendingReset::One(key));
+        }
+
+        if controls.clicked_down(Control::Click) {
+            let reset = if self.b_reset_scores.mouse_hovering() {
+                Some(PendingReset::Scores)
+            } else if self.b_reset_stats.mouse_hovering() {
+                Some(PendingReset::Stats)
+            } else if self.b_reset_all.mouse_hovering() {
+                Some(PendingReset::Everything)
+            } else {
+                None
+            };
+            if let Some(reset) = reset {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                return self.ask(reset);
+            }
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::Pop;
+        }
+
+        let mut play_enter = self.scores.mouse_entered();
+        self.scores.post_update();
+        for b in [
+            &mut self.b_reset_scores,
+            &mut self.b_reset_stats,
+            &mut self.b_reset_all,
+            &mut self.b_back,
+        ] {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+
+    fn on_reveal(&mut self, data: Option<Box<dyn Any>>, _assets: &Assets) {
+        let confirmed = data
+            .and_then(|data| data.downcast_ref::<Confirmed>().copied())
+            .map_or(false, |Confirmed(yes)| yes);
+        if let (Some(reset), true) = (self.pending.take(), confirmed) {
+            Profile::modify(|profile| match reset {
+                PendingReset::One(key) => {
+                    profile.clear_highscore(&key);
+                }
+                PendingReset::Scores => profile.clear_highscores(),
+                PendingReset::Stats => profile.clear_stats(),
+                PendingReset::Everything => profile.clear_all(),
+            });
+            self.reload();
+        }
+    }
+}
+
+impl GamemodeDrawer for ModeManageProfile {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let list = self.scores.bounds();
+        let panel = SidePanel::beside(list);
+        panel.draw_line(border);
+
+        let mut msg = None;
+
+        if self.highscores.is_empty() {
+            draw_menu_text(
+                "NO HISCORES YET",
+                list.x + list.w / 2.0,
+                list.y + m.px(2.0),
+                TextAlign::Center,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+        for (idx, rect, (key, score)) in self.shown_scores() {
+            let lit = self.scores.highlighted() == Some(idx);
+            draw_rectangle(
+                rect.x.round(),
+                rect.y.round(),
+                rect.w.round(),
+                rect.h.round(),
+                if lit { highlight } else { color },
+            );
+            draw_rectangle_lines(
+                rect.x.round(),
+                rect.y.round(),
+                rect.w.round(),
+                rect.h.round(),
+                1.01,
+                if lit { blight } else { border },
+            );
+            let mutator_count = key.mutators.iter().count();
+            let text = if mutator_count == 0 {
+                key.mode.name().to_owned()
+            } else {
+                format!("{} +{}", key.mode.name(), mutator_count)
+            };
+            draw_menu_text(
+                &text,
+                rect.x + rect.w / 2.0,
+                rect.y + m.px(2.0),
+                TextAlign::Center,
+                if lit { blight } else { border },
+                assets.textures.fonts.small,
+            );
+
+            if lit {
+                let mutators = if mutator_count == 0 {
+                    "NO MODIFIERS".to_owned()
+                } else {
+                    key.mutators
+                        .iter()
+                        .map(|m| m.name())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                let ranking = key.mode.ranking();
+                let mark = if self.modified {
+                    "\n(MODIFIED SAVE)"
+                } else {
+                    ""
+                };
+                msg = Some(format!(
+                    "{}\n\n{}\nSPEED {}\n\n{}: {}{}\n\nCLICK TO RESET",
+                    key.mode.name(),
+                    mutators,
+                    key.speed.name(),
+                    ranking.best_label(),
+                    ranking.format(*score),
+                    mark
+                ));
+            }
+        }
+
+        self.scores.draw_scrollbar(border);
+
+        for (b, text, desc) in [
+            (
+                &self.b_reset_scores,
+                "RESET SCORES",
+                "FORGET EVERY\nHISCORE.",
+            ),
+            (
+                &self.b_reset_stats,
+                "RESET STATS",
+                "FORGET YOUR BEST\nSPLITS AND CLEAR\nCOUNTS.\n\nHISCORES ARE KEPT.",
+            ),
+            (
+                &self.b_reset_all,
+                "RESET ALL",
+                "FORGET HISCORES,\nSTATS, AND\nSETTINGS.\n\nSTART FRESH.",
+            ),
+        ] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                b.text_color(border, blight),
+                assets.textures.fonts.small,
+            );
+            if b.mouse_hovering() {
+                msg = Some(if self.modified && text == "RESET SCORES" {
+                    // Wiping the scores is the only way to get rid of the mark
+                    format!(
+                        "{}\n\nTHIS SAVE WAS\nEDITED OUTSIDE\nTHE GAME. THIS\nCLEARS THE MARK.",
+                        desc
+                    )
+                } else {
+                    desc.to_owned()
+                });
+            }
+        }
+
+        if let Some(msg) = msg {
+            draw_menu_text(
+                &msg,
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_back.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            "RETURN",
+            self.b_back.x() + self.b_back.w() / 2.0,
+            self.b_back.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_back.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+    }
+}
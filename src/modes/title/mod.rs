@@ -1,46 +1,82 @@
+mod confirm;
+mod manage_profile;
+mod mode_select;
+mod mutator_select;
 mod play_settings;
+mod settings_list;
+mod stats;
 mod text_displayer;
+mod weekly;
 
-use std::any::{Any};
+use std::{any::Any, sync::Arc};
 
 use cogs_gamedev::controls::InputHandler;
-use macroquad::{audio::*, prelude::*};
+use macroquad::prelude::*;
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, SfxEvent},
+    audio::{AudioBackend, PlayParams},
     boilerplates::*,
     controls::{Control, InputSubscriber},
-    model::{BoardSettings, PlaySettings},
+    model::{Board, BoardSettings, CpuDifficulty},
     utils::{
         button::Button,
-        draw::{hexcolor, mouse_position_pixel},
-        profile::Profile,
-        text::{draw_pixel_text, TextAlign},
+        draw::hexcolor,
+        layout::{draw_menu_text, MenuMetrics},
+        music::{BeatPulse, MusicClock, TITLE_BPM},
+        profile::{self, PlaySettings, Profile},
+        text::TextAlign,
+        theme,
     },
-    HEIGHT, WIDTH,
+    height, width,
 };
 
-use self::{play_settings::ModePlaySettings, text_displayer::ModeTextDisplayer};
+pub use self::settings_list::{quick_settings, Setting};
 
-use super::ModePlaying;
+use self::{
+    mode_select::ModeModeSelect, play_settings::ModePlaySettings, stats::ModeStats,
+    text_displayer::ModeTextDisplayer, weekly::ModeWeekly,
+};
+
+use super::{draw_board_preview, start_game, CpuOpponent};
 
-/// How often new hexagons spawn.
-// Title screen music is in 12/8, 8th = 200bpm. we want a pulse every 3 beats.
-// (60 seconds / 1 minute) * (1 minute / 200 beats) * (3 beats / 1 hex)
-// then make it a *little* faster to combat lag.
-const HEX_TIMER: f64 = 60.0 / 200.0 * 3.0 * 0.99;
+/// How many beats apart the background brightens.
+// Title screen music is in 12/8, so this is once every dotted quarter.
+const BG_PULSE_BEATS: f64 = 3.0;
+/// How many frames the background stays brightened for after each pulse
+const BG_PULSE_TIME: u32 = 12;
+/// The board behind the title only ticks every this many frames, so it's easy to follow
+const DEMO_SLOWDOWN: u32 = 2;
+/// How much of the canvas the board behind the title takes up
+const DEMO_SCALE: f32 = 0.8;
 
-#[derive(Clone)]
 pub struct ModeTitle {
     b_play: Button,
+    b_weekly: Button,
     b_mode_select: Button,
     b_tutorial: Button,
     b_settings: Button,
+    b_stats: Button,
     b_credits: Button,
 
-    prev_hex_time: f64,
-    hexagons: Vec<(Vec2, u32)>,
+    music_clock: MusicClock,
+    bg_pulse: BeatPulse,
+    /// Frames left in the current background pulse
+    pulse_timer: u32,
+    /// The CPU playing a game behind the title, to show how it's done
+    demo: CpuOpponent,
+    demo_frames: u32,
+    /// The demo's board as of the last time it moved, shared with the drawers
+    demo_snapshot: Arc<Board>,
+
+    settings: PlaySettings,
+}
 
+/// Just what the title screen needs to draw a frame, instead of a copy of the whole CPU game.
+struct TitleDrawer {
+    buttons: Vec<(Button, &'static str)>,
+    pulse_timer: u32,
+    demo_board: Arc<Board>,
     settings: PlaySettings,
 }
 
@@ -51,28 +87,24 @@ impl Gamemode for ModeTitle {
         _frame_info: FrameInfo,
         assets: &Assets,
     ) -> Transition {
-        if controls.clicked_down(Control::Click) {
-            self.hexagons.push((mouse_position_pixel().into(), 0));
-        }
-        let now = macroquad::time::get_time();
-        if now > self.prev_hex_time + HEX_TIMER {
-            self.hexagons.push((vec2(WIDTH / 2.0, HEIGHT / 2.0), 0));
-            self.prev_hex_time = now;
+        if self.bg_pulse.update(self.music_clock.beats(), || BG_PULSE_BEATS) > 0 {
+            self.pulse_timer = BG_PULSE_TIME;
         }
+        self.pulse_timer = self.pulse_timer.saturating_sub(1);
 
-        for (_, time) in self.hexagons.iter_mut() {
-            *time += 1;
+        if advance_demo(&mut self.demo, &mut self.demo_frames) {
+            self.demo_snapshot = Arc::new(self.demo.board.clone());
         }
-        self.hexagons
-            .retain(|(_, time)| hex_radius(*time) < WIDTH * 2.0);
 
         let mut enter_sound = false;
         let mut click_sound = false;
         for button in [
             &self.b_play,
+            &self.b_weekly,
             &self.b_mode_select,
             &self.b_tutorial,
             &self.b_settings,
+            &self.b_stats,
             &self.b_credits,
         ] {
             if button.mouse_entered() {
@@ -83,23 +115,25 @@ impl Gamemode for ModeTitle {
             }
         }
         if click_sound {
-            play_sound_once(assets.sounds.close_loop);
+            assets.play_sfx(SfxEvent::ButtonClick);
         } else if enter_sound {
-            play_sound_once(assets.sounds.select);
+            assets.play_sfx(SfxEvent::ButtonHover);
         }
 
         let mut trans = Transition::None;
 
         if controls.clicked_down(Control::Click) {
             if self.b_play.mouse_hovering() {
-                trans = Transition::Push(Box::new(ModePlaying::new(
-                    BoardSettings::classic(),
-                    self.settings,
-                    assets,
-                )));
-                stop_sound(assets.sounds.title_music);
+                trans =
+                    Transition::Push(start_game(BoardSettings::classic(), self.settings, assets));
+            } else if self.b_weekly.mouse_hovering() {
+                trans = Transition::Push(Box::new(ModeWeekly::new(self.settings)));
+            } else if self.b_mode_select.mouse_hovering() {
+                trans = Transition::Push(Box::new(ModeModeSelect::new(self.settings)));
             } else if self.b_settings.mouse_hovering() {
                 trans = Transition::Push(Box::new(ModePlaySettings::new(self.settings)));
+            } else if self.b_stats.mouse_hovering() {
+                trans = Transition::Push(Box::new(ModeStats::new()));
             } else {
                 let message = if self.b_tutorial.mouse_hovering() {
                     let msg = format!(
@@ -127,7 +161,7 @@ DON'T LET THE BOARD FILL UP!",
                             "CLICK"
                         }
                     );
-                    Some((msg, hexcolor(0x291d2b_ff)))
+                    Some((msg, theme::shadow()))
                 } else if self.b_credits.mouse_hovering() {
                     let msg = format!(
                         r"HAXAGON v{}
@@ -164,9 +198,11 @@ GITHUB.COM/GAMMA-DELTA/HAXAGON",
 
         for button in [
             &mut self.b_play,
+            &mut self.b_weekly,
             &mut self.b_mode_select,
             &mut self.b_tutorial,
             &mut self.b_settings,
+            &mut self.b_stats,
             &mut self.b_credits,
         ] {
             button.post_update();
@@ -176,69 +212,97 @@ GITHUB.COM/GAMMA-DELTA/HAXAGON",
     }
 
     fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
-        Box::new(self.clone())
+        Box::new(TitleDrawer {
+            buttons: vec![
+                (self.b_play.clone(), "PLAY"),
+                (self.b_weekly.clone(), "WEEKLY"),
+                (self.b_mode_select.clone(), "MODE SELECT"),
+                (self.b_tutorial.clone(), "HOW TO PLAY"),
+                (self.b_settings.clone(), "SETTINGS"),
+                (self.b_stats.clone(), "STATS"),
+                (self.b_credits.clone(), "CREDITS"),
+            ],
+            pulse_timer: self.pulse_timer,
+            demo_board: self.demo_snapshot.clone(),
+            settings: self.settings,
+        })
     }
 
     fn on_reveal(&mut self, data: Option<Box<dyn Any>>, assets: &Assets) {
-        self.hexagons.clear();
         let mut restart_music = true;
+        // Some settings can be changed from the pause menu, so pick those up after a run
+        self.settings = Profile::get().settings;
 
         if let Some(data) = data {
             let data = &*data as &dyn Any;
             if data.is::<DontRestartMusicToken>() {
                 restart_music = false;
             } else if let Some(settings) = data.downcast_ref() {
+                // The resolution might have changed, so lay the buttons out again
+                let (music_clock, bg_pulse) = (self.music_clock, self.bg_pulse);
+                let demo = self.demo.clone();
+                let demo_snapshot = self.demo_snapshot.clone();
+                *self = ModeTitle::new();
                 self.settings = *settings;
+                self.music_clock = music_clock;
+                self.bg_pulse = bg_pulse;
+                self.demo = demo;
+                self.demo_snapshot = demo_snapshot;
                 restart_music = false;
             }
         }
 
         if restart_music {
-            play_sound(
+            // Pick up where the music left off before the game, if we can
+            let position = if assets.audio.can_seek() {
+                let music = assets.sounds.title_music;
+                assets.audio.stopped_at(music).unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            assets.audio.play(
                 assets.sounds.title_music,
-                PlaySoundParams {
+                PlayParams {
                     looped: true,
                     volume: 0.5,
+                    start_at: position,
+                    ..Default::default()
                 },
             );
+            self.music_clock = MusicClock::start_at(TITLE_BPM, position);
+            // Skip the pulses that went by while we were gone
+            self.bg_pulse = BeatPulse::new(BG_PULSE_BEATS);
+            self.bg_pulse
+                .update(self.music_clock.beats(), || BG_PULSE_BEATS);
         }
     }
 }
 
-impl GamemodeDrawer for ModeTitle {
+impl GamemodeDrawer for TitleDrawer {
     fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
-        clear_background(hexcolor(0x14182e_ff));
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
 
         if self.settings.funni_background {
-            for (pos, time) in self.hexagons.iter() {
-                draw_hexagon(
-                    pos.x,
-                    pos.y,
-                    hex_radius(*time),
-                    2.0,
-                    false,
-                    hexcolor(0x9c2a70_ff),
-                    hexcolor(0x14182e_ff),
-                );
-            }
+            let (w, h) = (width() * DEMO_SCALE, height() * DEMO_SCALE);
+            let area = Rect::new((width() - w) / 2.0, (height() - h) / 2.0, w, h);
+            draw_board_preview(&self.demo_board, area, false, self.settings, assets);
+            // Dim it so it stays in the background, a little less so on the beat
+            let mut dim = theme::bg();
+            dim.a = 0.8 - 0.15 * self.pulse_timer as f32 / BG_PULSE_TIME as f32;
+            draw_rectangle(0.0, 0.0, width(), height(), dim);
         }
 
-        let logo_x = WIDTH / 2.0 - assets.textures.title_logo.width() / 2.0;
-        let logo_y = HEIGHT * 0.15;
+        let logo_x = width() / 2.0 - assets.textures.title_logo.width() / 2.0;
+        let logo_y = height() * 0.15;
         draw_texture(assets.textures.title_logo, logo_x, logo_y, WHITE);
 
-        let color = hexcolor(0x4b1d52_ff);
-        let highlight = hexcolor(0x692464_ff);
-        let border = hexcolor(0xcc2f7b_ff);
-        let blight = hexcolor(0xff5277_ff);
-
-        for (button, text) in [
-            (&self.b_play, "PLAY"),
-            (&self.b_mode_select, "MODE SELECT"),
-            (&self.b_tutorial, "HOW TO PLAY"),
-            (&self.b_settings, "SETTINGS"),
-            (&self.b_credits, "CREDITS"),
-        ] {
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        for (button, text) in self.buttons.iter() {
             button.draw(color, border, highlight, blight, 1.01);
 
             let text_color = if button.mouse_hovering() {
@@ -246,54 +310,92 @@ impl GamemodeDrawer for ModeTitle {
             } else {
                 border
             };
-            draw_pixel_text(
+            draw_menu_text(
                 text,
                 button.x() + button.w() / 2.0,
-                button.y() + 2.0,
+                button.y() + m.px(2.0),
                 TextAlign::Center,
                 text_color,
                 assets.textures.fonts.small,
             );
         }
+
+        if profile::save_failed() {
+            draw_menu_text(
+                "COULDN'T SAVE:\nSTORAGE IS FULL",
+                m.px(3.0),
+                m.px(3.0),
+                TextAlign::Left,
+                blight,
+                assets.textures.fonts.small,
+            );
+        }
     }
 }
 
 impl ModeTitle {
     pub fn new() -> Self {
-        let w = 4.0 * 13.0;
-        let x = WIDTH / 2.0 - w / 2.0;
+        let m = MenuMetrics::current();
+        let w = m.px(4.0 * 13.0);
+        let x = width() / 2.0 - w / 2.0;
 
-        let h = 9.0;
-        let y_stride = h + 2.0;
-        let y = HEIGHT * 0.5;
+        let h = m.px(9.0);
+        let y_stride = h + m.px(2.0);
+        let y = height() * 0.5;
 
-        let wide_w = 4.0 * 16.0;
-        let wide_x = WIDTH / 2.0 - wide_w / 2.0;
+        let wide_w = m.px(4.0 * 16.0);
+        let wide_x = width() / 2.0 - wide_w / 2.0;
 
         let settings = {
             let profile = Profile::get();
             profile.settings
         };
 
+        let demo = new_demo();
+        let demo_snapshot = Arc::new(demo.board.clone());
+
         Self {
-            b_play: Button::new(x, y - y_stride, w, h),
-            // high quality gaming
-            b_mode_select: Button::new(-1000.0, y, w, h),
-            b_tutorial: Button::new(x, y, w, h),
-            b_settings: Button::new(x, y + y_stride, w, h),
+            b_play: Button::new(x, y - 2.0 * y_stride, w, h),
+            b_weekly: Button::new(x, y - y_stride, w, h),
+            b_mode_select: Button::new(x, y, w, h),
+            b_tutorial: Button::new(x, y + y_stride, w, h),
+            b_settings: Button::new(x, y + 2.0 * y_stride, w, h),
+            b_stats: Button::new(x, y + 3.0 * y_stride, w, h),
 
             b_credits: Button::new(wide_x, y + 4.0 * y_stride, wide_w, h),
 
             settings,
 
-            prev_hex_time: 0.0,
-            hexagons: Vec::new(),
+            music_clock: MusicClock::start(TITLE_BPM),
+            bg_pulse: BeatPulse::new(BG_PULSE_BEATS),
+            pulse_timer: 0,
+            demo,
+            demo_frames: 0,
+            demo_snapshot,
         }
     }
 }
 
-fn hex_radius(time: u32) -> f32 {
-    time as f32
+/// A fresh game of Classic for the CPU to play behind the title
+fn new_demo() -> CpuOpponent {
+    CpuOpponent::new(BoardSettings::classic(), CpuDifficulty::Normal)
+}
+
+/// Let the CPU play a frame's worth of its game, slowed down by `DEMO_SLOWDOWN`,
+/// and start it over when it loses. Return whether it played this frame.
+fn advance_demo(demo: &mut CpuOpponent, frames: &mut u32) -> bool {
+    *frames = frames.wrapping_add(1);
+    if *frames % DEMO_SLOWDOWN != 0 {
+        return false;
+    }
+    let failed = demo.play();
+    // Nobody's keeping track of what it clears
+    demo.board.take_clear_events();
+    demo.board.take_score_events();
+    if failed {
+        *demo = new_demo();
+    }
+    true
 }
 
 struct DontRestartMusicToken;
@@ -0,0 +1,460 @@
+use std::{any::Any, collections::HashMap};
+
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{
+        Board, BoardSettingsModeKey, CpuDifficulty, Cyclable, LeaderboardKey, MutatorSet,
+        SpecialAction,
+    },
+    modes::{draw_board_preview, start_game, ModeSandbox},
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        profile::{PlaySettings, Profile},
+        text::TextAlign,
+        theme,
+    },
+    Assets,
+};
+
+use super::{mutator_select::ModeMutatorSelect, DontRestartMusicToken};
+
+#[derive(Debug, Clone)]
+pub struct ModeModeSelect {
+    settings: PlaySettings,
+
+    /// Modifiers to start the run with
+    mutators: MutatorSet,
+    /// Start a practice run instead of a real one
+    practice: bool,
+    /// Play against a CPU of this difficulty
+    versus: Option<CpuDifficulty>,
+    /// Share the board with a second player on the keyboard
+    coop: bool,
+    /// What the charge meter does, if the run has one
+    special: Option<SpecialAction>,
+
+    b_modes: Vec<(Button, BoardSettingsModeKey)>,
+    /// A starting board for each mode with the current modifiers, to show off on hover
+    previews: Vec<Board>,
+    /// Copy of the highscores so we don't have to hit storage every frame
+    highscores: HashMap<LeaderboardKey, u32>,
+    /// The highscores have been edited outside the game
+    modified: bool,
+
+    b_sandbox: Button,
+    b_mutators: Button,
+    b_practice: Button,
+    b_special: Button,
+    b_versus: Button,
+    b_coop: Button,
+    b_back: Button,
+}
+
+impl Gamemode for ModeModeSelect {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Click) {
+            if let Some((_, key)) = self.b_modes.iter().find(|(b, _)| b.mouse_hovering()) {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                let mut board_settings = key.settings().with_mutators(self.mutators);
+                board_settings.practice = self.practice;
+                // Practice is for learning, not for racing the clock
+                board_settings.freeze_spawns_during_actions |= self.practice;
+                board_settings.versus = self.versus;
+                board_settings.coop = self.coop;
+                board_settings.special = self.special;
+                return Transition::Swap(start_game(board_settings, self.settings, assets));
+            }
+            if self.b_practice.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.practice = !self.practice;
+            }
+            if self.b_versus.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.versus = match self.versus {
+                    None => Some(CpuDifficulty::ALL[0]),
+                    Some(difficulty) if difficulty == *CpuDifficulty::ALL.last().unwrap() => None,
+                    Some(difficulty) => Some(difficulty.next()),
+                };
+            }
+            if self.b_special.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.special = match self.special {
+                    None => Some(SpecialAction::ALL[0]),
+                    Some(special) if special == *SpecialAction::ALL.last().unwrap() => None,
+                    Some(special) => Some(special.next()),
+                };
+            }
+            if self.b_coop.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.coop = !self.coop;
+            }
+            if self.b_mutators.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                return Transition::Push(Box::new(ModeMutatorSelect::new(self.mutators)));
+            }
+            if self.b_sandbox.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                return Transition::Push(Box::new(ModeSandbox::new(self.settings)));
+            }
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(DontRestartMusicToken));
+        }
+
+        let mut play_enter = false;
+        for b in self.b_modes.iter_mut().map(|(b, _)| b).chain([
+            &mut self.b_sandbox,
+            &mut self.b_mutators,
+            &mut self.b_practice,
+            &mut self.b_special,
+            &mut self.b_versus,
+            &mut self.b_coop,
+            &mut self.b_back,
+        ]) {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+
+    fn on_reveal(&mut self, data: Option<Box<dyn Any>>, _assets: &Assets) {
+        if let Some(mutators) = data.and_then(|data| data.downcast_ref::<MutatorSet>().copied()) {
+            self.mutators = mutators;
+            self.previews = previews(mutators);
+        }
+    }
+}
+
+impl GamemodeDrawer for ModeModeSelect {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let panel = SidePanel::beside(self.b_modes[0].0.bounds());
+        panel.draw_line(border);
+
+        for ((button, key), preview) in self.b_modes.iter().zip(self.previews.iter()) {
+            button.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                key.name(),
+                button.x() + button.w() / 2.0,
+                button.y() + m.px(2.0),
+                TextAlign::Center,
+                if button.mouse_hovering() {
+                    blight
+                } else {
+                    border
+                },
+                assets.textures.fonts.small,
+            );
+
+            if button.mouse_hovering() {
+                let leaderboard_key = LeaderboardKey {
+                    mode: *key,
+                    mutators: self.mutators,
+                    speed: self.settings.game_speed,
+                };
+                let hiscore = match self.highscores.get(&leaderboard_key) {
+                    Some(best) => {
+                        let ranking = key.ranking();
+                        let mark = if self.modified {
+                            "\n(MODIFIED SAVE)"
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "{}: {}{}",
+                            ranking.best_label(),
+                            ranking.format(*best),
+                            mark
+                        )
+                    }
+                    None => "NOT PLAYED YET".to_owned(),
+                };
+                draw_menu_text(
+                    &format!("{}\n\n{}", key.description(), hiscore),
+                    panel.text_x(),
+                    m.px(5.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+                // The description takes up the top half or so of the panel
+                let top = (height() / 2.0).round() + m.px(6.0);
+                let bottom = height() - m.px(16.0);
+                let area = Rect::new(
+                    panel.left + m.px(3.0),
+                    top,
+                    panel.right - panel.left - m.px(6.0),
+                    bottom - top,
+                );
+                draw_board_preview(preview, area, true, self.settings, assets);
+            }
+        }
+
+        self.b_mutators.draw(color, border, highlight, blight, 1.01);
+        let text = if self.mutators.is_empty() {
+            "MODIFIERS".to_owned()
+        } else {
+            format!("MODIFIERS ({})", self.mutators.iter().count())
+        };
+        draw_menu_text(
+            &text,
+            self.b_mutators.x() + self.b_mutators.w() / 2.0,
+            self.b_mutators.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_mutators.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+
+        self.b_sandbox.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            "SANDBOX",
+            self.b_sandbox.x() + self.b_sandbox.w() / 2.0,
+            self.b_sandbox.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_sandbox.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+        if self.b_sandbox.mouse_hovering() {
+            draw_menu_text(
+                "PLACE AND ERASE\nMARBLES YOURSELF,\nTHEN TURN ON SIM\nTO WATCH THEM FALL\nAND CLEAR.",
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_practice.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            if self.practice {
+                "PRACTICE ON"
+            } else {
+                "PRACTICE OFF"
+            },
+            self.b_practice.x() + self.b_practice.w() / 2.0,
+            self.b_practice.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_practice.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+        if self.b_practice.mouse_hovering() {
+            draw_menu_text(
+                "PRACTICE RUNS\nLET YOU HOLD R OR\nTHE REWIND BUTTON\nTO GO BACK IN TIME.\n\nTHEY DON'T COUNT\nFOR HISCORES.",
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_special.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            &match self.special {
+                Some(special) => format!("SPECIAL: {}", special.name()),
+                None => "SPECIAL: NONE".to_owned(),
+            },
+            self.b_special.x() + self.b_special.w() / 2.0,
+            self.b_special.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_special.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+        if self.b_special.mouse_hovering() {
+            let text = match self.special {
+                Some(special) => format!(
+                    "CLEARING MARBLES\nCHARGES A METER.\nWHEN IT'S FULL,\nPRESS S OR CLICK\nIT TO USE IT.\n\n{}\n\nRUNS WITH A\nSPECIAL DON'T\nCOUNT FOR HISCORES.",
+                    special.description()
+                ),
+                None => "PICK A SPECIAL\nACTION TO CHARGE\nUP BY CLEARING\nMARBLES.".to_owned(),
+            };
+            draw_menu_text(
+                &text,
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_versus.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            &match self.versus {
+                Some(difficulty) => format!("VS {}", difficulty.name()),
+                None => "VS OFF".to_owned(),
+            },
+            self.b_versus.x() + self.b_versus.w() / 2.0,
+            self.b_versus.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_versus.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+        if self.b_versus.mouse_hovering() {
+            draw_menu_text(
+                "RACE A CPU WITH\nITS OWN BOARD.\nCLEARING MARBLES\nDROPS STONES ON\nTHE OTHER BOARD.\nFILL IT UP TO WIN!\n\nVERSUS RUNS DON'T\nCOUNT FOR HISCORES.",
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_coop.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            if self.coop { "CO-OP ON" } else { "CO-OP OFF" },
+            self.b_coop.x() + self.b_coop.w() / 2.0,
+            self.b_coop.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_coop.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+        if self.b_coop.mouse_hovering() {
+            draw_menu_text(
+                "SHARE THE BOARD\nWITH A FRIEND.\nTHEY MOVE WITH\nU I H K N M AND\nHOLD SPACE TO DRAW.\n\nCO-OP RUNS DON'T\nCOUNT FOR HISCORES.",
+                panel.text_x(),
+                m.px(5.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        self.b_back.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            "RETURN",
+            self.b_back.x() + self.b_back.w() / 2.0,
+            self.b_back.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_back.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+    }
+}
+
+impl ModeModeSelect {
+    pub fn new(settings: PlaySettings) -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+        let y = m.px(5.0);
+        // Squeeze the modes together if they don't all fit above the other buttons
+        let sandbox_y = height() - 5.0 * (h + m.px(3.0));
+        let mutators_y = height() - 4.0 * (h + m.px(3.0));
+        let y_stride = ((sandbox_y - m.px(2.0) - y) / BoardSettingsModeKey::ALL.len() as f32)
+            .floor()
+            .min(h + m.px(2.0));
+
+        let b_modes = BoardSettingsModeKey::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| (Button::new(x, y + y_stride * idx as f32, w, h), *key))
+            .collect();
+
+        let (highscores, modified) = {
+            let profile = Profile::get();
+            (profile.highscores.clone(), profile.modified)
+        };
+
+        Self {
+            settings,
+            mutators: MutatorSet::empty(),
+            practice: false,
+            versus: None,
+            coop: false,
+            special: None,
+            b_modes,
+            previews: previews(MutatorSet::empty()),
+            highscores,
+            modified,
+            b_sandbox: Button::new(x, sandbox_y, w, h),
+            b_mutators: Button::new(x, mutators_y, w, h),
+            b_practice: Button::new(x, height() - 3.0 * (h + m.px(3.0)), w, h),
+            b_special: Button::new(x, height() - 2.0 * (h + m.px(3.0)), w, h),
+            b_versus: Button::new(
+                m.px(3.0 + 4.0 * 12.0 + 3.0),
+                height() - h - m.px(3.0),
+                m.px(4.0 * 12.0),
+                h,
+            ),
+            b_coop: Button::new(
+                m.px(3.0 + 2.0 * (4.0 * 12.0 + 3.0)),
+                height() - h - m.px(3.0),
+                m.px(4.0 * 12.0),
+                h,
+            ),
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        }
+    }
+}
+
+/// Roll a starting board for every mode, in the same order as the buttons.
+fn previews(mutators: MutatorSet) -> Vec<Board> {
+    BoardSettingsModeKey::ALL
+        .iter()
+        .map(|key| Board::new(key.settings().with_mutators(mutators)))
+        .collect()
+}
@@ -0,0 +1,154 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{Mutator, MutatorSet},
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        text::TextAlign,
+        theme,
+    },
+    Assets,
+};
+
+/// Toggle modifiers on and off. Pops with the selected `MutatorSet`.
+#[derive(Debug, Clone)]
+pub struct ModeMutatorSelect {
+    mutators: MutatorSet,
+
+    b_mutators: Vec<(Button, Mutator)>,
+    b_back: Button,
+}
+
+impl Gamemode for ModeMutatorSelect {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Click) {
+            if let Some((_, mutator)) = self.b_mutators.iter().find(|(b, _)| b.mouse_hovering()) {
+                self.mutators.toggle(*mutator);
+                assets.play_sfx(SfxEvent::ButtonClick);
+            }
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(self.mutators));
+        }
+
+        let mut play_enter = false;
+        for b in self
+            .b_mutators
+            .iter_mut()
+            .map(|(b, _)| b)
+            .chain(std::iter::once(&mut self.b_back))
+        {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeMutatorSelect {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let panel = SidePanel::beside(self.b_mutators[0].0.bounds());
+        panel.draw_line(border);
+
+        for (button, mutator) in self.b_mutators.iter() {
+            let on = self.mutators.contains(*mutator);
+            button.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                &format!("{} {}", mutator.name(), if on { "ON" } else { "OFF" }),
+                button.x() + button.w() / 2.0,
+                button.y() + m.px(2.0),
+                TextAlign::Center,
+                if button.mouse_hovering() {
+                    blight
+                } else {
+                    border
+                },
+                assets.textures.fonts.small,
+            );
+
+            if button.mouse_hovering() {
+                draw_menu_text(
+                    &format!(
+                        "{}\n\nCURRENTLY {}\n\nMODIFIED RUNS\nHAVE THEIR OWN\nHISCORES.",
+                        mutator.description(),
+                        if on { "ON" } else { "OFF" }
+                    ),
+                    panel.text_x(),
+                    m.px(5.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+            }
+        }
+
+        self.b_back.draw(color, border, highlight, blight, 1.01);
+        draw_menu_text(
+            "RETURN",
+            self.b_back.x() + self.b_back.w() / 2.0,
+            self.b_back.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.b_back.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+    }
+}
+
+impl ModeMutatorSelect {
+    pub fn new(mutators: MutatorSet) -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 17.0);
+        let h = m.px(9.0);
+        let y_stride = h + m.px(2.0);
+        let y = m.px(5.0);
+
+        let b_mutators = Mutator::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, mutator)| (Button::new(x, y + y_stride * idx as f32, w, h), *mutator))
+            .collect();
+
+        Self {
+            mutators,
+            b_mutators,
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        }
+    }
+}
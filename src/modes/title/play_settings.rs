@@ -1,30 +1,130 @@
+use std::any::Any;
+
 use cogs_gamedev::controls::InputHandler;
-use macroquad::{
-    audio::{play_sound_once},
-    prelude::*,
-};
+use macroquad::prelude::*;
 
 use crate::{
+    assets::SfxEvent,
+    audio::{set_music_volume, set_sfx_volume, AudioBackend},
     boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
-    model::PlaySettings,
+    height,
+    model::TICKS_PER_SECOND,
+    modes::{draw_background_hexes, draw_board_preview, CpuOpponent},
     utils::{
         button::Button,
-        draw::hexcolor,
-        profile::Profile,
-        text::{draw_pixel_text, TextAlign},
+        draw::set_crt_filter,
+        effects::heavy_effects,
+        layout::{draw_menu_text, set_mirrored, MenuMetrics, SidePanel},
+        profile::{PlaySettings, Profile},
+        text::TextAlign,
+        theme,
     },
-    Assets, HEIGHT,
+    Assets,
 };
 
+use super::{
+    advance_demo,
+    manage_profile::ModeManageProfile,
+    new_demo,
+    settings_list::{SettingsTab, SETTINGS},
+};
+
+/// Settings the preview pane shows off, by key
+const PREVIEWED: [&str; 4] = ["funni_background", "animations", "skin", "gravity_arrows"];
+
+/// The settings screen, with a tab for each `SettingsTab` and a button for each
+/// setting in `SETTINGS` on it.
 #[derive(Debug, Clone)]
 pub struct ModePlaySettings {
     settings: PlaySettings,
+    tab: SettingsTab,
 
-    b_background: Button,
-    b_animation: Button,
-
+    b_tabs: Vec<Button>,
+    /// The buttons on the current tab, with the index into `SETTINGS` each one is for
+    b_settings: Vec<(usize, Button)>,
+    b_manage: Button,
     b_back: Button,
+
+    /// The CPU playing a game in the preview pane, drawn with the settings as they are now
+    demo: CpuOpponent,
+    demo_frames: u32,
+}
+
+impl ModePlaySettings {
+    pub fn new(start_settings: PlaySettings) -> Self {
+        let m = MenuMetrics::current();
+        let h = m.px(9.0);
+        let tab_w = m.px(36.0);
+        let tab_stride = tab_w + m.px(2.0);
+        let back_w = m.px(4.0 * 12.0);
+        let b_back = Button::new(m.px(3.0), height() - h - m.px(3.0), back_w, h);
+        let b_manage = Button::new(
+            m.px(3.0) + back_w + m.px(2.0),
+            height() - h - m.px(3.0),
+            back_w,
+            h,
+        );
+
+        let mut out = Self {
+            settings: start_settings,
+            tab: SettingsTab::Gameplay,
+
+            b_tabs: (0..SettingsTab::ALL.len())
+                .map(|idx| Button::new(m.px(5.0) + tab_stride * idx as f32, m.px(3.0), tab_w, h))
+                .collect(),
+            b_settings: Vec::new(),
+            b_manage,
+            b_back,
+
+            demo: new_demo(),
+            demo_frames: 0,
+        };
+        out.open_tab(SettingsTab::Gameplay);
+        out
+    }
+
+    /// Lay out the buttons for everything on this tab.
+    fn open_tab(&mut self, tab: SettingsTab) {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+        let y_stride = h + m.px(2.0);
+        let y = m.px(3.0) + h + m.px(5.0);
+
+        self.tab = tab;
+        self.b_settings = SETTINGS
+            .iter()
+            .enumerate()
+            .filter(|(_, setting)| setting.tab == tab)
+            .enumerate()
+            .map(|(row, (idx, _))| (idx, Button::new(x, y + y_stride * row as f32, w, h)))
+            .collect();
+    }
+
+    /// Draw the CPU's game in a little box at the bottom of the panel,
+    /// so it's clear what the settings do.
+    fn draw_preview(&self, panel: &SidePanel, assets: &Assets) {
+        let m = MenuMetrics::current();
+        let w = (panel.right - panel.left - m.px(6.0)).min(m.px(56.0));
+        let area = Rect::new(panel.text_x(), height() - m.px(3.0) - w, w, w);
+
+        draw_rectangle(area.x, area.y, area.w, area.h, theme::bg());
+        if self.settings.funni_background && heavy_effects() {
+            let phase = self.demo_frames as f32 / TICKS_PER_SECOND as f32;
+            let center = vec2(area.x + area.w / 2.0, area.y + area.h / 2.0);
+            draw_background_hexes(center, area.w / 2.0, phase);
+        }
+        draw_board_preview(
+            &self.demo.board,
+            area,
+            self.settings.gravity_arrows,
+            self.settings,
+            assets,
+        );
+        draw_rectangle_lines(area.x, area.y, area.w, area.h, 1.01, theme::border());
+    }
 }
 
 impl Gamemode for ModePlaySettings {
@@ -35,40 +135,60 @@ impl Gamemode for ModePlaySettings {
         assets: &Assets,
     ) -> Transition {
         if controls.clicked_down(Control::Click) {
-            let mut sound = Some(assets.sounds.close_loop);
-            if self.b_background.mouse_hovering() {
-                self.settings.funni_background = !self.settings.funni_background;
-            } else if self.b_animation.mouse_hovering() {
-                self.settings.animations = !self.settings.animations;
-            } else if self.b_back.mouse_hovering() {
-                sound = Some(assets.sounds.shunt);
-            } else {
-                sound = None;
-            }
-            if let Some(sound) = sound {
-                play_sound_once(sound);
-            }
-
-            if self.b_back.mouse_hovering() {
-                let mut profile = Profile::get();
-                profile.settings = self.settings;
-                return Transition::PopWith(Box::new(self.settings) as _);
+            if let Some(idx) = self.b_tabs.iter().position(Button::mouse_hovering) {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.open_tab(SettingsTab::ALL[idx]);
+            } else if let Some((idx, _)) = self.b_settings.iter().find(|(_, b)| b.mouse_hovering())
+            {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                let setting = &SETTINGS[*idx];
+                setting.advance(&mut self.settings);
+                info!(
+                    "Setting {} to {}",
+                    setting.key,
+                    setting.value(&self.settings)
+                );
+                // Save it right away, so it sticks even if the game gets closed from here
+                let settings = self.settings;
+                Profile::modify(|profile| profile.settings = settings);
+                // These don't move anything around so it's safe to show them right away
+                set_crt_filter(self.settings.crt_filter);
+                self.settings.effects.apply();
+                set_music_volume(self.settings.music_volume);
+                set_sfx_volume(self.settings.sfx_volume);
+                // Nothing's ducked out here; this just turns the playing music to match
+                assets.audio.duck_music(1.0);
+            } else if self.b_manage.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                return Transition::Push(Box::new(ModeManageProfile::new()));
             }
         }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            self.settings.resolution.apply();
+            set_mirrored(self.settings.mirrored_ui);
+            self.settings.ui_scale.apply();
+            return Transition::PopWith(Box::new(self.settings) as _);
+        }
+
+        advance_demo(&mut self.demo, &mut self.demo_frames);
 
         let mut play_enter = false;
-        for b in [
-            &mut self.b_background,
-            &mut self.b_animation,
-            &mut self.b_back,
-        ] {
+        for b in self
+            .b_tabs
+            .iter_mut()
+            .chain(self.b_settings.iter_mut().map(|(_, b)| b))
+            .chain([&mut self.b_manage, &mut self.b_back])
+        {
             if b.mouse_entered() {
                 play_enter = true;
             }
             b.post_update();
         }
         if play_enter {
-            play_sound_once(assets.sounds.select);
+            assets.play_sfx(SfxEvent::ButtonHover);
         }
 
         Transition::None
@@ -77,125 +197,100 @@ impl Gamemode for ModePlaySettings {
     fn get_draw_info(&mut self) -> DrawerBox {
         Box::new(self.clone())
     }
+
+    fn on_reveal(&mut self, _data: Option<Box<dyn Any>>, assets: &Assets) {
+        // Coming back from the data screen, which might have reset our settings
+        self.settings = Profile::get().settings;
+        set_crt_filter(self.settings.crt_filter);
+        self.settings.effects.apply();
+        set_music_volume(self.settings.music_volume);
+        set_sfx_volume(self.settings.sfx_volume);
+        assets.audio.duck_music(1.0);
+    }
 }
 
 impl GamemodeDrawer for ModePlaySettings {
     fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
-        clear_background(hexcolor(0x14182e_ff));
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
 
-        let color = hexcolor(0x4b1d52_ff);
-        let highlight = hexcolor(0x692464_ff);
-        let border = hexcolor(0xcc2f7b_ff);
-        let blight = hexcolor(0xff5277_ff);
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
 
-        let line_x = self.b_animation.bounds().right() + 5.0;
-        draw_line(line_x, 0.0, line_x, HEIGHT, 1.0, border);
+        // The line starts under the tabs
+        let panel = SidePanel::beside(self.b_settings[0].1.bounds());
+        let top = self.b_tabs[0].y() + self.b_tabs[0].h() + m.px(2.0);
+        draw_line(panel.line_x, top, panel.line_x, height(), 1.0, border);
 
-        let msg = if self.b_background.mouse_hovering() {
-            Some(format!(
-                "ENABLE/DISABLE\nBACKGROUND EFFECTS\n\nCURRENTLY {}",
-                if self.settings.funni_background {
-                    "ON"
-                } else {
-                    "OFF"
-                }
-            ))
-        } else if self.b_animation.mouse_hovering() {
-            Some(format!("IF ON, MARBLES MOVE\nSMOOTHLY WHEN \nDRAGGED.\nIF OFF, MARBLES JUMP\nTO THEIR\nTARGET POSITIONS.\n\nCURRENTLY {}", if self.settings.animations {
-                "ON"
+        for (tab, b) in SettingsTab::ALL.iter().zip(self.b_tabs.iter()) {
+            if *tab == self.tab {
+                // The open tab always looks hovered
+                b.draw(highlight, blight, highlight, blight, 1.01);
             } else {
-                "OFF"
-            }))
-        } else {
-            None
-        };
-        if let Some(msg) = msg {
-            draw_pixel_text(
-                &msg,
-                line_x + 3.0,
-                5.0,
-                TextAlign::Left,
-                border,
+                b.draw(color, border, highlight, blight, 1.01);
+            }
+            draw_menu_text(
+                tab.name(),
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                if *tab == self.tab {
+                    blight
+                } else {
+                    b.text_color(border, blight)
+                },
                 assets.textures.fonts.small,
             );
         }
 
-        self.b_background
-            .draw(color, border, highlight, blight, 1.01);
-        let text = format!(
-            "BACKGROUND {}",
-            if self.settings.funni_background {
-                "ON"
-            } else {
-                "OFF"
-            }
-        );
-        draw_pixel_text(
-            &text,
-            self.b_background.x() + self.b_background.w() / 2.0,
-            self.b_background.y() + 2.0,
-            TextAlign::Center,
-            if self.b_background.mouse_hovering() {
-                blight
-            } else {
-                border
-            },
-            assets.textures.fonts.small,
-        );
-
-        self.b_animation
-            .draw(color, border, highlight, blight, 1.01);
-        let text = format!(
-            "ANIMATIONS {}",
-            if self.settings.animations {
-                "ON"
-            } else {
-                "OFF"
+        for (idx, b) in self.b_settings.iter() {
+            let setting = &SETTINGS[*idx];
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                &setting.button_text(&self.settings),
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                b.text_color(border, blight),
+                assets.textures.fonts.small,
+            );
+            if b.mouse_hovering() {
+                draw_menu_text(
+                    &setting.panel_text(&self.settings),
+                    panel.text_x(),
+                    top + m.px(3.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+                if PREVIEWED.contains(&setting.key) {
+                    self.draw_preview(&panel, assets);
+                }
             }
-        );
-        draw_pixel_text(
-            &text,
-            self.b_animation.x() + self.b_animation.w() / 2.0,
-            self.b_animation.y() + 2.0,
-            TextAlign::Center,
-            if self.b_animation.mouse_hovering() {
-                blight
-            } else {
-                border
-            },
-            assets.textures.fonts.small,
-        );
-
-        self.b_back.draw(color, border, highlight, blight, 1.01);
-        draw_pixel_text(
-            "RETURN",
-            self.b_back.x() + self.b_back.w() / 2.0,
-            self.b_back.y() + 2.0,
-            TextAlign::Center,
-            if self.b_back.mouse_hovering() {
-                blight
-            } else {
-                border
-            },
-            assets.textures.fonts.small,
-        );
-    }
-}
-
-impl ModePlaySettings {
-    pub fn new(start_settings: PlaySettings) -> Self {
-        let x = 5.0;
-        let w = 4.0 * 15.0;
-        let h = 9.0;
-        let y_stride = h + 2.0;
-        let y = 5.0;
-
-        Self {
-            settings: start_settings,
+        }
 
-            b_background: Button::new(x, y, w, h),
-            b_animation: Button::new(x, y + y_stride, w, h),
-            b_back: Button::new(3.0, HEIGHT - h - 3.0, 4.0 * 12.0, h),
+        for (b, text) in [(&self.b_manage, "DATA"), (&self.b_back, "RETURN")] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                b.text_color(border, blight),
+                assets.textures.fonts.small,
+            );
+        }
+        if self.b_manage.mouse_hovering() {
+            draw_menu_text(
+                "SEE AND RESET\nYOUR HISCORES\nAND STATS.",
+                panel.text_x(),
+                top + m.px(3.0),
+                TextAlign::Left,
+                border,
+                assets.textures.fonts.small,
+            );
         }
     }
 }
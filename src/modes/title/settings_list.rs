@@ -0,0 +1,323 @@
+use crate::{model::Cyclable, utils::profile::PlaySettings};
+
+/// How long, in seconds, the game can sit without input before pausing itself
+const AUTO_PAUSE_OPTIONS: [Option<u32>; 4] = [None, Some(30), Some(60), Some(120)];
+/// Keys of the settings that can also be changed from the pause menu
+const QUICK_SETTINGS: [&str; 4] = [
+    "music_volume",
+    "sfx_volume",
+    "funni_background",
+    "animations",
+];
+
+/// Which tab of the settings screen a setting shows up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsTab {
+    Gameplay,
+    Display,
+    Audio,
+    Controls,
+}
+
+impl SettingsTab {
+    pub const ALL: [SettingsTab; 4] = [
+        SettingsTab::Gameplay,
+        SettingsTab::Display,
+        SettingsTab::Audio,
+        SettingsTab::Controls,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingsTab::Gameplay => "GAMEPLAY",
+            SettingsTab::Display => "DISPLAY",
+            SettingsTab::Audio => "AUDIO",
+            SettingsTab::Controls => "CONTROLS",
+        }
+    }
+}
+
+/// One thing the player can change on the settings screen.
+pub struct Setting {
+    pub tab: SettingsTab,
+    /// Name of the field in `PlaySettings`, which is also what it's saved as in the profile
+    pub key: &'static str,
+    /// What the button says, before the current value
+    pub label: &'static str,
+    /// What the side panel says when the button is hovered
+    pub help: &'static str,
+    pub kind: SettingKind,
+}
+
+/// What clicking a setting's button does.
+pub enum SettingKind {
+    /// Flip a `bool` on and off, calling the two states these names
+    Toggle {
+        field: fn(&mut PlaySettings) -> &mut bool,
+        on: &'static str,
+        off: &'static str,
+    },
+    /// Step through a list of choices, wrapping around at the end
+    Cycle {
+        value: fn(&PlaySettings) -> String,
+        next: fn(&mut PlaySettings),
+        /// More to say about the current choice, after the help
+        details: Option<fn(&PlaySettings) -> &'static str>,
+    },
+}
+
+impl Setting {
+    /// The name of the current value, like `ON` or `160X144`
+    pub fn value(&self, settings: &PlaySettings) -> String {
+        match &self.kind {
+            SettingKind::Toggle { field, on, off } => {
+                let mut settings = *settings;
+                if *field(&mut settings) { on } else { off }.to_string()
+            }
+            SettingKind::Cycle { value, .. } => value(settings),
+        }
+    }
+
+    /// Change the setting to the next value.
+    pub fn advance(&self, settings: &mut PlaySettings) {
+        match &self.kind {
+            SettingKind::Toggle { field, .. } => {
+                let on = field(settings);
+                *on = !*on;
+            }
+            SettingKind::Cycle { next, .. } => next(settings),
+        }
+    }
+
+    /// What the button says
+    pub fn button_text(&self, settings: &PlaySettings) -> String {
+        format!("{} {}", self.label, self.value(settings))
+    }
+
+    /// What the side panel says about it
+    pub fn panel_text(&self, settings: &PlaySettings) -> String {
+        let details = match &self.kind {
+            SettingKind::Cycle {
+                details: Some(details),
+                ..
+            } => format!("\n\n{}", details(settings)),
+            _ => String::new(),
+        };
+        format!(
+            "{}{}\n\nCURRENTLY {}",
+            self.help,
+            details,
+            self.value(settings)
+        )
+    }
+}
+
+/// The settings that can be changed from the pause menu without leaving the run.
+pub fn quick_settings() -> impl Iterator<Item = &'static Setting> {
+    QUICK_SETTINGS
+        .iter()
+        .filter_map(|key| SETTINGS.iter().find(|setting| setting.key == *key))
+}
+
+/// A setting that flips one of the `bool`s in `PlaySettings`
+macro_rules! toggle {
+    ($tab:ident, $field:ident, $label:literal, $help:literal) => {
+        toggle!($tab, $field, $label, $help, "ON", "OFF")
+    };
+    ($tab:ident, $field:ident, $label:literal, $help:literal, $on:literal, $off:literal) => {
+        Setting {
+            tab: SettingsTab::$tab,
+            key: stringify!($field),
+            label: $label,
+            help: $help,
+            kind: SettingKind::Toggle {
+                field: |s| &mut s.$field,
+                on: $on,
+                off: $off,
+            },
+        }
+    };
+}
+
+/// A setting that steps through one of the `Cyclable` enums in `PlaySettings`,
+/// optionally with a method on it saying more about the current choice
+macro_rules! cycle {
+    ($tab:ident, $field:ident, $label:literal, $help:literal) => {
+        cycle!(@details $tab, $field, $label, $help, None)
+    };
+    ($tab:ident, $field:ident, $label:literal, $help:literal, $details:ident) => {
+        cycle!(@details $tab, $field, $label, $help, Some(|s| s.$field.$details()))
+    };
+    (@details $tab:ident, $field:ident, $label:literal, $help:literal, $details:expr) => {
+        Setting {
+            tab: SettingsTab::$tab,
+            key: stringify!($field),
+            label: $label,
+            help: $help,
+            kind: SettingKind::Cycle {
+                value: |s| Cyclable::name(&s.$field).to_owned(),
+                next: |s| s.$field = Cyclable::next(&s.$field),
+                details: $details,
+            },
+        }
+    };
+}
+
+/// Everything on the settings screen, in the order it's shown.
+pub const SETTINGS: &[Setting] = &[
+    toggle!(
+        Gameplay,
+        speedrun_timer,
+        "TIMER",
+        "SHOW A RUN TIMER\nWITH SPLITS AT\n1K, 5K, AND 10K\nPOINTS."
+    ),
+    toggle!(
+        Gameplay,
+        cascade_preview,
+        "PREVIEW",
+        "WHEN YOU CLOSE A\nLOOP, HIGHLIGHT THE\nMARBLES IT WOULD\nCLEAR BEFORE YOU\nLET GO."
+    ),
+    toggle!(
+        Gameplay,
+        tips,
+        "TIPS",
+        "POP UP HINTS ABOUT\nHOW TO PLAY DURING\nYOUR FIRST FEW\nRUNS. CLICK ONE TO\nMAKE IT GO AWAY."
+    ),
+    cycle!(
+        Gameplay,
+        game_speed,
+        "SPEED",
+        "SLOW THE GAME DOWN\nTO GIVE YOURSELF\nMORE TIME TO THINK.\n\nSLOWER RUNS HAVE\nTHEIR OWN HISCORES."
+    ),
+    toggle!(
+        Gameplay,
+        fair_spawns,
+        "SPAWNS",
+        "SPREAD NEW MARBLES\nOUT EVENLY INSTEAD\nOF LETTING THEM\nDRIFT TO ONE SIDE\nIN LONG RUNS.",
+        "EVEN",
+        "CLASSIC"
+    ),
+    cycle!(
+        Gameplay,
+        game_feel,
+        "FEEL",
+        "HOW LONG MOVES AND\nCLEARS TAKE TO PLAY\nOUT. SCORES AND\nHISCORES DON'T\nCHANGE."
+    ),
+    Setting {
+        tab: SettingsTab::Gameplay,
+        key: "auto_pause",
+        label: "IDLE",
+        help: "PAUSE THE GAME IF\nYOU DON'T TOUCH\nANYTHING FOR A\nWHILE.",
+        kind: SettingKind::Cycle {
+            value: |s| match s.auto_pause {
+                Some(secs) => format!("{}S", secs),
+                None => "OFF".to_owned(),
+            },
+            next: |s| {
+                let idx = AUTO_PAUSE_OPTIONS
+                    .iter()
+                    .position(|o| *o == s.auto_pause)
+                    .unwrap_or(0);
+                s.auto_pause = AUTO_PAUSE_OPTIONS[(idx + 1) % AUTO_PAUSE_OPTIONS.len()];
+            },
+            details: None,
+        },
+    },
+    toggle!(
+        Display,
+        funni_background,
+        "BACKGROUND",
+        "ENABLE/DISABLE\nBACKGROUND EFFECTS"
+    ),
+    toggle!(
+        Display,
+        animations,
+        "ANIMATIONS",
+        "IF ON, MARBLES MOVE\nSMOOTHLY WHEN \nDRAGGED.\nIF OFF, MARBLES JUMP\nTO THEIR\nTARGET POSITIONS."
+    ),
+    cycle!(
+        Display,
+        resolution,
+        "RES",
+        "SIZE OF THE SCREEN\nIN PIXELS. BIGGER\nSIZES FIT MORE\nONTO THE SCREEN.\n\nTAKES EFFECT WHEN\nYOU RETURN."
+    ),
+    cycle!(
+        Display,
+        skin,
+        "SKIN",
+        "WHAT THE MARBLES\nLOOK LIKE.",
+        description
+    ),
+    toggle!(
+        Display,
+        crt_filter,
+        "CRT",
+        "MAKE THE SCREEN\nLOOK LIKE AN OLD TV,\nWITH SCANLINES."
+    ),
+    cycle!(
+        Display,
+        effects,
+        "FX",
+        "HOW FANCY THE\nSHADERS AND\nBACKGROUNDS ARE.\nTURN IT DOWN IF THE\nGAME IS SLOW.",
+        description
+    ),
+    toggle!(
+        Display,
+        mirrored_ui,
+        "MIRROR",
+        "FLIP MENUS AND THE\nHUD LEFT TO RIGHT,\nFOR LEFT HANDS.\n\nTAKES EFFECT WHEN\nYOU RETURN."
+    ),
+    cycle!(
+        Display,
+        ui_scale,
+        "SIZE",
+        "MAKE TEXT AND\nBUTTONS BIGGER ON\nMENUS. NEEDS THE\n320X288 RESOLUTION\nTO HAVE ROOM.\n\nTAKES EFFECT WHEN\nYOU RETURN."
+    ),
+    toggle!(
+        Display,
+        high_vis_cursor,
+        "CURSOR",
+        "DRAW A CROSSHAIR\nAT THE MOUSE AND A\nTHICKER LINE WHEN\nDRAWING PATTERNS.",
+        "BIG",
+        "NORMAL"
+    ),
+    toggle!(
+        Display,
+        gravity_arrows,
+        "ARROWS",
+        "SHOW WHICH WAY\nMARBLES WILL FALL\nON EMPTY SPACES."
+    ),
+    cycle!(Audio, music_volume, "MUSIC", "HOW LOUD THE MUSIC\nIS."),
+    cycle!(
+        Audio,
+        sfx_volume,
+        "SFX",
+        "HOW LOUD SOUND\nEFFECTS AND COLOR\nTONES ARE."
+    ),
+    toggle!(
+        Audio,
+        color_tones,
+        "TONES",
+        "PLAY A DIFFERENT\nNOTE FOR EACH COLOR\nWHEN YOU DRAG OVER\nIT OR CLEAR IT."
+    ),
+    toggle!(
+        Controls,
+        sticky_drag,
+        "STICKY",
+        "CLICK ONCE TO START\nA PATTERN AND AGAIN\nTO FINISH IT, SO\nYOU DON'T HAVE TO\nHOLD THE BUTTON."
+    ),
+    cycle!(
+        Controls,
+        snap,
+        "SNAP",
+        "LET PATTERNS REACH\nA CELL WHEN YOU\nBRUSH PAST IT, NOT\nJUST WHEN YOU'RE\nRIGHT ON IT."
+    ),
+    toggle!(
+        Controls,
+        confirm_hexagons,
+        "HEX",
+        "MAKE HEXAGONS WAIT\nFOR ANOTHER CLICK\nBEFORE CLEARING,\nSO THEY CAN'T GO\nOFF BY ACCIDENT.",
+        "CONFIRM",
+        "INSTANT"
+    ),
+];
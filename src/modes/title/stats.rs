@@ -0,0 +1,469 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{format_ticks, BoardSettingsModeKey, Marble, Ranking},
+    modes::MARBLE_SIZE,
+    utils::{
+        button::Button,
+        draw::{draw_line_chart, draw_mini_board, marble_color, mouse_position_pixel},
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        profile::{ClearStats, Profile, RunCurve, RUN_SAMPLE_INTERVAL},
+        skin::MarbleSkin,
+        text::TextAlign,
+        theme,
+    },
+    Assets,
+};
+
+use super::DontRestartMusicToken;
+
+/// Room between each bar in the chart
+const BAR_GAP: f32 = 2.0;
+/// Tallest a bar can get
+const BAR_HEIGHT: f32 = 40.0;
+
+/// Bar chart of what's been cleared and heatmap of where,
+/// or a chart of how the score went in the latest runs.
+#[derive(Debug, Clone)]
+pub struct ModeStats {
+    /// Copy of the stats so we don't have to hit storage every frame
+    lifetime: Vec<(BoardSettingsModeKey, ClearStats)>,
+    last_run: Option<(BoardSettingsModeKey, ClearStats)>,
+    /// Score curves of the latest runs in each mode, oldest first
+    history: Vec<(BoardSettingsModeKey, Vec<RunCurve>)>,
+    skin: MarbleSkin,
+
+    selected: BoardSettingsModeKey,
+    /// Show the last run instead of all time
+    showing_last_run: bool,
+    /// Show the score chart instead of what's been cleared
+    showing_history: bool,
+
+    b_modes: Vec<(Button, BoardSettingsModeKey)>,
+    b_last_run: Button,
+    b_history: Button,
+    b_back: Button,
+}
+
+impl ModeStats {
+    pub fn new() -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+        let y = m.px(5.0);
+        // Squeeze the modes together if they don't all fit above the other buttons
+        let last_run_y = height() - 2.0 * (h + m.px(3.0));
+        let y_stride = ((last_run_y - m.px(2.0) - y) / BoardSettingsModeKey::ALL.len() as f32)
+            .floor()
+            .min(h + m.px(2.0));
+
+        let b_modes = BoardSettingsModeKey::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| (Button::new(x, y + y_stride * idx as f32, w, h), *key))
+            .collect();
+
+        let profile = Profile::get();
+        Self {
+            lifetime: profile
+                .clears
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            last_run: profile.last_run_clears.clone(),
+            history: profile
+                .run_history
+                .iter()
+                .map(|(k, v)| (*k, v.iter().cloned().collect()))
+                .collect(),
+            skin: profile.settings.skin,
+
+            selected: BoardSettingsModeKey::Classic,
+            showing_last_run: false,
+            showing_history: false,
+
+            b_modes,
+            b_last_run: Button::new(x, last_run_y, w, h),
+            b_history: Button::new(
+                m.px(3.0 + 4.0 * 12.0 + 3.0),
+                height() - h - m.px(3.0),
+                m.px(4.0 * 12.0),
+                h,
+            ),
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        }
+    }
+
+    /// The stats to show right now, if there are any
+    fn shown(&self) -> Option<&ClearStats> {
+        if self.showing_last_run {
+            self.last_run.as_ref().map(|(_, stats)| stats)
+        } else {
+            self.lifetime
+                .iter()
+                .find(|(mode, _)| *mode == self.selected)
+                .map(|(_, stats)| stats)
+        }
+    }
+
+    /// Chart the score over time for the latest runs in the selected mode.
+    fn draw_history(&self, panel: &SidePanel, assets: &Assets) {
+        let m = MenuMetrics::current();
+        let border = theme::border();
+
+        let runs = self
+            .history
+            .iter()
+            .find(|(mode, _)| *mode == self.selected)
+            .map_or(&[][..], |(_, runs)| &runs[..]);
+        let last = match runs.last() {
+            Some(it) => it,
+            None => {
+                draw_menu_text(
+                    &format!("{}\n\nNO RUNS YET", self.selected.name()),
+                    panel.text_x(),
+                    m.px(5.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+                return;
+            }
+        };
+
+        let final_score = |run: &RunCurve| run.samples.last().copied().unwrap_or(0);
+        let best = runs.iter().map(final_score).max().unwrap_or(0);
+        draw_menu_text(
+            &format!(
+                "{}\nLAST {} RUNS\n\nLATEST {}\nBEST {}",
+                self.selected.name(),
+                runs.len(),
+                Ranking::Score.format(final_score(last)),
+                Ranking::Score.format(best),
+            ),
+            panel.text_x(),
+            m.px(5.0),
+            TextAlign::Left,
+            border,
+            assets.textures.fonts.small,
+        );
+
+        let top = m.px(5.0 + 4.0 * 8.0);
+        let bottom = height() - m.px(16.0);
+        let area = Rect::new(
+            panel.text_x(),
+            top,
+            panel.right - panel.text_x() - m.px(3.0),
+            bottom - top,
+        );
+        // Axes along the left and bottom
+        draw_line(
+            area.x,
+            area.y,
+            area.x,
+            area.y + area.h + 1.0,
+            1.0,
+            theme::dim(),
+        );
+        draw_line(
+            area.x,
+            area.y + area.h + 0.5,
+            area.x + area.w,
+            area.y + area.h + 0.5,
+            1.0,
+            theme::dim(),
+        );
+        let series = runs.iter().map(|run| &run.samples[..]).collect::<Vec<_>>();
+        draw_line_chart(area, &series, |idx| {
+            if idx + 1 == runs.len() {
+                theme::accent()
+            } else {
+                // Older runs fade into the background
+                let dark = theme::shadow();
+                let t = (idx + 1) as f32 / runs.len() as f32;
+                Color::new(
+                    dark.r + (border.r - dark.r) * t,
+                    dark.g + (border.g - dark.g) * t,
+                    dark.b + (border.b - dark.b) * t,
+                    1.0,
+                )
+            }
+        });
+
+        let longest = runs.iter().map(|run| run.samples.len()).max().unwrap_or(0);
+        draw_menu_text(
+            &format_ticks(longest as u32 * RUN_SAMPLE_INTERVAL),
+            area.x + area.w,
+            area.y + area.h + m.px(2.0),
+            TextAlign::Right,
+            theme::dim(),
+            assets.textures.fonts.small,
+        );
+    }
+
+    /// Where the bar for each marble goes, bottom-left corner
+    fn bar_corner(&self, idx: usize) -> Vec2 {
+        let panel = SidePanel::beside(self.b_modes[0].0.bounds());
+        vec2(
+            panel.left + 4.0 + idx as f32 * (MARBLE_SIZE + BAR_GAP),
+            19.0 + BAR_HEIGHT,
+        )
+    }
+}
+
+impl Gamemode for ModeStats {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Click) {
+            if let Some((_, key)) = self.b_modes.iter().find(|(b, _)| b.mouse_hovering()) {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.selected = *key;
+                self.showing_last_run = false;
+            }
+            if self.b_last_run.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.showing_last_run = !self.showing_last_run;
+                self.showing_history = false;
+                if let (true, Some((mode, _))) = (self.showing_last_run, &self.last_run) {
+                    self.selected = *mode;
+                }
+            }
+            if self.b_history.mouse_hovering() {
+                assets.play_sfx(SfxEvent::ButtonClick);
+                self.showing_history = !self.showing_history;
+                self.showing_last_run = false;
+            }
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(DontRestartMusicToken));
+        }
+
+        let mut play_enter = false;
+        for b in self.b_modes.iter_mut().map(|(b, _)| b).chain([
+            &mut self.b_last_run,
+            &mut self.b_history,
+            &mut self.b_back,
+        ]) {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeStats {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+        let dark = theme::shadow();
+
+        let panel = SidePanel::beside(self.b_modes[0].0.bounds());
+        panel.draw_line(border);
+
+        for (button, key) in self.b_modes.iter() {
+            button.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                key.name(),
+                button.x() + button.w() / 2.0,
+                button.y() + m.px(2.0),
+                TextAlign::Center,
+                if button.mouse_hovering() || *key == self.selected {
+                    blight
+                } else {
+                    border
+                },
+                assets.textures.fonts.small,
+            );
+        }
+
+        for (b, text) in [
+            (
+                &self.b_last_run,
+                if self.showing_last_run {
+                    "LAST RUN"
+                } else {
+                    "ALL TIME"
+                },
+            ),
+            (&self.b_back, "RETURN"),
+        ] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                if b.mouse_hovering() { blight } else { border },
+                assets.textures.fonts.small,
+            );
+        }
+        if self.showing_history {
+            // It's on, so it always looks hovered
+            self.b_history
+                .draw(highlight, blight, highlight, blight, 1.01);
+        } else {
+            self.b_history.draw(color, border, highlight, blight, 1.01);
+        }
+        draw_menu_text(
+            "HISTORY",
+            self.b_history.x() + self.b_history.w() / 2.0,
+            self.b_history.y() + m.px(2.0),
+            TextAlign::Center,
+            if self.showing_history || self.b_history.mouse_hovering() {
+                blight
+            } else {
+                border
+            },
+            assets.textures.fonts.small,
+        );
+
+        if self.showing_history {
+            self.draw_history(&panel, assets);
+            return;
+        }
+
+        let title = if self.showing_last_run {
+            "LAST RUN".to_owned()
+        } else {
+            self.selected.name().to_owned()
+        };
+        let stats = match self.shown() {
+            Some(it) => it,
+            None => {
+                draw_menu_text(
+                    &format!("{}\n\nNOTHING CLEARED\nYET", title),
+                    panel.text_x(),
+                    m.px(5.0),
+                    TextAlign::Left,
+                    border,
+                    assets.textures.fonts.small,
+                );
+                return;
+            }
+        };
+
+        // Bar chart of each color
+        let (mx, my) = mouse_position_pixel();
+        let most = stats.by_marble.values().copied().max().unwrap_or(0).max(1);
+        let mut hovered = None;
+        let atlas = self.skin.atlas();
+        // One bar for every kind of marble
+        for (idx, marble) in Marble::ALL.iter().enumerate() {
+            let count = stats.by_marble.get(marble).copied().unwrap_or(0);
+            let corner = self.bar_corner(idx);
+            let bar_h = (count as f32 / most as f32 * BAR_HEIGHT).round();
+            draw_rectangle(
+                corner.x + 1.0,
+                corner.y - bar_h,
+                MARBLE_SIZE - 2.0,
+                bar_h,
+                marble_color(marble),
+            );
+
+            let sx = marble.clone() as u32 as f32 * MARBLE_SIZE;
+            for (sy, tint) in [(atlas.body_y, WHITE), (atlas.sigil_y, dark)] {
+                draw_texture_ex(
+                    assets.textures.marble_atlas,
+                    corner.x,
+                    corner.y + 1.0,
+                    tint,
+                    DrawTextureParams {
+                        source: Some(Rect::new(sx, sy, MARBLE_SIZE, MARBLE_SIZE)),
+                        ..Default::default()
+                    },
+                );
+            }
+
+            let hitbox = Rect::new(
+                corner.x,
+                corner.y - BAR_HEIGHT,
+                MARBLE_SIZE,
+                BAR_HEIGHT + MARBLE_SIZE + 1.0,
+            );
+            if hitbox.contains(vec2(mx, my)) {
+                hovered = Some(count);
+            }
+        }
+
+        let total: u32 = stats.by_marble.values().sum();
+        let subtitle = match hovered {
+            Some(count) => format!("{} CLEARED", count),
+            None => format!("TOTAL {}", total),
+        };
+        draw_menu_text(
+            &format!("{}\n{}", title, subtitle),
+            panel.text_x(),
+            m.px(5.0),
+            TextAlign::Left,
+            border,
+            assets.textures.fonts.small,
+        );
+
+        // Heatmap of where things got cleared
+        let mode = if self.showing_last_run {
+            self.last_run
+                .as_ref()
+                .map_or(self.selected, |(mode, _)| *mode)
+        } else {
+            self.selected
+        };
+        let settings = mode.settings();
+        let hottest = stats
+            .by_position
+            .values()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        draw_mini_board(
+            settings.cells(),
+            settings.radius,
+            (panel.left + panel.right) / 2.0,
+            self.bar_corner(0).y + MARBLE_SIZE + 5.0,
+            height() - 3.0,
+            |pos| {
+                let count = stats.by_position.get(&(pos.x, pos.y)).copied().unwrap_or(0);
+                if count == 0 {
+                    dark
+                } else {
+                    // Square root so the quieter spots still show up
+                    let heat = (count as f32 / hottest as f32).sqrt();
+                    Color::new(
+                        color.r + (blight.r - color.r) * heat,
+                        color.g + (blight.g - color.g) * heat,
+                        color.b + (blight.b - color.b) * heat,
+                        1.0,
+                    )
+                }
+            },
+        );
+    }
+}
@@ -1,19 +1,18 @@
 use cogs_gamedev::controls::InputHandler;
-use macroquad::{
-    audio::play_sound_once,
-    prelude::{clear_background, Color},
-};
+use macroquad::prelude::{clear_background, Color};
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, SfxEvent},
     boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
+    height,
     utils::{
         button::Button,
-        draw::hexcolor,
-        text::{draw_pixel_text, TextAlign},
+        layout::{draw_menu_text, MenuMetrics},
+        text::TextAlign,
+        theme,
     },
-    HEIGHT, WIDTH,
+    width,
 };
 
 use super::DontRestartMusicToken;
@@ -35,11 +34,11 @@ impl Gamemode for ModeTextDisplayer {
         if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
             || controls.clicked_down(Control::Pause)
         {
-            play_sound_once(assets.sounds.shunt);
+            assets.play_sfx(SfxEvent::MenuBack);
             return Transition::PopWith(Box::new(DontRestartMusicToken));
         }
         if self.b_back.mouse_entered() {
-            play_sound_once(assets.sounds.select);
+            assets.play_sfx(SfxEvent::ButtonHover);
         }
         self.b_back.post_update();
 
@@ -53,27 +52,28 @@ impl Gamemode for ModeTextDisplayer {
 
 impl GamemodeDrawer for ModeTextDisplayer {
     fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
         clear_background(self.bg_color);
 
-        let color = hexcolor(0x4b1d52_ff);
-        let highlight = hexcolor(0x692464_ff);
-        let border = hexcolor(0xcc2f7b_ff);
-        let blight = hexcolor(0xff5277_ff);
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
 
-        draw_pixel_text(
+        draw_menu_text(
             &self.message,
-            3.0,
-            3.0,
+            m.px(3.0),
+            m.px(3.0),
             TextAlign::Left,
             blight,
             assets.textures.fonts.small,
         );
 
         self.b_back.draw(color, border, highlight, blight, 1.01);
-        draw_pixel_text(
+        draw_menu_text(
             "RETURN",
             self.b_back.x() + self.b_back.w() / 2.0,
-            self.b_back.y() + 2.0,
+            self.b_back.y() + m.px(2.0),
             TextAlign::Center,
             if self.b_back.mouse_hovering() {
                 blight
@@ -87,13 +87,14 @@ impl GamemodeDrawer for ModeTextDisplayer {
 
 impl ModeTextDisplayer {
     pub fn new(message: String, bg_color: Color) -> Self {
-        let w = 4.0 * 12.0;
-        let h = 9.0;
+        let m = MenuMetrics::current();
+        let w = m.px(4.0 * 12.0);
+        let h = m.px(9.0);
 
         Self {
             message,
             bg_color,
-            b_back: Button::new(WIDTH - w - 3.0, HEIGHT - h - 3.0, w, h),
+            b_back: Button::new(width() - w - m.px(3.0), height() - h - m.px(3.0), w, h),
         }
     }
 }
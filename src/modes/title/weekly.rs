@@ -0,0 +1,180 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::{miniquad, prelude::*};
+
+use crate::{
+    assets::SfxEvent,
+    boilerplates::{DrawerBox, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    height,
+    model::{Board, Ranking},
+    modes::{draw_board_preview, start_game},
+    utils::{
+        button::Button,
+        layout::{draw_menu_text, MenuMetrics, SidePanel},
+        profile::{PlaySettings, Profile},
+        text::TextAlign,
+        theme,
+    },
+    weekly::{format_countdown, WeeklyChallenge, SECONDS_PER_DAY},
+    Assets,
+};
+
+use super::DontRestartMusicToken;
+
+/// This week's challenge: what's on, your best so far, and when it changes.
+#[derive(Debug, Clone)]
+pub struct ModeWeekly {
+    settings: PlaySettings,
+    challenge: WeeklyChallenge,
+    /// The starting board everyone gets this week
+    preview: Board,
+    best: Option<u32>,
+
+    b_play: Button,
+    b_back: Button,
+}
+
+impl Gamemode for ModeWeekly {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if self.b_play.mouse_hovering() && controls.clicked_down(Control::Click) {
+            assets.play_sfx(SfxEvent::ButtonClick);
+            return Transition::Swap(start_game(self.challenge.settings(), self.settings, assets));
+        }
+        if (self.b_back.mouse_hovering() && controls.clicked_down(Control::Click))
+            || controls.clicked_down(Control::Pause)
+        {
+            assets.play_sfx(SfxEvent::MenuBack);
+            return Transition::PopWith(Box::new(DontRestartMusicToken));
+        }
+
+        let mut play_enter = false;
+        for b in [&mut self.b_play, &mut self.b_back] {
+            if b.mouse_entered() {
+                play_enter = true;
+            }
+            b.post_update();
+        }
+        if play_enter {
+            assets.play_sfx(SfxEvent::ButtonHover);
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> DrawerBox {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeWeekly {
+    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+        let m = MenuMetrics::current();
+        clear_background(theme::bg());
+
+        let color = theme::panel();
+        let highlight = theme::panel_highlight();
+        let border = theme::border();
+        let blight = theme::accent();
+
+        let panel = SidePanel::beside(self.b_play.bounds());
+        panel.draw_line(border);
+
+        let mutators = self
+            .challenge
+            .mutators()
+            .iter()
+            .map(|mutator| format!("- {}", mutator.name()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let best = match self.best {
+            Some(best) => format!(
+                "{}: {}",
+                Ranking::Score.best_label(),
+                Ranking::Score.format(best)
+            ),
+            None => "NOT PLAYED YET".to_owned(),
+        };
+        draw_menu_text(
+            &format!(
+                "WEEK {} OF {}\n\nCLASSIC WITH:\n{}\n\n{}",
+                self.challenge.week, self.challenge.year, mutators, best
+            ),
+            self.b_play.x(),
+            m.px(5.0),
+            TextAlign::Left,
+            border,
+            assets.textures.fonts.small,
+        );
+        draw_menu_text(
+            &format!("NEXT CHALLENGE IN\n{}", format_countdown(seconds_left())),
+            self.b_play.x(),
+            self.b_play.y() - m.px(14.0),
+            TextAlign::Left,
+            blight,
+            assets.textures.fonts.small,
+        );
+
+        let top = m.px(5.0);
+        let bottom = height() - m.px(5.0);
+        let area = Rect::new(
+            panel.left + m.px(3.0),
+            top,
+            panel.right - panel.left - m.px(6.0),
+            bottom - top,
+        );
+        draw_board_preview(&self.preview, area, true, self.settings, assets);
+
+        for (b, text) in [(&self.b_play, "PLAY"), (&self.b_back, "RETURN")] {
+            b.draw(color, border, highlight, blight, 1.01);
+            draw_menu_text(
+                text,
+                b.x() + b.w() / 2.0,
+                b.y() + m.px(2.0),
+                TextAlign::Center,
+                b.text_color(border, blight),
+                assets.textures.fonts.small,
+            );
+        }
+    }
+}
+
+impl ModeWeekly {
+    pub fn new(settings: PlaySettings) -> Self {
+        let m = MenuMetrics::current();
+        let x = m.px(5.0);
+        let w = m.px(4.0 * 15.0);
+        let h = m.px(9.0);
+
+        let challenge = WeeklyChallenge::containing_day(today());
+        let best = Profile::get()
+            .weekly_highscores
+            .get(&challenge.id())
+            .copied();
+
+        Self {
+            settings,
+            challenge,
+            preview: Board::new(challenge.settings()),
+            best,
+            b_play: Button::new(x, height() - 2.0 * (h + m.px(3.0)), w, h),
+            b_back: Button::new(m.px(3.0), height() - h - m.px(3.0), m.px(4.0 * 12.0), h),
+        }
+    }
+}
+
+/// Days since 1970-01-01 in UTC
+fn today() -> i64 {
+    (miniquad::date::now() / SECONDS_PER_DAY as f64).floor() as i64
+}
+
+/// How long until this week's challenge gets replaced by next week's, in seconds.
+fn seconds_left() -> u64 {
+    let now = miniquad::date::now();
+    let next = WeeklyChallenge::replaced_on(today());
+    (next as f64 * SECONDS_PER_DAY as f64 - now).max(0.0) as u64
+}
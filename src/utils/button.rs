@@ -1,20 +1,33 @@
-use macroquad::prelude::{draw_rectangle, draw_rectangle_lines, vec2, Color, Rect};
+use macroquad::prelude::{
+    draw_rectangle, draw_rectangle_lines, is_mouse_button_down, vec2, Color, MouseButton, Rect,
+};
 
-use super::draw::mouse_position_pixel;
+use super::{draw::mouse_position_pixel, layout::flip_x, theme};
+
+/// How many frames a button stays pushed in after it's let go,
+/// so even the quickest click shows
+const PRESS_LINGER_FRAMES: u32 = 4;
 
 /// Button to be pressed
 #[derive(Debug, Clone)]
 pub struct Button {
     pub bounds: Rect,
+    /// Grayed out. The mouse can't hover over or click it.
+    pub disabled: bool,
     /// Was the mouse on here last frame?
     was_mouse_hovering: bool,
+    /// Frames left for it to look pushed in
+    press_timer: u32,
 }
 
 impl Button {
+    /// Lay out the button as if the UI wasn't mirrored; it gets flipped over if it is.
     pub fn new_from_rect(bounds: Rect) -> Self {
         Self {
-            bounds,
+            bounds: Rect::new(flip_x(bounds.x, bounds.w), bounds.y, bounds.w, bounds.h),
+            disabled: false,
             was_mouse_hovering: false,
+            press_timer: 0,
         }
     }
 
@@ -43,12 +56,29 @@ impl Button {
     /// processing is done.
     pub fn post_update(&mut self) {
         self.was_mouse_hovering = self.mouse_hovering();
+        if self.held() {
+            self.press_timer = PRESS_LINGER_FRAMES;
+        } else {
+            self.press_timer = self.press_timer.saturating_sub(1);
+        }
     }
 
     /// Is the mouse currently over this?
+    ///
+    /// Disabled buttons never have the mouse over them, so they can't be clicked.
     pub fn mouse_hovering(&self) -> bool {
         let (mx, my) = mouse_position_pixel();
-        self.bounds.contains(vec2(mx, my))
+        !self.disabled && self.bounds.contains(vec2(mx, my))
+    }
+
+    /// Is the mouse holding this down right now?
+    fn held(&self) -> bool {
+        self.mouse_hovering() && is_mouse_button_down(MouseButton::Left)
+    }
+
+    /// Should this be drawn pushed in?
+    pub fn pressed(&self) -> bool {
+        self.held() || self.press_timer > 0
     }
 
     /// Did the mouse enter the button this frame?
@@ -61,7 +91,20 @@ impl Button {
         self.was_mouse_hovering && !self.mouse_hovering()
     }
 
-    /// Quick-and-dirty drawing. `highlight` colors are for when the mouse is on the thing
+    /// Pick the color for the text on the button, to go with how `draw` draws it.
+    pub fn text_color(&self, color: Color, highlight: Color) -> Color {
+        if self.disabled {
+            theme::dim()
+        } else if self.mouse_hovering() {
+            highlight
+        } else {
+            color
+        }
+    }
+
+    /// Quick-and-dirty drawing. `highlight` colors are for when the mouse is on the thing.
+    ///
+    /// Pressed buttons shrink in by a pixel, and disabled ones get a dim border.
     pub fn draw(
         &self,
         color: Color,
@@ -75,15 +118,18 @@ impl Button {
         } else {
             color
         };
-        let border = if self.mouse_hovering() {
+        let border = if self.disabled {
+            theme::dim()
+        } else if self.mouse_hovering() {
             border_highlight
         } else {
             border
         };
-        let x = self.x().round();
-        let y = self.y().round();
-        let w = self.w().round();
-        let h = self.h().round();
+        let inset = if self.pressed() { 1.0 } else { 0.0 };
+        let x = self.x().round() + inset;
+        let y = self.y().round() + inset;
+        let w = self.w().round() - inset * 2.0;
+        let h = self.h().round() - inset * 2.0;
         draw_rectangle(x, y, w, h, color);
         draw_rectangle_lines(x, y, w, h, border_width, border);
     }
@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::warn;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+/// Name of the config file, which lives next to the executable
+const CONFIG_FILE: &str = "haxagon.toml";
+
+/// Advanced options from `haxagon.toml` and the command line.
+///
+/// These are read once at startup, before the window even opens.
+pub static CONFIG: Lazy<Config> = Lazy::new(Config::load);
+
+/// Options for people who know what they're doing, which don't belong in the profile.
+///
+/// Anything left out of the file is left at its default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Where to load the assets folder from, instead of the usual place
+    pub assets_path: Option<PathBuf>,
+    /// Don't draw more than this many frames a second
+    pub frame_cap: Option<u32>,
+    pub window_width: Option<i32>,
+    pub window_height: Option<i32>,
+    pub fullscreen: bool,
+    /// What to show first when the game starts
+    pub boot: BootTarget,
+    pub debug: DebugConfig,
+}
+
+/// Where the game starts up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootTarget {
+    /// The logo and jingle, then the title screen
+    Splash,
+    /// Straight to the title screen
+    Title,
+    /// Straight into a run of whatever mode was played last, with the title screen under it
+    LastMode,
+}
+
+impl Default for BootTarget {
+    fn default() -> Self {
+        BootTarget::Splash
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Start with the F3 performance overlay showing
+    pub perf_overlay: bool,
+    /// Print a line whenever drawing has to wait on the update thread
+    pub log_update_waits: bool,
+    /// Start with the F4 board coordinate overlay showing
+    pub coord_overlay: bool,
+}
+
+impl Config {
+    /// Read the config file if there is one, then apply the command line over it.
+    fn load() -> Self {
+        let mut config = match Self::from_file() {
+            Ok(it) => it,
+            Err(oh_no) => {
+                warn!(
+                    "Couldn't load {}! Using defaults...\n{:?}",
+                    CONFIG_FILE, oh_no
+                );
+                Config::default()
+            }
+        };
+        config.apply_args(std::env::args().skip(1));
+        config
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_file() -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let path = std::env::current_exe()
+            .context("When finding the executable")?
+            .with_file_name(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let src = std::fs::read_to_string(&path).context("When reading the config file")?;
+        let config = toml::from_str(&src).context("When parsing the config file")?;
+        Ok(config)
+    }
+
+    /// There's no file next to the executable on the web
+    #[cfg(target_arch = "wasm32")]
+    fn from_file() -> anyhow::Result<Self> {
+        Ok(Config::default())
+    }
+
+    /// Command line flags win over the file.
+    fn apply_args(&mut self, mut args: impl Iterator<Item = String>) {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fullscreen" => self.fullscreen = true,
+                "--skip-splash" => self.boot = BootTarget::Title,
+                "--last-mode" => self.boot = BootTarget::LastMode,
+                "--windowed" => self.fullscreen = false,
+                "--perf" => self.debug.perf_overlay = true,
+                "--log-update-waits" => self.debug.log_update_waits = true,
+                "--coords" => self.debug.coord_overlay = true,
+                "--assets" => self.assets_path = args.next().map(PathBuf::from),
+                "--frame-cap" => self.frame_cap = args.next().and_then(|n| n.parse().ok()),
+                "--width" => self.window_width = args.next().and_then(|n| n.parse().ok()),
+                "--height" => self.window_height = args.next().and_then(|n| n.parse().ok()),
+                _ => warn!("Unknown command line flag {:?}", arg),
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use macroquad::prelude::*;
+use once_cell::sync::Lazy;
+
+use super::{config::CONFIG, draw::width_height_deficit};
+use crate::width;
+
+/// Whether the overlay is up. Toggled with F4.
+static SHOWN: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(CONFIG.debug.coord_overlay));
+
+/// Labels waiting to be drawn this frame, at canvas positions.
+static LABELS: Lazy<Mutex<Vec<(Vec2, String, Color)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Size of the label text, in window pixels
+const TEXT_SIZE: f32 = 14.0;
+
+/// Whether to label every cell on the board with its coordinates and highlight the one
+/// the mouse is over. This is for tracking down input and transform bugs.
+pub fn coord_overlay() -> bool {
+    SHOWN.load(Ordering::Relaxed)
+}
+
+/// Put some text over the canvas at this canvas position.
+///
+/// The canvas is too small to fit much text, so labels get drawn straight to the window
+/// by `draw_labels` once the canvas is up.
+pub fn label(pos: Vec2, text: String, color: Color) {
+    LABELS.lock().unwrap().push((pos, text, color));
+}
+
+/// Draw everything labelled this frame over the canvas, and check for the F4 toggle.
+///
+/// Like the perf overlay, call this after the canvas is drawn to the window.
+pub fn draw_labels() {
+    if is_key_pressed(KeyCode::F4) {
+        SHOWN.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    let (wd, hd) = width_height_deficit();
+    let scale = (screen_width() - wd) / width();
+    for (pos, text, color) in LABELS.lock().unwrap().drain(..) {
+        let size = measure_text(&text, None, TEXT_SIZE as u16, 1.0);
+        let x = wd / 2.0 + pos.x * scale - size.width / 2.0;
+        let y = hd / 2.0 + pos.y * scale + size.height / 2.0;
+        draw_rectangle(
+            x - 1.0,
+            y - size.height - 1.0,
+            size.width + 2.0,
+            size.height + 2.0,
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        );
+        draw_text(&text, x, y, TEXT_SIZE, color);
+    }
+}
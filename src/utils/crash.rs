@@ -0,0 +1,134 @@
+use std::{fmt::Display, sync::Mutex};
+
+use macroquad::prelude::warn;
+use once_cell::sync::Lazy;
+use quad_wasmnastics::storage::{self, Location};
+
+/// Where in storage we remember where the last crash report went,
+/// so the next launch can tell the player about it.
+const LAST_CRASH_VERSION: &str = "last-crash-1";
+
+/// What the game was doing, for the crash report.
+/// The update thread fills this in and whichever thread panics reads it.
+static CONTEXT: Lazy<Mutex<CrashContext>> = Lazy::new(|| {
+    Mutex::new(CrashContext {
+        mode: "nothing yet",
+        board: None,
+    })
+});
+
+struct CrashContext {
+    /// Type name of the mode on top of the stack
+    mode: &'static str,
+    /// The board of the game in progress, drawn out as text
+    board: Option<String>,
+}
+
+/// Note which mode is on top of the stack.
+///
+/// Changing modes forgets the board, so a stale game doesn't end up in the report.
+pub fn set_mode(mode: &'static str) {
+    if let Ok(mut ctx) = CONTEXT.lock() {
+        if ctx.mode != mode {
+            ctx.mode = mode;
+            ctx.board = None;
+        }
+    }
+}
+
+/// Note what the board in play looks like.
+pub fn set_board(board: String) {
+    if let Ok(mut ctx) = CONTEXT.lock() {
+        ctx.board = Some(board);
+    }
+}
+
+/// Write a crash report whenever anything panics, then panic like normal.
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = make_report(info);
+        match save_report(&report) {
+            Ok(place) => {
+                let res = storage::save_to(place.as_bytes(), &last_crash_location());
+                if let Err(oh_no) = res {
+                    warn!(
+                        "Couldn't remember where the crash report went!\n{:?}",
+                        oh_no
+                    );
+                }
+            }
+            Err(oh_no) => {
+                warn!("Couldn't save crash report!\n{:?}\n{}", oh_no, report);
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+/// If the game crashed last time, where the report went.
+///
+/// This only says so once.
+pub fn take_last_crash() -> Option<String> {
+    let location = last_crash_location();
+    let data = storage::load_from(&location).ok()?;
+    if data.is_empty() {
+        return None;
+    }
+    if let Err(oh_no) = storage::save_to(&[], &location) {
+        warn!("Couldn't forget the last crash!\n{:?}", oh_no);
+    }
+    String::from_utf8(data).ok()
+}
+
+fn last_crash_location() -> Location {
+    Location {
+        version: String::from(LAST_CRASH_VERSION),
+        ..Default::default()
+    }
+}
+
+fn make_report(panic: &dyn Display) -> String {
+    // If the thread that panicked was holding the lock, waiting on it would hang forever
+    let (mode, board) = match CONTEXT.try_lock() {
+        Ok(ctx) => (ctx.mode, ctx.board.clone()),
+        Err(_) => ("unknown", None),
+    };
+    format!(
+        "Haxagon v{} crashed!\n\n{}\n\nMode: {}\n\nBoard:\n{}\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        panic,
+        mode,
+        board.as_deref().unwrap_or("(no game in progress)\n"),
+        std::backtrace::Backtrace::force_capture(),
+    )
+}
+
+/// Write the report somewhere the player can find it, and say where that is.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_report(report: &str) -> anyhow::Result<String> {
+    use anyhow::Context;
+
+    std::fs::create_dir_all("crashes").context("When making the crashes folder")?;
+    let name = format!(
+        "crashes/{}.txt",
+        macroquad::miniquad::date::now().floor() as u64
+    );
+    std::fs::write(&name, report).context("When writing the crash report")?;
+    Ok(name)
+}
+
+/// Write the report somewhere the player can find it, and say where that is.
+///
+/// The web version can't write files, so it goes in the browser's storage.
+#[cfg(target_arch = "wasm32")]
+fn save_report(report: &str) -> anyhow::Result<String> {
+    storage::save_to(
+        report.as_bytes(),
+        &Location {
+            version: String::from("crash-report-1"),
+            ..Default::default()
+        },
+    )?;
+    Ok("BROWSER STORAGE".to_owned())
+}
@@ -1,76 +1,170 @@
-use crate::{ASPECT_RATIO, HEIGHT, WIDTH};
-
-use macroquad::prelude::*;
-
-/// Make a Color from an RRGGBBAA hex code.
-pub fn hexcolor(code: u32) -> Color {
-    let [r, g, b, a] = code.to_be_bytes();
-    Color::from_rgba(r, g, b, a)
-}
-
-pub fn mouse_position_pixel() -> (f32, f32) {
-    let (mx, my) = mouse_position();
-    let (wd, hd) = width_height_deficit();
-    let mx = (mx - wd / 2.0) / ((screen_width() - wd) / WIDTH);
-    let my = (my - hd / 2.0) / ((screen_height() - hd) / HEIGHT);
-    (mx, my)
-}
-
-pub fn width_height_deficit() -> (f32, f32) {
-    if (screen_width() / screen_height()) > ASPECT_RATIO {
-        // it's too wide! put bars on the sides!
-        // the height becomes the authority on how wide to draw
-        let expected_width = screen_height() * ASPECT_RATIO;
-        (screen_width() - expected_width, 0.0f32)
-    } else {
-        // it's too tall! put bars on the ends!
-        // the width is the authority
-        let expected_height = screen_width() / ASPECT_RATIO;
-        (0.0f32, screen_height() - expected_height)
-    }
-}
-
-/// Draw a 9patch of a 3x3 grid of tiles.
-pub fn patch9(
-    tile_size: f32,
-    corner_x: f32,
-    corner_y: f32,
-    width: usize,
-    height: usize,
-    tex: Texture2D,
-) {
-    for x in 0..width {
-        for y in 0..height {
-            let px = corner_x + x as f32 * tile_size;
-            let py = corner_y + y as f32 * tile_size;
-
-            let sx = tile_size
-                * if x == 0 {
-                    0.0
-                } else if x == width - 1 {
-                    2.0
-                } else {
-                    1.0
-                };
-            let sy = tile_size
-                * if y == 0 {
-                    0.0
-                } else if y == height - 1 {
-                    2.0
-                } else {
-                    1.0
-                };
-
-            draw_texture_ex(
-                tex,
-                px,
-                py,
-                WHITE,
-                DrawTextureParams {
-                    source: Some(Rect::new(sx, sy, 16.0, 16.0)),
-                    ..Default::default()
-                },
-            );
-        }
-    }
-}
+use crate::{aspect_ratio, height, model::Marble, utils::hexmath::HexLayout, width};
+
+use hex2d::Coordinate;
+use macroquad::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether to draw the canvas through the CRT shader.
+/// This is set from the update thread and read from the draw thread, hence the atomic.
+static CRT_FILTER: AtomicBool = AtomicBool::new(false);
+
+/// Roughly what color each marble is, in the same order as `Marble`
+pub const MARBLE_COLORS: [u32; 8] = [
+    0xe04e43_ff,
+    0x64a743_ff,
+    0x3879be_ff,
+    0xeda33d_ff,
+    0x89e4ad_ff,
+    0x973999_ff,
+    0xeb5071_ff,
+    0x706c7c_ff,
+];
+
+/// Make a Color from an RRGGBBAA hex code.
+pub fn hexcolor(code: u32) -> Color {
+    let [r, g, b, a] = code.to_be_bytes();
+    Color::from_rgba(r, g, b, a)
+}
+
+/// One flat color to stand in for the marble when it's too small to draw properly.
+pub fn marble_color(marble: &Marble) -> Color {
+    hexcolor(MARBLE_COLORS[marble.clone() as usize])
+}
+
+pub fn set_crt_filter(on: bool) {
+    CRT_FILTER.store(on, Ordering::Relaxed);
+}
+
+pub fn crt_filter() -> bool {
+    CRT_FILTER.load(Ordering::Relaxed)
+}
+
+pub fn mouse_position_pixel() -> (f32, f32) {
+    let (mx, my) = mouse_position();
+    let (wd, hd) = width_height_deficit();
+    let mx = (mx - wd / 2.0) / ((screen_width() - wd) / width());
+    let my = (my - hd / 2.0) / ((screen_height() - hd) / height());
+    (mx, my)
+}
+
+pub fn width_height_deficit() -> (f32, f32) {
+    if (screen_width() / screen_height()) > aspect_ratio() {
+        // it's too wide! put bars on the sides!
+        // the height becomes the authority on how wide to draw
+        let expected_width = screen_height() * aspect_ratio();
+        (screen_width() - expected_width, 0.0f32)
+    } else {
+        // it's too tall! put bars on the ends!
+        // the width is the authority
+        let expected_height = screen_width() / aspect_ratio();
+        (0.0f32, screen_height() - expected_height)
+    }
+}
+
+/// Draw a 9patch of a 3x3 grid of tiles.
+pub fn patch9(
+    tile_size: f32,
+    corner_x: f32,
+    corner_y: f32,
+    width: usize,
+    height: usize,
+    tex: Texture2D,
+) {
+    for x in 0..width {
+        for y in 0..height {
+            let px = corner_x + x as f32 * tile_size;
+            let py = corner_y + y as f32 * tile_size;
+
+            let sx = tile_size
+                * if x == 0 {
+                    0.0
+                } else if x == width - 1 {
+                    2.0
+                } else {
+                    1.0
+                };
+            let sy = tile_size
+                * if y == 0 {
+                    0.0
+                } else if y == height - 1 {
+                    2.0
+                } else {
+                    1.0
+                };
+
+            draw_texture_ex(
+                tex,
+                px,
+                py,
+                WHITE,
+                DrawTextureParams {
+                    source: Some(Rect::new(sx, sy, 16.0, 16.0)),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
+/// Draw a tiny board as a dot per cell, centered horizontally at `cx`
+/// and fitting between `top` and `bottom`.
+pub fn draw_mini_board(
+    cells: impl IntoIterator<Item = Coordinate>,
+    radius: usize,
+    cx: f32,
+    top: f32,
+    bottom: f32,
+    color: impl Fn(Coordinate) -> Color,
+) {
+    let across = (radius * 2 + 1) as f32;
+    // Each cell is a `span_x - 1` square dot
+    let span_y = ((bottom - top) / across).floor().clamp(1.0, 3.0) as i32;
+    let span_x = span_y + 1;
+    let dot = (span_x - 1) as f32;
+
+    let layout = HexLayout::new(span_x, span_y, vec2(cx, (top + bottom) / 2.0));
+    for pos in cells {
+        let corner = layout.hex_to_corner(pos, dot);
+        draw_rectangle(corner.x, corner.y, dot, dot, color(pos));
+    }
+}
+
+/// Draw a line through each series of numbers, all scaled to fit in `area`.
+///
+/// Every line starts from zero at the bottom left, and the numbers in a series
+/// are spaced evenly after that, the same distance apart in every series.
+/// Series get drawn in order, so later ones end up on top.
+pub fn draw_line_chart(area: Rect, series: &[&[u32]], color: impl Fn(usize) -> Color) {
+    let longest = series.iter().map(|s| s.len()).max().unwrap_or(0).max(1);
+    let highest = series
+        .iter()
+        .flat_map(|s| s.iter().copied())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let point = |idx: usize, value: u32| {
+        vec2(
+            (area.x + area.w * idx as f32 / longest as f32).round(),
+            (area.y + area.h - area.h * value as f32 / highest as f32).round(),
+        )
+    };
+
+    for (idx, values) in series.iter().enumerate() {
+        let color = color(idx);
+        let mut prev = point(0, 0);
+        for (x, value) in values.iter().enumerate() {
+            let next = point(x + 1, *value);
+            // Nudge onto pixel centers so 1px lines don't smear
+            draw_line(
+                prev.x + 0.5,
+                prev.y + 0.5,
+                next.x + 0.5,
+                next.y + 0.5,
+                1.0,
+                color,
+            );
+            prev = next;
+        }
+    }
+}
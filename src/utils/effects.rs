@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use crate::model::Cyclable;
+
+/// Whether the shader effects and busy backgrounds are on.
+/// This is set from the update thread and read from the draw thread, hence the atomic.
+static HEAVY_EFFECTS: AtomicBool = AtomicBool::new(true);
+
+/// What the GPU looked like when it was checked at startup.
+static DETECTED: OnceCell<GpuTier> = OnceCell::new();
+
+/// Whether the shader effects and busy backgrounds should be drawn right now.
+pub fn heavy_effects() -> bool {
+    HEAVY_EFFECTS.load(Ordering::Relaxed)
+}
+
+/// Roughly how much the GPU can handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GpuTier {
+    /// Phones and old integrated graphics, in a browser
+    Weak,
+    Capable,
+}
+
+impl GpuTier {
+    /// Check what the GPU can do. The first call has to be after the window's up.
+    pub fn detected() -> Self {
+        *DETECTED.get_or_init(detect)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn detect() -> GpuTier {
+    use macroquad::miniquad::sapp::{glGetIntegerv, GL_MAX_TEXTURE_SIZE};
+
+    // There's no good way to ask a browser how fast its GPU is,
+    // but small textures are a decent sign of a phone or an old laptop
+    let mut max_texture_size = 0;
+    unsafe {
+        glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut max_texture_size);
+    }
+    if max_texture_size < 8192 {
+        GpuTier::Weak
+    } else {
+        GpuTier::Capable
+    }
+}
+
+/// Desktop builds can take it.
+#[cfg(not(target_arch = "wasm32"))]
+fn detect() -> GpuTier {
+    GpuTier::Capable
+}
+
+/// How fancy the shader effects, backgrounds and antialiasing are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EffectsQuality {
+    /// Go by what the GPU looks like it can do
+    Auto,
+    Low,
+    High,
+}
+
+impl EffectsQuality {
+    /// Turn the effects on or off to match.
+    pub fn apply(&self) {
+        HEAVY_EFFECTS.store(self.heavy(GpuTier::detected()), Ordering::Relaxed);
+    }
+
+    /// Whether heavy effects are on at this quality, on a GPU like this.
+    pub fn heavy(&self, tier: GpuTier) -> bool {
+        match self {
+            EffectsQuality::Auto => tier == GpuTier::Capable,
+            EffectsQuality::Low => false,
+            EffectsQuality::High => true,
+        }
+    }
+
+    /// What `Auto` ended up picking, for the settings screen.
+    pub fn description(&self) -> &'static str {
+        match self {
+            EffectsQuality::Auto => match GpuTier::detected() {
+                GpuTier::Weak => "YOUR GPU LOOKS\nSLOW, SO THIS IS\nLIKE LOW.",
+                GpuTier::Capable => "YOUR GPU LOOKS\nFINE, SO THIS IS\nLIKE HIGH.",
+            },
+            EffectsQuality::Low => "NO SHADERS, PLAIN\nBACKGROUNDS AND\nNO ANTIALIASING.",
+            EffectsQuality::High => "EVERYTHING ON.",
+        }
+    }
+}
+
+impl Cyclable for EffectsQuality {
+    const ALL: &'static [Self] = &[
+        EffectsQuality::Auto,
+        EffectsQuality::Low,
+        EffectsQuality::High,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            EffectsQuality::Auto => "AUTO",
+            EffectsQuality::Low => "LOW",
+            EffectsQuality::High => "HIGH",
+        }
+    }
+}
+
+impl Default for EffectsQuality {
+    fn default() -> Self {
+        EffectsQuality::Auto
+    }
+}
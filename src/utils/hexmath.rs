@@ -0,0 +1,96 @@
+use hex2d::{Angle, Coordinate, IntegerSpacing};
+use macroquad::prelude::{vec2, Mat2, Vec2};
+
+/// How pointy-topped hexes get laid out in pixels.
+///
+/// This only knows about spacing, where the middle goes, and scale;
+/// turning the view is up to whoever uses it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HexLayout {
+    /// Distance between neighbors in the same row
+    pub span_x: i32,
+    /// Distance between rows
+    pub span_y: i32,
+    /// Where the center hex goes
+    pub center: Vec2,
+    /// How much to scale the spacing by
+    pub zoom: f32,
+}
+
+impl HexLayout {
+    pub fn new(span_x: i32, span_y: i32, center: Vec2) -> Self {
+        Self {
+            span_x,
+            span_y,
+            center,
+            zoom: 1.0,
+        }
+    }
+
+    /// The same layout, but scaled
+    pub fn zoomed(self, zoom: f32) -> Self {
+        Self { zoom, ..self }
+    }
+
+    /// How far the hex is from the center hex, before zooming
+    pub fn offset(&self, pos: Coordinate) -> Vec2 {
+        let (ox, oy) = pos.to_pixel_integer(IntegerSpacing::PointyTop(self.span_x, self.span_y));
+        vec2(ox as f32, oy as f32)
+    }
+
+    /// Pixel position of the middle of the hex
+    pub fn hex_to_pixel(&self, pos: Coordinate) -> Vec2 {
+        self.offset(pos) * self.zoom + self.center
+    }
+
+    /// Top left corner of a `size`-pixel square centered on the hex,
+    /// snapped to the pixel grid
+    pub fn hex_to_corner(&self, pos: Coordinate, size: f32) -> Vec2 {
+        let corner = self.hex_to_pixel(pos) - Vec2::splat(size / 2.0);
+        vec2(corner.x.round(), corner.y.round())
+    }
+
+    /// Which hex the pixel is over
+    pub fn pixel_to_hex(&self, px: Vec2) -> Coordinate {
+        let board = (px - self.center) / self.zoom;
+
+        // hex2d does not come with a function to convert back from blocky pixel coords to hex.
+        // so we roll our own
+        let forward_transform = Mat2::from_cols_array(&[
+            self.span_x as f32,
+            0.0,
+            self.span_x as f32 / 2.0,
+            self.span_y as f32,
+        ]);
+        let transform = forward_transform.inverse();
+        let (q, r) = (transform * board).into();
+
+        // The matrix's axes don't match up with hex2d's, so turn it to line them up
+        Coordinate::<i32>::nearest(r, q).rotate_around_zero(Angle::RightBack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_to_hex_undoes_hex_to_pixel() {
+        let origin = Coordinate::new(0, 0);
+        for &(span_x, span_y) in &[(10, 8), (6, 5), (2, 1)] {
+            for &zoom in &[0.5, 1.0, 1.5, 3.0] {
+                let layout = HexLayout::new(span_x, span_y, vec2(160.0, 90.0)).zoomed(zoom);
+                for pos in origin.range_iter(8) {
+                    assert_eq!(
+                        layout.pixel_to_hex(layout.hex_to_pixel(pos)),
+                        pos,
+                        "spans {}x{} at zoom {}",
+                        span_x,
+                        span_y,
+                        zoom
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use macroquad::prelude::{draw_line, Color, Rect, Texture2D};
+use serde::{Deserialize, Serialize};
+
+use crate::{height, model::Cyclable, width};
+
+use super::text::{draw_pixel_text_scaled, TextAlign};
+
+/// Whether layouts are flipped left-to-right, for left-handed players.
+/// This is read from both the update and draw threads, hence the atomic.
+///
+/// Like the resolution, modes lay themselves out when they're constructed,
+/// so anything already on the stack keeps its old layout.
+static MIRRORED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_mirrored(on: bool) {
+    MIRRORED.store(on, Ordering::Relaxed);
+}
+
+pub fn mirrored() -> bool {
+    MIRRORED.load(Ordering::Relaxed)
+}
+
+/// Where the left edge of something `w` wide laid out at `x` really goes.
+pub fn flip_x(x: f32, w: f32) -> f32 {
+    if mirrored() {
+        width() - x - w
+    } else {
+        x
+    }
+}
+
+/// Text laid out hugging one edge of the screen should hug the other edge when mirrored.
+pub fn flip_align(align: TextAlign) -> TextAlign {
+    match (mirrored(), align) {
+        (true, TextAlign::Left) => TextAlign::Right,
+        (true, TextAlign::Right) => TextAlign::Left,
+        (_, align) => align,
+    }
+}
+
+/// The space beside a column of menu buttons, where descriptions go.
+#[derive(Debug, Clone, Copy)]
+pub struct SidePanel {
+    /// Where the line between the buttons and the panel goes
+    pub line_x: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+impl SidePanel {
+    /// The panel beside the column of buttons with these bounds.
+    pub fn beside(column: Rect) -> Self {
+        let gap = MenuMetrics::current().px(5.0);
+        if mirrored() {
+            let line_x = column.left() - gap;
+            Self {
+                line_x,
+                left: 0.0,
+                right: line_x,
+            }
+        } else {
+            let line_x = column.right() + gap;
+            Self {
+                line_x,
+                left: line_x,
+                right: width(),
+            }
+        }
+    }
+
+    /// Where left-aligned text in the panel starts
+    pub fn text_x(&self) -> f32 {
+        self.left + MenuMetrics::current().px(3.0)
+    }
+
+    pub fn draw_line(&self, color: Color) {
+        draw_line(self.line_x, 0.0, self.line_x, height(), 1.0, color);
+    }
+}
+
+/// How big menus are drawn, as an index into `UiScale::ALL`.
+static UI_SCALE: AtomicU8 = AtomicU8::new(0);
+
+/// How big text and buttons are on menu screens. The board isn't affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UiScale {
+    Normal,
+    Large,
+    Huge,
+}
+
+impl UiScale {
+    /// The scale menus are being laid out at right now
+    pub fn current() -> Self {
+        Self::ALL[UI_SCALE.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Lay menus out at this scale from now on.
+    ///
+    /// Like the resolution, anything already on the stack keeps its old layout.
+    pub fn apply(&self) {
+        UI_SCALE.store(*self as u8, Ordering::Relaxed);
+    }
+
+    pub fn factor(&self) -> f32 {
+        match self {
+            UiScale::Normal => 1.0,
+            UiScale::Large => 1.25,
+            UiScale::Huge => 1.5,
+        }
+    }
+}
+
+impl Cyclable for UiScale {
+    const ALL: &'static [Self] = &[UiScale::Normal, UiScale::Large, UiScale::Huge];
+
+    fn name(&self) -> &'static str {
+        match self {
+            UiScale::Normal => "1X",
+            UiScale::Large => "1.25X",
+            UiScale::Huge => "1.5X",
+        }
+    }
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale::Normal
+    }
+}
+
+/// Sizes for laying out menus at the current UI scale.
+///
+/// Menus are designed for a 144 pixel tall canvas, so they only get scaled up
+/// as far as the canvas has room for.
+#[derive(Debug, Clone, Copy)]
+pub struct MenuMetrics {
+    pub scale: f32,
+}
+
+impl MenuMetrics {
+    pub fn current() -> Self {
+        Self {
+            scale: UiScale::current().factor().min(height() / 144.0).max(1.0),
+        }
+    }
+
+    /// Scale a size from the normal layout
+    pub fn px(&self, px: f32) -> f32 {
+        px * self.scale
+    }
+}
+
+/// Draw text on a menu at the current UI scale.
+pub fn draw_menu_text(
+    text: &str,
+    cx: f32,
+    cy: f32,
+    align: TextAlign,
+    color: Color,
+    font: Texture2D,
+) {
+    draw_pixel_text_scaled(
+        text,
+        cx,
+        cy,
+        align,
+        color,
+        font,
+        MenuMetrics::current().scale,
+    );
+}
@@ -1,5 +1,19 @@
-pub mod button;
-pub mod draw;
-pub mod profile;
-pub mod serdeflate;
-pub mod text;
+pub mod button;
+pub mod config;
+pub mod coord_overlay;
+pub mod crash;
+pub mod draw;
+pub mod effects;
+pub mod hexmath;
+pub mod layout;
+pub mod music;
+pub mod perf;
+pub mod profile;
+pub mod puzzle;
+pub mod resolution;
+pub mod score_counter;
+pub mod scroll_list;
+pub mod serdeflate;
+pub mod skin;
+pub mod text;
+pub mod theme;
@@ -0,0 +1,86 @@
+use macroquad::time::get_time;
+
+/// Tempo of the title screen music. It's in 12/8, so this is eighth notes.
+pub const TITLE_BPM: f64 = 200.0;
+/// Tempos of the in-game music tracks, in the same order as `ModePlaying` picks them.
+/// These were worked out from the lengths of the loops.
+pub const GAME_BPMS: [f64; 3] = [160.0, 110.0, 117.5];
+
+/// Keeps track of how far into a piece of music we are, so things can happen on the beat.
+///
+/// Macroquad can't tell us where playback is, so this remembers when the track was started
+/// and goes by the clock from there.
+#[derive(Debug, Clone, Copy)]
+pub struct MusicClock {
+    start: f64,
+    bpm: f64,
+}
+
+impl MusicClock {
+    /// Call this right when the music starts playing.
+    pub fn start(bpm: f64) -> Self {
+        Self {
+            start: get_time(),
+            bpm,
+        }
+    }
+
+    /// Call this right when the music starts playing, partway through at `position` seconds in.
+    pub fn start_at(bpm: f64, position: f64) -> Self {
+        Self {
+            start: get_time() - position,
+            bpm,
+        }
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// How many beats have gone by since the music started.
+    pub fn beats(&self) -> f64 {
+        (get_time() - self.start) * self.bpm / 60.0
+    }
+}
+
+/// Counts off pulses that always start on a beat, but which can change how many beats long they are.
+#[derive(Debug, Clone, Copy)]
+pub struct BeatPulse {
+    /// How many pulses have finished
+    count: u32,
+    /// The beat the current pulse started on
+    start_beat: f64,
+    /// How many beats the current pulse lasts
+    length: f64,
+}
+
+impl BeatPulse {
+    pub fn new(length: f64) -> Self {
+        Self {
+            count: 0,
+            start_beat: 0.0,
+            length,
+        }
+    }
+
+    /// Catch up to the given beat.
+    /// Every time a new pulse starts, `next_length` picks how long it is.
+    ///
+    /// Returns how many new pulses started.
+    pub fn update(&mut self, beat: f64, mut next_length: impl FnMut() -> f64) -> u32 {
+        let mut started = 0;
+        while beat >= self.start_beat + self.length {
+            self.start_beat += self.length;
+            self.count += 1;
+            self.length = next_length();
+            started += 1;
+        }
+        started
+    }
+
+    /// How many pulses have gone by, plus the fraction of the way through the current one.
+    pub fn phase(&self, beat: f64) -> f64 {
+        let progress = (beat - self.start_beat) / self.length;
+        self.count as f64 + progress.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use macroquad::prelude::*;
+
+use super::config::CONFIG;
+
+/// How many updates have run since the program started.
+/// The update thread bumps this and the draw thread reads it, hence the atomic.
+static UPDATES_RAN: AtomicU64 = AtomicU64::new(0);
+
+/// How many frames of history the graph shows
+const GRAPH_LENGTH: usize = 120;
+/// How often the FPS and UPS readouts change, in seconds
+const SAMPLE_INTERVAL: f64 = 0.5;
+/// Frame times this long reach the top of the graph, in seconds
+const GRAPH_CEILING: f32 = 1.0 / 20.0;
+
+/// Call this once per update.
+pub fn record_update() {
+    UPDATES_RAN.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Frame rate, update rate, and frame time graph, toggled with F3.
+///
+/// This draws straight to the window after the canvas is blitted,
+/// so it never shows up on the canvas.
+pub struct PerfOverlay {
+    shown: bool,
+    /// Recent frame times and how much of each was spent waiting on the update thread, in seconds
+    frames: VecDeque<(f32, f32)>,
+
+    sample_start: f64,
+    sample_frames: u32,
+    sample_updates: u64,
+    fps: f32,
+    ups: f32,
+}
+
+impl PerfOverlay {
+    pub fn new() -> Self {
+        Self {
+            shown: CONFIG.debug.perf_overlay,
+            frames: VecDeque::with_capacity(GRAPH_LENGTH),
+            sample_start: get_time(),
+            sample_frames: 0,
+            sample_updates: UPDATES_RAN.load(Ordering::Relaxed),
+            fps: 0.0,
+            ups: 0.0,
+        }
+    }
+
+    /// Keep track of a drawn frame, and how long it waited on the update thread.
+    pub fn record_frame(&mut self, dt: f32, wait: f32) {
+        if is_key_pressed(KeyCode::F3) {
+            self.shown = !self.shown;
+        }
+
+        if self.frames.len() == GRAPH_LENGTH {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((dt, wait));
+
+        self.sample_frames += 1;
+        let now = get_time();
+        let elapsed = now - self.sample_start;
+        if elapsed >= SAMPLE_INTERVAL {
+            let updates = UPDATES_RAN.load(Ordering::Relaxed);
+            self.fps = (self.sample_frames as f64 / elapsed) as f32;
+            self.ups = ((updates - self.sample_updates) as f64 / elapsed) as f32;
+            self.sample_start = now;
+            self.sample_frames = 0;
+            self.sample_updates = updates;
+        }
+    }
+
+    pub fn draw(&self) {
+        if !self.shown {
+            return;
+        }
+
+        let (dt, wait) = self.frames.back().copied().unwrap_or_default();
+        let lines = [
+            format!("FPS {:.1}", self.fps),
+            format!("UPS {:.1}", self.ups),
+            format!("FRAME {:.2}ms", dt * 1000.0),
+            format!("WAIT {:.2}ms", wait * 1000.0),
+        ];
+        let line_height = 16.0;
+        let graph_height = 40.0;
+        let panel_height = lines.len() as f32 * line_height + graph_height + 12.0;
+        draw_rectangle(
+            4.0,
+            4.0,
+            GRAPH_LENGTH as f32 * 2.0 + 8.0,
+            panel_height,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        for (idx, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                8.0,
+                4.0 + line_height * (idx + 1) as f32,
+                line_height,
+                WHITE,
+            );
+        }
+
+        // Each frame is a bar; the part spent waiting on updates is red
+        let graph_bottom = 4.0 + panel_height - 4.0;
+        for (idx, (dt, wait)) in self.frames.iter().enumerate() {
+            let x = 8.0 + idx as f32 * 2.0;
+            let total = (dt / GRAPH_CEILING).min(1.0) * graph_height;
+            let waited = (wait / GRAPH_CEILING).min(1.0) * graph_height;
+            draw_rectangle(x, graph_bottom - total, 2.0, total, GREEN);
+            draw_rectangle(x, graph_bottom - waited, 2.0, waited, RED);
+        }
+        let target = graph_bottom - (1.0 / 60.0) / GRAPH_CEILING * graph_height;
+        draw_line(
+            8.0,
+            target,
+            8.0 + GRAPH_LENGTH as f32 * 2.0,
+            target,
+            1.0,
+            YELLOW,
+        );
+    }
+}
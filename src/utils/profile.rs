@@ -1,59 +1,476 @@
-use std::collections::HashMap;
-
-use macroquad::prelude::warn;
-use quad_wasmnastics::storage::{self, Location};
-use serde::{Deserialize, Serialize};
-
-use crate::model::{BoardSettingsModeKey, PlaySettings};
-
-const SERIALIZATION_VERSION: &str = "1";
-
-/// Profile information. The `get` function loads it from storage; on drop it saves it back.
-#[derive(Serialize, Deserialize, Default)]
-pub struct Profile {
-    #[serde(default)]
-    pub highscores: HashMap<BoardSettingsModeKey, u32>,
-    #[serde(default)]
-    pub settings: PlaySettings,
-}
-
-impl Profile {
-    pub fn get() -> Profile {
-        let maybe_profile: anyhow::Result<Profile> = (|| {
-            // note we save the raw bincode! it's already gzipped!
-            // if we gzipped it here it would jut be gzipped twice
-            let data = storage::load_from(&Location {
-                version: String::from(SERIALIZATION_VERSION),
-                ..Default::default()
-            })?;
-            let profile = bincode::deserialize(&data)?;
-            Ok(profile)
-        })();
-        match maybe_profile {
-            Ok(it) => it,
-            Err(oh_no) => {
-                warn!("Couldn't load profile! Loading default...\n{:?}", oh_no);
-                Profile::default()
-            }
-        }
-    }
-}
-
-impl Drop for Profile {
-    fn drop(&mut self) {
-        let res: anyhow::Result<()> = (|| {
-            let data = bincode::serialize(self)?;
-            storage::save_to(
-                &data,
-                &Location {
-                    version: String::from(SERIALIZATION_VERSION),
-                    ..Default::default()
-                },
-            )?;
-            Ok(())
-        })();
-        if let Err(oh_no) = res {
-            warn!("Couldn't save profile!\n{:?}", oh_no);
-        }
-    }
-}
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use hmac::{Hmac, Mac, NewMac};
+use macroquad::prelude::warn;
+use quad_wasmnastics::storage::{self, Location};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    audio::Volume,
+    model::{
+        BoardSettingsModeKey, ClearEvent, GameFeel, GameSpeed, LeaderboardKey, Marble, MutatorSet,
+        SnapRadius, TICKS_PER_SECOND,
+    },
+    modes::{Tip, SPLIT_COUNT},
+    utils::{
+        effects::{EffectsQuality, GpuTier},
+        layout::UiScale,
+        resolution::Resolution,
+        skin::MarbleSkin,
+    },
+};
+
+/// Saves are raw bincode, which has no field names to go by, so any change to the layout of
+/// `Profile` (or anything in it) needs this bumped and a way to read the old one in `load_older`.
+const SERIALIZATION_VERSION: &str = "2";
+/// What the high scores get sealed with if the build doesn't set `HAXAGON_SCORE_KEY`.
+/// Release builds should set it, or anyone reading this can forge a seal.
+const DEV_SCORE_KEY: &str = "haxagon dev build";
+
+/// How many of the latest runs in each mode to keep score curves for
+pub const RUN_HISTORY_LENGTH: usize = 8;
+/// How often a run's score gets written down for its curve, in ticks
+pub const RUN_SAMPLE_INTERVAL: u32 = TICKS_PER_SECOND * 5;
+
+/// Set if the last save didn't make it into storage, even after making room.
+static SAVE_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Profile information. The `get` function loads it from storage; on drop it saves it back.
+/// Anything added, removed or moved in here needs a new `SERIALIZATION_VERSION`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Profile {
+    pub highscores: HashMap<LeaderboardKey, u32>,
+    pub settings: PlaySettings,
+    /// Fastest time in ticks to reach each split milestone
+    pub best_splits: HashMap<LeaderboardKey, [Option<u32>; SPLIT_COUNT]>,
+    /// Everything ever cleared in each mode
+    pub clears: HashMap<BoardSettingsModeKey, ClearStats>,
+    /// What got cleared in the most recent run
+    pub last_run_clears: Option<(BoardSettingsModeKey, ClearStats)>,
+    /// Tips that have already popped up, so they don't pop up again
+    pub seen_tips: Vec<Tip>,
+    /// The mode of the most recently started run, to boot straight into
+    pub last_mode: Option<BoardSettingsModeKey>,
+    /// Best score in each weekly challenge, by `WeeklyChallenge::id`
+    pub weekly_highscores: HashMap<u32, u32>,
+    /// Score curves of the latest runs in each mode, oldest first
+    pub run_history: HashMap<BoardSettingsModeKey, VecDeque<RunCurve>>,
+    /// What the GPU looked like last launch, to pick antialiasing before the window's up
+    pub gpu_tier: Option<GpuTier>,
+    /// HMAC over the high scores, so saves edited by hand can be spotted.
+    /// This gets redone every time the profile is saved.
+    score_seal: Vec<u8>,
+    /// The high scores didn't match their seal at some point. This sticks around
+    /// until the high scores are wiped, and it's sealed too so it can't just be flipped back.
+    pub modified: bool,
+}
+
+/// How the score went over a run, in board points, written down every `RUN_SAMPLE_INTERVAL` ticks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunCurve {
+    pub samples: Vec<u32>,
+}
+
+impl RunCurve {
+    /// Write down the score if it's time for another sample.
+    pub fn sample(&mut self, tick: u32, score: u32) {
+        if tick % RUN_SAMPLE_INTERVAL == 0 {
+            self.samples.push(score);
+        }
+    }
+
+    /// Write down the final score, so the curve ends where the run did.
+    pub fn finish(&mut self, score: u32) {
+        self.samples.push(score);
+    }
+}
+
+/// Tallies of which marbles got cleared, and where.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClearStats {
+    pub by_marble: HashMap<Marble, u32>,
+    /// Keyed by hex coordinate
+    pub by_position: HashMap<(i32, i32), u32>,
+}
+
+impl ClearStats {
+    pub fn record(&mut self, event: &ClearEvent) {
+        *self.by_marble.entry(event.marble.clone()).or_insert(0) += 1;
+        *self
+            .by_position
+            .entry((event.pos.x, event.pos.y))
+            .or_insert(0) += 1;
+    }
+
+    /// Add all the other tallies into this one.
+    pub fn merge(&mut self, other: &ClearStats) {
+        for (marble, count) in other.by_marble.iter() {
+            *self.by_marble.entry(marble.clone()).or_insert(0) += count;
+        }
+        for (pos, count) in other.by_position.iter() {
+            *self.by_position.entry(*pos).or_insert(0) += count;
+        }
+    }
+}
+
+/// The very first layout, with just high scores for three modes and two settings.
+#[derive(Deserialize)]
+struct ProfileV1 {
+    highscores: HashMap<ModeKeyV1, u32>,
+    settings: PlaySettingsV1,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Hash)]
+enum ModeKeyV1 {
+    Classic,
+    Advanced,
+    NoGravity,
+}
+
+#[derive(Deserialize)]
+struct PlaySettingsV1 {
+    funni_background: bool,
+    animations: bool,
+}
+
+impl From<ProfileV1> for Profile {
+    fn from(old: ProfileV1) -> Self {
+        let mut profile = Profile::default();
+        profile
+            .highscores
+            .extend(old.highscores.into_iter().map(|(mode, score)| {
+                let mode = match mode {
+                    ModeKeyV1::Classic => BoardSettingsModeKey::Classic,
+                    ModeKeyV1::Advanced => BoardSettingsModeKey::Advanced,
+                    ModeKeyV1::NoGravity => BoardSettingsModeKey::NoGravity,
+                };
+                // Back then there were no mutators or speeds to file them under
+                let key = LeaderboardKey {
+                    mode,
+                    mutators: MutatorSet::empty(),
+                    speed: GameSpeed::Full,
+                };
+                (key, score)
+            }));
+        profile.settings.funni_background = old.settings.funni_background;
+        profile.settings.animations = old.settings.animations;
+        profile
+    }
+}
+
+fn location(version: &str) -> Location {
+    Location {
+        version: String::from(version),
+        ..Default::default()
+    }
+}
+
+/// If there's no save in the current layout, look for one in an older layout and bring it up
+/// to date. It gets saved in the new layout the next time the profile is, and the old one is
+/// left alone.
+fn load_older() -> anyhow::Result<Profile> {
+    let data = storage::load_from(&location("1"))?;
+    let old: ProfileV1 = bincode::deserialize(&data)?;
+    Ok(old.into())
+}
+
+impl Profile {
+    pub fn get() -> Profile {
+        let maybe_profile: anyhow::Result<Profile> = (|| {
+            // note we save the raw bincode! it's already gzipped!
+            // if we gzipped it here it would jut be gzipped twice
+            match storage::load_from(&location(SERIALIZATION_VERSION)) {
+                Ok(data) => Profile::from_saved(&data),
+                // Saves from before there were seals don't have one; they get sealed on the next save
+                Err(_) => load_older(),
+            }
+        })();
+        match maybe_profile {
+            Ok(it) => it,
+            Err(oh_no) => {
+                warn!("Couldn't load profile! Loading default...\n{:?}", oh_no);
+                Profile::default()
+            }
+        }
+    }
+
+    /// Read a save in the current layout. Every save in this layout got sealed,
+    /// so a seal that's missing counts as not matching.
+    fn from_saved(data: &[u8]) -> anyhow::Result<Profile> {
+        let mut profile: Profile = bincode::deserialize(data)?;
+        if !profile.modified && profile.score_seal != profile.seal() {
+            warn!("The high scores don't match their seal; marking the profile as modified");
+            profile.modified = true;
+        }
+        Ok(profile)
+    }
+
+    /// Load the profile, change it, and save it straight back.
+    pub fn modify<T>(f: impl FnOnce(&mut Profile) -> T) -> T {
+        let mut profile = Profile::get();
+        f(&mut profile)
+    }
+
+    /// Forget one high score. Returns what it was, if there was one.
+    pub fn clear_highscore(&mut self, key: &LeaderboardKey) -> Option<u32> {
+        self.best_splits.remove(key);
+        self.highscores.remove(key)
+    }
+
+    /// Forget every high score.
+    ///
+    /// Whatever was edited is gone now, so this clears the modified mark too.
+    pub fn clear_highscores(&mut self) {
+        self.highscores.clear();
+        self.weekly_highscores.clear();
+        self.modified = false;
+    }
+
+    /// Forget all the statistics, like best splits and clear counts, but keep the high scores.
+    pub fn clear_stats(&mut self) {
+        self.best_splits.clear();
+        self.clears.clear();
+        self.last_run_clears = None;
+        self.run_history.clear();
+    }
+
+    /// Wipe everything, settings included.
+    pub fn clear_all(&mut self) {
+        self.clear_highscores();
+        self.clear_stats();
+        self.seen_tips.clear();
+        self.settings = PlaySettings::default();
+    }
+
+    /// Work out the seal for the high scores as they are now.
+    fn seal(&self) -> Vec<u8> {
+        let key = option_env!("HAXAGON_SCORE_KEY").unwrap_or(DEV_SCORE_KEY);
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+        // The maps come out in a different order every time, so sort the entries first
+        let mut entries = self
+            .highscores
+            .iter()
+            .map(|entry| bincode::serialize(&(false, entry)))
+            .chain(
+                self.weekly_highscores
+                    .iter()
+                    .map(|entry| bincode::serialize(&(true, entry))),
+            )
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_default();
+        entries.sort();
+        mac.update(&[self.modified as u8]);
+        for entry in entries {
+            mac.update(&(entry.len() as u64).to_le_bytes());
+            mac.update(&entry);
+        }
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        // This gets deflated on its way into storage, so there's no need to zip it here
+        let data = bincode::serialize(self)?;
+        storage::save_to(&data, &location(SERIALIZATION_VERSION))?;
+        Ok(())
+    }
+
+    /// Throw away the oldest thing that's only kept for looking back on, to make the save
+    /// smaller. Score curves go first, from whichever mode has the most, then the last run's
+    /// clears, then where clears happened. High scores and settings are never touched.
+    ///
+    /// Returns false if there was nothing left to throw away.
+    fn prune_history(&mut self) -> bool {
+        let longest = self
+            .run_history
+            .values_mut()
+            .max_by_key(|runs| runs.len())
+            .filter(|runs| !runs.is_empty());
+        if let Some(runs) = longest {
+            runs.pop_front();
+            return true;
+        }
+        if self.last_run_clears.take().is_some() {
+            return true;
+        }
+        if self
+            .clears
+            .values()
+            .any(|stats| !stats.by_position.is_empty())
+        {
+            for stats in self.clears.values_mut() {
+                stats.by_position.clear();
+            }
+            return true;
+        }
+        false
+    }
+}
+
+/// Whether the last time the profile got saved, it didn't fit.
+pub fn save_failed() -> bool {
+    SAVE_FAILED.load(Ordering::Relaxed)
+}
+
+impl Drop for Profile {
+    fn drop(&mut self) {
+        self.score_seal = self.seal();
+        let mut res = self.save();
+
+        // Browsers only give each site so much local storage, and a save that doesn't fit
+        // doesn't happen at all. Rather than lose everything since the last save that did,
+        // throw away old history a bit at a time until it fits.
+        if cfg!(target_arch = "wasm32") {
+            let mut pruned = 0;
+            while res.is_err() && self.prune_history() {
+                pruned += 1;
+                res = self.save();
+            }
+            if pruned > 0 && res.is_ok() {
+                warn!(
+                    "Storage is full, so {} pieces of old history got thrown away to fit the profile",
+                    pruned
+                );
+            }
+        }
+
+        SAVE_FAILED.store(res.is_err(), Ordering::Relaxed);
+        if let Err(oh_no) = res {
+            warn!("Couldn't save profile!\n{:?}", oh_no);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PlaySettings {
+    pub funni_background: bool,
+    pub animations: bool,
+    /// Show a run timer with splits
+    pub speedrun_timer: bool,
+    /// Highlight what a closed loop would clear before letting go of it
+    pub cascade_preview: bool,
+    /// Size of the virtual canvas
+    pub resolution: Resolution,
+    /// Which marble sprites to use
+    pub skin: MarbleSkin,
+    /// Draw the screen like an old CRT
+    pub crt_filter: bool,
+    /// Play a different tone for each color when it's dragged over or cleared
+    pub color_tones: bool,
+    /// How fast the board runs
+    pub game_speed: GameSpeed,
+    /// How long moves and clears take to play out
+    pub game_feel: GameFeel,
+    /// Flip menus and the HUD left-to-right
+    pub mirrored_ui: bool,
+    /// How big menus are drawn
+    pub ui_scale: UiScale,
+    /// Draw a big crosshair at the mouse and a thicker pattern beam
+    pub high_vis_cursor: bool,
+    /// Draw arrows on empty cells showing which way marbles fall
+    pub gravity_arrows: bool,
+    /// Pop up hints on how to play until they've all been seen once
+    pub tips: bool,
+    /// Pause the game after this many seconds without input
+    pub auto_pause: Option<u32>,
+    /// Click once to start a pattern and again to finish it, instead of dragging
+    pub sticky_drag: bool,
+    /// Hexagons don't go off until they're clicked again
+    pub confirm_hexagons: bool,
+    /// How fancy the shaders and backgrounds are
+    pub effects: EffectsQuality,
+    /// How close patterns have to get to a cell to reach it
+    pub snap: SnapRadius,
+    /// Spread spawns out evenly instead of always crawling the same way
+    pub fair_spawns: bool,
+    /// How loud the music is
+    pub music_volume: Volume,
+    /// How loud everything but the music is
+    pub sfx_volume: Volume,
+}
+
+impl Default for PlaySettings {
+    fn default() -> Self {
+        Self {
+            funni_background: true,
+            animations: true,
+            speedrun_timer: false,
+            cascade_preview: true,
+            resolution: Resolution::Classic,
+            skin: MarbleSkin::Classic,
+            crt_filter: false,
+            color_tones: false,
+            game_speed: GameSpeed::Full,
+            game_feel: GameFeel::Normal,
+            mirrored_ui: false,
+            ui_scale: UiScale::default(),
+            high_vis_cursor: false,
+            gravity_arrows: false,
+            tips: true,
+            auto_pause: Some(60),
+            sticky_drag: false,
+            confirm_hexagons: false,
+            effects: EffectsQuality::Auto,
+            snap: SnapRadius::Near,
+            fair_spawns: false,
+            music_volume: Volume::Full,
+            sfx_volume: Volume::Full,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::ManuallyDrop;
+
+    use super::*;
+
+    // Dropping a profile saves it, so everything here is kept from dropping
+    // to stay out of the real save.
+
+    fn saved_with_score(score: u32) -> ManuallyDrop<Profile> {
+        let mut profile = ManuallyDrop::new(Profile::default());
+        let key = LeaderboardKey {
+            mode: BoardSettingsModeKey::Classic,
+            mutators: MutatorSet::empty(),
+            speed: GameSpeed::Full,
+        };
+        profile.highscores.insert(key, score);
+        profile.score_seal = profile.seal();
+        profile
+    }
+
+    fn reload(profile: &Profile) -> ManuallyDrop<Profile> {
+        let data = bincode::serialize(profile).unwrap();
+        ManuallyDrop::new(Profile::from_saved(&data).unwrap())
+    }
+
+    #[test]
+    fn matching_seal_is_not_modified() {
+        let profile = saved_with_score(1000);
+        assert!(!reload(&profile).modified);
+    }
+
+    #[test]
+    fn edited_score_is_modified() {
+        let mut profile = saved_with_score(1000);
+        for score in profile.highscores.values_mut() {
+            *score = 999_999;
+        }
+        assert!(reload(&profile).modified);
+    }
+
+    #[test]
+    fn emptied_seal_is_modified() {
+        let mut profile = saved_with_score(1000);
+        for score in profile.highscores.values_mut() {
+            *score = 999_999;
+        }
+        profile.score_seal.clear();
+        assert!(reload(&profile).modified);
+    }
+}
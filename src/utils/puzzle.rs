@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Board, BoardSettingsModeKey, Marble};
+
+use super::serdeflate::binzip64;
+
+/// Bump this when the puzzle format changes so old codes can be told apart.
+const PUZZLE_VERSION: u32 = 1;
+
+/// A hand-built starting board and what you have to do on it, made in the sandbox.
+///
+/// Puzzles are shared as codes: gzipped bincode in base64.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Puzzle {
+    version: u32,
+    /// Which mode's board and rules the puzzle is played with
+    pub mode: BoardSettingsModeKey,
+    /// Every marble on the board at the start, by its axial coordinates
+    pub marbles: Vec<((i32, i32), Marble)>,
+    pub goals: PuzzleGoals,
+}
+
+/// What you have to do to beat a puzzle. Goals that aren't set don't count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PuzzleGoals {
+    /// How many patterns you get to draw
+    pub move_limit: Option<u32>,
+    /// Score to reach, in board points (the HUD shows 100 times this)
+    pub target_score: Option<u32>,
+    /// How many marbles you have to clear
+    pub required_clears: Option<u32>,
+}
+
+impl Puzzle {
+    /// Take a snapshot of the marbles on the board.
+    pub fn from_board(board: &Board, mode: BoardSettingsModeKey, goals: PuzzleGoals) -> Self {
+        let mut marbles: Vec<_> = board
+            .get_marbles()
+            .iter()
+            .map(|(pos, marble)| ((pos.x, pos.y), marble.clone()))
+            .collect();
+        // So the same board always makes the same code
+        marbles.sort_by_key(|(pos, _)| *pos);
+        Self {
+            version: PUZZLE_VERSION,
+            mode,
+            marbles,
+            goals,
+        }
+    }
+
+    pub fn to_code(&self) -> anyhow::Result<String> {
+        binzip64(self)
+    }
+
+    /// Save the puzzle's code somewhere the player can get at it,
+    /// and say where that is.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export(&self) -> anyhow::Result<String> {
+        use anyhow::Context;
+
+        let code = self.to_code()?;
+        std::fs::create_dir_all("puzzles").context("When making the puzzles folder")?;
+        let name = format!(
+            "puzzles/{}.txt",
+            macroquad::miniquad::date::now().floor() as u64
+        );
+        std::fs::write(&name, code).context("When writing the puzzle")?;
+        Ok(name)
+    }
+
+    /// Save the puzzle's code somewhere the player can get at it,
+    /// and say where that is.
+    ///
+    /// The web version can't write files, so it goes to the browser console.
+    #[cfg(target_arch = "wasm32")]
+    pub fn export(&self) -> anyhow::Result<String> {
+        let code = self.to_code()?;
+        macroquad::prelude::info!("Puzzle code:\n{}", code);
+        Ok("THE CONSOLE".to_owned())
+    }
+}
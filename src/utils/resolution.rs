@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::Cyclable;
+
+/// The resolution everything is currently drawn at.
+/// This is read from both the update and draw threads, hence the atomic.
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Size of the virtual canvas the game draws onto before it gets scaled to the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Resolution {
+    /// 160x144, like a Game Boy
+    Classic,
+    /// 320x288, for more room
+    Double,
+    /// 256x144, for widescreen monitors
+    Wide,
+}
+
+impl Resolution {
+    /// The resolution everything is currently being laid out for
+    pub fn current() -> Self {
+        Self::ALL[CURRENT.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Switch the canvas over to this resolution.
+    ///
+    /// The canvas gets recreated on the next frame; modes lay themselves out
+    /// when they're constructed, so anything already on the stack keeps its old layout.
+    pub fn apply(&self) {
+        CURRENT.store(*self as u8, Ordering::Relaxed);
+    }
+
+    pub fn width(&self) -> f32 {
+        match self {
+            Resolution::Classic => 160.0,
+            Resolution::Double => 320.0,
+            Resolution::Wide => 256.0,
+        }
+    }
+
+    pub fn height(&self) -> f32 {
+        match self {
+            Resolution::Classic => 144.0,
+            Resolution::Double => 288.0,
+            Resolution::Wide => 144.0,
+        }
+    }
+}
+
+impl Cyclable for Resolution {
+    const ALL: &'static [Self] = &[Resolution::Classic, Resolution::Double, Resolution::Wide];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Resolution::Classic => "160X144",
+            Resolution::Double => "320X288",
+            Resolution::Wide => "256X144",
+        }
+    }
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Resolution::Classic
+    }
+}
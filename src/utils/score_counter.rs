@@ -0,0 +1,65 @@
+/// Score gains at least this big (in board points) make the counter flash
+const FLASH_THRESHOLD: u32 = 10;
+/// How many ticks a flash lasts
+const FLASH_TIME: u32 = 20;
+
+/// Counts a displayed score towards the real one over time, instead of jumping straight to it.
+#[derive(Debug, Clone)]
+pub struct ScoreCounter {
+    /// What was shown when the target last changed
+    from: f32,
+    target: u32,
+    /// Ticks since the target last changed
+    timer: u32,
+    /// How many ticks it takes to catch up to a new target
+    duration: u32,
+    /// Ticks left on the flash
+    flash: u32,
+}
+
+impl ScoreCounter {
+    pub fn new(start: u32, duration: u32) -> Self {
+        Self {
+            from: start as f32,
+            target: start,
+            timer: duration,
+            duration,
+            flash: 0,
+        }
+    }
+
+    /// Call this once a tick with the real score.
+    pub fn update(&mut self, target: u32) {
+        if target != self.target {
+            if target >= self.target.saturating_add(FLASH_THRESHOLD) {
+                self.flash = FLASH_TIME;
+            }
+            self.from = self.shown_exact();
+            self.target = target;
+            self.timer = 0;
+        }
+        self.timer = (self.timer + 1).min(self.duration);
+        self.flash = self.flash.saturating_sub(1);
+    }
+
+    /// The score to show right now
+    pub fn shown(&self) -> u32 {
+        self.shown_exact().round() as u32
+    }
+
+    /// How strongly to flash, from 0 to 1
+    pub fn flash(&self) -> f32 {
+        self.flash as f32 / FLASH_TIME as f32
+    }
+
+    fn shown_exact(&self) -> f32 {
+        let t = if self.duration == 0 {
+            1.0
+        } else {
+            self.timer as f32 / self.duration as f32
+        };
+        // Ease out so it slows down as it gets to the real score
+        let eased = 1.0 - (1.0 - t).powi(3);
+        self.from + (self.target as f32 - self.from) * eased
+    }
+}
@@ -0,0 +1,202 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{draw_rectangle, mouse_wheel, vec2, Color, Rect};
+
+use crate::controls::{Control, InputSubscriber};
+
+use super::{
+    draw::mouse_position_pixel,
+    layout::{flip_x, mirrored},
+};
+
+/// How far the mouse has to move with the button down before it counts as a drag
+/// instead of a click, in pixels
+const DRAG_THRESHOLD: f32 = 3.0;
+
+/// A column of same-sized rows, more than fit on screen at once.
+///
+/// It scrolls a whole row at a time, with the mouse wheel, by dragging,
+/// or by moving the keyboard focus past the edge.
+/// The list only keeps track of which rows go where; the mode owning it draws them.
+#[derive(Debug, Clone)]
+pub struct ScrollList {
+    bounds: Rect,
+    row_height: f32,
+    /// Distance from the top of one row to the top of the next
+    row_stride: f32,
+    len: usize,
+    /// Index of the row at the top
+    first: usize,
+    /// Row picked with the keyboard, which shows highlighted like the mouse is on it
+    focus: Option<usize>,
+    drag: Option<Drag>,
+    /// Row the mouse was on last frame
+    was_hovering: Option<usize>,
+}
+
+/// The mouse went down on the list and hasn't come up yet
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    start_y: f32,
+    start_first: usize,
+    /// Once it's moved far enough it's a drag, and letting go won't click anything
+    moved: bool,
+}
+
+impl ScrollList {
+    /// Lay out the list as if the UI wasn't mirrored; it gets flipped over if it is.
+    pub fn new(bounds: Rect, row_height: f32, gap: f32) -> Self {
+        Self {
+            bounds: Rect::new(flip_x(bounds.x, bounds.w), bounds.y, bounds.w, bounds.h),
+            row_height,
+            row_stride: row_height + gap,
+            len: 0,
+            first: 0,
+            focus: None,
+            drag: None,
+            was_hovering: None,
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Set how many rows there are, keeping the scroll and focus in range.
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.first = self.first.min(self.max_first());
+        self.focus = self.focus.filter(|&idx| idx < len);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many rows fit at once
+    pub fn rows_shown(&self) -> usize {
+        (((self.bounds.h + self.row_stride - self.row_height) / self.row_stride) as usize).max(1)
+    }
+
+    fn max_first(&self) -> usize {
+        self.len.saturating_sub(self.rows_shown())
+    }
+
+    /// The rows on screen right now, and where each one goes.
+    pub fn rows(&self) -> impl Iterator<Item = (usize, Rect)> + '_ {
+        (self.first..self.len.min(self.first + self.rows_shown())).map(move |idx| {
+            let y = self.bounds.y + (idx - self.first) as f32 * self.row_stride;
+            (
+                idx,
+                Rect::new(self.bounds.x, y, self.bounds.w, self.row_height),
+            )
+        })
+    }
+
+    /// The row the mouse is over, if any.
+    pub fn mouse_row(&self) -> Option<usize> {
+        let (mx, my) = mouse_position_pixel();
+        self.rows()
+            .find(|(_, rect)| rect.contains(vec2(mx, my)))
+            .map(|(idx, _)| idx)
+    }
+
+    /// The row to draw highlighted: the one under the mouse, or else the focused one.
+    pub fn highlighted(&self) -> Option<usize> {
+        self.mouse_row().or(self.focus)
+    }
+
+    /// Did the mouse move onto a new row since last frame?
+    pub fn mouse_entered(&self) -> bool {
+        let row = self.mouse_row();
+        row.is_some() && row != self.was_hovering
+    }
+
+    /// Scroll, drag, and move the focus around.
+    ///
+    /// Return the row that got clicked on or picked with the keyboard this frame, if any.
+    pub fn update(&mut self, controls: &InputSubscriber) -> Option<usize> {
+        let (_, wheel) = mouse_wheel();
+        if wheel > 0.0 {
+            self.first = self.first.saturating_sub(1);
+        } else if wheel < 0.0 {
+            self.first = (self.first + 1).min(self.max_first());
+        }
+
+        let mut clicked = None;
+        let (_, my) = mouse_position_pixel();
+        if controls.clicked_down(Control::Click) && self.mouse_row().is_some() {
+            self.drag = Some(Drag {
+                start_y: my,
+                start_first: self.first,
+                moved: false,
+            });
+        }
+        if let Some(drag) = &mut self.drag {
+            if controls.pressed(Control::Click) {
+                let dy = my - drag.start_y;
+                drag.moved |= dy.abs() > DRAG_THRESHOLD;
+                if drag.moved {
+                    let rows = (dy / self.row_stride).round() as isize;
+                    let first = drag.start_first as isize - rows;
+                    self.first = (first.max(0) as usize).min(self.max_first());
+                }
+            } else {
+                // Letting go without dragging clicks whatever's under the mouse
+                if !drag.moved {
+                    clicked = self.mouse_row();
+                }
+                self.drag = None;
+            }
+        }
+
+        if self.len > 0 {
+            let moved_to = if controls.clicked_down(Control::MenuUp) {
+                Some(self.focus.map_or(self.len - 1, |idx| idx.saturating_sub(1)))
+            } else if controls.clicked_down(Control::MenuDown) {
+                Some(self.focus.map_or(0, |idx| (idx + 1).min(self.len - 1)))
+            } else {
+                None
+            };
+            if let Some(focus) = moved_to {
+                // Scroll the newly focused row onto the screen
+                if focus < self.first {
+                    self.first = focus;
+                } else if focus >= self.first + self.rows_shown() {
+                    self.first = focus + 1 - self.rows_shown();
+                }
+                self.focus = Some(focus);
+            }
+            if let (Some(focus), true) = (self.focus, controls.clicked_down(Control::MenuSelect)) {
+                clicked = Some(focus);
+            }
+        }
+
+        clicked
+    }
+
+    /// You must call this at the *end* of every frame, after all
+    /// processing is done.
+    pub fn post_update(&mut self) {
+        self.was_hovering = self.mouse_row();
+    }
+
+    /// Draw a thin bar down the side showing how far down the list is scrolled,
+    /// if there's anything to scroll.
+    pub fn draw_scrollbar(&self, color: Color) {
+        if self.len <= self.rows_shown() {
+            return;
+        }
+        let x = if mirrored() {
+            self.bounds.left() - 3.0
+        } else {
+            self.bounds.right() + 2.0
+        };
+        let h = self.bounds.h * self.rows_shown() as f32 / self.len as f32;
+        let y = self.bounds.y + (self.bounds.h - h) * self.first as f32 / self.max_first() as f32;
+        draw_rectangle(x, y, 1.0, h, color);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::Cyclable;
+
+/// Which set of marble sprites to draw with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MarbleSkin {
+    Classic,
+    Gems,
+    Runes,
+    /// Every color gets its own shape, for telling them apart without color
+    Shapes,
+}
+
+/// Where a skin's sprites live in the marble atlas.
+///
+/// Each marble's column is `marble as u32 * MARBLE_SIZE`; the skin just picks the rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinAtlas {
+    /// Y offset of the row of sigils, which get tinted when drawn
+    pub sigil_y: f32,
+    /// Y offset of the row of marble bodies, which are drawn as-is
+    pub body_y: f32,
+}
+
+impl MarbleSkin {
+    pub fn atlas(&self) -> SkinAtlas {
+        // The background ticks are squeezed in between the classic skin and the rest
+        let (sigil_y, body_y) = match self {
+            MarbleSkin::Classic => (0.0, 8.0),
+            MarbleSkin::Gems => (24.0, 32.0),
+            MarbleSkin::Runes => (40.0, 48.0),
+            MarbleSkin::Shapes => (56.0, 64.0),
+        };
+        SkinAtlas { sigil_y, body_y }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            MarbleSkin::Classic => "THE ORIGINAL\nMARBLES.",
+            MarbleSkin::Gems => "SHINY CUT GEMS.",
+            MarbleSkin::Runes => "STONE TABLETS\nCARVED WITH RUNES.",
+            MarbleSkin::Shapes => "A DIFFERENT SHAPE\nFOR EVERY COLOR,\nIN HIGH CONTRAST.",
+        }
+    }
+}
+
+impl Cyclable for MarbleSkin {
+    const ALL: &'static [Self] = &[
+        MarbleSkin::Classic,
+        MarbleSkin::Gems,
+        MarbleSkin::Runes,
+        MarbleSkin::Shapes,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MarbleSkin::Classic => "CLASSIC",
+            MarbleSkin::Gems => "GEMS",
+            MarbleSkin::Runes => "RUNES",
+            MarbleSkin::Shapes => "SHAPES",
+        }
+    }
+}
+
+impl Default for MarbleSkin {
+    fn default() -> Self {
+        MarbleSkin::Classic
+    }
+}
@@ -3,7 +3,7 @@
 mod billboard;
 pub use billboard::Billboard;
 use itertools::Itertools;
-use macroquad::prelude::{draw_texture_ex, Color, DrawTextureParams, Rect, Texture2D};
+use macroquad::prelude::{draw_texture_ex, vec2, Color, DrawTextureParams, Rect, Texture2D};
 
 
 
@@ -74,6 +74,19 @@ pub fn draw_pixel_text(
     align: TextAlign,
     color: Color,
     font: Texture2D,
+) {
+    draw_pixel_text_scaled(text, cx, cy, align, color, font, 1.0);
+}
+
+/// Draw some text like `draw_pixel_text`, but with every pixel of the font `scale` pixels big.
+pub fn draw_pixel_text_scaled(
+    text: &str,
+    cx: f32,
+    cy: f32,
+    align: TextAlign,
+    color: Color,
+    font: Texture2D,
+    scale: f32,
 ) {
     let mut cursor_x = 0usize;
     let mut cursor_y = 0usize;
@@ -101,10 +114,10 @@ pub fn draw_pixel_text(
             TextAlign::Center => -0.5,
             TextAlign::Right => -1.0,
         };
-        let offset = line_widths[cursor_y] as f32 * (char_width + 1.0) * offset_prop;
+        let offset = line_widths[cursor_y] as f32 * (char_width + 1.0) * scale * offset_prop;
 
-        let x = cx + cursor_x as f32 * (char_width + 1.0) + offset;
-        let y = cy + cursor_y as f32 * (char_height + 1.0);
+        let x = cx + cursor_x as f32 * (char_width + 1.0) * scale + offset;
+        let y = cy + cursor_y as f32 * (char_height + 1.0) * scale;
 
         draw_texture_ex(
             font,
@@ -113,6 +126,7 @@ pub fn draw_pixel_text(
             color,
             DrawTextureParams {
                 source: Some(Rect::new(sx, 0.0, char_width, char_height)),
+                dest_size: Some(vec2(char_width * scale, char_height * scale)),
                 ..Default::default()
             },
         );
@@ -0,0 +1,48 @@
+//! The colors menus and the HUD are drawn with, named by what they're for.
+//!
+//! Draw with these instead of spelling out hex codes,
+//! so the whole game can change colors in one place.
+
+use macroquad::prelude::Color;
+
+use super::draw::hexcolor;
+
+/// Behind everything
+pub fn bg() -> Color {
+    hexcolor(0x14182e_ff)
+}
+
+/// Inside buttons and panels
+pub fn panel() -> Color {
+    hexcolor(0x4b1d52_ff)
+}
+
+/// Inside buttons and panels the mouse is over
+pub fn panel_highlight() -> Color {
+    hexcolor(0x692464_ff)
+}
+
+/// Outlines, lines, and ordinary menu text
+pub fn border() -> Color {
+    hexcolor(0xcc2f7b_ff)
+}
+
+/// Outlines and text the mouse is over, and anything urgent
+pub fn accent() -> Color {
+    hexcolor(0xff5277_ff)
+}
+
+/// Text and highlights that need to stand out, like scores
+pub fn text() -> Color {
+    hexcolor(0xffee83_ff)
+}
+
+/// Things that are turned off or not available yet
+pub fn dim() -> Color {
+    hexcolor(0x9c2a70_ff)
+}
+
+/// Empty slots and the unfilled part of bars
+pub fn shadow() -> Color {
+    hexcolor(0x291d2b_ff)
+}
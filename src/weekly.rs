@@ -0,0 +1,166 @@
+use crate::model::{BoardSettings, Mutator, MutatorSet};
+
+pub const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
+
+/// Classic with a fixed starting board and a couple of modifiers, the same for everyone
+/// for a whole ISO week. A new one starts every Monday at midnight UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeeklyChallenge {
+    /// The ISO week-numbering year, which isn't always the calendar year near New Year's
+    pub year: i32,
+    /// ISO week number, from 1 to 53
+    pub week: u32,
+}
+
+impl WeeklyChallenge {
+    /// The challenge for the week the given day (counted from 1970-01-01) is in.
+    pub fn containing_day(day: i64) -> Self {
+        // ISO weeks belong to whatever year their Thursday is in
+        let thursday = monday_of(day) + 3;
+        let year = year_of_day(thursday);
+        let week = (thursday - day_of_year_start(year)) / 7 + 1;
+        Self {
+            year,
+            week: week as u32,
+        }
+    }
+
+    /// Number to file highscores under, like `202342` for week 42 of 2023.
+    pub fn id(&self) -> u32 {
+        self.year as u32 * 100 + self.week
+    }
+
+    /// Seed for the RNG when the starting board gets rolled.
+    pub fn seed(&self) -> u64 {
+        mix(self.id() as u64)
+    }
+
+    /// The one or two modifiers turned on this week.
+    pub fn mutators(&self) -> MutatorSet {
+        let mut hash = mix(self.seed());
+        let count = 1 + (hash & 1) as usize;
+        let mut out = MutatorSet::empty();
+        while out.iter().count() < count {
+            hash = mix(hash);
+            let mutator = Mutator::ALL[(hash % Mutator::ALL.len() as u64) as usize];
+            if !out.contains(mutator) {
+                out.toggle(mutator);
+            }
+        }
+        out
+    }
+
+    /// The settings to play this week's challenge with.
+    pub fn settings(&self) -> BoardSettings {
+        BoardSettings {
+            weekly: Some(*self),
+            ..BoardSettings::classic()
+        }
+        .with_mutators(self.mutators())
+    }
+
+    /// The day the challenge running on the given day gets replaced by next week's.
+    pub fn replaced_on(day: i64) -> i64 {
+        monday_of(day) + 7
+    }
+}
+
+/// Format a countdown like `3D 04H` or `04H 12M`.
+pub fn format_countdown(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    let hours = minutes / 60;
+    let days = hours / 24;
+    if days > 0 {
+        format!("{}D {:02}H", days, hours % 24)
+    } else {
+        format!("{:02}H {:02}M", hours, minutes % 60)
+    }
+}
+
+/// The Monday on or before the given day
+fn monday_of(day: i64) -> i64 {
+    // 1970-01-01 was a Thursday, which is 3 days after Monday
+    day - (day + 3).rem_euclid(7)
+}
+
+/// The calendar year a day falls in.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn year_of_day(day: i64) -> i32 {
+    let z = day + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    // The algorithm's years start in March, so January and February are in the next one
+    let year = yoe + era * 400 + if mp >= 10 { 1 } else { 0 };
+    year as i32
+}
+
+/// The day January 1st of the given year falls on.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn day_of_year_start(year: i32) -> i64 {
+    // Count from the March before, so the leap day is at the end
+    let y = year as i64 - 1;
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    // January is 306 days after March 1st
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + 306;
+    era * 146097 + doe - 719468
+}
+
+/// Scramble a number up, so neighboring weeks get unrelated boards.
+/// This is splitmix64's finalizer.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn week_of(day: i64) -> (i32, u32) {
+        let challenge = WeeklyChallenge::containing_day(day);
+        (challenge.year, challenge.week)
+    }
+
+    #[test]
+    fn days_line_up_with_years() {
+        assert_eq!(day_of_year_start(1970), 0);
+        assert_eq!(year_of_day(-1), 1969);
+        assert_eq!(year_of_day(day_of_year_start(2024) + 365), 2024);
+        assert_eq!(year_of_day(day_of_year_start(2024) + 366), 2025);
+    }
+
+    #[test]
+    fn iso_weeks_near_new_year() {
+        // Sunday 2021-01-03 is still in the last week of 2020
+        assert_eq!(week_of(day_of_year_start(2021) + 2), (2020, 53));
+        // and Monday 2021-01-04 starts the first week of 2021
+        assert_eq!(week_of(day_of_year_start(2021) + 3), (2021, 1));
+        // Monday 2024-12-30 is already in the first week of 2025
+        assert_eq!(week_of(day_of_year_start(2025) - 2), (2025, 1));
+        assert_eq!(week_of(day_of_year_start(2025) - 3), (2024, 52));
+        // Friday 2027-01-01 belongs to the week before
+        assert_eq!(week_of(day_of_year_start(2027)), (2026, 53));
+    }
+
+    #[test]
+    fn iso_week_in_the_middle_of_the_year() {
+        // Monday 2023-10-16
+        assert_eq!(week_of(19646), (2023, 42));
+        assert_eq!(WeeklyChallenge::containing_day(19646).id(), 202342);
+    }
+
+    #[test]
+    fn challenges_change_on_monday() {
+        let sunday = day_of_year_start(2021) + 2;
+        assert_eq!(WeeklyChallenge::replaced_on(sunday), sunday + 1);
+        assert_eq!(WeeklyChallenge::replaced_on(sunday + 1), sunday + 8);
+    }
+}